@@ -18,6 +18,7 @@
 //! | `docker/throughput` | Lines/s multiplexed across N containers |
 //! | `file/throughput` | Lines/s from inotify-based file tail |
 //! | `stdin/burst` | Lines/s when all lines arrive in a single burst |
+//! | `demo/generate` | Time to generate a minute of each shipped demo scenario |
 //!
 //! # Viewing results
 //!
@@ -156,6 +157,39 @@ fn stdin_burst(c: &mut Criterion) {
     group.finish();
 }
 
+// ---------------------------------------------------------------------------
+// Demo scenario generation
+// ---------------------------------------------------------------------------
+
+fn demo_generate(c: &mut Criterion) {
+    use fml_feeds::demo::{Scenario, Simulation, BUILTIN};
+    let mut group = c.benchmark_group("demo/generate");
+    let start: chrono::DateTime<chrono::Utc> = "2024-01-15T10:00:00Z".parse().unwrap();
+    let step = std::time::Duration::from_millis(100);
+
+    for (name, _) in BUILTIN {
+        let scenario = Scenario::load(name).unwrap();
+        group.bench_with_input(
+            BenchmarkId::new("minute", name),
+            &scenario,
+            |b, scenario| {
+                b.iter(|| {
+                    // Same seed every iteration, so every run generates the same lines.
+                    let mut simulation = Simulation::new(scenario.clone());
+                    (1..=600)
+                        .map(|i| {
+                            let now = start + chrono::TimeDelta::milliseconds(100 * i);
+                            simulation.advance(step, now).entries.len()
+                        })
+                        .sum::<usize>()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
 // ---------------------------------------------------------------------------
 // Criterion registration
 // ---------------------------------------------------------------------------
@@ -167,5 +201,6 @@ criterion_group!(
     docker_throughput,
     file_throughput,
     stdin_burst,
+    demo_generate,
 );
 criterion_main!(ingestor_benches);
@@ -5,6 +5,7 @@
 //! the same defaults without touching the filesystem (useful in tests).
 
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 // ---------------------------------------------------------------------------
@@ -39,6 +40,12 @@ pub struct Config {
     pub ui: UiConfig,
     #[serde(default)]
     pub keybindings: KeybindingsConfig,
+    #[serde(default)]
+    pub feeds: FeedsConfig,
+    #[serde(default)]
+    pub ingest: IngestConfig,
+    #[serde(default)]
+    pub producers: ProducersConfig,
 }
 
 /// `[ui]` section of `config.toml`.
@@ -133,6 +140,215 @@ impl Default for KeybindingsConfig {
     }
 }
 
+/// `[ingest]` section: what each feed does when the UI falls behind.
+///
+/// ```toml
+/// [ingest]
+/// overflow = "drop-oldest"
+/// buffer = 50000
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct IngestConfig {
+    /// `block`, `drop-oldest` or `sample`. If unset, `block`.
+    #[serde(default)]
+    pub overflow: Option<String>,
+    /// Lines each feed buffers before the policy applies. If unset, the
+    /// feeds' default.
+    #[serde(default)]
+    pub buffer: Option<usize>,
+}
+
+/// `[producers]` section: how producer names are rewritten before they reach
+/// the tree. Rules run in order, then the built-in ones, each on the whole
+/// producer path; `source` limits a rule to one feed kind.
+///
+/// ```toml
+/// [producers]
+/// builtin = true
+///
+/// [[producers.rules]]
+/// match = "^(.+)-canary$"
+/// replace = "$1"
+/// source = "kubernetes"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProducersConfig {
+    /// Strip ReplicaSet hashes and Compose indices. If unset, `true`.
+    #[serde(default = "default_true")]
+    pub builtin: bool,
+    #[serde(default)]
+    pub rules: Vec<ProducerRuleConfig>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for ProducersConfig {
+    fn default() -> Self {
+        Self {
+            builtin: true,
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// One `[[producers.rules]]` entry.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProducerRuleConfig {
+    /// Regex matched against the producer path.
+    #[serde(rename = "match")]
+    pub pattern: String,
+    /// Replacement, with `$1` / `${name}` for capture groups.
+    pub replace: String,
+    /// Feed kind the rule applies to (`docker`, `kubernetes`, …). If unset,
+    /// every feed.
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+/// `[feeds]` section of `config.toml` — per-feed settings.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FeedsConfig {
+    #[serde(default)]
+    pub exec: ExecFeedConfig,
+    #[serde(default)]
+    pub docker: DockerFeedConfig,
+    #[serde(default)]
+    pub kubernetes: KubernetesFeedConfig,
+    #[serde(default)]
+    pub file: FileFeedConfig,
+    #[serde(default)]
+    pub stdin: StdinFeedConfig,
+    #[serde(default)]
+    pub journald: JournaldFeedConfig,
+}
+
+/// `[feeds.docker]` section. An empty socket means the default
+/// (`/var/run/docker.sock`).
+///
+/// ```toml
+/// [feeds.docker]
+/// socket = "/run/user/1000/docker.sock"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DockerFeedConfig {
+    /// Engine socket path or `DOCKER_HOST`-style URL (`unix://…`, `tcp://…`).
+    #[serde(default)]
+    pub socket: String,
+}
+
+/// `[feeds.kubernetes]` section. Empty strings mean "use the kubeconfig's".
+///
+/// ```toml
+/// [feeds.kubernetes]
+/// context = "staging"
+/// default_namespace = "shop"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct KubernetesFeedConfig {
+    /// Kubeconfig context to use instead of the current one.
+    #[serde(default)]
+    pub context: String,
+    /// Namespace to follow when `--namespace` is not given.
+    #[serde(default)]
+    pub default_namespace: String,
+}
+
+/// `[feeds.file]` section: what `--feed file` follows when no `--path` is
+/// given.
+///
+/// ```toml
+/// [feeds.file]
+/// paths = ["~/logs/**/*.log"]
+/// exclude = ["*.gz"]
+/// max_open_files = 64
+/// archives = true
+/// archive_horizon = "24h"
+/// resume = true
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FileFeedConfig {
+    /// Files, directories or glob patterns (`**` matches any depth).
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Glob patterns of files never to open.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Most files held open at once. If unset, the feed's default.
+    #[serde(default)]
+    pub max_open_files: Option<usize>,
+    /// Backfill rotated archives (`app.log.1`, `app.log.2.gz`, …) first.
+    #[serde(default)]
+    pub archives: bool,
+    /// How far back archives go: an age (`24h`) or a size on disk
+    /// (`500MB`). If unset, all of them.
+    #[serde(default)]
+    pub archive_horizon: Option<String>,
+    /// Save read offsets under [`state_dir`] and resume from them on the
+    /// next run.
+    #[serde(default)]
+    pub resume: bool,
+}
+
+/// `[feeds.stdin]` section.
+///
+/// ```toml
+/// [feeds.stdin]
+/// prefix = "compose"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StdinFeedConfig {
+    /// How lines name their producer: `auto`, `none`, `compose`, `kubectl`,
+    /// `stern`, or a regex with a `producer` group. If unset, `auto`.
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+/// `[feeds.journald]` section: filters passed to `journalctl`.
+///
+/// ```toml
+/// [feeds.journald]
+/// units = ["nginx.service"]
+/// priority = 4
+/// since = "-1h"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JournaldFeedConfig {
+    /// Only follow these units. Empty follows the whole journal.
+    #[serde(default)]
+    pub units: Vec<String>,
+    /// Maximum syslog priority, 0 (emerg) to 7 (debug). If unset, all.
+    #[serde(default)]
+    pub priority: Option<u8>,
+    /// Backfill start point, in any format `journalctl --since` accepts.
+    #[serde(default)]
+    pub since: Option<String>,
+}
+
+/// `[feeds.exec]` section: named commands runnable with `--exec <name>`.
+///
+/// ```toml
+/// [feeds.exec.commands.bastion]
+/// command = ["ssh", "bastion-1", "tail", "-F", "/var/log/app.log"]
+/// restart = true
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExecFeedConfig {
+    #[serde(default)]
+    pub commands: BTreeMap<String, ExecCommandConfig>,
+}
+
+/// A single named command under `[feeds.exec.commands.<name>]`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExecCommandConfig {
+    /// Program followed by its arguments.
+    pub command: Vec<String>,
+    /// Restart with exponential backoff when the command exits.
+    #[serde(default)]
+    pub restart: bool,
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self::defaults()
@@ -191,6 +407,19 @@ fn config_path() -> PathBuf {
         .join("config.toml")
 }
 
+/// Where fml keeps state between runs: `$XDG_STATE_HOME/fml`, else
+/// `~/.local/state/fml`.
+pub fn state_dir() -> PathBuf {
+    std::env::var("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| ".".to_string()))
+                .join(".local")
+                .join("state")
+        })
+        .join("fml")
+}
+
 // ---------------------------------------------------------------------------
 // Tests
 // ---------------------------------------------------------------------------
@@ -206,5 +435,173 @@ mod tests {
         assert_eq!(cfg.ui.producer_pane_width_pct, 25);
         assert_eq!(cfg.keybindings.query_focus, "/");
         assert_eq!(cfg.keybindings.greed_up, "]");
+        assert!(cfg.feeds.exec.commands.is_empty());
+    }
+
+    #[test]
+    fn exec_commands_parse() {
+        let cfg: Config = config::Config::builder()
+            .add_source(config::File::from_str(
+                r#"
+                [feeds.exec.commands.tests]
+                command = ["cargo", "test"]
+                restart = true
+                "#,
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        let tests = &cfg.feeds.exec.commands["tests"];
+        assert_eq!(tests.command, ["cargo", "test"]);
+        assert!(tests.restart);
+    }
+
+    #[test]
+    fn kubernetes_section_parses() {
+        let cfg: Config = config::Config::builder()
+            .add_source(config::File::from_str(
+                r#"
+                [feeds.kubernetes]
+                context = "staging"
+                "#,
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(cfg.feeds.kubernetes.context, "staging");
+        assert_eq!(cfg.feeds.kubernetes.default_namespace, "");
+    }
+
+    #[test]
+    fn docker_section_parses() {
+        let cfg: Config = config::Config::builder()
+            .add_source(config::File::from_str(
+                r#"
+                [feeds.docker]
+                socket = "/run/user/1000/docker.sock"
+                "#,
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(cfg.feeds.docker.socket, "/run/user/1000/docker.sock");
+        assert_eq!(Config::defaults().feeds.docker.socket, "");
+    }
+
+    #[test]
+    fn stdin_section_parses() {
+        let cfg: Config = config::Config::builder()
+            .add_source(config::File::from_str(
+                r#"
+                [feeds.stdin]
+                prefix = '^(?P<producer>\w+): '
+                "#,
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(
+            cfg.feeds.stdin.prefix.as_deref(),
+            Some(r"^(?P<producer>\w+): ")
+        );
+        assert_eq!(Config::defaults().feeds.stdin.prefix, None);
+    }
+
+    #[test]
+    fn file_section_parses() {
+        let cfg: Config = config::Config::builder()
+            .add_source(config::File::from_str(
+                r#"
+                [feeds.file]
+                paths = ["~/logs/**/*.log"]
+                max_open_files = 64
+                archive_horizon = "24h"
+                resume = true
+                "#,
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(cfg.feeds.file.paths, ["~/logs/**/*.log"]);
+        assert!(cfg.feeds.file.exclude.is_empty());
+        assert_eq!(cfg.feeds.file.max_open_files, Some(64));
+        assert!(!cfg.feeds.file.archives);
+        assert_eq!(cfg.feeds.file.archive_horizon.as_deref(), Some("24h"));
+        assert!(cfg.feeds.file.resume);
+    }
+
+    #[test]
+    fn journald_section_parses() {
+        let cfg: Config = config::Config::builder()
+            .add_source(config::File::from_str(
+                r#"
+                [feeds.journald]
+                units = ["nginx.service", "sshd.service"]
+                priority = 4
+                "#,
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(cfg.feeds.journald.units, ["nginx.service", "sshd.service"]);
+        assert_eq!(cfg.feeds.journald.priority, Some(4));
+        assert_eq!(cfg.feeds.journald.since, None);
+        assert!(Config::defaults().feeds.journald.units.is_empty());
+    }
+
+    #[test]
+    fn ingest_section_parses() {
+        let cfg: Config = config::Config::builder()
+            .add_source(config::File::from_str(
+                r#"
+                [ingest]
+                overflow = "sample"
+                "#,
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(cfg.ingest.overflow.as_deref(), Some("sample"));
+        assert_eq!(cfg.ingest.buffer, None);
+        assert_eq!(Config::defaults().ingest.overflow, None);
+    }
+
+    #[test]
+    fn producer_rules_parse() {
+        let cfg: Config = config::Config::builder()
+            .add_source(config::File::from_str(
+                r#"
+                [producers]
+                builtin = false
+
+                [[producers.rules]]
+                match = "^(.+)-canary$"
+                replace = "$1"
+                source = "kubernetes"
+                "#,
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert!(!cfg.producers.builtin);
+        assert_eq!(cfg.producers.rules[0].pattern, "^(.+)-canary$");
+        assert_eq!(cfg.producers.rules[0].source.as_deref(), Some("kubernetes"));
+        assert!(Config::defaults().producers.builtin);
     }
 }
@@ -1,3 +1,103 @@
 //! Export — formats and writes log entries to files or stdout.
 //!
 //! Supports raw lines, JSON-L, and CSV output formats across configurable scopes.
+//! A JSON-L line is a [`JsonlRecord`], which the replay feed reads back.
+
+use crate::{FeedKind, LogEntry};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// One line of a `jsonl` export: everything on a [`LogEntry`] except the
+/// session-local `seq`, with fields sorted for stable output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonlRecord {
+    pub ts: DateTime<Utc>,
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    pub producer: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub raw: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, Value>,
+}
+
+impl From<&LogEntry> for JsonlRecord {
+    fn from(entry: &LogEntry) -> Self {
+        Self {
+            ts: entry.ts,
+            source: entry.source.to_string(),
+            instance: entry.instance.clone(),
+            producer: entry.producer.clone(),
+            level: entry.level.map(|level| level.to_string()),
+            message: entry.message.clone(),
+            raw: entry.raw.clone(),
+            fields: entry
+                .fields
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl JsonlRecord {
+    /// Rebuild the entry. A source this build does not know becomes
+    /// [`FeedKind::Replay`]; an unknown level is dropped.
+    pub fn into_entry(self) -> LogEntry {
+        LogEntry {
+            seq: 0,
+            raw: self.raw,
+            ts: self.ts,
+            level: self.level.and_then(|level| level.parse().ok()),
+            source: self.source.parse().unwrap_or(FeedKind::Replay),
+            instance: self.instance,
+            producer: self.producer,
+            fields: self.fields.into_iter().collect(),
+            message: self.message,
+        }
+    }
+}
+
+// ----- Tests -----
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LogLevel;
+
+    #[test]
+    fn records_round_trip_entries() {
+        let entry = LogEntry {
+            seq: 42,
+            raw: r#"{"msg":"boom","code":500}"#.into(),
+            ts: "2024-01-15T10:32:05.250Z".parse().unwrap(),
+            level: Some(LogLevel::Error),
+            source: FeedKind::Docker,
+            instance: Some("podman".into()),
+            producer: "shop/api-1".into(),
+            fields: [("code".to_string(), Value::from(500))].into(),
+            message: Some("boom".into()),
+        };
+        let line = serde_json::to_string(&JsonlRecord::from(&entry)).unwrap();
+        let record: JsonlRecord = serde_json::from_str(&line).unwrap();
+        assert_eq!(record.into_entry(), LogEntry { seq: 0, ..entry });
+    }
+
+    #[test]
+    fn unknown_sources_become_replay() {
+        let record: JsonlRecord = serde_json::from_str(
+            r#"{"ts":"2024-01-15T10:32:05Z","source":"fluentd","producer":"p","raw":"x"}"#,
+        )
+        .unwrap();
+        let entry = record.into_entry();
+        assert_eq!(entry.source, FeedKind::Replay);
+        assert!(entry.fields.is_empty());
+        assert_eq!(entry.level, None);
+    }
+}
@@ -0,0 +1,108 @@
+//! The control surface every running feed exposes to the UI.
+//!
+//! A [`FeedHandle`] addresses producers by their producer tree id: the feed
+//! root (`docker`, `docker@podman`), then the producer's path below it
+//! (`docker/shop/api`). A path also covers everything beneath it, so pausing
+//! `docker/shop` pauses each of its services, pausing `docker` pauses the
+//! whole feed, and `""` means every root the handle owns. Paths a handle does
+//! not own are ignored, so the UI can send each command to every handle.
+//!
+//! Calls never block: they queue the change for the feed, which reports the
+//! result on its status channel like any other [`ProducerStatus`].
+
+use crate::types::ProducerStatus;
+
+/// Start, stop, pause and inspect the producers of one running feed.
+pub trait FeedHandle: Send + Sync {
+    /// Hold back lines from producers under `path` until they are resumed.
+    /// Their streams stay open.
+    fn pause(&self, path: &str);
+
+    /// Deliver lines held back by [`pause`](Self::pause), then carry on.
+    fn resume(&self, path: &str);
+
+    /// Discard lines from producers under `path` until they are started.
+    fn stop(&self, path: &str);
+
+    /// Undo [`stop`](Self::stop).
+    fn start(&self, path: &str);
+
+    /// Close and reopen the streams of producers under `path`. Feeds that
+    /// cannot reopen one producer's stream on its own ignore this.
+    fn reconnect(&self, path: &str);
+
+    /// Every producer the feed has reported on, with its counters.
+    fn health(&self) -> Vec<ProducerHealth>;
+
+    /// The UI has put `lines` more lines from feed root `root` in its store,
+    /// in the order they arrived. Push feeds answer their senders once a
+    /// batch is stored; other handles can ignore this.
+    fn stored(&self, _root: &str, _lines: u64) {}
+}
+
+/// One producer's state as its [`FeedHandle`] sees it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProducerHealth {
+    /// Producer tree id: `root/producer`, or just the root for the feed's own
+    /// node.
+    pub path: String,
+    /// Last status the feed reported, before any pause or stop.
+    pub status: Option<ProducerStatus>,
+    pub paused: bool,
+    pub stopped: bool,
+    /// Stopping closes the producer's stream. Otherwise its feed has no
+    /// stream of its own to close and only discards its lines.
+    pub closes: bool,
+    /// Reconnecting reopens the producer's stream. Otherwise it does nothing.
+    pub reopens: bool,
+    pub counters: ProducerCounters,
+    /// How far behind its own timestamp the producer's latest line reached
+    /// the UI.
+    pub lag: Option<std::time::Duration>,
+    /// When the producer's latest line arrived from the feed.
+    pub last_line: Option<chrono::DateTime<chrono::Utc>>,
+    /// The message of the latest [`ProducerStatus::Error`] it reported.
+    pub last_error: Option<String>,
+}
+
+/// What a producer has delivered so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProducerCounters {
+    /// Bytes of raw line text, without line endings.
+    pub bytes: u64,
+    pub lines: u64,
+    /// Times the producer's stream came back after the first connect.
+    pub reconnects: u32,
+    /// Lines lost to the feed's overflow policy or a full pause backlog.
+    pub dropped: u64,
+    /// Lines that had to wait because the UI was behind.
+    pub delayed: u64,
+}
+
+/// Whether the tree id `id` lies under `path` (or is it). `""` covers every
+/// id.
+pub fn covers(path: &str, id: &str) -> bool {
+    path.is_empty()
+        || id
+            .strip_prefix(path)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_cover_their_subtree() {
+        assert!(covers("", "docker/api"));
+        assert!(covers("docker", "docker"));
+        assert!(covers("docker", "docker/shop/api"));
+        assert!(covers("docker/shop", "docker/shop/api"));
+        assert!(!covers("docker/shop", "docker/shopping"));
+        assert!(!covers("docker", "docker@podman/api"));
+    }
+}
@@ -16,9 +16,16 @@
 
 pub mod config;
 pub mod export;
+pub mod handle;
 pub mod normalizer;
+pub mod rewrite;
 pub mod search;
 pub mod store;
 pub mod types;
 
-pub use types::{FeedKind, LogEntry, LogLevel};
+pub use handle::{FeedHandle, ProducerCounters, ProducerHealth};
+pub use rewrite::ProducerRules;
+pub use types::{
+    feed_root, FeedKind, LogEntry, LogLevel, Overflow, ProducerMeta, ProducerState, ProducerStatus,
+    ProducerUpdate, ReplayCommand, ReplayTarget, Speed, StateSeverity,
+};
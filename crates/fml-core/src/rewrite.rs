@@ -0,0 +1,207 @@
+//! Producer rewrite rules.
+//!
+//! Feeds name producers after what they follow, so the replicas of one
+//! service arrive as `shop/api/api-7f9b4d-x2k9p`, `shop/api-2` or
+//! `myapp_api_1`, and the names change with every rollout. [`ProducerRules`]
+//! map those names to the logical producer they belong to: each rule is a
+//! regex replaced over the whole producer path, applied in order, after which
+//! a segment repeating its parent (`shop/api/api`) is folded into it.
+//!
+//! The built-in rules strip ReplicaSet pod hashes from any feed and Compose
+//! replica indices from Docker and stdin producers.
+
+use crate::config::ProducersConfig;
+use crate::types::FeedKind;
+use regex::Regex;
+
+/// Characters Kubernetes uses for pod-template hashes and pod suffixes.
+const HASH: &str = "[bcdfghjklmnpqrstvwxz2456789]";
+
+/// Ordered producer rewrite rules. The default rewrites nothing.
+#[derive(Debug, Clone, Default)]
+pub struct ProducerRules {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    source: Option<FeedKind>,
+    pattern: Regex,
+    replace: String,
+}
+
+impl ProducerRules {
+    /// ReplicaSet hashes and Compose indices.
+    pub fn builtin() -> Self {
+        let replica_set = format!(r"(^|/)([a-z0-9][-a-z0-9.]*?)-{HASH}{{6,10}}-{HASH}{{5}}(/|$)");
+        let compose_v1 = r"^([a-z0-9][a-z0-9-]*)_([a-z0-9][a-z0-9_.-]*?)_\d+$";
+        Self::default()
+            .with_rule(None, &replica_set, "$1$2$3")
+            .and_then(|r| r.with_rule(Some(FeedKind::Docker), compose_v1, "$1/$2"))
+            .and_then(|r| r.with_rule(Some(FeedKind::Docker), r"^([^/]+/[^/]+?)-\d+$", "$1"))
+            .and_then(|r| r.with_rule(Some(FeedKind::Stdin), compose_v1, "$1/$2"))
+            .and_then(|r| r.with_rule(Some(FeedKind::Stdin), r"^([^/]+?)-\d+$", "$1"))
+            .expect("built-in rules compile")
+    }
+
+    /// The `[producers]` rules, then the built-in ones unless turned off.
+    pub fn from_config(config: &ProducersConfig) -> Result<Self, String> {
+        let mut rules = Self::default();
+        for rule in &config.rules {
+            let source = match &rule.source {
+                Some(name) => Some(
+                    name.parse::<FeedKind>()
+                        .map_err(|()| format!("`{name}` is not a feed kind"))?,
+                ),
+                None => None,
+            };
+            rules = rules
+                .with_rule(source, &rule.pattern, &rule.replace)
+                .map_err(|e| format!("`{}`: {e}", rule.pattern))?;
+        }
+        if config.builtin {
+            rules.rules.extend(Self::builtin().rules);
+        }
+        Ok(rules)
+    }
+
+    /// Add a rule replacing `pattern` with `replace` in producers of
+    /// `source`, or of every feed when `None`.
+    pub fn with_rule(
+        mut self,
+        source: Option<FeedKind>,
+        pattern: &str,
+        replace: &str,
+    ) -> Result<Self, regex::Error> {
+        self.rules.push(Rule {
+            source,
+            pattern: Regex::new(pattern)?,
+            replace: replace.to_string(),
+        });
+        Ok(self)
+    }
+
+    /// The logical producer for `producer` from a `source` feed, or `None`
+    /// when no rule changes it.
+    pub fn rewrite(&self, source: FeedKind, producer: &str) -> Option<String> {
+        let mut name = producer.to_string();
+        for rule in &self.rules {
+            if rule.source.is_none_or(|s| s == source) {
+                if let std::borrow::Cow::Owned(changed) =
+                    rule.pattern.replace_all(&name, rule.replace.as_str())
+                {
+                    name = changed;
+                }
+            }
+        }
+        if name == producer {
+            return None;
+        }
+        let mut segments: Vec<&str> = Vec::new();
+        for segment in name.split('/').filter(|s| !s.is_empty()) {
+            if segments.last() != Some(&segment) {
+                segments.push(segment);
+            }
+        }
+        let name = segments.join("/");
+        (!name.is_empty() && name != producer).then_some(name)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProducerRuleConfig;
+
+    fn builtin(source: FeedKind, producer: &str) -> Option<String> {
+        ProducerRules::builtin().rewrite(source, producer)
+    }
+
+    #[test]
+    fn replica_set_pods_fold_into_their_workload() {
+        let k8s = FeedKind::Kubernetes;
+        assert_eq!(
+            builtin(k8s, "shop/api/api-7f9b4d-x2k9p").as_deref(),
+            Some("shop/api")
+        );
+        assert_eq!(
+            builtin(k8s, "shop/api/api-7d9f8b6c5d-x2x4q/sidecar").as_deref(),
+            Some("shop/api/sidecar")
+        );
+        assert_eq!(
+            builtin(FeedKind::Stdin, "api-7d9f8b6c5d-x2x4q").as_deref(),
+            Some("api")
+        );
+        assert_eq!(builtin(k8s, "shop/db/db-0"), None, "StatefulSet names stay");
+        assert_eq!(builtin(k8s, "shop/web/web-stage"), None);
+    }
+
+    #[test]
+    fn compose_indices_are_stripped() {
+        assert_eq!(
+            builtin(FeedKind::Docker, "shop/api-2").as_deref(),
+            Some("shop/api")
+        );
+        assert_eq!(
+            builtin(FeedKind::Docker, "myapp_api_1").as_deref(),
+            Some("myapp/api")
+        );
+        assert_eq!(builtin(FeedKind::Stdin, "api-1").as_deref(), Some("api"));
+        assert_eq!(builtin(FeedKind::Docker, "shop/api"), None);
+        assert_eq!(builtin(FeedKind::File, "shop/api-2"), None, "not a replica");
+    }
+
+    #[test]
+    fn configured_rules_run_before_the_builtin_ones() {
+        let config = ProducersConfig {
+            builtin: true,
+            rules: vec![ProducerRuleConfig {
+                pattern: "-canary$".into(),
+                replace: String::new(),
+                source: Some("docker".into()),
+            }],
+        };
+        let rules = ProducerRules::from_config(&config).unwrap();
+        assert_eq!(
+            rules
+                .rewrite(FeedKind::Docker, "shop/api-2-canary")
+                .as_deref(),
+            Some("shop/api")
+        );
+        assert_eq!(rules.rewrite(FeedKind::Http, "api-canary"), None);
+
+        let off = ProducersConfig {
+            builtin: false,
+            ..config.clone()
+        };
+        let rules = ProducerRules::from_config(&off).unwrap();
+        assert_eq!(
+            rules
+                .rewrite(FeedKind::Docker, "shop/api-2-canary")
+                .as_deref(),
+            Some("shop/api-2")
+        );
+    }
+
+    #[test]
+    fn bad_rules_are_reported() {
+        let rule = |pattern: &str, source: Option<&str>| ProducersConfig {
+            builtin: false,
+            rules: vec![ProducerRuleConfig {
+                pattern: pattern.into(),
+                replace: String::new(),
+                source: source.map(Into::into),
+            }],
+        };
+        assert!(ProducerRules::from_config(&rule("(", None))
+            .unwrap_err()
+            .starts_with("`(`"));
+        assert!(ProducerRules::from_config(&rule("x", Some("nope")))
+            .unwrap_err()
+            .contains("not a feed kind"));
+    }
+}
@@ -1,8 +1,11 @@
 //! Core types for fml-core — Feed Me Logs.
 //!
 //! This module defines the fundamental data structures shared across all
-//! architectural layers: the normalised [`LogEntry`], its [`LogLevel`], and
-//! the [`FeedKind`] discriminant.
+//! architectural layers: the normalised [`LogEntry`], its [`LogLevel`], the
+//! [`FeedKind`] discriminant, the [`ProducerUpdate`] lifecycle events feeds
+//! report alongside their entries, and the [`ProducerMeta`] describing the
+//! state of the thing behind each producer, and the [`ReplayCommand`]s the
+//! TUI sends a replay feed.
 
 /// A normalised log entry produced by the ingestor and stored in the ring buffer.
 ///
@@ -23,6 +26,10 @@ pub struct LogEntry {
     pub level: Option<LogLevel>,
     /// Feed type that produced this entry.
     pub source: FeedKind,
+    /// Which instance of the feed produced this entry, when several run at
+    /// once (e.g. one Docker feed per engine). `None` for a feed's only
+    /// instance.
+    pub instance: Option<String>,
     /// Producer name (pod, container, filename, …).
     pub producer: String,
     /// Structured fields extracted during normalisation. Keys are lowercase.
@@ -36,6 +43,14 @@ pub struct LogEntry {
     pub message: Option<String>,
 }
 
+impl LogEntry {
+    /// Name of the producer tree root this entry belongs under; see
+    /// [`feed_root`].
+    pub fn feed_root(&self) -> String {
+        feed_root(self.source, self.instance.as_deref())
+    }
+}
+
 /// Log severity level, normalised across all feed types.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum LogLevel {
@@ -60,6 +75,27 @@ impl std::fmt::Display for LogLevel {
     }
 }
 
+impl std::str::FromStr for LogLevel {
+    type Err = ();
+
+    /// Parse a level name case-insensitively, accepting the common aliases
+    /// (`warning`, `err`, `critical`, `notice`, …) used by logging libraries
+    /// and syslog.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "trace" | "trc" => Ok(LogLevel::Trace),
+            "debug" | "dbg" => Ok(LogLevel::Debug),
+            "info" | "inf" | "information" | "informational" | "notice" => Ok(LogLevel::Info),
+            "warn" | "wrn" | "warning" => Ok(LogLevel::Warn),
+            "error" | "err" | "eror" => Ok(LogLevel::Error),
+            "fatal" | "crit" | "critical" | "panic" | "alert" | "emerg" | "emergency" => {
+                Ok(LogLevel::Fatal)
+            }
+            _ => Err(()),
+        }
+    }
+}
+
 /// Which feed produced a log entry.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum FeedKind {
@@ -67,6 +103,15 @@ pub enum FeedKind {
     Docker,
     File,
     Stdin,
+    Journald,
+    Exec,
+    Syslog,
+    Http,
+    Otlp,
+    Replay,
+    Demo,
+    Pipe,
+    Forward,
 }
 
 impl std::fmt::Display for FeedKind {
@@ -76,6 +121,419 @@ impl std::fmt::Display for FeedKind {
             FeedKind::Docker => write!(f, "docker"),
             FeedKind::File => write!(f, "file"),
             FeedKind::Stdin => write!(f, "stdin"),
+            FeedKind::Journald => write!(f, "journald"),
+            FeedKind::Exec => write!(f, "exec"),
+            FeedKind::Syslog => write!(f, "syslog"),
+            FeedKind::Http => write!(f, "http"),
+            FeedKind::Otlp => write!(f, "otlp"),
+            FeedKind::Replay => write!(f, "replay"),
+            FeedKind::Demo => write!(f, "demo"),
+            FeedKind::Pipe => write!(f, "pipe"),
+            FeedKind::Forward => write!(f, "forward"),
+        }
+    }
+}
+
+impl std::str::FromStr for FeedKind {
+    type Err = ();
+
+    /// Parse the name [`Display`](std::fmt::Display) writes.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "kubernetes" => Ok(FeedKind::Kubernetes),
+            "docker" => Ok(FeedKind::Docker),
+            "file" => Ok(FeedKind::File),
+            "stdin" => Ok(FeedKind::Stdin),
+            "journald" => Ok(FeedKind::Journald),
+            "exec" => Ok(FeedKind::Exec),
+            "syslog" => Ok(FeedKind::Syslog),
+            "http" => Ok(FeedKind::Http),
+            "otlp" => Ok(FeedKind::Otlp),
+            "replay" => Ok(FeedKind::Replay),
+            "demo" => Ok(FeedKind::Demo),
+            "pipe" => Ok(FeedKind::Pipe),
+            "forward" => Ok(FeedKind::Forward),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Lifecycle state of a single producer, as reported by its feed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProducerStatus {
+    /// Opening the producer's stream.
+    Connecting,
+    /// Connected and delivering lines.
+    Streaming,
+    /// Waiting before the `attempt`-th consecutive reconnect or restart.
+    Backoff { attempt: u32 },
+    /// The producer's stream has finished. `code` is the process exit status
+    /// when the producer is a process, `None` otherwise (or when killed by a
+    /// signal).
+    Ended { code: Option<i32> },
+    /// The producer's container has restarted `count` times in total. A
+    /// marker rather than a state: it does not replace the last lifecycle
+    /// status.
+    Restarted { count: u32 },
+    /// The producer no longer exists (e.g. its container was deleted) and
+    /// will not come back.
+    Removed,
+    /// Its lines are held back until it is resumed; the stream stays open.
+    Paused,
+    /// Its lines are discarded until it is started again.
+    Stopped,
+    /// The feed failed and gave up.
+    Error { message: String },
+}
+
+impl std::fmt::Display for ProducerStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProducerStatus::Connecting => write!(f, "connecting"),
+            ProducerStatus::Streaming => write!(f, "streaming"),
+            ProducerStatus::Backoff { attempt } => write!(f, "backoff({attempt})"),
+            ProducerStatus::Ended { code: Some(code) } => write!(f, "exited({code})"),
+            ProducerStatus::Ended { code: None } => write!(f, "ended"),
+            ProducerStatus::Restarted { count } => write!(f, "restarted({count})"),
+            ProducerStatus::Removed => write!(f, "removed"),
+            ProducerStatus::Paused => write!(f, "paused"),
+            ProducerStatus::Stopped => write!(f, "stopped"),
+            ProducerStatus::Error { message } => write!(f, "error: {message}"),
+        }
+    }
+}
+
+/// A status change for one producer. `source` and `producer` identify the node
+/// the same way as on [`LogEntry`]; `producer` may name an interior tree node
+/// (e.g. an exec command whose `stdout` / `stderr` are the leaves).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProducerUpdate {
+    pub source: FeedKind,
+    pub instance: Option<String>,
+    pub producer: String,
+    pub status: ProducerStatus,
+}
+
+impl ProducerUpdate {
+    /// Name of the producer tree root this update belongs under; see
+    /// [`feed_root`].
+    pub fn feed_root(&self) -> String {
+        feed_root(self.source, self.instance.as_deref())
+    }
+}
+
+/// What the platform says about the object behind a producer: a pod's
+/// container, a Docker container, a file. Where [`ProducerStatus`] tracks
+/// fml's own stream, this tracks the thing being streamed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProducerState {
+    /// Up, with no health check to say more.
+    Running,
+    /// Up, and its health check is still in its start period.
+    Starting,
+    /// Up, and its health check passes.
+    Healthy,
+    /// Up, but its health check fails.
+    Unhealthy,
+    /// Not running yet or between restarts, with the platform's reason
+    /// (`ContainerCreating`, `CrashLoopBackOff`, `ImagePullBackOff`).
+    Waiting { reason: String },
+    /// Stopped. `reason` is the platform's explanation when it gives one
+    /// (`OOMKilled`, `Error`, `Completed`).
+    Terminated {
+        reason: Option<String>,
+        code: Option<i32>,
+    },
+    /// The file was rotated away and a new one took its place.
+    Rotated,
+    /// The file was deleted.
+    Deleted,
+}
+
+/// How worrying a [`ProducerState`] is; the TUI colours states by it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StateSeverity {
+    Ok,
+    Warn,
+    Error,
+}
+
+impl ProducerState {
+    pub fn severity(&self) -> StateSeverity {
+        match self {
+            ProducerState::Running | ProducerState::Healthy => StateSeverity::Ok,
+            ProducerState::Starting | ProducerState::Rotated => StateSeverity::Warn,
+            ProducerState::Waiting { reason } => match reason.as_str() {
+                "ContainerCreating" | "PodInitializing" => StateSeverity::Warn,
+                _ => StateSeverity::Error,
+            },
+            ProducerState::Terminated { reason, code } => {
+                let clean = matches!(reason.as_deref(), None | Some("Completed"));
+                if clean && *code == Some(0) {
+                    StateSeverity::Ok
+                } else {
+                    StateSeverity::Error
+                }
+            }
+            ProducerState::Unhealthy | ProducerState::Deleted => StateSeverity::Error,
+        }
+    }
+}
+
+impl std::fmt::Display for ProducerState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProducerState::Running => write!(f, "running"),
+            ProducerState::Starting => write!(f, "starting"),
+            ProducerState::Healthy => write!(f, "healthy"),
+            ProducerState::Unhealthy => write!(f, "unhealthy"),
+            ProducerState::Waiting { reason } => write!(f, "{reason}"),
+            ProducerState::Terminated {
+                reason: Some(reason),
+                ..
+            } => write!(f, "{reason}"),
+            ProducerState::Terminated {
+                reason: None,
+                code: Some(code),
+            } => write!(f, "exited({code})"),
+            ProducerState::Terminated {
+                reason: None,
+                code: None,
+            } => write!(f, "exited"),
+            ProducerState::Rotated => write!(f, "rotated"),
+            ProducerState::Deleted => write!(f, "deleted"),
         }
     }
 }
+
+/// The latest [`ProducerState`] of one producer, plus facts worth searching
+/// on (`restarts`, `exit_code`, `last_reason`, …). Sent by feeds on their
+/// metadata channel whenever either changes; each message replaces the last.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProducerMeta {
+    pub source: FeedKind,
+    pub instance: Option<String>,
+    pub producer: String,
+    pub state: ProducerState,
+    pub fields: std::collections::BTreeMap<String, String>,
+}
+
+impl ProducerMeta {
+    /// Name of the producer tree root this producer belongs under; see
+    /// [`feed_root`].
+    pub fn feed_root(&self) -> String {
+        feed_root(self.source, self.instance.as_deref())
+    }
+
+    /// The state and fields as searchable key-value pairs, state first under
+    /// the key `state`.
+    pub fn searchable(&self) -> impl Iterator<Item = (&str, String)> {
+        std::iter::once(("state", self.state.to_string())).chain(
+            self.fields
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.clone())),
+        )
+    }
+}
+
+/// The producer tree root for a feed: the feed kind (`docker`), or
+/// `kind@instance` (`docker@podman`) for one of several instances.
+pub fn feed_root(source: FeedKind, instance: Option<&str>) -> String {
+    match instance {
+        Some(instance) => format!("{source}@{instance}"),
+        None => source.to_string(),
+    }
+}
+
+/// How fast a replay runs relative to the original pace: `1x` is real time,
+/// `10x` ten times faster. Kept in thousandths so commands stay comparable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Speed(u32);
+
+impl Speed {
+    pub const REALTIME: Speed = Speed(1000);
+
+    /// The multiplier as a float (`10x` is `10.0`).
+    pub fn factor(self) -> f64 {
+        f64::from(self.0) / 1000.0
+    }
+}
+
+impl Default for Speed {
+    fn default() -> Self {
+        Speed::REALTIME
+    }
+}
+
+impl std::str::FromStr for Speed {
+    type Err = String;
+
+    /// A positive multiplier with an optional `x`: `10x`, `0.5x`, `2`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = s.trim();
+        let number = number.strip_suffix(['x', 'X']).unwrap_or(number).trim_end();
+        let factor: f64 = number
+            .parse()
+            .map_err(|_| format!("`{s}` is not a speed like 10x or 0.5x"))?;
+        let thousandths = (factor * 1000.0).round();
+        if !(1.0..=f64::from(u32::MAX)).contains(&thousandths) {
+            return Err(format!("speed `{s}` is out of range (0.001x and up)"));
+        }
+        Ok(Speed(thousandths as u32))
+    }
+}
+
+impl std::fmt::Display for Speed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x", self.factor())
+    }
+}
+
+/// What a feed does with new lines while the UI cannot keep up and its
+/// buffer is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Stop reading the feed until there is room; nothing is lost, but the
+    /// source may back up.
+    #[default]
+    Block,
+    /// Keep reading and drop the oldest buffered lines.
+    DropOldest,
+    /// Keep reading and keep one line in ten from each producer, dropping
+    /// the oldest buffered line to make room.
+    Sample,
+}
+
+impl std::str::FromStr for Overflow {
+    type Err = String;
+
+    /// `block`, `drop-oldest` or `sample`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "block" => Ok(Overflow::Block),
+            "drop-oldest" => Ok(Overflow::DropOldest),
+            "sample" => Ok(Overflow::Sample),
+            other => Err(format!(
+                "`{other}` is not an overflow policy (block, drop-oldest, sample)"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Overflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Overflow::Block => write!(f, "block"),
+            Overflow::DropOldest => write!(f, "drop-oldest"),
+            Overflow::Sample => write!(f, "sample"),
+        }
+    }
+}
+
+/// Where a replay jump lands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayTarget {
+    /// An absolute instant.
+    At(chrono::DateTime<chrono::Utc>),
+    /// The next time the replayed logs reach this time of day (UTC).
+    TimeOfDay(chrono::NaiveTime),
+}
+
+impl std::str::FromStr for ReplayTarget {
+    type Err = String;
+
+    /// RFC 3339 (`2024-01-15T10:32:00Z`), `2024-01-15 10:32:00`, or a time of
+    /// day (`10:32`, `10:32:05.250`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use chrono::{DateTime, NaiveDateTime, NaiveTime, Utc};
+        let s = s.trim();
+        if let Ok(ts) = DateTime::parse_from_rfc3339(s) {
+            return Ok(ReplayTarget::At(ts.with_timezone(&Utc)));
+        }
+        if let Ok(ts) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f") {
+            return Ok(ReplayTarget::At(ts.and_utc()));
+        }
+        ["%H:%M:%S%.f", "%H:%M"]
+            .iter()
+            .find_map(|format| NaiveTime::parse_from_str(s, format).ok())
+            .map(ReplayTarget::TimeOfDay)
+            .ok_or_else(|| format!("`{s}` is not a time like 10:32:05 or 2024-01-15T10:32:05Z"))
+    }
+}
+
+/// Control message for a running replay feed, sent from the TUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayCommand {
+    /// Stop the replay clock.
+    Pause,
+    /// Restart the replay clock where it stopped.
+    Resume,
+    /// Change the pace from here on.
+    Speed(Speed),
+    /// Deliver everything up to the target at once and continue from there.
+    /// Targets already played are ignored.
+    Jump(ReplayTarget),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn states_display_platform_reasons_verbatim() {
+        let waiting = ProducerState::Waiting {
+            reason: "CrashLoopBackOff".into(),
+        };
+        assert_eq!(waiting.to_string(), "CrashLoopBackOff");
+        assert_eq!(waiting.severity(), StateSeverity::Error);
+
+        let oom = ProducerState::Terminated {
+            reason: Some("OOMKilled".into()),
+            code: Some(137),
+        };
+        assert_eq!(oom.to_string(), "OOMKilled");
+
+        let exited = ProducerState::Terminated {
+            reason: None,
+            code: Some(0),
+        };
+        assert_eq!(exited.to_string(), "exited(0)");
+        assert_eq!(exited.severity(), StateSeverity::Ok);
+        assert_eq!(ProducerState::Unhealthy.severity(), StateSeverity::Error);
+        let creating = ProducerState::Waiting {
+            reason: "ContainerCreating".into(),
+        };
+        assert_eq!(creating.severity(), StateSeverity::Warn);
+    }
+
+    #[test]
+    fn speeds_parse_with_or_without_x() {
+        assert_eq!("10x".parse::<Speed>().unwrap().factor(), 10.0);
+        assert_eq!("0.5X".parse::<Speed>().unwrap().to_string(), "0.5x");
+        assert_eq!("2".parse::<Speed>().unwrap(), Speed(2000));
+        assert!("0x".parse::<Speed>().is_err());
+        assert!("-1x".parse::<Speed>().is_err());
+        assert!("fast".parse::<Speed>().is_err());
+    }
+
+    #[test]
+    fn overflow_policies_round_trip() {
+        for policy in [Overflow::Block, Overflow::DropOldest, Overflow::Sample] {
+            assert_eq!(policy.to_string().parse::<Overflow>(), Ok(policy));
+        }
+        assert!("drop".parse::<Overflow>().is_err());
+    }
+
+    #[test]
+    fn replay_targets_parse() {
+        let at = "2024-01-15T10:32:05Z".parse::<ReplayTarget>().unwrap();
+        assert_eq!(at, "2024-01-15 10:32:05".parse::<ReplayTarget>().unwrap());
+        assert_eq!(
+            "10:32".parse::<ReplayTarget>(),
+            Ok(ReplayTarget::TimeOfDay(
+                chrono::NaiveTime::from_hms_opt(10, 32, 0).unwrap()
+            ))
+        );
+        assert!("yesterday".parse::<ReplayTarget>().is_err());
+    }
+}
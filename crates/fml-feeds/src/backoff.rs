@@ -0,0 +1,77 @@
+//! Exponential reconnect / restart backoff shared by the feeds.
+//!
+//! Delays double from `initial` up to `max` (30 s by default) and reset once a
+//! connection proves healthy.
+
+use std::time::Duration;
+
+/// Exponential backoff state for one producer.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max: max.max(initial),
+            attempt: 0,
+        }
+    }
+
+    /// Advance to the next attempt and return how long to wait before it.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self
+            .initial
+            .saturating_mul(2u32.saturating_pow(self.attempt))
+            .min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+        delay
+    }
+
+    /// Number of consecutive retries so far.
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// The upper bound on any single delay.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Forget previous failures; the next delay is `initial` again.
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1), Duration::from_secs(30))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_double_and_cap() {
+        let mut b = Backoff::default();
+        let delays: Vec<u64> = (0..7).map(|_| b.next_delay().as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 30, 30]);
+        assert_eq!(b.attempt(), 7);
+    }
+
+    #[test]
+    fn reset_restarts_from_initial() {
+        let mut b = Backoff::default();
+        b.next_delay();
+        b.next_delay();
+        b.reset();
+        assert_eq!(b.next_delay(), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,1478 @@
+//! Producer control for any feed.
+//!
+//! A [`FeedControl`] sits between one feed and the channels it would write
+//! to. The feed sends its entries and status updates to a [`FeedSenders`];
+//! the [`Relay`] task counts them per producer and passes them on, holding
+//! back or discarding the lines of paused and stopped producers. The UI
+//! drives it through [`FeedHandle`] without knowing the feed's kind.
+//!
+//! Pausing every root the feed owns stops the relay reading the feed's
+//! entries at all, so the feed blocks on its next send with its connections
+//! left open. Pausing only some producers holds their lines, up to
+//! [`PAUSED_BACKLOG`], until they are resumed. Stopping closes the producer's
+//! stream: feeds that follow one stream per producer are told through their
+//! [`Signals`] and reopen it, from the current time, once started. Lines that
+//! still arrive for a stopped producer, including every line of push feeds,
+//! which have no stream of their own to close, are discarded.
+//!
+//! When the UI falls behind, lines wait in the relay. Once `buffer` are
+//! waiting the feed's [`Overflow`] policy applies: block the feed, drop the
+//! oldest waiting line, or keep one line in [`SAMPLE_EVERY`] per producer.
+//! Lines lost either way are counted against their producer as `dropped`,
+//! lines that had to wait as `delayed`.
+//!
+//! Reconnecting needs the feed's help too: the same [`Signals`] ask a
+//! follower to reopen its stream at once. Followers register the streams they
+//! own by asking [`Signals::stopped`] about them, so each producer's
+//! [`ProducerHealth`] can say whether stop and reconnect reach a stream.
+//!
+//! Push feeds answer their senders only once a batch is in the UI's store.
+//! They announce each batch through [`Deliveries`]; the relay follows its
+//! lines to the UI, which reports what it has stored per feed root through
+//! [`FeedHandle::stored`], and the batch's [`Receipt`] settles when all of
+//! them are in, or as soon as one is lost to the overflow policy.
+//!
+//! With [`ProducerRules`] the relay renames producers on the way through, so
+//! replicas share one logical producer: one tree node, one set of counters,
+//! paused and stopped as a unit. Each line keeps its original producer in a
+//! `replica` field, and one replica ending is not shown while another is
+//! still live. Metadata is kept per replica: the logical node shows the most
+//! worrying replica's state, and each line is stamped with its own replica's.
+
+use fml_core::handle::covers;
+use fml_core::{
+    FeedHandle, FeedKind, LogEntry, Overflow, ProducerCounters, ProducerHealth, ProducerMeta,
+    ProducerRules, ProducerStatus, ProducerUpdate,
+};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+/// Capacity of the channels between a feed and its relay.
+pub const RELAY_CAPACITY: usize = 1024;
+
+/// Lines held for paused producers before the oldest are dropped.
+pub const PAUSED_BACKLOG: usize = 10_000;
+
+/// Lines waiting for the UI before the overflow policy applies.
+pub const DEFAULT_BUFFER: usize = 10_000;
+
+/// Under [`Overflow::Sample`], one line in this many is kept per producer.
+pub const SAMPLE_EVERY: u64 = 10;
+
+/// Signals a slow follower can miss before older ones are lost.
+const SIGNAL_CAPACITY: usize = 16;
+
+/// [`FeedHandle`] for one feed, backed by its [`Relay`]. Cheap to clone.
+#[derive(Clone)]
+pub struct FeedControl {
+    state: Arc<Mutex<State>>,
+    changed: mpsc::UnboundedSender<String>,
+    signals: broadcast::Sender<Request>,
+}
+
+/// What [`FeedControl`] broadcasts to its feed's followers.
+#[derive(Debug, Clone)]
+enum Request {
+    /// Reopen the streams under this path.
+    Reconnect(String),
+    /// Producers were stopped or started; each follower checks its own.
+    Control,
+}
+
+/// The ends a controlled feed writes to in place of the shared channels.
+pub struct FeedSenders {
+    pub entries: mpsc::Sender<LogEntry>,
+    pub updates: mpsc::Sender<ProducerUpdate>,
+    pub metadata: mpsc::Sender<ProducerMeta>,
+}
+
+#[derive(Default)]
+struct State {
+    rules: ProducerRules,
+    producers: BTreeMap<String, Producer>,
+    /// Source and instance of each feed root seen, to address updates.
+    roots: HashMap<String, (FeedKind, Option<String>)>,
+    paused: HashSet<String>,
+    stopped: HashSet<String>,
+    /// Tree ids of the streams the feed's followers can close and reopen,
+    /// as they asked [`Signals`] about them.
+    followed: HashSet<String>,
+    /// Push batches not yet settled, by id.
+    batches: BTreeMap<u64, Batch>,
+    /// Batches with lines the relay has still to read, in the order the
+    /// feed sends them.
+    unread: VecDeque<u64>,
+    next_batch: u64,
+    /// Lines passed to the UI per feed root, and lines it has stored.
+    handed: HashMap<String, u64>,
+    stored: HashMap<String, u64>,
+}
+
+/// One push batch on its way to the store.
+struct Batch {
+    /// Lines the relay has still to read from the feed.
+    unread: usize,
+    /// Lines read but not yet passed to the UI or lost.
+    pending: usize,
+    /// Lines lost to the overflow policy or a full pause backlog.
+    dropped: usize,
+    /// Per feed root, the UI's stored count that covers the batch.
+    stored_at: HashMap<String, u64>,
+    done: oneshot::Sender<Delivery>,
+}
+
+/// What became of a push batch, from [`Receipt::stored`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// Every line is in the store, or was discarded because its producer is
+    /// stopped.
+    Stored,
+    /// This many lines were lost because the UI could not keep up.
+    Dropped(usize),
+    /// The relay went away before the batch settled.
+    Closed,
+}
+
+/// What happened to one line of a push batch.
+enum Fate {
+    /// Passed to the UI.
+    Handed,
+    /// Discarded for a stopped producer.
+    Discarded,
+    Dropped,
+}
+
+#[derive(Default)]
+struct Producer {
+    status: Option<ProducerStatus>,
+    counters: ProducerCounters,
+    /// Each tree id the feed reported this producer under, before rules.
+    replicas: BTreeMap<String, Replica>,
+    lag: Option<std::time::Duration>,
+    last_line: Option<chrono::DateTime<chrono::Utc>>,
+    last_error: Option<String>,
+    /// Lines seen while sampling, to pick which to keep.
+    sampled: u64,
+}
+
+#[derive(Default)]
+struct Replica {
+    streamed: bool,
+    live: bool,
+    /// The replica's last reported metadata.
+    meta: Option<ProducerMeta>,
+}
+
+impl Producer {
+    /// Metadata to show for the logical producer: `raw`'s, unless another
+    /// replica is in a more worrying state.
+    fn shown_meta(&self, raw: &str) -> Option<&ProducerMeta> {
+        let own = self.replicas.get(raw)?.meta.as_ref()?;
+        let worst = self
+            .replicas
+            .iter()
+            .filter(|(other, _)| *other != raw)
+            .filter_map(|(_, replica)| replica.meta.as_ref())
+            .max_by_key(|meta| meta.state.severity());
+        match worst {
+            Some(worst) if worst.state.severity() > own.state.severity() => Some(worst),
+            _ => Some(own),
+        }
+    }
+
+    /// Metadata of `raw` when it is one of several replicas reporting any;
+    /// a lone producer's lines are stamped by the UI.
+    fn replica_meta(&self, raw: &str) -> Option<&ProducerMeta> {
+        let reporting = self.replicas.values().filter(|r| r.meta.is_some()).count();
+        if reporting < 2 {
+            return None;
+        }
+        self.replicas.get(raw)?.meta.as_ref()
+    }
+}
+
+impl State {
+    fn owns(&self, path: &str) -> bool {
+        let root = path.split_once('/').map_or(path, |(root, _)| root);
+        path.is_empty() || self.roots.contains_key(root)
+    }
+
+    fn paused(&self, id: &str) -> bool {
+        self.paused.iter().any(|path| covers(path, id))
+    }
+
+    fn stopped(&self, id: &str) -> bool {
+        self.stopped.iter().any(|path| covers(path, id))
+    }
+
+    /// Every root is paused, so nothing the feed sends can be delivered.
+    fn blocked(&self) -> bool {
+        !self.roots.is_empty() && self.roots.keys().all(|root| self.paused(root))
+    }
+
+    fn note(&mut self, source: FeedKind, instance: &Option<String>) -> String {
+        let root = fml_core::feed_root(source, instance.as_deref());
+        if !self.roots.contains_key(&root) {
+            self.roots.insert(root.clone(), (source, instance.clone()));
+        }
+        root
+    }
+
+    /// Whether the producer a feed follows as tree id `raw` is stopped,
+    /// under its own name or the logical producer it is grouped into.
+    fn stopped_raw(&self, raw: &str) -> bool {
+        self.stopped(raw) || self.logical(raw).is_some_and(|id| self.stopped(&id))
+    }
+
+    /// Tree id of the logical producer the feed's producer `raw` is grouped
+    /// into, if a rule renames it.
+    fn logical(&self, raw: &str) -> Option<String> {
+        let (root, producer) = raw.split_once('/').unwrap_or((raw, ""));
+        let (source, _) = self.roots.get(root)?;
+        let logical = self.rules.rewrite(*source, producer)?;
+        Some(tree_id(root, &logical))
+    }
+
+    /// Whether stopping `path` closes a followed stream, rather than only
+    /// discarding lines.
+    fn closes(&self, path: &str) -> bool {
+        self.followed
+            .iter()
+            .any(|raw| covers(path, raw) || self.logical(raw).is_some_and(|id| covers(path, &id)))
+    }
+
+    /// Whether reconnecting `path` reopens a followed stream: one under it,
+    /// or the one stream it is carried on.
+    fn reopens(&self, path: &str) -> bool {
+        self.closes(path) || self.followed.iter().any(|raw| covers(raw, path))
+    }
+
+    /// Rename `producer` to its logical producer, returning the original
+    /// name if a rule changed it.
+    fn rename(&self, source: FeedKind, producer: &mut String) -> Option<String> {
+        let logical = self.rules.rewrite(source, producer)?;
+        Some(std::mem::replace(producer, logical))
+    }
+
+    /// The status the UI should show for `id`.
+    fn shown(&self, id: &str) -> ProducerStatus {
+        if self.stopped(id) {
+            ProducerStatus::Stopped
+        } else if self.paused(id) {
+            ProducerStatus::Paused
+        } else {
+            self.producers
+                .get(id)
+                .and_then(|p| p.status.clone())
+                .unwrap_or(ProducerStatus::Streaming)
+        }
+    }
+
+    /// Updates restating every node under `path` after a change to it.
+    fn restate(&self, path: &str) -> Vec<ProducerUpdate> {
+        let mut ids: Vec<&str> = Vec::new();
+        if path.is_empty() {
+            ids.extend(self.roots.keys().map(String::as_str));
+        } else {
+            ids.push(path);
+        }
+        ids.extend(
+            self.producers
+                .keys()
+                .map(String::as_str)
+                .filter(|id| covers(path, id) && *id != path),
+        );
+        ids.iter()
+            .filter_map(|id| {
+                let (root, producer) = id.split_once('/').unwrap_or((id, ""));
+                let (source, instance) = self.roots.get(root)?;
+                Some(ProducerUpdate {
+                    source: *source,
+                    instance: instance.clone(),
+                    producer: producer.to_string(),
+                    status: self.shown(id),
+                })
+            })
+            .collect()
+    }
+}
+
+impl State {
+    /// Attribute the next line read from the feed to the batch it belongs
+    /// to, if any.
+    fn read_batch(&mut self) -> Option<u64> {
+        let id = *self.unread.front()?;
+        let batch = self.batches.get_mut(&id)?;
+        batch.unread -= 1;
+        batch.pending += 1;
+        if batch.unread == 0 {
+            self.unread.pop_front();
+        }
+        Some(id)
+    }
+
+    /// Count a line with tree id `id` as passed to the UI and settle it.
+    fn hand(&mut self, id: &str, batch: Option<u64>) {
+        let root = id.split_once('/').map_or(id, |(root, _)| root);
+        let handed = match self.handed.get_mut(root) {
+            Some(handed) => handed,
+            None => self.handed.entry(root.to_string()).or_default(),
+        };
+        *handed += 1;
+        let at = *handed;
+        if let Some(batch) = batch.and_then(|b| self.batches.get_mut(&b)) {
+            batch.stored_at.insert(root.to_string(), at);
+        }
+        self.settle(batch, Fate::Handed);
+    }
+
+    /// Record what became of one line of `batch`.
+    fn settle(&mut self, batch: Option<u64>, fate: Fate) {
+        let Some(id) = batch else {
+            return;
+        };
+        let Some(batch) = self.batches.get_mut(&id) else {
+            return;
+        };
+        batch.pending -= 1;
+        match fate {
+            Fate::Handed | Fate::Discarded => {}
+            Fate::Dropped => batch.dropped += 1,
+        }
+        self.finish(id);
+    }
+
+    /// Answer batch `id` if every line is accounted for and either one was
+    /// lost or all of them are stored.
+    fn finish(&mut self, id: u64) {
+        let Some(batch) = self.batches.get(&id) else {
+            return;
+        };
+        if batch.unread > 0 || batch.pending > 0 {
+            return;
+        }
+        let delivery = if batch.dropped > 0 {
+            Delivery::Dropped(batch.dropped)
+        } else if batch
+            .stored_at
+            .iter()
+            .all(|(root, at)| self.stored.get(root).is_some_and(|stored| stored >= at))
+        {
+            Delivery::Stored
+        } else {
+            return;
+        };
+        if let Some(batch) = self.batches.remove(&id) {
+            let _ = batch.done.send(delivery);
+        }
+    }
+}
+
+/// Tree id of `producer` under `root`; the root's own id when it is empty.
+pub(crate) fn tree_id(root: &str, producer: &str) -> String {
+    if producer.is_empty() {
+        root.to_string()
+    } else {
+        format!("{root}/{producer}")
+    }
+}
+
+impl FeedControl {
+    /// Put a control in front of `tx` and `updates`. The feed writes to the
+    /// returned [`FeedSenders`]; the [`Relay`] must be spawned to move
+    /// anything along.
+    pub fn attach(
+        tx: mpsc::Sender<LogEntry>,
+        updates: mpsc::Sender<ProducerUpdate>,
+        metadata: mpsc::Sender<ProducerMeta>,
+    ) -> (Self, FeedSenders, Relay) {
+        let (entry_tx, entries) = mpsc::channel(RELAY_CAPACITY);
+        let (update_tx, feed_updates) = mpsc::channel(RELAY_CAPACITY);
+        let (meta_tx, feed_metadata) = mpsc::channel(RELAY_CAPACITY);
+        let (changed_tx, changed) = mpsc::unbounded_channel();
+        let state = Arc::new(Mutex::new(State::default()));
+        let control = FeedControl {
+            state: Arc::clone(&state),
+            changed: changed_tx,
+            signals: broadcast::channel(SIGNAL_CAPACITY).0,
+        };
+        let senders = FeedSenders {
+            entries: entry_tx,
+            updates: update_tx,
+            metadata: meta_tx,
+        };
+        let relay = Relay {
+            state,
+            entries,
+            updates: feed_updates,
+            metadata: feed_metadata,
+            changed,
+            out: updates,
+            meta_out: metadata,
+            held: VecDeque::new(),
+            outbox: Outbox {
+                tx,
+                queue: VecDeque::new(),
+                overflow: Overflow::Block,
+                buffer: DEFAULT_BUFFER,
+            },
+        };
+        (control, senders, relay)
+    }
+
+    /// Where a push feed announces its batches to learn when they are
+    /// stored.
+    pub fn deliveries(&self) -> Deliveries {
+        Deliveries(Some(Arc::clone(&self.state)))
+    }
+
+    /// Stop and reconnect requests for a feed's followers to act on.
+    pub fn signals(&self) -> Signals {
+        Signals(Some(Listener {
+            rx: self.signals.subscribe(),
+            state: Arc::clone(&self.state),
+        }))
+    }
+
+    /// Apply a change under `path`, returning whether anything changed.
+    fn change(&self, path: &str, apply: impl FnOnce(&mut State) -> bool) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let changed = state.owns(path) && apply(&mut state);
+        if changed {
+            let _ = self.changed.send(path.to_string());
+        }
+        changed
+    }
+}
+
+impl FeedHandle for FeedControl {
+    fn pause(&self, path: &str) {
+        self.change(path, |state| state.paused.insert(path.to_string()));
+    }
+
+    fn resume(&self, path: &str) {
+        self.change(path, |state| {
+            let before = state.paused.len();
+            state
+                .paused
+                .retain(|p| !covers(path, p) && !covers(p, path));
+            state.paused.len() != before
+        });
+    }
+
+    fn stop(&self, path: &str) {
+        if self.change(path, |state| state.stopped.insert(path.to_string())) {
+            let _ = self.signals.send(Request::Control);
+        }
+    }
+
+    fn start(&self, path: &str) {
+        let started = self.change(path, |state| {
+            let before = state.stopped.len();
+            state
+                .stopped
+                .retain(|p| !covers(path, p) && !covers(p, path));
+            state.stopped.len() != before
+        });
+        if started {
+            let _ = self.signals.send(Request::Control);
+        }
+    }
+
+    fn reconnect(&self, path: &str) {
+        let state = self.state.lock().unwrap();
+        if !state.owns(path) {
+            return;
+        }
+        let _ = self.signals.send(Request::Reconnect(path.to_string()));
+        // Feeds follow replicas under their own names.
+        let replicas = state
+            .producers
+            .iter()
+            .filter(|(id, _)| covers(path, id))
+            .flat_map(|(_, producer)| producer.replicas.keys())
+            .filter(|raw| !covers(path, raw));
+        for raw in replicas {
+            let _ = self.signals.send(Request::Reconnect(raw.clone()));
+        }
+    }
+
+    fn health(&self) -> Vec<ProducerHealth> {
+        let state = self.state.lock().unwrap();
+        state
+            .producers
+            .iter()
+            .map(|(id, producer)| ProducerHealth {
+                path: id.clone(),
+                status: producer.status.clone(),
+                paused: state.paused(id),
+                stopped: state.stopped(id),
+                closes: state.closes(id),
+                reopens: state.reopens(id),
+                counters: producer.counters,
+                lag: producer.lag,
+                last_line: producer.last_line,
+                last_error: producer.last_error.clone(),
+            })
+            .collect()
+    }
+
+    fn stored(&self, root: &str, lines: u64) {
+        let mut state = self.state.lock().unwrap();
+        if !state.roots.contains_key(root) {
+            return;
+        }
+        *state.stored.entry(root.to_string()).or_default() += lines;
+        let waiting: Vec<u64> = state.batches.keys().copied().collect();
+        for id in waiting {
+            state.finish(id);
+        }
+    }
+}
+
+/// What a follower is asked to do with its producer's stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// Reopen the stream now.
+    Reconnect,
+    /// Close the stream until [`Signals::started`].
+    Stop,
+}
+
+/// Stop and reconnect requests for producers' streams, from
+/// [`FeedControl::signals`]. The default never asks.
+#[derive(Default)]
+pub struct Signals(Option<Listener>);
+
+struct Listener {
+    rx: broadcast::Receiver<Request>,
+    state: Arc<Mutex<State>>,
+}
+
+impl Clone for Signals {
+    /// A clone sees requests made after it was taken.
+    fn clone(&self) -> Self {
+        Signals(self.0.as_ref().map(|listener| Listener {
+            rx: listener.rx.resubscribe(),
+            state: Arc::clone(&listener.state),
+        }))
+    }
+}
+
+impl Signals {
+    /// Whether the producer followed as tree id `id` is stopped. Asking also
+    /// tells the control that the feed closes and reopens `id`'s stream, so
+    /// [`ProducerHealth`] can say so.
+    pub fn stopped(&self, id: &str) -> bool {
+        self.0.as_ref().is_some_and(|listener| {
+            let mut state = listener.state.lock().unwrap();
+            if !state.followed.contains(id) {
+                state.followed.insert(id.to_string());
+            }
+            state.stopped_raw(id)
+        })
+    }
+
+    /// Wait until the producer followed as tree id `id` is stopped or a
+    /// reconnect is requested for it, for a producer under it, or for one
+    /// its stream carries (a command's `stdout`). Cancel-safe.
+    pub async fn next(&mut self, id: &str) -> Signal {
+        loop {
+            if self.stopped(id) {
+                return Signal::Stop;
+            }
+            let Some(listener) = self.0.as_mut() else {
+                return std::future::pending().await;
+            };
+            match listener.rx.recv().await {
+                Ok(Request::Reconnect(path)) if covers(&path, id) || covers(id, &path) => {
+                    return Signal::Reconnect
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => self.0 = None,
+            }
+        }
+    }
+
+    /// Wait until the producer followed as tree id `id` is no longer
+    /// stopped. Returns at once if it is not, or if the control is gone.
+    pub async fn started(&mut self, id: &str) {
+        while self.stopped(id) {
+            let Some(listener) = self.0.as_mut() else {
+                return;
+            };
+            if let Err(broadcast::error::RecvError::Closed) = listener.rx.recv().await {
+                self.0 = None;
+            }
+        }
+    }
+
+    /// For feeds that follow many producers in one task: wait for the next
+    /// request for any of them. Returns the path to reconnect, or `None`
+    /// when producers were stopped or started and each should be checked
+    /// with [`stopped`](Self::stopped). Cancel-safe.
+    pub async fn changed(&mut self) -> Option<String> {
+        loop {
+            let Some(listener) = self.0.as_mut() else {
+                return std::future::pending().await;
+            };
+            match listener.rx.recv().await {
+                Ok(Request::Reconnect(path)) => return Some(path),
+                Ok(Request::Control) | Err(broadcast::error::RecvError::Lagged(_)) => return None,
+                Err(broadcast::error::RecvError::Closed) => self.0 = None,
+            }
+        }
+    }
+}
+
+/// Announces a push feed's batches, from [`FeedControl::deliveries`]. The
+/// default has no store to wait for and reports each batch stored at once.
+#[derive(Clone, Default)]
+pub struct Deliveries(Option<Arc<Mutex<State>>>);
+
+impl Deliveries {
+    /// Announce that the next `lines` entries the feed sends are one batch.
+    /// They must follow each other and any batch announced before, with
+    /// nothing else sent in between.
+    pub fn batch(&self, lines: usize) -> Receipt {
+        let Some(state) = &self.0 else {
+            return Receipt(None);
+        };
+        let (done, rx) = oneshot::channel();
+        let mut guard = state.lock().unwrap();
+        let id = guard.next_batch;
+        guard.next_batch += 1;
+        guard.batches.insert(
+            id,
+            Batch {
+                unread: lines,
+                pending: 0,
+                dropped: 0,
+                stored_at: HashMap::new(),
+                done,
+            },
+        );
+        if lines > 0 {
+            guard.unread.push_back(id);
+        }
+        guard.finish(id);
+        Receipt(Some((Arc::clone(state), id, rx)))
+    }
+}
+
+/// The outcome of one announced batch, from [`Deliveries::batch`].
+pub struct Receipt(Option<(Arc<Mutex<State>>, u64, oneshot::Receiver<Delivery>)>);
+
+impl Receipt {
+    /// The last `unsent` lines of the batch will not be sent after all.
+    pub fn abandon(&mut self, unsent: usize) {
+        let Some((state, id, _)) = &self.0 else {
+            return;
+        };
+        let mut state = state.lock().unwrap();
+        let Some(batch) = state.batches.get_mut(id) else {
+            return;
+        };
+        batch.unread -= unsent.min(batch.unread);
+        if batch.unread == 0 {
+            state.unread.retain(|other| other != id);
+        }
+        state.finish(*id);
+    }
+
+    /// Wait until the batch is stored, or some of it is lost.
+    pub async fn stored(self) -> Delivery {
+        match self.0 {
+            Some((_, _, rx)) => rx.await.unwrap_or(Delivery::Closed),
+            None => Delivery::Stored,
+        }
+    }
+}
+
+/// Task moving one feed's output through its [`FeedControl`].
+pub struct Relay {
+    state: Arc<Mutex<State>>,
+    entries: mpsc::Receiver<LogEntry>,
+    updates: mpsc::Receiver<ProducerUpdate>,
+    metadata: mpsc::Receiver<ProducerMeta>,
+    changed: mpsc::UnboundedReceiver<String>,
+    out: mpsc::Sender<ProducerUpdate>,
+    meta_out: mpsc::Sender<ProducerMeta>,
+    /// Lines of paused producers, oldest first.
+    held: VecDeque<Queued>,
+    outbox: Outbox,
+}
+
+/// Lines on their way to the UI, and what to do when they pile up.
+struct Outbox {
+    tx: mpsc::Sender<LogEntry>,
+    queue: VecDeque<Queued>,
+    overflow: Overflow,
+    buffer: usize,
+}
+
+/// A line waiting in the relay, under its producer's tree id and with the
+/// push batch it belongs to.
+struct Queued {
+    id: String,
+    entry: LogEntry,
+    batch: Option<u64>,
+}
+
+impl Outbox {
+    /// Queue `entry` for delivery, applying the overflow policy if the
+    /// buffer is full.
+    fn push(&mut self, state: &mut State, line: Queued) {
+        let waiting = !self.queue.is_empty() || self.tx.capacity() == 0;
+        let full = self.queue.len() >= self.buffer;
+        let producer = state.producers.entry(line.id.clone()).or_default();
+        if waiting {
+            producer.counters.delayed += 1;
+        }
+        if full && self.overflow != Overflow::Block {
+            if self.overflow == Overflow::Sample {
+                let keep = producer.sampled.is_multiple_of(SAMPLE_EVERY);
+                producer.sampled += 1;
+                if !keep {
+                    producer.counters.dropped += 1;
+                    state.settle(line.batch, Fate::Dropped);
+                    return;
+                }
+            }
+            if let Some(oldest) = self.queue.pop_front() {
+                state
+                    .producers
+                    .entry(oldest.id)
+                    .or_default()
+                    .counters
+                    .dropped += 1;
+                state.settle(oldest.batch, Fate::Dropped);
+            }
+        }
+        self.queue.push_back(line);
+    }
+}
+
+impl Relay {
+    /// Apply `overflow` once `buffer` lines are waiting for the UI. The
+    /// default blocks the feed.
+    pub fn with_overflow(mut self, overflow: Overflow, buffer: usize) -> Self {
+        self.outbox.overflow = overflow;
+        self.outbox.buffer = buffer.max(1);
+        self
+    }
+
+    /// Rename producers by `rules` before counting and passing them on. The
+    /// default keeps the feed's names.
+    pub fn with_rules(self, rules: ProducerRules) -> Self {
+        self.state.lock().unwrap().rules = rules;
+        self
+    }
+
+    /// Run until the feed has finished and nothing is waiting or held back,
+    /// or the receiving side goes away.
+    pub async fn run(mut self) {
+        let (mut entries_done, mut updates_done, mut controls_done) = (false, false, false);
+        let mut metadata_done = false;
+        loop {
+            if entries_done
+                && updates_done
+                && metadata_done
+                && self.outbox.queue.is_empty()
+                && (self.held.is_empty() || controls_done)
+            {
+                return;
+            }
+            let blocked = self.state.lock().unwrap().blocked();
+            let room = self.outbox.overflow != Overflow::Block || self.outbox.queue.is_empty();
+            let reading = !entries_done && !blocked && room;
+            let sending = !self.outbox.queue.is_empty();
+            let tx = self.outbox.tx.clone();
+            let delivered = tokio::select! {
+                biased;
+                path = self.changed.recv(), if !controls_done => match path {
+                    Some(path) => self.restate(&path).await,
+                    None => {
+                        controls_done = true;
+                        true
+                    }
+                },
+                update = self.updates.recv(), if !updates_done => match update {
+                    Some(update) => self.update(update).await,
+                    None => {
+                        updates_done = true;
+                        true
+                    }
+                },
+                meta = self.metadata.recv(), if !metadata_done => match meta {
+                    Some(meta) => self.meta(meta).await,
+                    None => {
+                        metadata_done = true;
+                        true
+                    }
+                },
+                permit = tx.reserve(), if sending => match permit {
+                    Ok(permit) => {
+                        let line = self.outbox.queue.pop_front().expect("queue not empty");
+                        let lag = (chrono::Utc::now() - line.entry.ts).to_std().unwrap_or_default();
+                        let mut state = self.state.lock().unwrap();
+                        state.hand(&line.id, line.batch);
+                        state.producers.entry(line.id).or_default().lag = Some(lag);
+                        permit.send(line.entry);
+                        true
+                    }
+                    Err(_) => false,
+                },
+                entry = self.entries.recv(), if reading => {
+                    match entry {
+                        Some(entry) => self.entry(entry),
+                        None => entries_done = true,
+                    }
+                    true
+                }
+            };
+            if !delivered {
+                return;
+            }
+        }
+    }
+
+    /// Count `entry` and queue it, hold it or drop it.
+    fn entry(&mut self, mut entry: LogEntry) {
+        let mut state = self.state.lock().unwrap();
+        let batch = state.read_batch();
+        let root = state.note(entry.source, &entry.instance);
+        let replica = state.rename(entry.source, &mut entry.producer);
+        let id = tree_id(&root, &entry.producer);
+        let producer = state.producers.entry(id.clone()).or_default();
+        let raw = replica.as_ref().map_or(id.clone(), |r| tree_id(&root, r));
+        if let Some(replica) = replica {
+            producer.replicas.entry(raw.clone()).or_default();
+            entry
+                .fields
+                .entry("replica".to_string())
+                .or_insert(serde_json::Value::String(replica));
+        }
+        if let Some(meta) = producer.replica_meta(&raw) {
+            for (key, value) in meta.searchable() {
+                entry
+                    .fields
+                    .entry(key.to_string())
+                    .or_insert_with(|| value.into());
+            }
+        }
+        producer.counters.bytes += entry.raw.len() as u64;
+        producer.counters.lines += 1;
+        producer.last_line = Some(chrono::Utc::now());
+        if state.stopped(&id) {
+            state.settle(batch, Fate::Discarded);
+            return;
+        }
+        let line = Queued { id, entry, batch };
+        if !state.paused(&line.id) {
+            self.outbox.push(&mut state, line);
+            return;
+        }
+        if self.held.len() == PAUSED_BACKLOG {
+            if let Some(oldest) = self.held.pop_front() {
+                state
+                    .producers
+                    .entry(oldest.id)
+                    .or_default()
+                    .counters
+                    .dropped += 1;
+                state.settle(oldest.batch, Fate::Dropped);
+            }
+        }
+        self.held.push_back(line);
+    }
+
+    /// Record `update` and pass it on unless its producer is paused or
+    /// stopped; those show their last status again once released. A replica
+    /// ending is not passed on while another replica is live.
+    async fn update(&mut self, mut update: ProducerUpdate) -> bool {
+        let forward = {
+            let mut state = self.state.lock().unwrap();
+            let root = state.note(update.source, &update.instance);
+            let raw = tree_id(&root, &update.producer);
+            state.rename(update.source, &mut update.producer);
+            let id = tree_id(&root, &update.producer);
+            let held = state.paused(&id) || state.stopped(&id);
+            let producer = state.producers.entry(id).or_default();
+            let others_live = producer
+                .replicas
+                .iter()
+                .any(|(other, replica)| *other != raw && replica.live);
+            let replica = producer.replicas.entry(raw).or_default();
+            match &update.status {
+                ProducerStatus::Restarted { .. } => true,
+                status => {
+                    if let ProducerStatus::Error { message } = status {
+                        producer.last_error = Some(message.clone());
+                    }
+                    if *status == ProducerStatus::Streaming {
+                        if replica.streamed {
+                            producer.counters.reconnects += 1;
+                        }
+                        replica.streamed = true;
+                    }
+                    replica.live = !matches!(status, ProducerStatus::Ended { .. });
+                    if replica.live || !others_live {
+                        producer.status = Some(status.clone());
+                        !held
+                    } else {
+                        false
+                    }
+                }
+            }
+        };
+        !forward || self.out.send(update).await.is_ok()
+    }
+
+    /// Record `meta` against its replica and pass on what the logical
+    /// producer should show.
+    async fn meta(&mut self, mut meta: ProducerMeta) -> bool {
+        let shown = {
+            let mut state = self.state.lock().unwrap();
+            let root = state.note(meta.source, &meta.instance);
+            let raw = tree_id(&root, &meta.producer);
+            state.rename(meta.source, &mut meta.producer);
+            let id = tree_id(&root, &meta.producer);
+            let producer = state.producers.entry(id).or_default();
+            producer.replicas.entry(raw.clone()).or_default().meta = Some(meta);
+            producer.shown_meta(&raw).cloned()
+        };
+        match shown {
+            Some(meta) => self.meta_out.send(meta).await.is_ok(),
+            None => true,
+        }
+    }
+
+    /// Show the new status of everything under `path` and release or drop
+    /// held lines accordingly.
+    async fn restate(&mut self, path: &str) -> bool {
+        let updates = {
+            let mut state = self.state.lock().unwrap();
+            let mut kept = VecDeque::new();
+            for line in std::mem::take(&mut self.held) {
+                if state.stopped(&line.id) {
+                    state.settle(line.batch, Fate::Discarded);
+                } else if state.paused(&line.id) {
+                    kept.push_back(line);
+                } else {
+                    self.outbox.push(&mut state, line);
+                }
+            }
+            self.held = kept;
+            state.restate(path)
+        };
+        for update in updates {
+            if self.out.send(update).await.is_err() {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    struct Harness {
+        control: FeedControl,
+        feed: FeedSenders,
+        entries: mpsc::Receiver<LogEntry>,
+        updates: mpsc::Receiver<ProducerUpdate>,
+        metadata: mpsc::Receiver<ProducerMeta>,
+    }
+
+    fn harness() -> Harness {
+        harness_with(64, Overflow::Block, DEFAULT_BUFFER)
+    }
+
+    /// A relay whose UI side holds `capacity` lines.
+    fn harness_with(capacity: usize, overflow: Overflow, buffer: usize) -> Harness {
+        spawn(capacity, |relay| relay.with_overflow(overflow, buffer))
+    }
+
+    /// A relay grouping replicas by the built-in rules.
+    fn grouped() -> Harness {
+        spawn(64, |relay| relay.with_rules(ProducerRules::builtin()))
+    }
+
+    fn spawn(capacity: usize, configure: impl FnOnce(Relay) -> Relay) -> Harness {
+        let (tx, entries) = mpsc::channel(capacity);
+        let (utx, updates) = mpsc::channel(64);
+        let (mtx, metadata) = mpsc::channel(64);
+        let (control, feed, relay) = FeedControl::attach(tx, utx, mtx);
+        tokio::spawn(configure(relay).run());
+        Harness {
+            control,
+            feed,
+            entries,
+            updates,
+            metadata,
+        }
+    }
+
+    fn line(producer: &str, text: &str) -> LogEntry {
+        crate::line_entry(FeedKind::Docker, producer, text.to_string())
+    }
+
+    fn status(producer: &str, status: ProducerStatus) -> ProducerUpdate {
+        ProducerUpdate {
+            source: FeedKind::Docker,
+            instance: None,
+            producer: producer.into(),
+            status,
+        }
+    }
+
+    async fn recv<T>(rx: &mut mpsc::Receiver<T>) -> T {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("message should arrive")
+            .expect("channel open")
+    }
+
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    #[tokio::test]
+    async fn paused_producers_hold_their_lines_until_resumed() {
+        let mut h = harness();
+        h.feed.entries.send(line("api", "one")).await.unwrap();
+        h.feed.entries.send(line("web", "one")).await.unwrap();
+        assert_eq!(recv(&mut h.entries).await.producer, "api");
+        assert_eq!(recv(&mut h.entries).await.producer, "web");
+
+        h.control.pause("docker/api");
+        let update = recv(&mut h.updates).await;
+        assert_eq!(
+            (update.producer.as_str(), update.status),
+            ("api", ProducerStatus::Paused)
+        );
+        h.feed.entries.send(line("api", "two")).await.unwrap();
+        h.feed.entries.send(line("web", "two")).await.unwrap();
+        assert_eq!(recv(&mut h.entries).await.producer, "web");
+        settle().await;
+        assert!(h.entries.try_recv().is_err(), "api is held");
+
+        h.control.resume("docker/api");
+        assert_eq!(recv(&mut h.updates).await.status, ProducerStatus::Streaming);
+        let released = recv(&mut h.entries).await;
+        assert_eq!(released.message.as_deref(), Some("two"));
+        assert_eq!(released.producer, "api");
+    }
+
+    #[tokio::test]
+    async fn pausing_the_whole_feed_stops_reading_it() {
+        let mut h = harness();
+        h.feed.entries.send(line("api", "one")).await.unwrap();
+        recv(&mut h.entries).await;
+
+        h.control.pause("");
+        assert_eq!(recv(&mut h.updates).await.status, ProducerStatus::Paused);
+        settle().await;
+        for _ in 0..RELAY_CAPACITY {
+            h.feed.entries.try_send(line("api", "queued")).unwrap();
+        }
+        assert!(
+            h.feed.entries.try_send(line("api", "blocked")).is_err(),
+            "the feed is held at its channel"
+        );
+
+        h.control.resume("");
+        for _ in 0..RELAY_CAPACITY {
+            recv(&mut h.entries).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn stopped_producers_drop_lines_and_keep_counting() {
+        let mut h = harness();
+        h.feed
+            .updates
+            .send(status("api", ProducerStatus::Streaming))
+            .await
+            .unwrap();
+        recv(&mut h.updates).await;
+
+        h.control.stop("docker");
+        let stopped: Vec<_> = [recv(&mut h.updates).await, recv(&mut h.updates).await]
+            .into_iter()
+            .map(|u| (u.producer, u.status))
+            .collect();
+        assert_eq!(
+            stopped,
+            [
+                (String::new(), ProducerStatus::Stopped),
+                ("api".to_string(), ProducerStatus::Stopped)
+            ]
+        );
+        h.feed.entries.send(line("api", "gone")).await.unwrap();
+        h.feed
+            .updates
+            .send(status("api", ProducerStatus::Backoff { attempt: 1 }))
+            .await
+            .unwrap();
+        h.feed
+            .updates
+            .send(status("api", ProducerStatus::Streaming))
+            .await
+            .unwrap();
+        settle().await;
+        assert!(h.entries.try_recv().is_err());
+        assert!(h.updates.try_recv().is_err(), "stopped shows as stopped");
+
+        h.control.start("docker/api");
+        let update = recv(&mut h.updates).await;
+        assert_eq!(update.status, ProducerStatus::Streaming);
+        let health = h.control.health();
+        assert_eq!(health.len(), 1);
+        assert_eq!(health[0].path, "docker/api");
+        assert!(!health[0].stopped);
+        assert_eq!(
+            health[0].counters,
+            ProducerCounters {
+                bytes: 4,
+                lines: 1,
+                reconnects: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn health_keeps_the_last_line_and_error() {
+        let mut h = harness();
+        h.feed.entries.send(line("api", "one")).await.unwrap();
+        recv(&mut h.entries).await;
+        let error = ProducerStatus::Error {
+            message: "connection refused".into(),
+        };
+        h.feed.updates.send(status("api", error)).await.unwrap();
+        h.feed
+            .updates
+            .send(status("api", ProducerStatus::Streaming))
+            .await
+            .unwrap();
+        recv(&mut h.updates).await;
+        recv(&mut h.updates).await;
+
+        let health = h.control.health();
+        assert!(health[0].last_line.is_some());
+        assert_eq!(health[0].last_error.as_deref(), Some("connection refused"));
+        assert_eq!(health[0].status, Some(ProducerStatus::Streaming));
+    }
+
+    #[tokio::test]
+    async fn paths_of_other_feeds_are_ignored() {
+        let mut h = harness();
+        h.feed.entries.send(line("api", "one")).await.unwrap();
+        recv(&mut h.entries).await;
+        h.control.pause("kubernetes/shop");
+        h.control.stop("docker@podman");
+        settle().await;
+        assert!(h.updates.try_recv().is_err());
+        h.feed.entries.send(line("api", "two")).await.unwrap();
+        recv(&mut h.entries).await;
+    }
+
+    #[tokio::test]
+    async fn health_says_which_streams_a_follower_closes() {
+        let mut h = harness();
+        for producer in ["job", "job/stdout", "web"] {
+            h.feed.entries.send(line(producer, "x")).await.unwrap();
+            recv(&mut h.entries).await;
+        }
+        // A follower of `docker/job` carries `docker/job/stdout` too.
+        assert!(!h.control.signals().stopped("docker/job"));
+        let health: HashMap<String, (bool, bool)> = h
+            .control
+            .health()
+            .into_iter()
+            .map(|p| (p.path, (p.closes, p.reopens)))
+            .collect();
+        assert_eq!(health["docker/job"], (true, true));
+        assert_eq!(health["docker/job/stdout"], (false, true));
+        assert_eq!(health["docker/web"], (false, false));
+    }
+
+    /// Sends `count` numbered lines from `api` while nothing reads the UI side.
+    async fn flood(h: &Harness, count: usize) {
+        for n in 0..count {
+            h.feed
+                .entries
+                .send(line("api", &n.to_string()))
+                .await
+                .unwrap();
+        }
+        settle().await;
+    }
+
+    fn received(h: &mut Harness) -> Vec<String> {
+        std::iter::from_fn(|| h.entries.try_recv().ok())
+            .map(|e| e.message.unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn blocking_delays_lines_without_losing_any() {
+        let mut h = harness_with(1, Overflow::Block, 2);
+        let mut old = line("api", "old");
+        old.ts -= chrono::TimeDelta::seconds(5);
+        h.feed.entries.send(old).await.unwrap();
+        flood(&h, 5).await;
+        let mut got = Vec::new();
+        while got.len() < 6 {
+            got.push(recv(&mut h.entries).await.message.unwrap());
+        }
+        assert_eq!(got, ["old", "0", "1", "2", "3", "4"]);
+        let health = &h.control.health()[0];
+        assert_eq!(health.counters.dropped, 0);
+        assert!(health.counters.delayed > 0);
+        assert!(health.lag.is_some());
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_keeps_the_newest_lines() {
+        let mut h = harness_with(1, Overflow::DropOldest, 2);
+        flood(&h, 10).await;
+        assert_eq!(received(&mut h), ["0"]);
+        settle().await;
+        assert_eq!(received(&mut h), ["8"]);
+        settle().await;
+        assert_eq!(received(&mut h), ["9"]);
+        let counters = h.control.health()[0].counters;
+        assert_eq!((counters.lines, counters.dropped), (10, 7));
+        assert_eq!(counters.delayed, 9);
+    }
+
+    #[tokio::test]
+    async fn sampling_keeps_one_line_in_ten() {
+        let mut h = harness_with(1, Overflow::Sample, 2);
+        flood(&h, 30).await;
+        let mut got = Vec::new();
+        for _ in 0..3 {
+            got.extend(received(&mut h));
+            settle().await;
+        }
+        assert_eq!(got, ["0", "13", "23"]);
+        assert_eq!(h.control.health()[0].counters.dropped, 27);
+    }
+
+    #[tokio::test]
+    async fn reconnects_reach_the_producers_asked_for() {
+        let h = harness();
+        h.feed.entries.send(line("shop/api", "one")).await.unwrap();
+        settle().await;
+        let mut api = h.control.signals();
+        let mut web = api.clone();
+        h.control.reconnect("docker/shop/api");
+        let signal = tokio::time::timeout(Duration::from_secs(5), api.next("docker/shop/api"))
+            .await
+            .expect("api is asked to reconnect");
+        assert_eq!(signal, Signal::Reconnect);
+        let asked = tokio::time::timeout(Duration::from_millis(50), web.next("docker/shop/web"));
+        assert!(asked.await.is_err(), "web is not");
+    }
+
+    #[tokio::test]
+    async fn replicas_group_under_their_logical_producer() {
+        let mut h = grouped();
+        h.feed.entries.send(line("shop/api", "one")).await.unwrap();
+        h.feed
+            .entries
+            .send(line("shop/api-2", "two"))
+            .await
+            .unwrap();
+        let first = recv(&mut h.entries).await;
+        let second = recv(&mut h.entries).await;
+        assert_eq!(
+            (first.producer.as_str(), second.producer.as_str()),
+            ("shop/api", "shop/api")
+        );
+        assert!(!first.fields.contains_key("replica"));
+        assert_eq!(second.fields["replica"], "shop/api-2");
+
+        let health = h.control.health();
+        assert_eq!(health.len(), 1);
+        assert_eq!(health[0].counters.lines, 2);
+
+        h.control.pause("docker/shop/api");
+        recv(&mut h.updates).await;
+        h.feed
+            .entries
+            .send(line("shop/api-2", "held"))
+            .await
+            .unwrap();
+        settle().await;
+        assert!(h.entries.try_recv().is_err(), "the replica is paused too");
+    }
+
+    #[tokio::test]
+    async fn one_replica_ending_is_hidden_while_another_is_live() {
+        let mut h = grouped();
+        for producer in ["shop/api", "shop/api-2"] {
+            h.feed
+                .updates
+                .send(status(producer, ProducerStatus::Streaming))
+                .await
+                .unwrap();
+            assert_eq!(recv(&mut h.updates).await.producer, "shop/api");
+        }
+        let ended = ProducerStatus::Ended { code: Some(0) };
+        h.feed
+            .updates
+            .send(status("shop/api-2", ended.clone()))
+            .await
+            .unwrap();
+        settle().await;
+        assert!(h.updates.try_recv().is_err());
+        assert_eq!(h.control.health()[0].counters.reconnects, 0);
+
+        h.feed
+            .updates
+            .send(status("shop/api", ended.clone()))
+            .await
+            .unwrap();
+        assert_eq!(recv(&mut h.updates).await.status, ended);
+    }
+
+    #[tokio::test]
+    async fn reconnecting_a_group_reaches_each_replica() {
+        let mut h = grouped();
+        h.feed
+            .entries
+            .send(line("shop/api-2", "one"))
+            .await
+            .unwrap();
+        recv(&mut h.entries).await;
+        let mut replica = h.control.signals();
+        h.control.reconnect("docker/shop/api");
+        let signal =
+            tokio::time::timeout(Duration::from_secs(5), replica.next("docker/shop/api-2"))
+                .await
+                .expect("the replica is asked to reconnect");
+        assert_eq!(signal, Signal::Reconnect);
+    }
+
+    #[tokio::test]
+    async fn stopping_a_group_closes_each_replica_until_started() {
+        let mut h = grouped();
+        h.feed
+            .entries
+            .send(line("shop/api-2", "one"))
+            .await
+            .unwrap();
+        recv(&mut h.entries).await;
+        let mut replica = h.control.signals();
+        let mut other = replica.clone();
+        let waiting = tokio::spawn(async move { replica.next("docker/shop/api-2").await });
+
+        h.control.stop("docker/shop/api");
+        let signal = tokio::time::timeout(Duration::from_secs(5), waiting)
+            .await
+            .expect("the replica is asked to stop")
+            .unwrap();
+        assert_eq!(signal, Signal::Stop);
+        let asked = tokio::time::timeout(Duration::from_millis(50), other.next("docker/shop/web"));
+        assert!(asked.await.is_err(), "other producers keep streaming");
+
+        let mut replica = h.control.signals();
+        let started = tokio::time::timeout(
+            Duration::from_millis(50),
+            replica.started("docker/shop/api-2"),
+        );
+        assert!(started.await.is_err(), "still stopped");
+        h.control.start("docker/shop/api");
+        tokio::time::timeout(Duration::from_secs(5), replica.started("docker/shop/api-2"))
+            .await
+            .expect("the replica is started again");
+    }
+
+    #[tokio::test]
+    async fn metadata_follows_the_rename() {
+        let mut h = grouped();
+        h.feed
+            .metadata
+            .send(ProducerMeta {
+                source: FeedKind::Docker,
+                instance: None,
+                producer: "myapp_api_1".into(),
+                state: fml_core::ProducerState::Running,
+                fields: Default::default(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(recv(&mut h.metadata).await.producer, "myapp/api");
+    }
+
+    fn meta(producer: &str, state: fml_core::ProducerState, restarts: &str) -> ProducerMeta {
+        ProducerMeta {
+            source: FeedKind::Docker,
+            instance: None,
+            producer: producer.into(),
+            state,
+            fields: [("restarts".to_string(), restarts.to_string())].into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn replicas_keep_their_own_metadata() {
+        use fml_core::ProducerState;
+        let crashing = ProducerState::Waiting {
+            reason: "CrashLoopBackOff".into(),
+        };
+        let mut h = grouped();
+        h.feed
+            .metadata
+            .send(meta("shop/api-2", crashing.clone(), "4"))
+            .await
+            .unwrap();
+        assert_eq!(recv(&mut h.metadata).await.state, crashing);
+        // A healthy replica reporting later does not hide the crashing one.
+        h.feed
+            .metadata
+            .send(meta("shop/api", ProducerState::Running, "0"))
+            .await
+            .unwrap();
+        let shown = recv(&mut h.metadata).await;
+        assert_eq!(
+            (shown.producer.as_str(), &shown.state),
+            ("shop/api", &crashing)
+        );
+        assert_eq!(shown.fields["restarts"], "4");
+
+        // Each line carries its own replica's state.
+        h.feed.entries.send(line("shop/api", "ok")).await.unwrap();
+        h.feed
+            .entries
+            .send(line("shop/api-2", "boom"))
+            .await
+            .unwrap();
+        let healthy = recv(&mut h.entries).await;
+        assert_eq!(healthy.fields["state"], "running");
+        assert_eq!(healthy.fields["restarts"], "0");
+        let crashed = recv(&mut h.entries).await;
+        assert_eq!(crashed.fields["state"], crashing.to_string());
+        assert_eq!(crashed.fields["restarts"], "4");
+
+        // Once the crashing replica recovers, the node follows the latest.
+        h.feed
+            .metadata
+            .send(meta("shop/api-2", ProducerState::Running, "4"))
+            .await
+            .unwrap();
+        assert_eq!(recv(&mut h.metadata).await.state, ProducerState::Running);
+    }
+}
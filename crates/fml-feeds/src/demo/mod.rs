@@ -0,0 +1,691 @@
+//! Demo feed — generates the logs of a scripted incident.
+//!
+//! A [`Scenario`] describes services (producers, replicas, output format,
+//! baseline rate and lines) and timed events that raise error rates, slow
+//! services down, silence or restart them, and change their reported state.
+//! The [`Simulation`] turns it into entries step by step; the feed runs it
+//! against the wall clock. The same scenario and seed produce the same lines
+//! for the same steps, so it doubles as a reproducible load source.
+//!
+//! Shipped scenarios ([`BUILTIN`]) cover every line format (JSON, logfmt,
+//! plain, access log) and every search domain between them:
+//!
+//! | Scenario        | What happens                                                       |
+//! |-----------------|--------------------------------------------------------------------|
+//! | `redis-outage`  | Redis refuses connections; workers fail, API latency and 5xx climb |
+//! | `auth-storm`    | Credential stuffing: 401s, lockouts, rate limiting, token errors   |
+//! | `oom-crashloop` | Payments leaks memory, is `OOMKilled` and crash-loops; DB pool and slow queries follow |
+
+mod scenario;
+
+pub use scenario::{parse_offset, Event, Format, Scenario, Service, Template, BUILTIN};
+
+use crate::FeedError;
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use fml_core::{
+    FeedKind, LogEntry, LogLevel, ProducerMeta, ProducerState, ProducerStatus, ProducerUpdate,
+};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{self, MissedTickBehavior};
+
+/// How often the feed advances the simulation.
+const TICK: Duration = Duration::from_millis(100);
+
+const USERS: &[&str] = &[
+    "alice",
+    "bob",
+    "carol",
+    "dave",
+    "erin",
+    "mallory",
+    "svc-batch",
+];
+
+/// A scenario being played: where it is and what each replica has done.
+pub struct Simulation {
+    scenario: Scenario,
+    rng: Rng,
+    replicas: Vec<Replica>,
+    elapsed: Duration,
+    finished: bool,
+}
+
+struct Replica {
+    /// Index into the scenario's services.
+    service: usize,
+    producer: String,
+    /// Lines owed but not yet written (rates are fractional).
+    carry: f64,
+    restarts: u32,
+    /// Whether an event has reported a state for it.
+    stated: bool,
+}
+
+/// What one step produced.
+#[derive(Debug, Default)]
+pub struct Step {
+    pub entries: Vec<LogEntry>,
+    pub updates: Vec<ProducerUpdate>,
+    pub metadata: Vec<ProducerMeta>,
+}
+
+/// The combined effect of the events active on a replica.
+struct Effects {
+    error_rate: f64,
+    latency: f64,
+    rate: f64,
+}
+
+impl Simulation {
+    pub fn new(scenario: Scenario) -> Self {
+        let replicas = scenario
+            .services
+            .iter()
+            .enumerate()
+            .flat_map(|(service, config)| {
+                config
+                    .replica_names()
+                    .into_iter()
+                    .map(move |producer| Replica {
+                        service,
+                        producer,
+                        carry: 0.0,
+                        restarts: 0,
+                        stated: false,
+                    })
+            })
+            .collect();
+        Self {
+            rng: Rng(scenario.seed),
+            scenario,
+            replicas,
+            elapsed: Duration::ZERO,
+            finished: false,
+        }
+    }
+
+    /// Producer names, service by service.
+    pub fn producers(&self) -> impl Iterator<Item = &str> {
+        self.replicas.iter().map(|r| r.producer.as_str())
+    }
+
+    /// Whether a non-repeating scenario has run its course.
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Play the next `step` of the scenario, which ends at wall time `now`.
+    /// Lines are spread evenly across the step.
+    pub fn advance(&mut self, step: Duration, now: DateTime<Utc>) -> Step {
+        let mut out = Step::default();
+        if self.finished {
+            return out;
+        }
+        let from = self.elapsed;
+        let to = from + step;
+        let start = now - TimeDelta::from_std(step).unwrap_or_default();
+
+        // Ends first, so an event can hand its replicas straight to the next.
+        for index in 0..self.scenario.events.len() {
+            let end = self.scenario.events[index].end();
+            if end.is_some_and(|end| (from..to).contains(&end)) {
+                self.finish(index, &mut out);
+            }
+        }
+        for index in 0..self.scenario.events.len() {
+            let event = &self.scenario.events[index];
+            if (from..to).contains(&event.at) {
+                tracing::debug!(at = ?event.at, description = %event.description, "demo: event starts");
+                self.begin(index, start, &mut out);
+            }
+        }
+
+        let secs = step.as_secs_f64();
+        for index in 0..self.replicas.len() {
+            let effects = self.effects(index, from);
+            let replica = &mut self.replicas[index];
+            let service = &self.scenario.services[replica.service];
+            replica.carry += service.rate * effects.rate * secs;
+            let count = replica.carry.floor();
+            replica.carry -= count;
+            let count = count as u32;
+            for i in 0..count {
+                let offset = step.mul_f64(f64::from(i + 1) / f64::from(count + 1));
+                let ts = start + TimeDelta::from_std(offset).unwrap_or_default();
+                let service = &self.scenario.services[self.replicas[index].service];
+                let errors = !service.errors.is_empty() && self.rng.unit() < effects.error_rate;
+                let templates = if errors {
+                    &service.errors
+                } else {
+                    &service.lines
+                };
+                let template = self.rng.pick(templates).clone();
+                let entry = self.render(index, &template, effects.latency, ts);
+                out.entries.push(entry);
+            }
+        }
+        out.entries.sort_by_key(|entry| entry.ts);
+
+        self.elapsed = to;
+        if self
+            .scenario
+            .duration
+            .is_some_and(|duration| self.elapsed >= duration)
+        {
+            self.restart_scenario(&mut out);
+        }
+        out
+    }
+
+    /// Start over, or stop for good.
+    fn restart_scenario(&mut self, out: &mut Step) {
+        for replica in &mut self.replicas {
+            replica.carry = 0.0;
+            if std::mem::take(&mut replica.stated) {
+                out.metadata
+                    .push(meta(&replica.producer, ProducerState::Running));
+            }
+        }
+        self.elapsed = Duration::ZERO;
+        self.finished = !self.scenario.repeat;
+    }
+
+    /// Apply the one-off parts of event `index`: restarts, state, `say`.
+    fn begin(&mut self, index: usize, ts: DateTime<Utc>, out: &mut Step) {
+        for replica in 0..self.replicas.len() {
+            if !self.targets(index, replica) {
+                continue;
+            }
+            let event = &self.scenario.events[index];
+            let (restart, state, say) = (event.restart, event.state.clone(), event.say.clone());
+            let r = &mut self.replicas[replica];
+            if restart {
+                r.restarts += 1;
+                out.updates.push(ProducerUpdate {
+                    source: FeedKind::Demo,
+                    instance: None,
+                    producer: r.producer.clone(),
+                    status: ProducerStatus::Restarted { count: r.restarts },
+                });
+            }
+            if let Some(state) = state {
+                r.stated = true;
+                out.metadata.push(meta(&r.producer, parse_state(&state)));
+            }
+            for template in &say {
+                let entry = self.render(replica, template, 1.0, ts);
+                out.entries.push(entry);
+            }
+        }
+    }
+
+    /// Event `index` is over: its replicas are running again.
+    fn finish(&mut self, index: usize, out: &mut Step) {
+        if self.scenario.events[index].state.is_none() {
+            return;
+        }
+        for replica in 0..self.replicas.len() {
+            if self.targets(index, replica) {
+                let r = &mut self.replicas[replica];
+                r.stated = false;
+                out.metadata.push(meta(&r.producer, ProducerState::Running));
+            }
+        }
+    }
+
+    fn targets(&self, event: usize, replica: usize) -> bool {
+        let replica = &self.replicas[replica];
+        let service = &self.scenario.services[replica.service].name;
+        self.scenario.events[event].targets(service, &replica.producer)
+    }
+
+    /// Events active on `replica` at `at`, each scaled by how far its ramp
+    /// has got.
+    fn effects(&self, replica: usize, at: Duration) -> Effects {
+        let mut effects = Effects {
+            error_rate: 0.0,
+            latency: 1.0,
+            rate: 1.0,
+        };
+        for (index, event) in self.scenario.events.iter().enumerate() {
+            let active = event.at <= at && event.end().is_none_or(|end| at < end);
+            if !active || !self.targets(index, replica) {
+                continue;
+            }
+            let progress = if event.ramp.is_zero() {
+                1.0
+            } else {
+                ((at - event.at).as_secs_f64() / event.ramp.as_secs_f64()).min(1.0)
+            };
+            effects.error_rate = effects.error_rate.max(event.error_rate * progress);
+            effects.latency *= 1.0 + (event.latency - 1.0) * progress;
+            effects.rate *= 1.0 + (event.rate - 1.0) * progress;
+        }
+        effects
+    }
+
+    /// Fill in `template` for `replica` and write it in its service's format.
+    fn render(
+        &mut self,
+        replica: usize,
+        template: &Template,
+        latency: f64,
+        ts: DateTime<Utc>,
+    ) -> LogEntry {
+        let replica = &self.replicas[replica];
+        let service = &self.scenario.services[replica.service];
+        // A long tail, so p99 sits well above the median.
+        let spread = if self.rng.unit() < 0.03 { 4.0 } else { 1.0 };
+        let vars = Vars {
+            latency_ms: (service.latency_ms * latency * (0.6 + 0.8 * self.rng.unit()) * spread)
+                .round() as u64,
+            id: format!("{:08x}", self.rng.next() as u32),
+            n: self.rng.next() % 1000,
+            user: USERS[self.rng.below(USERS.len())],
+            ip: format!("10.0.{}.{}", self.rng.next() % 8, self.rng.next() % 250 + 2),
+            replica: &replica.producer,
+        };
+        let message = vars.fill(&template.message);
+        let fields: BTreeMap<String, Value> = template
+            .fields
+            .iter()
+            .map(|(key, value)| (key.clone(), vars.value(value)))
+            .collect();
+        let raw = format_line(service.format, ts, template.level, &message, &fields, &vars);
+        let structured = matches!(service.format, Format::Json | Format::Logfmt);
+        LogEntry {
+            seq: 0,
+            raw,
+            ts,
+            level: Some(template.level),
+            source: FeedKind::Demo,
+            instance: None,
+            producer: replica.producer.clone(),
+            fields: if structured {
+                fields.into_iter().collect()
+            } else {
+                Default::default()
+            },
+            message: Some(message),
+        }
+    }
+}
+
+/// Placeholder values for one line.
+struct Vars<'a> {
+    latency_ms: u64,
+    id: String,
+    n: u64,
+    user: &'a str,
+    ip: String,
+    replica: &'a str,
+}
+
+impl Vars<'_> {
+    fn fill(&self, text: &str) -> String {
+        if !text.contains('{') {
+            return text.to_string();
+        }
+        text.replace("{latency_ms}", &self.latency_ms.to_string())
+            .replace("{id}", &self.id)
+            .replace("{n}", &self.n.to_string())
+            .replace("{user}", self.user)
+            .replace("{ip}", &self.ip)
+            .replace("{replica}", self.replica)
+    }
+
+    fn value(&self, value: &Value) -> Value {
+        match value.as_str() {
+            Some("{latency_ms}") => self.latency_ms.into(),
+            Some("{n}") => self.n.into(),
+            Some(text) => self.fill(text).into(),
+            None => value.clone(),
+        }
+    }
+}
+
+fn format_line(
+    format: Format,
+    ts: DateTime<Utc>,
+    level: LogLevel,
+    message: &str,
+    fields: &BTreeMap<String, Value>,
+    vars: &Vars,
+) -> String {
+    let stamp = ts.to_rfc3339_opts(SecondsFormat::Millis, true);
+    let level = level.to_string();
+    match format {
+        Format::Json => {
+            let mut line = format!(
+                r#"{{"ts":"{stamp}","level":"{}","msg":{}"#,
+                level.to_ascii_lowercase(),
+                Value::from(message)
+            );
+            for (key, value) in fields {
+                line.push_str(&format!(",{}:{value}", Value::from(key.as_str())));
+            }
+            line.push('}');
+            line
+        }
+        Format::Logfmt => {
+            let mut line = format!(
+                "ts={stamp} level={} msg={}",
+                level.to_ascii_lowercase(),
+                logfmt_value(message)
+            );
+            for (key, value) in fields {
+                let value = match value {
+                    Value::String(s) => logfmt_value(s),
+                    other => other.to_string(),
+                };
+                line.push_str(&format!(" {key}={value}"));
+            }
+            line
+        }
+        Format::Plain => format!("{stamp} {level:<5} {message}"),
+        Format::Access => {
+            let field = |key: &str, default: &str| match fields.get(key) {
+                Some(Value::String(s)) => s.clone(),
+                Some(other) => other.to_string(),
+                None => default.to_string(),
+            };
+            format!(
+                r#"{} - - [{}] "{message}" {} {} "-" "{}" {:.3}"#,
+                vars.ip,
+                ts.format("%d/%b/%Y:%H:%M:%S %z"),
+                field("status", "200"),
+                field("bytes", "512"),
+                field("agent", "Mozilla/5.0"),
+                vars.latency_ms as f64 / 1000.0,
+            )
+        }
+    }
+}
+
+/// A logfmt value, quoted when it has to be.
+fn logfmt_value(s: &str) -> String {
+    if s.is_empty() || s.contains([' ', '"', '=']) {
+        format!("{s:?}")
+    } else {
+        s.to_string()
+    }
+}
+
+fn meta(producer: &str, state: ProducerState) -> ProducerMeta {
+    ProducerMeta {
+        source: FeedKind::Demo,
+        instance: None,
+        producer: producer.to_string(),
+        state,
+        fields: Default::default(),
+    }
+}
+
+/// An event's `state` as a [`ProducerState`]: the plain states by name,
+/// `OOMKilled` as a kill, anything else (`CrashLoopBackOff`) as a wait.
+fn parse_state(state: &str) -> ProducerState {
+    match state {
+        "running" => ProducerState::Running,
+        "starting" => ProducerState::Starting,
+        "healthy" => ProducerState::Healthy,
+        "unhealthy" => ProducerState::Unhealthy,
+        "OOMKilled" => ProducerState::Terminated {
+            reason: Some(state.to_string()),
+            code: Some(137),
+        },
+        reason => ProducerState::Waiting {
+            reason: reason.to_string(),
+        },
+    }
+}
+
+/// SplitMix64: small, fast, and the same on every platform and release.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// A template, by weight.
+    fn pick<'a>(&mut self, templates: &'a [Template]) -> &'a Template {
+        let total: f64 = templates.iter().map(|t| t.weight).sum();
+        let mut target = self.unit() * total;
+        for template in templates {
+            target -= template.weight;
+            if target < 0.0 {
+                return template;
+            }
+        }
+        &templates[templates.len() - 1]
+    }
+}
+
+/// Plays a scenario in real time.
+pub struct DemoFeed {
+    simulation: Simulation,
+    metadata: Option<mpsc::Sender<ProducerMeta>>,
+}
+
+impl DemoFeed {
+    pub fn new(scenario: Scenario) -> Self {
+        Self {
+            simulation: Simulation::new(scenario),
+            metadata: None,
+        }
+    }
+
+    /// Report the states events give replicas on `metadata`.
+    pub fn with_metadata(mut self, metadata: mpsc::Sender<ProducerMeta>) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Generate lines until a non-repeating scenario ends.
+    pub async fn run(
+        mut self,
+        tx: mpsc::Sender<LogEntry>,
+        updates: mpsc::Sender<ProducerUpdate>,
+    ) -> Result<(), FeedError> {
+        let producers: Vec<String> = self.simulation.producers().map(str::to_string).collect();
+        let report = |status: ProducerStatus| {
+            let updates = updates.clone();
+            let producers = producers.clone();
+            async move {
+                for producer in producers {
+                    let update = ProducerUpdate {
+                        source: FeedKind::Demo,
+                        instance: None,
+                        producer,
+                        status: status.clone(),
+                    };
+                    let _ = updates.send(update).await;
+                }
+            }
+        };
+        report(ProducerStatus::Streaming).await;
+        if let Some(metadata) = &self.metadata {
+            for producer in &producers {
+                let _ = metadata.send(meta(producer, ProducerState::Running)).await;
+            }
+        }
+
+        let mut ticker = time::interval(TICK);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticker.tick().await;
+        while !self.simulation.finished() {
+            ticker.tick().await;
+            let step = self.simulation.advance(TICK, Utc::now());
+            for update in step.updates {
+                let _ = updates.send(update).await;
+            }
+            if let Some(metadata) = &self.metadata {
+                for meta in step.metadata {
+                    let _ = metadata.send(meta).await;
+                }
+            }
+            for entry in step.entries {
+                tx.send(entry).await.map_err(|_| FeedError::ChannelClosed)?;
+            }
+        }
+        report(ProducerStatus::Ended { code: None }).await;
+        Ok(())
+    }
+}
+
+// ----- Tests -----
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scenario(text: &str) -> Scenario {
+        Scenario::from_toml(text).unwrap()
+    }
+
+    const OUTAGE: &str = r#"
+        name = "t"
+        seed = 3
+        duration = "10s"
+        repeat = false
+
+        [[service]]
+        name = "worker"
+        replicas = 2
+        format = "logfmt"
+        rate = 10
+        latency_ms = 10
+        lines = [{ message = "done in {latency_ms}ms", fields = { took = "{latency_ms}", user = "{user}" } }]
+        errors = [{ level = "error", message = "connection refused" }]
+
+        [[event]]
+        at = "5s"
+        services = ["worker-2"]
+        error_rate = 1.0
+        state = "CrashLoopBackOff"
+        restart = true
+        say = [{ level = "warn", message = "restarting" }]
+    "#;
+
+    fn run(simulation: &mut Simulation, steps: u32) -> Step {
+        let mut all = Step::default();
+        let start: DateTime<Utc> = "2024-01-15T10:00:00Z".parse().unwrap();
+        for i in 1..=steps {
+            let now = start + TimeDelta::milliseconds(100 * i64::from(i));
+            let step = simulation.advance(TICK, now);
+            all.entries.extend(step.entries);
+            all.updates.extend(step.updates);
+            all.metadata.extend(step.metadata);
+        }
+        all
+    }
+
+    #[test]
+    fn rates_and_events_shape_the_output() {
+        let mut simulation = Simulation::new(scenario(OUTAGE));
+        let before = run(&mut simulation, 50);
+        assert_eq!(before.entries.len(), 2 * 10 * 5);
+        assert!(before
+            .entries
+            .iter()
+            .all(|e| e.level == Some(LogLevel::Info)));
+        let line = &before.entries[0];
+        assert!(
+            line.raw.starts_with("ts=2024-01-15T10:00:00."),
+            "{}",
+            line.raw
+        );
+        assert!(line.fields["took"].is_u64());
+
+        let after = run(&mut simulation, 50);
+        assert!(simulation.finished());
+        let errors: Vec<_> = after
+            .entries
+            .iter()
+            .filter(|e| e.level == Some(LogLevel::Error))
+            .collect();
+        assert_eq!(errors.len(), 50);
+        assert!(errors.iter().all(|e| e.producer == "worker-2"));
+        assert!(after
+            .entries
+            .iter()
+            .any(|e| e.message.as_deref() == Some("restarting")));
+        assert_eq!(
+            after.updates[0].status,
+            ProducerStatus::Restarted { count: 1 }
+        );
+        let states: Vec<_> = after.metadata.iter().map(|m| m.state.to_string()).collect();
+        assert_eq!(states, ["CrashLoopBackOff", "running"]);
+    }
+
+    #[test]
+    fn same_seed_same_lines() {
+        let raw = |seed: &str| {
+            let text = OUTAGE.replace("seed = 3", seed);
+            let entries = run(&mut Simulation::new(scenario(&text)), 100).entries;
+            entries.into_iter().map(|e| e.raw).collect::<Vec<_>>()
+        };
+        assert_eq!(raw("seed = 3"), raw("seed = 3"));
+        assert_ne!(raw("seed = 3"), raw("seed = 4"));
+    }
+
+    #[test]
+    fn formats_render() {
+        let ts: DateTime<Utc> = "2024-01-15T10:32:05.250Z".parse().unwrap();
+        let vars = Vars {
+            latency_ms: 42,
+            id: "0000beef".into(),
+            n: 7,
+            user: "alice",
+            ip: "10.0.1.9".into(),
+            replica: "api-1",
+        };
+        let fields = BTreeMap::from([
+            ("status".to_string(), Value::from(502)),
+            ("route".to_string(), Value::from("/orders")),
+        ]);
+        let line = |format| {
+            format_line(
+                format,
+                ts,
+                LogLevel::Warn,
+                "GET /orders HTTP/1.1",
+                &fields,
+                &vars,
+            )
+        };
+        assert_eq!(
+            line(Format::Json),
+            r#"{"ts":"2024-01-15T10:32:05.250Z","level":"warn","msg":"GET /orders HTTP/1.1","route":"/orders","status":502}"#
+        );
+        assert_eq!(
+            line(Format::Logfmt),
+            r#"ts=2024-01-15T10:32:05.250Z level=warn msg="GET /orders HTTP/1.1" route=/orders status=502"#
+        );
+        assert_eq!(
+            line(Format::Plain),
+            "2024-01-15T10:32:05.250Z WARN  GET /orders HTTP/1.1"
+        );
+        assert_eq!(
+            line(Format::Access),
+            r#"10.0.1.9 - - [15/Jan/2024:10:32:05 +0000] "GET /orders HTTP/1.1" 502 512 "-" "Mozilla/5.0" 0.042"#
+        );
+    }
+}
@@ -0,0 +1,370 @@
+//! Scenario files: services, their baseline output, and timed events.
+//!
+//! ```toml
+//! name = "redis-outage"
+//! duration = "5m"          # then start over (repeat = false to end instead)
+//! seed = 7                 # same seed, same lines
+//!
+//! [[service]]
+//! name = "shop/worker"     # replicas are shop/worker-1, shop/worker-2, …
+//! replicas = 3
+//! format = "logfmt"        # json | logfmt | plain | access
+//! rate = 4                 # lines per second per replica
+//! latency_ms = 15          # baseline for {latency_ms}
+//! lines = [{ message = "job done in {latency_ms}ms", fields = { job = "{id}" } }]
+//! errors = [{ level = "error", message = "dial tcp 10.0.3.7:6379: connection refused" }]
+//!
+//! [[event]]
+//! at = "60s"
+//! for = "3m"               # default: until the scenario ends
+//! ramp = "30s"             # effects grow linearly to full strength
+//! services = ["shop/worker-*"]
+//! error_rate = 0.6         # share of lines drawn from `errors`
+//! latency = 8              # {latency_ms} multiplier
+//! rate = 2                 # rate multiplier; 0 silences the replicas
+//! state = "CrashLoopBackOff"  # reported as the replicas' state, `running` after
+//! restart = true           # count a restart on each replica
+//! say = [{ level = "warn", message = "connection pool exhausted" }]  # once, at `at`
+//! ```
+//!
+//! Event `services` are patterns with `*` wildcards, matched against service
+//! and replica names. Messages and string field values may use
+//! `{latency_ms}`, `{id}`, `{n}`, `{user}`, `{ip}` and `{replica}`; a field
+//! that is just `{latency_ms}` or `{n}` becomes a number.
+
+use crate::FeedError;
+use fml_core::LogLevel;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Scenarios shipped with fml, by name.
+pub const BUILTIN: &[(&str, &str)] = &[
+    (
+        "redis-outage",
+        include_str!("../../scenarios/redis-outage.toml"),
+    ),
+    (
+        "auth-storm",
+        include_str!("../../scenarios/auth-storm.toml"),
+    ),
+    (
+        "oom-crashloop",
+        include_str!("../../scenarios/oom-crashloop.toml"),
+    ),
+];
+
+/// A parsed scenario file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Seeds the generator, so a scenario always produces the same lines.
+    #[serde(default)]
+    pub seed: u64,
+    /// How long the scenario runs before repeating or ending. Unset runs
+    /// forever.
+    #[serde(default, deserialize_with = "optional_offset")]
+    pub duration: Option<Duration>,
+    #[serde(default = "yes")]
+    pub repeat: bool,
+    #[serde(rename = "service")]
+    pub services: Vec<Service>,
+    #[serde(default, rename = "event")]
+    pub events: Vec<Event>,
+}
+
+/// One service and its baseline output.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Service {
+    /// Producer name; numbered per replica when there are several.
+    pub name: String,
+    #[serde(default = "one")]
+    pub replicas: u32,
+    #[serde(default)]
+    pub format: Format,
+    /// Lines per second per replica.
+    pub rate: f64,
+    #[serde(default = "default_latency")]
+    pub latency_ms: f64,
+    pub lines: Vec<Template>,
+    /// Drawn from instead of `lines` at an event's `error_rate`.
+    #[serde(default)]
+    pub errors: Vec<Template>,
+}
+
+impl Service {
+    /// Producer names of the replicas.
+    pub fn replica_names(&self) -> Vec<String> {
+        match self.replicas {
+            1 => vec![self.name.clone()],
+            n => (1..=n).map(|i| format!("{}-{i}", self.name)).collect(),
+        }
+    }
+}
+
+/// How a service writes its lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// `{"ts":…,"level":"info","msg":…,…fields}`
+    Json,
+    /// `ts=… level=info msg="…" key=value`
+    Logfmt,
+    /// `2024-01-15T10:32:05.250Z ERROR message`
+    #[default]
+    Plain,
+    /// Combined access log; the message is the request line and the
+    /// `status` / `bytes` fields fill their columns.
+    Access,
+}
+
+/// A line a service may write.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Template {
+    #[serde(default = "info", deserialize_with = "level")]
+    pub level: LogLevel,
+    pub message: String,
+    /// Relative frequency among its list.
+    #[serde(default = "unit")]
+    pub weight: f64,
+    #[serde(default)]
+    pub fields: BTreeMap<String, Value>,
+}
+
+/// Something that happens to some services for a while.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Event {
+    #[serde(deserialize_with = "offset")]
+    pub at: Duration,
+    #[serde(default, rename = "for", deserialize_with = "optional_offset")]
+    pub lasts: Option<Duration>,
+    #[serde(default, deserialize_with = "offset")]
+    pub ramp: Duration,
+    pub services: Vec<String>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub error_rate: f64,
+    #[serde(default = "unit")]
+    pub latency: f64,
+    #[serde(default = "unit")]
+    pub rate: f64,
+    #[serde(default)]
+    pub state: Option<String>,
+    #[serde(default)]
+    pub restart: bool,
+    #[serde(default)]
+    pub say: Vec<Template>,
+}
+
+impl Event {
+    /// When the event stops, if it does.
+    pub fn end(&self) -> Option<Duration> {
+        self.lasts.map(|lasts| self.at + lasts)
+    }
+
+    /// Whether the event applies to a replica of `service`.
+    pub fn targets(&self, service: &str, replica: &str) -> bool {
+        self.services
+            .iter()
+            .any(|pattern| wildcard(pattern, service) || wildcard(pattern, replica))
+    }
+}
+
+impl Scenario {
+    /// Parse and check a scenario file.
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let scenario: Scenario = toml::from_str(text).map_err(|e| e.to_string())?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// A shipped scenario by name, else the scenario file at `name`.
+    pub fn load(name: &str) -> Result<Self, FeedError> {
+        let invalid = |e| FeedError::DemoScenario(format!("{name}: {e}"));
+        if let Some((_, text)) = BUILTIN.iter().find(|(builtin, _)| *builtin == name) {
+            return Self::from_toml(text).map_err(invalid);
+        }
+        let text = std::fs::read_to_string(name).map_err(|e| {
+            let names: Vec<_> = BUILTIN.iter().map(|(name, _)| *name).collect();
+            FeedError::DemoScenario(format!(
+                "`{name}` is neither a scenario file ({e}) nor one of {}",
+                names.join(", ")
+            ))
+        })?;
+        Self::from_toml(&text).map_err(invalid)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.services.is_empty() {
+            return Err("no [[service]]".into());
+        }
+        for service in &self.services {
+            let name = &service.name;
+            if !non_negative(service.rate) || !non_negative(service.latency_ms) {
+                return Err(format!(
+                    "service `{name}`: rate and latency_ms must be >= 0"
+                ));
+            }
+            if service.replicas == 0 || service.lines.is_empty() {
+                return Err(format!("service `{name}` needs replicas and lines"));
+            }
+            let mut templates = service.lines.iter().chain(&service.errors);
+            if templates.any(|t| !non_negative(t.weight) || t.weight == 0.0) {
+                return Err(format!("service `{name}`: weights must be > 0"));
+            }
+        }
+        for event in &self.events {
+            let at = event.at.as_secs_f64();
+            if !(0.0..=1.0).contains(&event.error_rate) {
+                return Err(format!("event at {at}s: error_rate must be within 0-1"));
+            }
+            if !non_negative(event.latency) || !non_negative(event.rate) {
+                return Err(format!("event at {at}s: latency and rate must be >= 0"));
+            }
+            let matched = self.services.iter().any(|service| {
+                service
+                    .replica_names()
+                    .iter()
+                    .any(|replica| event.targets(&service.name, replica))
+            });
+            if !matched {
+                return Err(format!(
+                    "event at {at}s: {:?} match no service",
+                    event.services
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn non_negative(x: f64) -> bool {
+    x.is_finite() && x >= 0.0
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of characters.
+fn wildcard(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => {
+            let Some(text) = text.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=text.len())
+                .filter(|&i| text.is_char_boundary(i))
+                .any(|i| wildcard(rest, &text[i..]))
+        }
+    }
+}
+
+/// Parse a scenario offset: `500ms`, `90s`, `5m`, `1h`.
+pub fn parse_offset(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("`{s}` is not a duration like 90s or 5m");
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .ok_or_else(invalid)?;
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number.parse().map_err(|_| invalid())?;
+    let secs = match unit {
+        "ms" => number / 1000.0,
+        "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return Err(invalid()),
+    };
+    Ok(Duration::from_secs_f64(secs))
+}
+
+fn offset<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    parse_offset(&s).map_err(serde::de::Error::custom)
+}
+
+fn optional_offset<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    offset(deserializer).map(Some)
+}
+
+fn level<'de, D: Deserializer<'de>>(deserializer: D) -> Result<LogLevel, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    s.parse()
+        .map_err(|_| serde::de::Error::custom(format!("`{s}` is not a log level")))
+}
+
+fn yes() -> bool {
+    true
+}
+
+fn one() -> u32 {
+    1
+}
+
+fn unit() -> f64 {
+    1.0
+}
+
+fn info() -> LogLevel {
+    LogLevel::Info
+}
+
+fn default_latency() -> f64 {
+    20.0
+}
+
+// ----- Tests -----
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_scenarios_parse() {
+        for (name, text) in BUILTIN {
+            let scenario = Scenario::from_toml(text).unwrap_or_else(|e| panic!("{name}: {e}"));
+            assert_eq!(scenario.name, *name);
+        }
+    }
+
+    #[test]
+    fn wildcards_match_service_and_replica_names() {
+        assert!(wildcard("shop/worker-*", "shop/worker-2"));
+        assert!(wildcard("*/worker*", "shop/worker"));
+        assert!(wildcard("redis", "redis"));
+        assert!(!wildcard("redis", "redis-1"));
+        assert!(!wildcard("api-*", "shop/api-1"));
+    }
+
+    #[test]
+    fn bad_scenarios_are_refused() {
+        let service = r#"
+            [[service]]
+            name = "api"
+            rate = 1
+            lines = [{ message = "ok" }]
+        "#;
+        assert!(Scenario::from_toml(&format!("name = 'x'\n{service}")).is_ok());
+        let event = |body: &str| format!("name = 'x'\n{service}\n[[event]]\n{body}");
+        assert!(Scenario::from_toml(&event("at = '1m'\nservices = ['db']")).is_err());
+        assert!(Scenario::from_toml(&event("at = 'soon'\nservices = ['api']")).is_err());
+        assert!(
+            Scenario::from_toml(&event("at = '1m'\nservices = ['api']\nerror_rate = 2.0")).is_err()
+        );
+        assert!(Scenario::from_toml("name = 'x'\n[[service]]\nname = 'a'\nrate = 1\nlines = [{ message = 'm', level = 'loud' }]").is_err());
+    }
+
+    #[test]
+    fn offsets_parse() {
+        assert_eq!(parse_offset("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_offset("1.5m"), Ok(Duration::from_secs(90)));
+        assert!(parse_offset("90").is_err());
+    }
+}
@@ -1 +0,0 @@
-//! Docker feed — tails container logs via the Docker API or `docker logs -f`.
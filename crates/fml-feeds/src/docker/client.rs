@@ -0,0 +1,386 @@
+//! Minimal Docker Engine API client.
+//!
+//! Only what the feed needs: JSON `GET`s and long-lived streaming `GET`s
+//! (`/events`, `/containers/{id}/logs?follow=1`). Every request opens its own
+//! HTTP/1.1 connection, which is what long-lived streams need anyway and
+//! keeps the transport (Unix socket, TCP, or TCP with TLS) a detail of
+//! [`DockerClient::connect`]. Podman serves the same API on its own socket.
+//!
+//! TLS follows the Docker CLI: the server is verified against `ca.pem` and
+//! the client presents `cert.pem` / `key.pem` when given. Verification can be
+//! turned off (a context's `SkipTLSVerify`), in which case only the handshake
+//! signatures are checked.
+
+use crate::FeedError;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use http_body_util::{BodyExt, Empty};
+use hyper::{Request, StatusCode};
+use hyper_util::rt::TokioIo;
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use serde_json::Value;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpStream, UnixStream};
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::crypto::{ring, verify_tls12_signature, verify_tls13_signature};
+use tokio_rustls::rustls::{
+    self, ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use tokio_rustls::TlsConnector;
+
+/// Default Engine socket on Linux.
+pub const DEFAULT_SOCKET: &str = "/var/run/docker.sock";
+
+/// Where the Engine API is served.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    /// `unix:///var/run/docker.sock`
+    Unix(PathBuf),
+    /// `tcp://host:2375` or `http://host:2375`; `tcp://host:2376` with TLS.
+    Tcp { addr: String, tls: Option<Tls> },
+}
+
+/// TLS settings for a TCP endpoint, as in `DOCKER_CERT_PATH` or a context's
+/// TLS directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tls {
+    /// CA to verify the server against (`ca.pem`).
+    pub ca: Option<PathBuf>,
+    /// Client certificate and key (`cert.pem`, `key.pem`).
+    pub client: Option<(PathBuf, PathBuf)>,
+    /// Verify the server's certificate. Requires `ca`.
+    pub verify: bool,
+}
+
+impl Tls {
+    /// Use whichever of `ca.pem`, `cert.pem` and `key.pem` exist in `dir`.
+    pub fn from_dir(dir: &Path, verify: bool) -> Self {
+        let file = |name: &str| Some(dir.join(name)).filter(|p| p.is_file());
+        Self {
+            ca: file("ca.pem"),
+            client: file("cert.pem").zip(file("key.pem")),
+            verify,
+        }
+    }
+
+    fn client_config(&self) -> Result<ClientConfig, FeedError> {
+        let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(tls_error)?;
+        let builder = if self.verify {
+            let ca = self.ca.as_ref().ok_or_else(|| {
+                FeedError::Docker("TLS verification needs a CA certificate (ca.pem)".into())
+            })?;
+            let mut roots = RootCertStore::empty();
+            for cert in read_certs(ca)? {
+                roots.add(cert).map_err(tls_error)?;
+            }
+            builder.with_root_certificates(roots)
+        } else {
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(NoVerification))
+        };
+        match &self.client {
+            Some((cert, key)) => {
+                let key = PrivateKeyDer::from_pem_file(key)
+                    .map_err(|e| FeedError::Docker(format!("{}: {e}", key.display())))?;
+                builder
+                    .with_client_auth_cert(read_certs(cert)?, key)
+                    .map_err(tls_error)
+            }
+            None => Ok(builder.with_no_client_auth()),
+        }
+    }
+}
+
+fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, FeedError> {
+    CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect())
+        .map_err(|e| FeedError::Docker(format!("{}: {e}", path.display())))
+}
+
+fn tls_error(e: rustls::Error) -> FeedError {
+    FeedError::Docker(format!("TLS: {e}"))
+}
+
+/// Accepts any server certificate, for contexts with `SkipTLSVerify`.
+#[derive(Debug)]
+struct NoVerification;
+
+impl ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        let algorithms = ring::default_provider().signature_verification_algorithms;
+        verify_tls12_signature(message, cert, dss, &algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        let algorithms = ring::default_provider().signature_verification_algorithms;
+        verify_tls13_signature(message, cert, dss, &algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        ring::default_provider()
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+impl Default for Endpoint {
+    fn default() -> Self {
+        Endpoint::Unix(PathBuf::from(DEFAULT_SOCKET))
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::Unix(path) => write!(f, "unix://{}", path.display()),
+            Endpoint::Tcp { addr, .. } => write!(f, "tcp://{addr}"),
+        }
+    }
+}
+
+impl FromStr for Endpoint {
+    type Err = String;
+
+    /// Parse a `DOCKER_HOST`-style URL. A bare path is taken as a socket.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("unix://") {
+            return Ok(Endpoint::Unix(PathBuf::from(path)));
+        }
+        if let Some(addr) = s
+            .strip_prefix("tcp://")
+            .or_else(|| s.strip_prefix("http://"))
+        {
+            let addr = addr.trim_end_matches('/');
+            if addr.is_empty() {
+                return Err(format!("docker host `{s}` has no address"));
+            }
+            return Ok(Endpoint::Tcp {
+                addr: addr.to_string(),
+                tls: None,
+            });
+        }
+        if s.starts_with('/') {
+            return Ok(Endpoint::Unix(PathBuf::from(s)));
+        }
+        Err(format!(
+            "unsupported docker host `{s}` (expected unix://, tcp:// or http://)"
+        ))
+    }
+}
+
+/// A streaming response body.
+pub(super) type ByteStream = BoxStream<'static, std::io::Result<Bytes>>;
+
+/// A bidirectional byte stream an HTTP connection can run over.
+trait Io: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Io for T {}
+
+/// A client for one Engine.
+#[derive(Clone)]
+pub(super) struct DockerClient {
+    endpoint: Endpoint,
+    /// Built once from the endpoint's [`Tls`] settings.
+    tls: Option<TlsConnector>,
+}
+
+impl DockerClient {
+    /// Fails if the endpoint's TLS material cannot be loaded.
+    pub(super) fn new(endpoint: Endpoint) -> Result<Self, FeedError> {
+        let tls = match &endpoint {
+            Endpoint::Tcp { tls: Some(tls), .. } => {
+                Some(TlsConnector::from(Arc::new(tls.client_config()?)))
+            }
+            _ => None,
+        };
+        Ok(Self { endpoint, tls })
+    }
+
+    async fn connect(&self) -> std::io::Result<Box<dyn Io>> {
+        Ok(match (&self.endpoint, &self.tls) {
+            (Endpoint::Unix(path), _) => Box::new(UnixStream::connect(path).await?),
+            (Endpoint::Tcp { addr, .. }, None) => {
+                Box::new(TcpStream::connect(addr.as_str()).await?)
+            }
+            (Endpoint::Tcp { addr, .. }, Some(connector)) => {
+                let host = host_of(addr);
+                let name = ServerName::try_from(host.to_string())
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+                let tcp = TcpStream::connect(addr.as_str()).await?;
+                Box::new(connector.connect(name, tcp).await?)
+            }
+        })
+    }
+
+    /// `GET path` and parse the JSON body. `Ok(None)` on 404.
+    pub(super) async fn get_json(&self, path: &str) -> Result<Option<Value>, FeedError> {
+        let Some(body) = self.get(path).await? else {
+            return Ok(None);
+        };
+        let bytes = body
+            .collect()
+            .await
+            .map_err(|e| docker_error(path, e))?
+            .to_bytes();
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| docker_error(path, e))
+    }
+
+    /// `GET path` and return the body as a byte stream. `Ok(None)` on 404.
+    pub(super) async fn get_stream(&self, path: &str) -> Result<Option<ByteStream>, FeedError> {
+        let Some(body) = self.get(path).await? else {
+            return Ok(None);
+        };
+        Ok(Some(
+            body.into_data_stream()
+                .map_err(std::io::Error::other)
+                .boxed(),
+        ))
+    }
+
+    async fn get(&self, path: &str) -> Result<Option<hyper::body::Incoming>, FeedError> {
+        let io = self
+            .connect()
+            .await
+            .map_err(|e| FeedError::Docker(format!("connecting to {}: {e}", self.endpoint)))?;
+        let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(io))
+            .await
+            .map_err(|e| docker_error(path, e))?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                tracing::debug!(error = %e, "docker: connection closed");
+            }
+        });
+
+        let request = Request::get(path)
+            .header(hyper::header::HOST, "docker")
+            .body(Empty::<Bytes>::new())
+            .map_err(|e| docker_error(path, e))?;
+        let response = sender
+            .send_request(request)
+            .await
+            .map_err(|e| docker_error(path, e))?;
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            // The Engine explains errors as `{"message": "..."}`.
+            let body = response.into_body().collect().await.ok();
+            let message = body
+                .and_then(|b| serde_json::from_slice::<Value>(&b.to_bytes()).ok())
+                .and_then(|v| v["message"].as_str().map(str::to_string))
+                .unwrap_or_default();
+            return Err(FeedError::Docker(format!("{path}: {status} {message}")));
+        }
+        Ok(Some(response.into_body()))
+    }
+}
+
+/// The host part of `host:port` or `[v6]:port`, for TLS server names.
+fn host_of(addr: &str) -> &str {
+    if let Some(rest) = addr.strip_prefix('[') {
+        return rest.split(']').next().unwrap_or(rest);
+    }
+    addr.rsplit_once(':').map_or(addr, |(host, _)| host)
+}
+
+fn docker_error(path: &str, e: impl fmt::Display) -> FeedError {
+    FeedError::Docker(format!("{path}: {e}"))
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoints_parse_docker_host_urls() {
+        assert_eq!(
+            "unix:///run/user/1000/docker.sock".parse(),
+            Ok(Endpoint::Unix("/run/user/1000/docker.sock".into()))
+        );
+        assert_eq!(
+            "tcp://10.0.0.5:2375".parse(),
+            Ok(Endpoint::Tcp {
+                addr: "10.0.0.5:2375".into(),
+                tls: None
+            })
+        );
+        assert_eq!(
+            "http://127.0.0.1:2375/"
+                .parse::<Endpoint>()
+                .unwrap()
+                .to_string(),
+            "tcp://127.0.0.1:2375"
+        );
+        assert_eq!(
+            "/var/run/docker.sock".parse(),
+            Ok(Endpoint::Unix(DEFAULT_SOCKET.into()))
+        );
+        assert!("ssh://user@host".parse::<Endpoint>().is_err());
+        assert!("tcp://".parse::<Endpoint>().is_err());
+    }
+
+    #[test]
+    fn tls_server_names_drop_the_port() {
+        assert_eq!(host_of("docker.example.com:2376"), "docker.example.com");
+        assert_eq!(host_of("10.0.0.5:2376"), "10.0.0.5");
+        assert_eq!(host_of("[::1]:2376"), "::1");
+    }
+
+    #[test]
+    fn verifying_tls_needs_a_ca() {
+        let tls = Tls {
+            ca: None,
+            client: None,
+            verify: true,
+        };
+        let endpoint = Endpoint::Tcp {
+            addr: "10.0.0.5:2376".into(),
+            tls: Some(tls.clone()),
+        };
+        assert!(DockerClient::new(endpoint).is_err());
+
+        let insecure = Tls {
+            verify: false,
+            ..tls
+        };
+        assert!(insecure.client_config().is_ok());
+    }
+}
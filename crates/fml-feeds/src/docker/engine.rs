@@ -0,0 +1,321 @@
+//! Finding the Docker-compatible engines to connect to.
+//!
+//! Resolution follows the Docker CLI, then looks for local sockets:
+//!
+//! 1. `DOCKER_HOST` (with TLS from `DOCKER_CERT_PATH` when
+//!    `DOCKER_TLS_VERIFY` is set).
+//! 2. The current context — `DOCKER_CONTEXT`, else `currentContext` in
+//!    `~/.docker/config.json` — unless it is `default`.
+//! 3. The first socket that exists: `/var/run/docker.sock`, rootless Docker
+//!    (`$XDG_RUNTIME_DIR/docker.sock`), rootless Podman
+//!    (`$XDG_RUNTIME_DIR/podman/podman.sock`), then rootful Podman
+//!    (`/run/podman/podman.sock`).
+//!
+//! Contexts are read from `~/.docker/contexts/meta/*/meta.json`; their TLS
+//! material lives in the matching `contexts/tls/<id>/docker/` directory.
+
+use super::client::{Endpoint, Tls, DEFAULT_SOCKET};
+use crate::FeedError;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Rootful Podman's API socket.
+const PODMAN_SOCKET: &str = "/run/podman/podman.sock";
+
+/// A named engine. The name labels the engine's producer tree root when
+/// several run at once (`docker@podman`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Engine {
+    pub name: String,
+    pub endpoint: Endpoint,
+}
+
+impl Engine {
+    /// An engine named after its endpoint: `podman` for Podman sockets, the
+    /// socket's file stem for other sockets, `host:port` for TCP.
+    pub fn from_endpoint(endpoint: Endpoint) -> Self {
+        let name = match &endpoint {
+            Endpoint::Unix(path) if path.to_string_lossy().contains("podman") => {
+                "podman".to_string()
+            }
+            Endpoint::Unix(path) => path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "docker".to_string()),
+            Endpoint::Tcp { addr, .. } => addr.clone(),
+        };
+        Self { name, endpoint }
+    }
+
+    fn socket(name: &str, path: PathBuf) -> Self {
+        Self {
+            name: name.to_string(),
+            endpoint: Endpoint::Unix(path),
+        }
+    }
+}
+
+/// The parts of the process environment engine resolution reads.
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    /// `DOCKER_HOST`.
+    pub docker_host: Option<String>,
+    /// `DOCKER_TLS_VERIFY` is set to a non-empty value.
+    pub tls_verify: bool,
+    /// `DOCKER_CERT_PATH`; defaults to the config directory.
+    pub cert_path: Option<PathBuf>,
+    /// `DOCKER_CONTEXT`.
+    pub context: Option<String>,
+    /// `DOCKER_CONFIG`, else `~/.docker`.
+    pub config_dir: PathBuf,
+    /// `XDG_RUNTIME_DIR`, where rootless engines put their sockets.
+    pub runtime_dir: Option<PathBuf>,
+    /// Rootful sockets to probe, in order. Only overridden by tests.
+    pub system_sockets: Vec<(String, PathBuf)>,
+}
+
+impl Environment {
+    pub fn from_process() -> Self {
+        let var = |key: &str| std::env::var(key).ok().filter(|v| !v.is_empty());
+        let config_dir = var("DOCKER_CONFIG").map(PathBuf::from).unwrap_or_else(|| {
+            var("HOME")
+                .map(PathBuf::from)
+                .unwrap_or_default()
+                .join(".docker")
+        });
+        Self {
+            docker_host: var("DOCKER_HOST"),
+            tls_verify: var("DOCKER_TLS_VERIFY").is_some(),
+            cert_path: var("DOCKER_CERT_PATH").map(PathBuf::from),
+            context: var("DOCKER_CONTEXT"),
+            config_dir,
+            runtime_dir: var("XDG_RUNTIME_DIR").map(PathBuf::from),
+            system_sockets: vec![
+                ("docker".to_string(), PathBuf::from(DEFAULT_SOCKET)),
+                ("podman".to_string(), PathBuf::from(PODMAN_SOCKET)),
+            ],
+        }
+    }
+
+    /// Attach `DOCKER_CERT_PATH` certificates to a TCP endpoint when
+    /// `DOCKER_TLS_VERIFY` is set, as the Docker CLI does for `-H`.
+    pub fn with_tls(&self, mut endpoint: Endpoint) -> Endpoint {
+        if let Endpoint::Tcp {
+            tls: tls @ None, ..
+        } = &mut endpoint
+        {
+            if self.tls_verify {
+                let certs = self.cert_path.as_deref().unwrap_or(&self.config_dir);
+                *tls = Some(Tls::from_dir(certs, true));
+            }
+        }
+        endpoint
+    }
+
+    /// The context selected by `DOCKER_CONTEXT` or `config.json`.
+    fn current_context(&self) -> Option<String> {
+        if let Some(context) = &self.context {
+            return Some(context.clone());
+        }
+        let config = std::fs::read(self.config_dir.join("config.json")).ok()?;
+        let config: Value = serde_json::from_slice(&config).ok()?;
+        config["currentContext"]
+            .as_str()
+            .filter(|c| !c.is_empty())
+            .map(str::to_string)
+    }
+}
+
+/// The engine to use when none is named on the command line.
+pub fn detect(env: &Environment) -> Result<Engine, FeedError> {
+    if let Some(host) = &env.docker_host {
+        let endpoint = host.parse().map_err(FeedError::DockerConfig)?;
+        return Ok(Engine::from_endpoint(env.with_tls(endpoint)));
+    }
+    if let Some(name) = env.current_context().filter(|c| c != "default") {
+        return context(env, &name);
+    }
+
+    let mut candidates = Vec::new();
+    let mut system = env.system_sockets.iter();
+    candidates.extend(system.next().map(|(n, p)| Engine::socket(n, p.clone())));
+    if let Some(runtime) = &env.runtime_dir {
+        candidates.push(Engine::socket("rootless", runtime.join("docker.sock")));
+        candidates.push(Engine::socket("podman", runtime.join("podman/podman.sock")));
+    }
+    candidates.extend(system.map(|(n, p)| Engine::socket(n, p.clone())));
+
+    let found = candidates
+        .into_iter()
+        .find(|e| matches!(&e.endpoint, Endpoint::Unix(path) if path.exists()));
+    Ok(found.unwrap_or_else(|| Engine::socket("docker", PathBuf::from(DEFAULT_SOCKET))))
+}
+
+/// The engine of Docker context `name`.
+pub fn context(env: &Environment, name: &str) -> Result<Engine, FeedError> {
+    if name == "default" {
+        return Ok(Engine::socket("default", PathBuf::from(DEFAULT_SOCKET)));
+    }
+    let contexts = env.config_dir.join("contexts");
+    let (id, meta) = find_context(&contexts.join("meta"), name)?;
+    let docker = &meta["Endpoints"]["docker"];
+    let host = docker["Host"]
+        .as_str()
+        .ok_or_else(|| FeedError::DockerConfig(format!("context `{name}` has no docker host")))?;
+    let mut endpoint: Endpoint = host
+        .parse()
+        .map_err(|e| FeedError::DockerConfig(format!("context `{name}`: {e}")))?;
+    if let Endpoint::Tcp { tls, .. } = &mut endpoint {
+        let dir = contexts.join("tls").join(id).join("docker");
+        if dir.is_dir() {
+            let verify = !docker["SkipTLSVerify"].as_bool().unwrap_or(false);
+            *tls = Some(Tls::from_dir(&dir, verify));
+        }
+    }
+    Ok(Engine {
+        name: name.to_string(),
+        endpoint,
+    })
+}
+
+/// Find the `meta.json` whose `Name` is `name`. Returns its directory name,
+/// which also keys the context's TLS directory.
+fn find_context(meta_dir: &Path, name: &str) -> Result<(String, Value), FeedError> {
+    let entries = std::fs::read_dir(meta_dir).map_err(|e| {
+        FeedError::DockerConfig(format!("no context `{name}` ({}: {e})", meta_dir.display()))
+    })?;
+    for entry in entries.flatten() {
+        let Ok(meta) = std::fs::read(entry.path().join("meta.json")) else {
+            continue;
+        };
+        let Ok(meta) = serde_json::from_slice::<Value>(&meta) else {
+            continue;
+        };
+        if meta["Name"] == name {
+            return Ok((entry.file_name().to_string_lossy().into_owned(), meta));
+        }
+    }
+    Err(FeedError::DockerConfig(format!("no context `{name}`")))
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(dir: &Path) -> Environment {
+        Environment {
+            config_dir: dir.join(".docker"),
+            ..Environment::default()
+        }
+    }
+
+    fn write_context(config_dir: &Path, id: &str, meta: Value, tls: bool) {
+        let meta_dir = config_dir.join("contexts/meta").join(id);
+        std::fs::create_dir_all(&meta_dir).unwrap();
+        std::fs::write(meta_dir.join("meta.json"), meta.to_string()).unwrap();
+        if tls {
+            let tls_dir = config_dir.join("contexts/tls").join(id).join("docker");
+            std::fs::create_dir_all(&tls_dir).unwrap();
+            for file in ["ca.pem", "cert.pem", "key.pem"] {
+                std::fs::write(tls_dir.join(file), "").unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn docker_host_wins_and_takes_tls_from_cert_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let certs = dir.path().join("certs");
+        std::fs::create_dir_all(&certs).unwrap();
+        std::fs::write(certs.join("ca.pem"), "").unwrap();
+        let env = Environment {
+            docker_host: Some("tcp://10.0.0.5:2376".into()),
+            tls_verify: true,
+            cert_path: Some(certs.clone()),
+            context: Some("remote".into()),
+            ..env(dir.path())
+        };
+        let engine = detect(&env).unwrap();
+        assert_eq!(engine.name, "10.0.0.5:2376");
+        let Endpoint::Tcp { tls: Some(tls), .. } = engine.endpoint else {
+            panic!("expected TLS endpoint");
+        };
+        assert_eq!(tls.ca, Some(certs.join("ca.pem")));
+        assert_eq!(tls.client, None);
+        assert!(tls.verify);
+    }
+
+    #[test]
+    fn current_context_is_resolved_with_its_tls_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = env(dir.path());
+        let meta = serde_json::json!({
+            "Name": "staging",
+            "Endpoints": {"docker": {"Host": "tcp://staging:2376", "SkipTLSVerify": true}},
+        });
+        write_context(&env.config_dir, "5f2b", meta, true);
+        std::fs::write(
+            env.config_dir.join("config.json"),
+            r#"{"currentContext": "staging"}"#,
+        )
+        .unwrap();
+
+        let engine = detect(&env).unwrap();
+        assert_eq!(engine.name, "staging");
+        let Endpoint::Tcp {
+            addr,
+            tls: Some(tls),
+        } = engine.endpoint
+        else {
+            panic!("expected TLS endpoint");
+        };
+        assert_eq!(addr, "staging:2376");
+        let tls_dir = env.config_dir.join("contexts/tls/5f2b/docker");
+        assert_eq!(tls.ca, Some(tls_dir.join("ca.pem")));
+        assert_eq!(
+            tls.client,
+            Some((tls_dir.join("cert.pem"), tls_dir.join("key.pem")))
+        );
+        assert!(!tls.verify);
+
+        assert!(context(&env, "missing").is_err());
+    }
+
+    #[test]
+    fn local_sockets_are_probed_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = dir.path().join("run");
+        let env = Environment {
+            runtime_dir: Some(runtime.clone()),
+            system_sockets: vec![("docker".into(), dir.path().join("docker.sock"))],
+            ..env(dir.path())
+        };
+        // Nothing exists: fall back to the default socket.
+        assert_eq!(
+            detect(&env).unwrap().endpoint,
+            Endpoint::Unix(DEFAULT_SOCKET.into())
+        );
+
+        std::fs::create_dir_all(runtime.join("podman")).unwrap();
+        std::fs::write(runtime.join("podman/podman.sock"), "").unwrap();
+        assert_eq!(detect(&env).unwrap().name, "podman");
+
+        std::fs::write(runtime.join("docker.sock"), "").unwrap();
+        assert_eq!(detect(&env).unwrap().name, "rootless");
+
+        std::fs::write(dir.path().join("docker.sock"), "").unwrap();
+        assert_eq!(detect(&env).unwrap().name, "docker");
+    }
+
+    #[test]
+    fn engines_are_named_after_their_endpoint() {
+        let name = |s: &str| Engine::from_endpoint(s.parse().unwrap()).name;
+        assert_eq!(name("unix:///var/run/docker.sock"), "docker");
+        assert_eq!(name("unix:///run/user/1000/podman/podman.sock"), "podman");
+        assert_eq!(name("tcp://10.0.0.5:2375"), "10.0.0.5:2375");
+    }
+}
@@ -0,0 +1,372 @@
+//! Per-container log streaming for the Docker feed.
+//!
+//! Containers without a TTY have their stdout and stderr multiplexed into one
+//! response, each chunk wrapped in an 8-byte frame header:
+//! `[stream, 0, 0, 0, size (u32 big-endian)]` with stream `1` = stdout and
+//! `2` = stderr. Frames do not respect line boundaries, so [`Demux`] keeps a
+//! partial-line buffer per stream. TTY containers send plain bytes.
+//!
+//! Logs are requested with `timestamps=1` and re-opened with `since` after a
+//! disconnect, using the same [`Cursor`] as the Kubernetes feed so reconnects
+//! never duplicate lines.
+
+use super::client::{ByteStream, DockerClient};
+use super::Container;
+use crate::control::{tree_id, Signal, Signals};
+use crate::lines::{self, LineReader};
+use crate::resume::{split_timestamp, Cursor};
+use crate::{line_entry, report, Backoff};
+use bytes::Bytes;
+use fml_core::{FeedKind, LogEntry, ProducerStatus, ProducerUpdate};
+use std::collections::VecDeque;
+use std::io::ErrorKind;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::mpsc;
+use tokio_util::io::StreamReader;
+
+/// Which output stream a line was written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Output {
+    Stdout,
+    Stderr,
+}
+
+impl Output {
+    fn as_str(self) -> &'static str {
+        match self {
+            Output::Stdout => "stdout",
+            Output::Stderr => "stderr",
+        }
+    }
+}
+
+/// Splits a multiplexed log stream into lines, tagged with their stream.
+pub(super) struct Demux<R> {
+    inner: R,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    ready: VecDeque<(Output, String)>,
+    eof: bool,
+}
+
+impl<R: AsyncRead + Unpin> Demux<R> {
+    pub(super) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+            ready: VecDeque::new(),
+            eof: false,
+        }
+    }
+
+    /// The next complete line, or `None` at EOF. Unterminated trailing
+    /// output is returned as a final line.
+    pub(super) async fn next_line(&mut self) -> std::io::Result<Option<(Output, String)>> {
+        loop {
+            if let Some(line) = self.ready.pop_front() {
+                return Ok(Some(line));
+            }
+            if self.eof {
+                return Ok(None);
+            }
+            let mut header = [0u8; 8];
+            match self.inner.read_exact(&mut header).await {
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                    self.eof = true;
+                    for output in [Output::Stdout, Output::Stderr] {
+                        let buf = std::mem::take(self.buffer(output));
+                        if !buf.is_empty() {
+                            self.ready.push_back((output, lossy_line(&buf)));
+                        }
+                    }
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+            let output = match header[0] {
+                2 => Output::Stderr,
+                _ => Output::Stdout,
+            };
+            let size = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+            let mut payload = vec![0; size];
+            self.inner.read_exact(&mut payload).await?;
+
+            let buf = self.buffer(output);
+            buf.extend_from_slice(&payload);
+            let mut lines = Vec::new();
+            while let Some(end) = buf.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buf.drain(..=end).collect();
+                lines.push((output, lossy_line(&line[..end])));
+            }
+            self.ready.extend(lines);
+        }
+    }
+
+    fn buffer(&mut self, output: Output) -> &mut Vec<u8> {
+        match output {
+            Output::Stdout => &mut self.stdout,
+            Output::Stderr => &mut self.stderr,
+        }
+    }
+}
+
+fn lossy_line(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// A container's log response: multiplexed, or raw for TTY containers.
+enum LogLines {
+    Multiplexed(Demux<StreamReader<ByteStream, Bytes>>),
+    Raw(LineReader<StreamReader<ByteStream, Bytes>>),
+}
+
+impl LogLines {
+    fn new(stream: ByteStream, tty: bool) -> Self {
+        if tty {
+            LogLines::Raw(lines::from_stream(stream))
+        } else {
+            LogLines::Multiplexed(Demux::new(StreamReader::new(stream)))
+        }
+    }
+
+    async fn next_line(&mut self) -> std::io::Result<Option<(Output, String)>> {
+        match self {
+            LogLines::Multiplexed(demux) => demux.next_line().await,
+            LogLines::Raw(reader) => Ok(reader.next_line().await?.map(|l| (Output::Stdout, l))),
+        }
+    }
+}
+
+/// `/containers/{id}/logs` in follow mode: backfill `tail` lines on the
+/// first connect, then re-open from the cursor.
+fn logs_path(id: &str, cursor: &Cursor, tail: Option<u64>) -> String {
+    let mut path = format!("/containers/{id}/logs?follow=1&stdout=1&stderr=1&timestamps=1");
+    match (cursor.last(), tail) {
+        (Some(last), _) => path.push_str(&format!(
+            "&since={}.{:09}",
+            last.timestamp(),
+            last.timestamp_subsec_nanos()
+        )),
+        (None, Some(tail)) => path.push_str(&format!("&tail={tail}")),
+        (None, None) => {}
+    }
+    path
+}
+
+/// Stream `container`'s log until it stops or is removed, or `tx` is closed.
+/// Exits are reported by the feed's event loop, which knows the exit code.
+/// A reconnect on `signals` reopens the stream at once; a stop closes it
+/// until the container is started again, leaving out what it logged between.
+pub(super) async fn follow(
+    client: DockerClient,
+    container: Container,
+    tail: Option<u64>,
+    mut backoff: Backoff,
+    mut signals: Signals,
+    tx: mpsc::Sender<LogEntry>,
+    updates: mpsc::Sender<ProducerUpdate>,
+) {
+    let root = fml_core::feed_root(FeedKind::Docker, container.instance.as_deref());
+    let id = tree_id(&root, &container.producer);
+    let mut cursor = Cursor::default();
+    loop {
+        if signals.stopped(&id) {
+            tracing::debug!(producer = %container.producer, "docker: stopped");
+            tokio::select! {
+                () = signals.started(&id) => {}
+                () = tx.closed() => return,
+            }
+            backoff.reset();
+            cursor.restart_at(chrono::Utc::now());
+        }
+        let mut signal = None;
+        let path = logs_path(&container.id, &cursor, tail);
+        let status = ProducerStatus::Connecting;
+        report(
+            &updates,
+            FeedKind::Docker,
+            container.instance.as_deref(),
+            &container.producer,
+            status,
+        )
+        .await;
+        match client.get_stream(&path).await {
+            Ok(Some(stream)) => {
+                let status = ProducerStatus::Streaming;
+                report(
+                    &updates,
+                    FeedKind::Docker,
+                    container.instance.as_deref(),
+                    &container.producer,
+                    status,
+                )
+                .await;
+                let mut lines = LogLines::new(stream, container.tty);
+                loop {
+                    let line = tokio::select! {
+                        line = lines.next_line() => line,
+                        asked = signals.next(&id) => {
+                            signal = Some(asked);
+                            break;
+                        }
+                    };
+                    match line {
+                        Ok(Some((output, line))) => {
+                            let (ts, message) = split_timestamp(&line);
+                            if !cursor.admit(ts) {
+                                continue;
+                            }
+                            backoff.reset();
+                            let mut entry = container_entry(&container, message);
+                            if let Some(ts) = ts {
+                                entry.ts = ts;
+                            }
+                            entry
+                                .fields
+                                .insert("stream".to_string(), output.as_str().into());
+                            if tx.send(entry).await.is_err() {
+                                return;
+                            }
+                        }
+                        Ok(None) => break,
+                        Err(e) => {
+                            tracing::debug!(producer = %container.producer, error = %e, "docker: log stream error");
+                            break;
+                        }
+                    }
+                }
+            }
+            // Removed.
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!(producer = %container.producer, error = %e, "docker: log request failed");
+            }
+        }
+
+        match signal {
+            Some(Signal::Reconnect) => {
+                tracing::debug!(producer = %container.producer, "docker: reconnect requested");
+                backoff.reset();
+                cursor.resume();
+                continue;
+            }
+            // Closed until started, at the top of the loop.
+            Some(Signal::Stop) => continue,
+            None => {}
+        }
+
+        // The stream ended. Only reconnect if the container is still up.
+        let inspect = format!("/containers/{}/json", container.id);
+        match client.get_json(&inspect).await {
+            Ok(Some(info)) if info["State"]["Running"] == true => {}
+            Ok(_) => return,
+            Err(e) => {
+                tracing::debug!(producer = %container.producer, error = %e, "docker: inspect failed");
+            }
+        }
+        if tx.is_closed() {
+            return;
+        }
+
+        cursor.resume();
+        let delay = backoff.next_delay();
+        report(
+            &updates,
+            FeedKind::Docker,
+            container.instance.as_deref(),
+            &container.producer,
+            ProducerStatus::Backoff {
+                attempt: backoff.attempt(),
+            },
+        )
+        .await;
+        tokio::select! {
+            () = tokio::time::sleep(delay) => {}
+            _ = signals.next(&id) => backoff.reset(),
+        }
+    }
+}
+
+fn container_entry(container: &Container, message: &str) -> LogEntry {
+    let mut entry = line_entry(FeedKind::Docker, &container.producer, message.to_string());
+    entry.instance = container.instance.clone();
+    let short_id = &container.id[..container.id.len().min(12)];
+    for (key, value) in [
+        ("container", container.name.as_str()),
+        ("container_id", short_id),
+        ("image", container.image.as_str()),
+    ] {
+        entry.fields.insert(key.to_string(), value.into());
+    }
+    entry
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(stream: u8, payload: &[u8]) -> Vec<u8> {
+        let mut out = vec![stream, 0, 0, 0];
+        out.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        out.extend_from_slice(payload);
+        out
+    }
+
+    async fn demux_all(bytes: Vec<u8>) -> Vec<(Output, String)> {
+        let mut demux = Demux::new(bytes.as_slice());
+        let mut out = Vec::new();
+        while let Some(line) = demux.next_line().await.unwrap() {
+            out.push(line);
+        }
+        out
+    }
+
+    #[tokio::test]
+    async fn frames_are_split_into_tagged_lines() {
+        let mut bytes = frame(1, b"one\ntwo\n");
+        bytes.extend(frame(2, b"oops\r\n"));
+        let lines = demux_all(bytes).await;
+        assert_eq!(
+            lines,
+            [
+                (Output::Stdout, "one".to_string()),
+                (Output::Stdout, "two".to_string()),
+                (Output::Stderr, "oops".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn lines_split_across_frames_are_reassembled_per_stream() {
+        let mut bytes = frame(1, b"hel");
+        bytes.extend(frame(2, b"err "));
+        bytes.extend(frame(1, b"lo\n"));
+        bytes.extend(frame(2, b"tail"));
+        let lines = demux_all(bytes).await;
+        assert_eq!(
+            lines,
+            [
+                (Output::Stdout, "hello".to_string()),
+                (Output::Stderr, "err tail".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn logs_path_switches_from_tail_to_since() {
+        let mut cursor = Cursor::default();
+        assert!(logs_path("abc", &cursor, Some(100)).ends_with("&tail=100"));
+        let ts = chrono::DateTime::parse_from_rfc3339("2024-01-15T10:00:00.5Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        cursor.admit(Some(ts));
+        assert!(logs_path("abc", &cursor, Some(100)).ends_with("&since=1705312800.500000000"));
+    }
+}
@@ -0,0 +1,583 @@
+//! Docker feed — follows container logs through the Engine API.
+//!
+//! The feed talks HTTP to the Engine socket directly (see [`client`]); no
+//! `docker` CLI is involved. It subscribes to `GET /events` for container
+//! lifecycle events *before* listing the running containers, so a container
+//! started between the two calls is not missed. Then:
+//!
+//! - `start` attaches a log stream (`/containers/{id}/logs?follow=1`, see
+//!   [`logs`]) to the container if it matches the filters.
+//! - `die` reports the container as [`ProducerStatus::Ended`] with its exit
+//!   code. A restarted container is re-attached on its next `start`.
+//! - `destroy` reports [`ProducerStatus::Removed`], so the tree drops it.
+//!
+//! With a metadata channel ([`DockerFeed::with_metadata`]) the feed also
+//! reports each container's [`ProducerState`]: `running`, or the health
+//! check's `starting` / `healthy` / `unhealthy` (updated from
+//! `health_status` events), then `exited(code)` — or `OOMKilled` after an
+//! `oom` event — when it dies. Fields: `restarts`, and `exit_code` once
+//! stopped.
+//!
+//! With [`DockerFeed::with_signals`] a container's log stream is reopened
+//! on request, resuming from the last line seen, and closed while the
+//! container is stopped in the UI.
+//!
+//! If the event stream drops, it is re-opened with `since` set to the last
+//! event seen, so nothing that happened in between is lost.
+//!
+//! Producers are the container name, or `project/service` for Compose
+//! containers (`project/service-2` for the second and later replicas), so
+//! the tree groups a Compose project's services under it. Every entry carries
+//! `container`, `container_id`, `image` and `stream` (`stdout` / `stderr`).
+//!
+//! One feed follows one engine: a local or rootless Docker daemon, Podman,
+//! or a remote daemon over TCP/TLS (see [`engine`] for how it is found).
+//! Several engines run as several feeds, each with its own
+//! [`instance`](DockerConfig::instance) name so they get separate roots in
+//! the producer tree.
+
+mod client;
+pub mod engine;
+mod logs;
+
+pub use client::{Endpoint, Tls, DEFAULT_SOCKET};
+pub use engine::Engine;
+
+use crate::{control::Signals, lines, report, Backoff, FeedError};
+use client::DockerClient;
+use fml_core::{FeedKind, LogEntry, ProducerMeta, ProducerState, ProducerStatus, ProducerUpdate};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// `filters={"type":["container"]}`, URL-encoded.
+const CONTAINER_EVENTS: &str = "/events?filters=%7B%22type%22%3A%5B%22container%22%5D%7D";
+
+/// The Engine's event stream: one JSON object per line.
+type EventLines = lines::LineReader<tokio_util::io::StreamReader<client::ByteStream, bytes::Bytes>>;
+
+/// Compose labels used to name producers.
+const COMPOSE_PROJECT: &str = "com.docker.compose.project";
+const COMPOSE_SERVICE: &str = "com.docker.compose.service";
+const COMPOSE_NUMBER: &str = "com.docker.compose.container-number";
+
+/// Where to connect and what to follow.
+#[derive(Debug, Clone, Default)]
+pub struct DockerConfig {
+    /// The Engine to connect to.
+    pub endpoint: Endpoint,
+    /// Name of this feed instance when several engines are followed at
+    /// once; labels its producer tree root (`docker@podman`).
+    pub instance: Option<String>,
+    /// Only follow containers matching at least one filter. Empty follows
+    /// every container.
+    pub filters: Vec<ContainerFilter>,
+    /// Lines of existing output to backfill per container on attach. `None`
+    /// backfills the container's whole log.
+    pub tail: Option<u64>,
+}
+
+/// Selects containers by name or label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContainerFilter {
+    /// `name=api` — the container name contains `api`.
+    Name(String),
+    /// `label=key` or `label=key=value`.
+    Label { key: String, value: Option<String> },
+}
+
+impl ContainerFilter {
+    fn matches(&self, container: &Container) -> bool {
+        match self {
+            ContainerFilter::Name(name) => container.name.contains(name.as_str()),
+            ContainerFilter::Label { key, value } => match container.labels.get(key) {
+                Some(actual) => value.as_ref().is_none_or(|v| v == actual),
+                None => false,
+            },
+        }
+    }
+}
+
+impl fmt::Display for ContainerFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContainerFilter::Name(name) => write!(f, "name={name}"),
+            ContainerFilter::Label { key, value: None } => write!(f, "label={key}"),
+            ContainerFilter::Label {
+                key,
+                value: Some(value),
+            } => write!(f, "label={key}={value}"),
+        }
+    }
+}
+
+impl FromStr for ContainerFilter {
+    type Err = String;
+
+    /// Parse `name=SUBSTR`, `label=KEY` or `label=KEY=VALUE`, as accepted by
+    /// `docker ps --filter`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            || format!("container filter `{s}` must be name=..., label=KEY or label=KEY=VALUE");
+        let (kind, rest) = s.split_once('=').ok_or_else(invalid)?;
+        if rest.is_empty() {
+            return Err(invalid());
+        }
+        match kind {
+            "name" => Ok(ContainerFilter::Name(rest.to_string())),
+            "label" => Ok(match rest.split_once('=') {
+                Some((key, value)) => ContainerFilter::Label {
+                    key: key.to_string(),
+                    value: Some(value.to_string()),
+                },
+                None => ContainerFilter::Label {
+                    key: rest.to_string(),
+                    value: None,
+                },
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// What the feed needs to know about one container, from its inspect output.
+#[derive(Debug, Clone)]
+struct Container {
+    id: String,
+    name: String,
+    image: String,
+    labels: HashMap<String, String>,
+    tty: bool,
+    running: bool,
+    producer: String,
+    /// Instance of the feed following the container; see
+    /// [`DockerConfig::instance`].
+    instance: Option<String>,
+}
+
+impl Container {
+    /// Parse `GET /containers/{id}/json`.
+    fn from_inspect(info: &Value) -> Option<Self> {
+        let id = info["Id"].as_str()?.to_string();
+        let name = info["Name"]
+            .as_str()
+            .unwrap_or(&id)
+            .trim_start_matches('/')
+            .to_string();
+        let config = &info["Config"];
+        let labels: HashMap<String, String> = config["Labels"]
+            .as_object()
+            .map(|labels| {
+                labels
+                    .iter()
+                    .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let producer = producer_name(&name, &labels);
+        Some(Self {
+            image: config["Image"].as_str().unwrap_or_default().to_string(),
+            tty: config["Tty"].as_bool().unwrap_or(false),
+            running: info["State"]["Running"].as_bool().unwrap_or(false),
+            id,
+            name,
+            labels,
+            producer,
+            instance: None,
+        })
+    }
+}
+
+/// `project/service[-N]` for Compose containers, the container name
+/// otherwise.
+fn producer_name(name: &str, labels: &HashMap<String, String>) -> String {
+    match (labels.get(COMPOSE_PROJECT), labels.get(COMPOSE_SERVICE)) {
+        (Some(project), Some(service)) => {
+            match labels
+                .get(COMPOSE_NUMBER)
+                .and_then(|n| n.parse::<u32>().ok())
+            {
+                Some(n) if n > 1 => format!("{project}/{service}-{n}"),
+                _ => format!("{project}/{service}"),
+            }
+        }
+        _ => name.to_string(),
+    }
+}
+
+/// A Docker feed for one Engine, ready to [`run`](DockerFeed::run).
+pub struct DockerFeed {
+    client: DockerClient,
+    instance: Option<String>,
+    filters: Vec<ContainerFilter>,
+    tail: Option<u64>,
+    metadata: Option<mpsc::Sender<ProducerMeta>>,
+    signals: Signals,
+}
+
+/// A container the feed has attached to.
+struct Tracked {
+    producer: String,
+    task: JoinHandle<()>,
+    restarts: u32,
+    /// An `oom` event arrived since the container last started.
+    oom: bool,
+}
+
+impl DockerFeed {
+    /// Fails if the endpoint's TLS certificates cannot be loaded.
+    pub fn new(config: &DockerConfig) -> Result<Self, FeedError> {
+        Ok(Self {
+            client: DockerClient::new(config.endpoint.clone())?,
+            instance: config.instance.clone(),
+            filters: config.filters.clone(),
+            tail: config.tail,
+            metadata: None,
+            signals: Signals::default(),
+        })
+    }
+
+    /// Report each container's [`ProducerState`] on `metadata`.
+    pub fn with_metadata(mut self, metadata: mpsc::Sender<ProducerMeta>) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Reopen or close containers' log streams when `signals` asks.
+    pub fn with_signals(mut self, signals: Signals) -> Self {
+        self.signals = signals;
+        self
+    }
+
+    /// Attach to every matching running container and follow the Engine's
+    /// events until `tx` is closed. Fails if the Engine cannot be reached at
+    /// startup; later disconnects are retried.
+    pub async fn run(
+        self,
+        tx: mpsc::Sender<LogEntry>,
+        updates: mpsc::Sender<ProducerUpdate>,
+    ) -> Result<(), FeedError> {
+        let mut events = self.open_events(None).await?;
+        let mut tracked: HashMap<String, Tracked> = HashMap::new();
+
+        let listed = self
+            .client
+            .get_json("/containers/json")
+            .await?
+            .unwrap_or_default();
+        for id in listed
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|c| c["Id"].as_str())
+        {
+            self.attach(id, &mut tracked, &tx, &updates).await;
+        }
+
+        let mut last_event: Option<i64> = None;
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(30));
+        loop {
+            let line = tokio::select! {
+                _ = tx.closed() => break,
+                line = events.next_line() => line,
+            };
+            match line {
+                Ok(Some(line)) => {
+                    let Ok(event) = serde_json::from_str::<Value>(&line) else {
+                        continue;
+                    };
+                    backoff.reset();
+                    last_event = event["time"].as_i64().or(last_event);
+                    self.handle_event(&event, &mut tracked, &tx, &updates).await;
+                }
+                Ok(None) | Err(_) => {
+                    tracing::debug!("docker: event stream ended, reconnecting");
+                    loop {
+                        tokio::select! {
+                            _ = tx.closed() => return Ok(()),
+                            _ = tokio::time::sleep(backoff.next_delay()) => {}
+                        }
+                        match self.open_events(last_event).await {
+                            Ok(reopened) => {
+                                events = reopened;
+                                break;
+                            }
+                            Err(e) => {
+                                tracing::warn!(error = %e, "docker: event stream reconnect failed")
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        for (_, container) in tracked {
+            container.task.abort();
+        }
+        Ok(())
+    }
+
+    async fn open_events(&self, since: Option<i64>) -> Result<EventLines, FeedError> {
+        let path = match since {
+            Some(since) => format!("{CONTAINER_EVENTS}&since={since}"),
+            None => CONTAINER_EVENTS.to_string(),
+        };
+        let stream = self
+            .client
+            .get_stream(&path)
+            .await?
+            .ok_or_else(|| FeedError::Docker(format!("{path}: not found")))?;
+        Ok(lines::from_stream(stream))
+    }
+
+    async fn handle_event(
+        &self,
+        event: &Value,
+        tracked: &mut HashMap<String, Tracked>,
+        tx: &mpsc::Sender<LogEntry>,
+        updates: &mpsc::Sender<ProducerUpdate>,
+    ) {
+        let Some(id) = event["Actor"]["ID"].as_str().or(event["id"].as_str()) else {
+            return;
+        };
+        match event["Action"].as_str().or(event["status"].as_str()) {
+            Some("start") => self.attach(id, tracked, tx, updates).await,
+            Some("oom") => {
+                if let Some(container) = tracked.get_mut(id) {
+                    container.oom = true;
+                }
+            }
+            Some("die") => {
+                if let Some(container) = tracked.get(id) {
+                    let code = event["Actor"]["Attributes"]["exitCode"]
+                        .as_str()
+                        .and_then(|c| c.parse().ok());
+                    let status = ProducerStatus::Ended { code };
+                    report(
+                        updates,
+                        FeedKind::Docker,
+                        self.instance.as_deref(),
+                        &container.producer,
+                        status,
+                    )
+                    .await;
+                    let state = ProducerState::Terminated {
+                        reason: container.oom.then(|| "OOMKilled".to_string()),
+                        code,
+                    };
+                    self.describe(container, state).await;
+                }
+            }
+            Some(action) if action.starts_with("health_status") => {
+                let health = action.rsplit(':').next().unwrap_or_default().trim();
+                if let (Some(container), Some(state)) = (tracked.get(id), health_state(health)) {
+                    self.describe(container, state).await;
+                }
+            }
+            Some("destroy") => {
+                if let Some(container) = tracked.remove(id) {
+                    container.task.abort();
+                    let status = ProducerStatus::Removed;
+                    report(
+                        updates,
+                        FeedKind::Docker,
+                        self.instance.as_deref(),
+                        &container.producer,
+                        status,
+                    )
+                    .await;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Inspect container `id` and start following its log if it is running,
+    /// matches the filters and is not already followed.
+    async fn attach(
+        &self,
+        id: &str,
+        tracked: &mut HashMap<String, Tracked>,
+        tx: &mpsc::Sender<LogEntry>,
+        updates: &mpsc::Sender<ProducerUpdate>,
+    ) {
+        let info = match self
+            .client
+            .get_json(&format!("/containers/{id}/json"))
+            .await
+        {
+            Ok(Some(info)) => info,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!(container = id, error = %e, "docker: inspect failed");
+                return;
+            }
+        };
+        let Some(mut container) = Container::from_inspect(&info) else {
+            return;
+        };
+        container.instance = self.instance.clone();
+        let restarts = info["RestartCount"].as_u64().unwrap_or(0) as u32;
+        if let Some(followed) = tracked.get_mut(id).filter(|t| !t.task.is_finished()) {
+            // Restarted before the old stream noticed; it carries on.
+            followed.restarts = restarts;
+            followed.oom = false;
+            self.describe(followed, inspect_state(&info)).await;
+            return;
+        }
+        if !container.running
+            || !(self.filters.is_empty() || self.filters.iter().any(|f| f.matches(&container)))
+        {
+            return;
+        }
+
+        tracing::debug!(producer = %container.producer, "docker: attaching");
+        let producer = container.producer.clone();
+        let task = tokio::spawn(logs::follow(
+            self.client.clone(),
+            container,
+            self.tail,
+            Backoff::new(Duration::from_secs(1), Duration::from_secs(30)),
+            self.signals.clone(),
+            tx.clone(),
+            updates.clone(),
+        ));
+        let followed = Tracked {
+            producer,
+            task,
+            restarts,
+            oom: false,
+        };
+        self.describe(&followed, inspect_state(&info)).await;
+        tracked.insert(id.to_string(), followed);
+    }
+
+    /// Send `container`'s state on the metadata channel, if there is one.
+    async fn describe(&self, container: &Tracked, state: ProducerState) {
+        let Some(metadata) = &self.metadata else {
+            return;
+        };
+        let mut fields = BTreeMap::from([("restarts".to_string(), container.restarts.to_string())]);
+        if let ProducerState::Terminated {
+            code: Some(code), ..
+        } = &state
+        {
+            fields.insert("exit_code".to_string(), code.to_string());
+        }
+        // Metadata is advisory, like status.
+        let _ = metadata
+            .send(ProducerMeta {
+                source: FeedKind::Docker,
+                instance: self.instance.clone(),
+                producer: container.producer.clone(),
+                state,
+                fields,
+            })
+            .await;
+    }
+}
+
+/// A container's state from its inspect output.
+fn inspect_state(info: &Value) -> ProducerState {
+    let state = &info["State"];
+    if state["Running"] == true {
+        let health = state["Health"]["Status"].as_str().unwrap_or_default();
+        return health_state(health).unwrap_or(ProducerState::Running);
+    }
+    ProducerState::Terminated {
+        reason: (state["OOMKilled"] == true).then(|| "OOMKilled".to_string()),
+        code: state["ExitCode"].as_i64().map(|c| c as i32),
+    }
+}
+
+/// A health check status (`healthy`, `unhealthy`, `starting`).
+fn health_state(health: &str) -> Option<ProducerState> {
+    match health {
+        "healthy" => Some(ProducerState::Healthy),
+        "unhealthy" => Some(ProducerState::Unhealthy),
+        "starting" => Some(ProducerState::Starting),
+        _ => None,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn container(name: &str, labels: &[(&str, &str)]) -> Container {
+        Container::from_inspect(&serde_json::json!({
+            "Id": "0123456789abcdef",
+            "Name": format!("/{name}"),
+            "Config": {
+                "Image": "nginx:latest",
+                "Tty": false,
+                "Labels": labels.iter().cloned().collect::<HashMap<_, _>>(),
+            },
+            "State": {"Running": true},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn compose_containers_are_named_by_project_and_service() {
+        let plain = container("nginx", &[]);
+        assert_eq!(plain.producer, "nginx");
+        assert_eq!(plain.name, "nginx");
+
+        let compose = [
+            (COMPOSE_PROJECT, "shop"),
+            (COMPOSE_SERVICE, "api"),
+            (COMPOSE_NUMBER, "1"),
+        ];
+        assert_eq!(container("shop-api-1", &compose).producer, "shop/api");
+
+        let second = [
+            (COMPOSE_PROJECT, "shop"),
+            (COMPOSE_SERVICE, "api"),
+            (COMPOSE_NUMBER, "2"),
+        ];
+        assert_eq!(container("shop-api-2", &second).producer, "shop/api-2");
+    }
+
+    #[test]
+    fn filters_parse_and_match() {
+        let by_name: ContainerFilter = "name=api".parse().unwrap();
+        let by_label: ContainerFilter = "label=tier".parse().unwrap();
+        let by_value: ContainerFilter = "label=tier=web".parse().unwrap();
+        assert_eq!(by_value.to_string(), "label=tier=web");
+
+        let web = container("shop-api-1", &[("tier", "web")]);
+        let db = container("db", &[("tier", "data")]);
+        assert!(by_name.matches(&web) && !by_name.matches(&db));
+        assert!(by_label.matches(&web) && by_label.matches(&db));
+        assert!(by_value.matches(&web) && !by_value.matches(&db));
+
+        assert!("name=".parse::<ContainerFilter>().is_err());
+        assert!("status=running".parse::<ContainerFilter>().is_err());
+        assert!("api".parse::<ContainerFilter>().is_err());
+    }
+
+    #[test]
+    fn state_comes_from_health_and_exit() {
+        let healthy = serde_json::json!({
+            "State": {"Running": true, "Health": {"Status": "unhealthy"}},
+        });
+        assert_eq!(inspect_state(&healthy), ProducerState::Unhealthy);
+        let plain = serde_json::json!({"State": {"Running": true}});
+        assert_eq!(inspect_state(&plain), ProducerState::Running);
+        let oom = serde_json::json!({
+            "State": {"Running": false, "OOMKilled": true, "ExitCode": 137},
+        });
+        assert_eq!(inspect_state(&oom).to_string(), "OOMKilled");
+        let exited = serde_json::json!({"State": {"Running": false, "ExitCode": 2}});
+        assert_eq!(inspect_state(&exited).to_string(), "exited(2)");
+    }
+}
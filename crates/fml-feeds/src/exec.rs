@@ -0,0 +1,266 @@
+//! Exec feed — runs an arbitrary command and tails its stdout and stderr.
+//!
+//! Anything that writes logs to a terminal can be a producer: `ssh host tail
+//! -f`, `aws logs tail`, `stern`, a test runner. Each command is named (the
+//! config key, or the program's basename for ad-hoc `fml --feed exec -- …`
+//! invocations) and contributes two producers, `<name>/stdout` and
+//! `<name>/stderr`. Every entry carries a synthetic `stream` field
+//! (`stdout` / `stderr`) so stderr output can be filtered on directly.
+//!
+//! Lifecycle changes are reported for the `<name>` node: [`ProducerStatus::Streaming`]
+//! on spawn, [`ProducerStatus::Ended`] with the exit code when the process
+//! exits, and [`ProducerStatus::Backoff`] while waiting to restart when
+//! [`ExecConfig::restart`] is set.
+//!
+//! Under [`run_with_signals`], stopping `<name>` kills the command and
+//! starting it spawns it afresh; a reconnect of `<name>` or either of its
+//! streams kills and respawns it at once.
+
+use crate::control::{tree_id, Signal, Signals};
+use crate::{line_entry, lines, Backoff, FeedError};
+use bytes::Bytes;
+use fml_core::{FeedKind, LogEntry, ProducerStatus, ProducerUpdate};
+use futures::Stream;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::process::Child;
+use tokio::sync::mpsc;
+use tokio_util::io::ReaderStream;
+
+/// One command to run as a producer.
+#[derive(Debug, Clone)]
+pub struct ExecConfig {
+    /// Producer name; the root of this command's `stdout` / `stderr` nodes.
+    pub name: String,
+    pub program: String,
+    pub args: Vec<String>,
+    /// Restart the command with exponential backoff whenever it exits.
+    pub restart: bool,
+    /// First restart delay; doubles on each consecutive failure.
+    pub initial_backoff: Duration,
+    /// Upper bound on the restart delay.
+    pub max_backoff: Duration,
+}
+
+impl ExecConfig {
+    /// Build a config from a command line, naming it after the program's
+    /// basename. Returns `None` if `argv` is empty.
+    pub fn from_argv(argv: &[String]) -> Option<Self> {
+        let (program, args) = argv.split_first()?;
+        let name = std::path::Path::new(program)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| program.clone());
+        Some(Self::named(name, program.clone(), args.to_vec()))
+    }
+
+    /// Build a config with an explicit producer name and default backoff.
+    pub fn named(name: impl Into<String>, program: impl Into<String>, args: Vec<String>) -> Self {
+        Self {
+            name: name.into(),
+            program: program.into(),
+            args,
+            restart: false,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Run the command described by `config`, forwarding its output to `tx` and
+/// lifecycle changes to `updates`.
+///
+/// Without [`ExecConfig::restart`] this returns once the process exits. With
+/// it, the command is restarted after each exit (or failed spawn) until `tx`
+/// is closed. A run that stays up for longer than the maximum backoff resets
+/// the backoff.
+pub async fn run(
+    config: &ExecConfig,
+    tx: mpsc::Sender<LogEntry>,
+    updates: mpsc::Sender<ProducerUpdate>,
+) -> Result<(), FeedError> {
+    run_with_signals(config, Signals::default(), tx, updates).await
+}
+
+/// Like [`run`], killing the command while `signals` has it stopped and
+/// respawning it on a reconnect.
+pub async fn run_with_signals(
+    config: &ExecConfig,
+    mut signals: Signals,
+    tx: mpsc::Sender<LogEntry>,
+    updates: mpsc::Sender<ProducerUpdate>,
+) -> Result<(), FeedError> {
+    let id = tree_id(&fml_core::feed_root(FeedKind::Exec, None), &config.name);
+    let mut backoff = Backoff::new(config.initial_backoff, config.max_backoff);
+    let report = |status: ProducerStatus| {
+        let updates = updates.clone();
+        let producer = config.name.clone();
+        async move {
+            // Status is advisory; a closed receiver must not stop the feed.
+            let update = ProducerUpdate {
+                source: FeedKind::Exec,
+                instance: None,
+                producer,
+                status,
+            };
+            let _ = updates.send(update).await;
+        }
+    };
+
+    loop {
+        if signals.stopped(&id) {
+            tracing::debug!(name = %config.name, "exec: stopped");
+            tokio::select! {
+                () = signals.started(&id) => {}
+                () = tx.closed() => return Err(FeedError::ChannelClosed),
+            }
+            backoff.reset();
+        }
+        let started = Instant::now();
+        let mut signal = None;
+        match spawn(config) {
+            Ok(child) => {
+                report(ProducerStatus::Streaming).await;
+                // Dropping the child kills it.
+                tokio::select! {
+                    code = tail(config, child, &tx) => {
+                        let code = code?;
+                        tracing::debug!(name = %config.name, ?code, "exec: process exited");
+                        report(ProducerStatus::Ended { code }).await;
+                    }
+                    asked = signals.next(&id) => signal = Some(asked),
+                }
+            }
+            Err(FeedError::Spawn { program, source }) if config.restart => {
+                tracing::warn!(name = %config.name, %program, error = %source, "exec: spawn failed");
+                report(ProducerStatus::Ended { code: None }).await;
+            }
+            Err(e) => return Err(e),
+        }
+
+        match signal {
+            Some(Signal::Reconnect) => {
+                tracing::debug!(name = %config.name, "exec: restart requested");
+                backoff.reset();
+                continue;
+            }
+            // Killed until started, at the top of the loop.
+            Some(Signal::Stop) => continue,
+            None => {}
+        }
+        if !config.restart {
+            return Ok(());
+        }
+        if tx.is_closed() {
+            return Err(FeedError::ChannelClosed);
+        }
+        if started.elapsed() >= backoff.max() {
+            backoff.reset();
+        }
+
+        let delay = backoff.next_delay();
+        report(ProducerStatus::Backoff {
+            attempt: backoff.attempt(),
+        })
+        .await;
+        tokio::select! {
+            () = tokio::time::sleep(delay) => {}
+            _ = signals.next(&id) => backoff.reset(),
+        }
+    }
+}
+
+fn spawn(config: &ExecConfig) -> Result<Child, FeedError> {
+    tokio::process::Command::new(&config.program)
+        .args(&config.args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|source| FeedError::Spawn {
+            program: config.program.clone(),
+            source,
+        })
+}
+
+/// Tail a spawned command to completion and return its exit code.
+async fn tail(
+    config: &ExecConfig,
+    mut child: Child,
+    tx: &mpsc::Sender<LogEntry>,
+) -> Result<Option<i32>, FeedError> {
+    let stdout = ReaderStream::new(child.stdout.take().expect("stdout is piped"));
+    let stderr = ReaderStream::new(child.stderr.take().expect("stderr is piped"));
+    ingest(&config.name, stdout, stderr, tx.clone()).await?;
+
+    let status = child.wait().await?;
+    Ok(status.code())
+}
+
+/// Read a command's stdout and stderr concurrently until both reach EOF,
+/// forwarding lines as `<name>/stdout` and `<name>/stderr` entries.
+pub async fn ingest<O, E>(
+    name: &str,
+    stdout: O,
+    stderr: E,
+    tx: mpsc::Sender<LogEntry>,
+) -> Result<(), FeedError>
+where
+    O: Stream<Item = std::io::Result<Bytes>> + Unpin,
+    E: Stream<Item = std::io::Result<Bytes>> + Unpin,
+{
+    let (out, err) = tokio::join!(
+        forward(name, "stdout", stdout, tx.clone()),
+        forward(name, "stderr", stderr, tx),
+    );
+    out.and(err)
+}
+
+async fn forward<S>(
+    name: &str,
+    stream_name: &str,
+    stream: S,
+    tx: mpsc::Sender<LogEntry>,
+) -> Result<(), FeedError>
+where
+    S: Stream<Item = std::io::Result<Bytes>> + Unpin,
+{
+    let producer = format!("{name}/{stream_name}");
+    let mut reader = lines::from_stream(stream);
+    while let Some(line) = reader.next_line().await? {
+        let mut entry = line_entry(FeedKind::Exec, &producer, line);
+        entry
+            .fields
+            .insert("stream".to_string(), stream_name.into());
+        tx.send(entry).await.map_err(|_| FeedError::ChannelClosed)?;
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn argv_is_named_after_program_basename() {
+        let argv: Vec<String> = ["/usr/bin/aws", "logs", "tail"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let cfg = ExecConfig::from_argv(&argv).unwrap();
+        assert_eq!(cfg.name, "aws");
+        assert_eq!(cfg.program, "/usr/bin/aws");
+        assert_eq!(cfg.args, ["logs", "tail"]);
+        assert!(!cfg.restart);
+    }
+
+    #[test]
+    fn empty_argv_is_rejected() {
+        assert!(ExecConfig::from_argv(&[]).is_none());
+    }
+}
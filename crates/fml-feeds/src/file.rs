@@ -1 +0,0 @@
-//! File feed — inotify-based tail with rotation support.
@@ -0,0 +1,293 @@
+//! Rotated archives of a followed file: `app.log.1`, `app.log.2.gz`,
+//! `app.log-20240101.zst`, `app.log.2024-01-01.bz2`, …
+//!
+//! Archives are read oldest first, before the live file's own backfill, so
+//! the stream starts where the retained history starts. Gzip is decoded in
+//! process; zstd and bzip2 through the `zstd` / `bzip2` tools, which must be
+//! on `PATH`.
+
+use std::cmp::Reverse;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+
+/// Lines buffered between the decompressing thread and the feed.
+const LINE_BUFFER: usize = 1024;
+
+/// How far back archives are read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveHorizon {
+    /// Archives last written within this long (`24h`, `30m`, `7d`).
+    Age(Duration),
+    /// The newest archives totalling at most this many bytes on disk
+    /// (`500MB`, `1GiB`, `64k`).
+    Bytes(u64),
+}
+
+impl FromStr for ArchiveHorizon {
+    type Err = String;
+
+    /// Parse an age (`90s`, `30m`, `24h`, `7d`) or a size (`512B`, `64k`,
+    /// `500MB`, `1GiB`; units are powers of 1024).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("`{s}` is neither an age (24h, 7d) nor a size (500MB)");
+        let split = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+        let (number, unit) = s.split_at(split);
+        let number: u64 = number.parse().map_err(|_| invalid())?;
+        let age = |secs: u64| Ok(ArchiveHorizon::Age(Duration::from_secs(number * secs)));
+        let bytes = |shift: u32| Ok(ArchiveHorizon::Bytes(number << shift));
+        match unit.to_ascii_lowercase().as_str() {
+            "s" => age(1),
+            "m" => age(60),
+            "h" => age(3600),
+            "d" => age(86_400),
+            "b" => bytes(0),
+            "k" | "kb" | "kib" => bytes(10),
+            "mb" | "mib" => bytes(20),
+            "g" | "gb" | "gib" => bytes(30),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// How an archive is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    /// Split a compression extension off `name`.
+    fn strip(name: &str) -> (&str, Self) {
+        for (ext, compression) in [
+            (".gz", Compression::Gzip),
+            (".zst", Compression::Zstd),
+            (".bz2", Compression::Bzip2),
+        ] {
+            if let Some(stem) = name.strip_suffix(ext) {
+                return (stem, compression);
+            }
+        }
+        (name, Compression::None)
+    }
+}
+
+/// A rotated sibling of a live file.
+#[derive(Debug, Clone)]
+pub(super) struct Archive {
+    pub path: PathBuf,
+    pub id: super::tail::FileId,
+    modified: SystemTime,
+    size: u64,
+    /// Numeric rotation index (`.3`), higher is older.
+    index: Option<u64>,
+    compression: Compression,
+}
+
+/// Whether `name` is a rotated copy of `live` (the file name of the followed
+/// file): the live name, then an index or date after `.`, `-` or `_`,
+/// optionally compressed. Returns the index and compression.
+fn rotated(live: &str, name: &str) -> Option<(Option<u64>, Compression)> {
+    let (stem, compression) = Compression::strip(name);
+    let suffix = stem.strip_prefix(live)?;
+    if suffix.is_empty() {
+        // `app.log.gz`: compressed without a suffix.
+        return (compression != Compression::None).then_some((None, compression));
+    }
+    let rest = suffix.strip_prefix(['.', '-', '_'])?;
+    let dated = rest.starts_with(|c: char| c.is_ascii_digit())
+        && rest
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '-' | '_' | '.' | 'T' | ':'));
+    // Small numbers are rotation indexes; `20240101` is a date.
+    let index = rest.parse().ok().filter(|&n| n < 10_000);
+    dated.then_some((index, compression))
+}
+
+/// Rotated siblings of `live` within `horizon`, oldest first.
+pub(super) fn siblings(live: &Path, horizon: Option<ArchiveHorizon>) -> Vec<Archive> {
+    let (Some(dir), Some(name)) = (live.parent(), live.file_name().and_then(|n| n.to_str())) else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut archives: Vec<Archive> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let (index, compression) = rotated(name, entry.file_name().to_str()?)?;
+            let meta = std::fs::metadata(entry.path()).ok()?;
+            meta.is_file().then(|| Archive {
+                path: entry.path(),
+                id: super::tail::FileId::of(&meta),
+                modified: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                size: meta.len(),
+                index,
+                compression,
+            })
+        })
+        .collect();
+
+    // Newest first to apply the horizon, then flipped for reading.
+    archives.sort_by_key(|a| (Reverse(a.modified), a.index));
+    match horizon {
+        Some(ArchiveHorizon::Age(age)) => {
+            let since = SystemTime::now() - age;
+            archives.retain(|a| a.modified >= since);
+        }
+        Some(ArchiveHorizon::Bytes(limit)) => {
+            let mut total = 0;
+            archives.retain(|a| {
+                total += a.size;
+                total <= limit
+            });
+        }
+        None => {}
+    }
+    archives.reverse();
+    archives
+}
+
+/// Stream an archive's lines. Decompression runs on a blocking thread; a read
+/// error ends the stream with that error.
+pub(super) fn lines(archive: &Archive) -> mpsc::Receiver<std::io::Result<String>> {
+    let (tx, rx) = mpsc::channel(LINE_BUFFER);
+    let path = archive.path.clone();
+    let compression = archive.compression;
+    tokio::task::spawn_blocking(move || {
+        let reader = match open(&path, compression) {
+            Ok(reader) => BufReader::new(reader),
+            Err(e) => {
+                let _ = tx.blocking_send(Err(e));
+                return;
+            }
+        };
+        for line in reader.split(b'\n') {
+            let line = line.map(|mut bytes| {
+                if bytes.last() == Some(&b'\r') {
+                    bytes.pop();
+                }
+                String::from_utf8_lossy(&bytes).into_owned()
+            });
+            let failed = line.is_err();
+            if tx.blocking_send(line).is_err() || failed {
+                return;
+            }
+        }
+    });
+    rx
+}
+
+/// Open a log file, decompressing it by extension (`.gz`, `.zst`, `.bz2`).
+pub(crate) fn open_decompressed(path: &Path) -> std::io::Result<Box<dyn Read + Send>> {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    open(path, Compression::strip(name).1)
+}
+
+/// `name` without a compression extension.
+pub(crate) fn uncompressed_name(name: &str) -> &str {
+    Compression::strip(name).0
+}
+
+fn open(path: &Path, compression: Compression) -> std::io::Result<Box<dyn Read + Send>> {
+    let file = std::fs::File::open(path)?;
+    Ok(match compression {
+        Compression::None => Box::new(file),
+        Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        Compression::Zstd => Box::new(Decompressor::spawn("zstd", file)?),
+        Compression::Bzip2 => Box::new(Decompressor::spawn("bzip2", file)?),
+    })
+}
+
+/// `<program> -dc` reading the archive on stdin; killed if dropped early.
+struct Decompressor {
+    child: Child,
+}
+
+impl Decompressor {
+    fn spawn(program: &str, input: std::fs::File) -> std::io::Result<Self> {
+        let child = Command::new(program)
+            .arg("-dc")
+            .stdin(input)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| std::io::Error::new(e.kind(), format!("cannot run `{program}`: {e}")))?;
+        Ok(Self { child })
+    }
+}
+
+impl Read for Decompressor {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let stdout = self.child.stdout.as_mut().expect("stdout is piped");
+        let n = stdout.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            let status = self.child.wait()?;
+            if !status.success() {
+                return Err(std::io::Error::other(format!("decompressor {status}")));
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl Drop for Decompressor {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// ----- Tests -----
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotated_names_are_recognised() {
+        assert_eq!(
+            rotated("app.log", "app.log.1"),
+            Some((Some(1), Compression::None))
+        );
+        assert_eq!(
+            rotated("app.log", "app.log.2.gz"),
+            Some((Some(2), Compression::Gzip))
+        );
+        assert_eq!(
+            rotated("app.log", "app.log-20240101.zst"),
+            Some((None, Compression::Zstd))
+        );
+        assert_eq!(
+            rotated("app.log", "app.log.2024-01-01.bz2"),
+            Some((None, Compression::Bzip2))
+        );
+        assert_eq!(
+            rotated("app.log", "app.log.gz"),
+            Some((None, Compression::Gzip))
+        );
+        assert_eq!(rotated("app.log", "app.log"), None);
+        assert_eq!(rotated("app.log", "app.log.bak"), None);
+        assert_eq!(rotated("app.log", "app.logger.1"), None);
+    }
+
+    #[test]
+    fn horizons_parse() {
+        let hours = |h: u64| ArchiveHorizon::Age(Duration::from_secs(h * 3600));
+        assert_eq!("24h".parse(), Ok(hours(24)));
+        assert_eq!("7d".parse(), Ok(hours(7 * 24)));
+        assert_eq!("500MB".parse(), Ok(ArchiveHorizon::Bytes(500 << 20)));
+        assert_eq!("64k".parse(), Ok(ArchiveHorizon::Bytes(64 << 10)));
+        assert!("soon".parse::<ArchiveHorizon>().is_err());
+        assert!("12parsecs".parse::<ArchiveHorizon>().is_err());
+    }
+}
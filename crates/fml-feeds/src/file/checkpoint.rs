@@ -0,0 +1,278 @@
+//! Read positions saved across restarts.
+//!
+//! Each followed file's position is its identity (device and inode), the
+//! offset of the first byte not yet sent, and a [`Fingerprint`] of its first
+//! bytes. Positions are kept as JSON, keyed by path, in one file shared by
+//! every fml run; a save merges into what is on disk, so runs following
+//! other paths keep their entries.
+//!
+//! On restart a checkpointed path is matched back to its file with
+//! [`locate`]: still at the path, or renamed to a rotated sibling while fml
+//! was down. The fingerprint tells a reused inode or a truncated file from
+//! the one the offset was taken in.
+
+use super::tail::FileId;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// How many leading bytes are fingerprinted.
+pub(super) const FINGERPRINT_LEN: u64 = 1024;
+
+/// Hash of a file's first bytes. Files shorter than [`FINGERPRINT_LEN`] when
+/// it was taken are fingerprinted over what they had.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct Fingerprint {
+    /// Bytes hashed.
+    pub len: u64,
+    /// FNV-1a of those bytes.
+    pub hash: u64,
+}
+
+impl Fingerprint {
+    /// Fingerprint the first bytes of `file`, at most `len`, without moving
+    /// its read position.
+    pub fn read(file: &std::fs::File, len: u64) -> std::io::Result<Self> {
+        use std::os::unix::fs::FileExt;
+        let mut buf = vec![0; len as usize];
+        let mut filled = 0;
+        while filled < buf.len() {
+            match file.read_at(&mut buf[filled..], filled as u64)? {
+                0 => break,
+                n => filled += n,
+            }
+        }
+        buf.truncate(filled);
+        Ok(Self::of(&buf))
+    }
+
+    fn of(bytes: &[u8]) -> Self {
+        let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash: u64, &b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        });
+        Self {
+            len: bytes.len() as u64,
+            hash,
+        }
+    }
+
+    /// Whether this covers as much of the file as it ever will.
+    pub fn complete(&self) -> bool {
+        self.len >= FINGERPRINT_LEN
+    }
+}
+
+/// Where reading a file stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct Position {
+    pub dev: u64,
+    pub ino: u64,
+    /// First byte not yet sent.
+    pub offset: u64,
+    pub fingerprint: Fingerprint,
+}
+
+impl Position {
+    pub fn id(&self) -> FileId {
+        FileId {
+            dev: self.dev,
+            ino: self.ino,
+        }
+    }
+}
+
+/// How well a file matches a [`Position`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Match {
+    /// Same content, different inode: a copy (`copytruncate`, `cp`).
+    Content,
+    /// The very file.
+    Inode,
+}
+
+/// Compare the file at `path` against `position`. It must start with the
+/// fingerprinted bytes and still reach the offset; an empty fingerprint
+/// proves nothing, so then only the inode counts.
+fn compare(path: &Path, position: &Position) -> Option<Match> {
+    let file = std::fs::File::open(path).ok()?;
+    let meta = file.metadata().ok()?;
+    if meta.len() < position.offset {
+        return None;
+    }
+    let fingerprint = Fingerprint::read(&file, position.fingerprint.len).ok()?;
+    if fingerprint != position.fingerprint {
+        return None;
+    }
+    if FileId::of(&meta) == position.id() {
+        Some(Match::Inode)
+    } else {
+        (position.fingerprint.len > 0).then_some(Match::Content)
+    }
+}
+
+/// The candidate `position` was taken in: the one with its inode, else the
+/// first with the same content.
+pub(super) fn locate<'a>(
+    position: &Position,
+    candidates: impl IntoIterator<Item = &'a Path>,
+) -> Option<&'a Path> {
+    let mut best: Option<(Match, &Path)> = None;
+    for path in candidates {
+        if let Some(found) = compare(path, position) {
+            if best.is_none_or(|(m, _)| found > m) {
+                best = Some((found, path));
+            }
+        }
+    }
+    best.map(|(_, path)| path)
+}
+
+/// The checkpoint file.
+#[derive(Debug)]
+pub(super) struct Checkpoints {
+    path: PathBuf,
+    /// What this run last wrote for each path it follows.
+    saved: BTreeMap<PathBuf, Option<Position>>,
+}
+
+impl Checkpoints {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            saved: BTreeMap::new(),
+        }
+    }
+
+    /// Every saved position. A missing or unreadable file is empty.
+    pub fn load(&self) -> BTreeMap<PathBuf, Position> {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return BTreeMap::new(),
+            Err(e) => {
+                tracing::warn!(path = %self.path.display(), error = %e, "cannot read file offsets");
+                return BTreeMap::new();
+            }
+        };
+        serde_json::from_str(&text).unwrap_or_else(|e| {
+            tracing::warn!(path = %self.path.display(), error = %e, "ignoring corrupt file offsets");
+            BTreeMap::new()
+        })
+    }
+
+    /// Record `positions` (`None` forgets a path), rewriting the file if
+    /// anything changed. Entries for other paths are kept.
+    pub fn save(&mut self, positions: BTreeMap<PathBuf, Option<Position>>) -> std::io::Result<()> {
+        let changed: Vec<_> = positions
+            .into_iter()
+            .filter(|(path, position)| self.saved.get(path) != Some(position))
+            .collect();
+        if changed.is_empty() {
+            return Ok(());
+        }
+        let mut all = self.load();
+        for (path, position) in &changed {
+            match position {
+                Some(position) => all.insert(path.clone(), *position),
+                None => all.remove(path),
+            };
+        }
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Written aside and renamed, so a crash never leaves half a file.
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&all)?)?;
+        std::fs::rename(&tmp, &self.path)?;
+        self.saved.extend(changed);
+        Ok(())
+    }
+}
+
+// ----- Tests -----
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint_of(path: &Path) -> Fingerprint {
+        Fingerprint::read(&std::fs::File::open(path).unwrap(), FINGERPRINT_LEN).unwrap()
+    }
+
+    fn position_of(path: &Path, offset: u64) -> Position {
+        let id = FileId::of(&std::fs::metadata(path).unwrap());
+        Position {
+            dev: id.dev,
+            ino: id.ino,
+            offset,
+            fingerprint: fingerprint_of(path),
+        }
+    }
+
+    #[test]
+    fn fingerprint_reads_at_most_its_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        std::fs::write(&path, "x".repeat(4096)).unwrap();
+        let file = std::fs::File::open(&path).unwrap();
+        let fingerprint = Fingerprint::read(&file, FINGERPRINT_LEN).unwrap();
+        assert_eq!(fingerprint, Fingerprint::of(&[b'x'; 1024]));
+        assert!(fingerprint.complete());
+        assert!(!Fingerprint::read(&file, 3).unwrap().complete());
+    }
+
+    #[test]
+    fn located_by_inode_before_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let live = dir.path().join("app.log");
+        let rotated = dir.path().join("app.log.1");
+        std::fs::write(&live, "first\nsecond\n").unwrap();
+        let position = position_of(&live, 6);
+
+        // A copy of the same bytes, then the original renamed away.
+        std::fs::copy(&live, dir.path().join("copy")).unwrap();
+        std::fs::rename(&live, &rotated).unwrap();
+        std::fs::rename(dir.path().join("copy"), &live).unwrap();
+        let candidates = [live.as_path(), rotated.as_path()];
+        assert_eq!(locate(&position, candidates), Some(rotated.as_path()));
+
+        std::fs::remove_file(&rotated).unwrap();
+        assert_eq!(locate(&position, candidates), Some(live.as_path()));
+    }
+
+    #[test]
+    fn rewritten_or_truncated_files_do_not_match() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        std::fs::write(&path, "first\nsecond\n").unwrap();
+        let position = position_of(&path, 13);
+
+        std::fs::write(&path, "first\n").unwrap();
+        assert_eq!(locate(&position, [path.as_path()]), None);
+        std::fs::write(&path, "other\nsecond\n").unwrap();
+        assert_eq!(locate(&position, [path.as_path()]), None);
+    }
+
+    #[test]
+    fn saves_merge_with_other_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("state/file-offsets.json");
+        let position = |offset| Position {
+            dev: 1,
+            ino: 2,
+            offset,
+            fingerprint: Fingerprint::of(b""),
+        };
+        let mut ours = Checkpoints::new(file.clone());
+        let mut theirs = Checkpoints::new(file.clone());
+        ours.save(BTreeMap::from([("/a".into(), Some(position(1)))]))
+            .unwrap();
+        theirs
+            .save(BTreeMap::from([("/b".into(), Some(position(2)))]))
+            .unwrap();
+        ours.save(BTreeMap::from([("/a".into(), None)])).unwrap();
+        assert_eq!(
+            Checkpoints::new(file).load(),
+            BTreeMap::from([("/b".into(), position(2))])
+        );
+    }
+}
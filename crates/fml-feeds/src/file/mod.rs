@@ -0,0 +1,929 @@
+//! File feed — tails files, directories and glob patterns.
+//!
+//! Each entry of [`FileConfig::paths`] is one of:
+//!
+//! | Path                        | Follows                                      |
+//! |-----------------------------|----------------------------------------------|
+//! | `./app.log`                 | that file, created later if it does not exist |
+//! | `/var/log/app`              | every file below the directory, recursively   |
+//! | `/var/log/app/*.log`        | matching files directly in the directory      |
+//! | `/var/log/app/**/*.log`     | matching files at any depth                   |
+//!
+//! The directory before the first glob component is watched with inotify
+//! (via `notify`, recursively when the pattern reaches below it), so files
+//! and directories created later are picked up as they appear. Paths
+//! matching an [`exclude`](FileConfig::exclude) pattern are never opened.
+//!
+//! Existing content is backfilled from the start of each file, then new lines
+//! are tailed. The path is re-checked on every change and once a second:
+//!
+//! - a different inode at the path means the file was rotated; the old file
+//!   is drained to EOF before the new one is read from its start, so no line
+//!   is lost or repeated across the rotation;
+//! - a file shorter than the read offset was truncated and is re-read from 0;
+//! - an unlinked file is drained and closed, and followed again from its
+//!   start if the path comes back.
+//!
+//! With [`archives`](FileConfig::archives) set, a file's rotated siblings
+//! (`app.log.1`, `app.log.2.gz`, `app.log-20240101.zst`, …) are read first,
+//! oldest first and back to the [`archive_horizon`](FileConfig::archive_horizon),
+//! before the file's own backfill (see [`archive`]).
+//!
+//! With a [`checkpoint`](FileConfig::checkpoint) file, where each file was
+//! read to is saved once a second and a restarted feed resumes from there
+//! instead of the start. A file rotated while fml was down is found among its
+//! rotated siblings by inode or by its first bytes, drained from the saved
+//! offset, and the live file is then read whole (see [`checkpoint`]).
+//!
+//! At most [`max_open`](FileConfig::max_open) files are held open. When more
+//! match, the most recently modified win: a quiet file is parked (its offset
+//! kept) to make room for one that was just written, and resumes where it
+//! stopped once it is written again.
+//!
+//! Producers are the path relative to the watched directory, under that
+//! directory's name (`app/api/access.log` for `/var/log/app/**/*.log`), so the
+//! producer tree mirrors the directory hierarchy. Every entry carries the
+//! absolute `path`. With a metadata channel ([`FileFeed::with_metadata`]) the
+//! feed reports `rotated` and `deleted` states, with a `rotations` count.
+//!
+//! With [`FileFeed::with_signals`], stopping a file closes it (its position
+//! is still checkpointed) and starting it reopens it at its current end,
+//! leaving out what was written in between. A reconnect closes the file and
+//! reopens it where it was read to.
+
+mod archive;
+mod checkpoint;
+mod tail;
+
+pub use archive::ArchiveHorizon;
+pub(crate) use archive::{open_decompressed, uncompressed_name};
+
+use crate::control::{tree_id, Signals};
+use crate::{line_entry, FeedError};
+use checkpoint::{Checkpoints, Position};
+use fml_core::handle::covers;
+use fml_core::{FeedKind, LogEntry, ProducerMeta, ProducerState, ProducerStatus, ProducerUpdate};
+use glob::{MatchOptions, Pattern};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tail::{FileId, PathState, Tail};
+use tokio::sync::mpsc;
+
+/// Default for [`FileConfig::max_open`].
+pub const DEFAULT_MAX_OPEN: usize = 256;
+
+/// How often open files are re-checked without an inotify event, in case one
+/// was missed (or the filesystem does not deliver them).
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Archive lines sent per batch.
+const ARCHIVE_BATCH: usize = 256;
+
+/// `*` and `?` stay within one path component; only `**` crosses `/`.
+const MATCH: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// What to follow.
+#[derive(Debug, Clone)]
+pub struct FileConfig {
+    /// Files, directories or glob patterns. A leading `~/` is the home
+    /// directory; relative paths are resolved against the working directory.
+    pub paths: Vec<String>,
+    /// Glob patterns of files to skip. A pattern containing `/` is matched
+    /// against the absolute path, otherwise against the file name
+    /// (`*.gz`).
+    pub exclude: Vec<String>,
+    /// Most files held open at once.
+    pub max_open: usize,
+    /// Backfill rotated (and compressed) archives of each file before the
+    /// file itself.
+    pub archives: bool,
+    /// How far back archives go. `None` reads all of them.
+    pub archive_horizon: Option<ArchiveHorizon>,
+    /// File to save read positions to and resume from. `None` reads every
+    /// file from the start.
+    pub checkpoint: Option<PathBuf>,
+}
+
+impl Default for FileConfig {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            exclude: Vec::new(),
+            max_open: DEFAULT_MAX_OPEN,
+            archives: false,
+            archive_horizon: None,
+            checkpoint: None,
+        }
+    }
+}
+
+/// One entry of [`FileConfig::paths`], split into the directory to watch and
+/// the pattern files below it must match.
+#[derive(Debug, Clone)]
+struct PathSpec {
+    /// Deepest directory without glob characters.
+    base: PathBuf,
+    /// Whether matches can be below `base`'s immediate children.
+    recursive: bool,
+    /// Full-path pattern.
+    pattern: Pattern,
+    /// Top of the producer names: `base`'s own name.
+    label: String,
+}
+
+impl PathSpec {
+    fn parse(spec: &str) -> Result<Self, FeedError> {
+        let invalid = |reason: String| FeedError::FileConfig(format!("path `{spec}`: {reason}"));
+        let path = std::path::absolute(expand_home(spec)).map_err(|e| invalid(e.to_string()))?;
+        let path = normalize(&path);
+
+        let components: Vec<&str> = path
+            .iter()
+            .skip(1)
+            .map(|c| c.to_str().ok_or_else(|| invalid("not valid UTF-8".into())))
+            .collect::<Result<_, _>>()?;
+        let literal = components
+            .iter()
+            .position(|c| c.contains(['*', '?', '[']))
+            .unwrap_or(components.len());
+
+        let (base, recursive, pattern) = if literal < components.len() {
+            let base: PathBuf = std::iter::once("/")
+                .chain(components[..literal].iter().copied())
+                .collect();
+            let rest = &components[literal..];
+            let recursive = rest.len() > 1 || rest.contains(&"**");
+            (base, recursive, path.to_string_lossy().into_owned())
+        } else if path.is_dir() {
+            let pattern = format!("{}/**/*", Pattern::escape(&path.to_string_lossy()));
+            (path, true, pattern)
+        } else {
+            let base = path.parent().unwrap_or(Path::new("/")).to_path_buf();
+            (base, false, Pattern::escape(&path.to_string_lossy()))
+        };
+        let label = base
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Self {
+            pattern: Pattern::new(&pattern).map_err(|e| invalid(e.to_string()))?,
+            base,
+            recursive,
+            label,
+        })
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        self.pattern.matches_path_with(path, MATCH)
+    }
+
+    /// `label/relative/path` for a file matched by this spec.
+    fn producer(&self, path: &Path) -> String {
+        let relative = path.strip_prefix(&self.base).unwrap_or(path);
+        let relative = relative.to_string_lossy();
+        let relative = relative.trim_start_matches('/');
+        if self.label.is_empty() {
+            relative.to_string()
+        } else {
+            format!("{}/{relative}", self.label)
+        }
+    }
+}
+
+/// Replace a leading `~/` with `$HOME`.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => Path::new(&home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Drop `.` and resolve `..` lexically, so a spec's base is a prefix of the
+/// paths inotify reports below it.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Tails files matched by a set of paths and patterns.
+pub struct FileFeed {
+    specs: Vec<PathSpec>,
+    exclude: Vec<Pattern>,
+    max_open: usize,
+    archives: bool,
+    archive_horizon: Option<ArchiveHorizon>,
+    checkpoint: Option<PathBuf>,
+    metadata: Option<mpsc::Sender<ProducerMeta>>,
+    signals: Signals,
+}
+
+impl FileFeed {
+    /// Validate the configuration. Nothing is opened until [`run`](Self::run).
+    pub fn new(config: &FileConfig) -> Result<Self, FeedError> {
+        if config.paths.is_empty() {
+            return Err(FeedError::FileConfig("no paths to follow".into()));
+        }
+        let specs = config
+            .paths
+            .iter()
+            .map(|spec| PathSpec::parse(spec))
+            .collect::<Result<_, _>>()?;
+        let exclude = config
+            .exclude
+            .iter()
+            .map(|glob| {
+                Pattern::new(&expand_home(glob).to_string_lossy())
+                    .map_err(|e| FeedError::FileConfig(format!("exclude `{glob}`: {e}")))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            specs,
+            exclude,
+            max_open: config.max_open.max(1),
+            archives: config.archives,
+            archive_horizon: config.archive_horizon,
+            checkpoint: config.checkpoint.clone(),
+            metadata: None,
+            signals: Signals::default(),
+        })
+    }
+
+    /// Also report each file's [`ProducerState`] (`rotated`, `deleted`) on
+    /// `metadata`.
+    pub fn with_metadata(mut self, metadata: mpsc::Sender<ProducerMeta>) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Close or reopen files when `signals` asks.
+    pub fn with_signals(mut self, signals: Signals) -> Self {
+        self.signals = signals;
+        self
+    }
+
+    /// Follow matching files until `tx` is closed.
+    pub async fn run(
+        self,
+        tx: mpsc::Sender<LogEntry>,
+        updates: mpsc::Sender<ProducerUpdate>,
+    ) -> Result<(), FeedError> {
+        let (events_tx, mut events) = mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event) => {
+                    let _ = events_tx.send(event);
+                }
+                Err(e) => tracing::warn!(error = %e, "file watch error"),
+            })
+            .map_err(|e| FeedError::Io(std::io::Error::other(e)))?;
+
+        let checkpoints = self.checkpoint.clone().map(Checkpoints::new);
+        let mut session = Session {
+            feed: &self,
+            files: BTreeMap::new(),
+            retired: HashSet::new(),
+            saved: checkpoints
+                .as_ref()
+                .map(Checkpoints::load)
+                .unwrap_or_default(),
+            checkpoints,
+            tx,
+            updates,
+        };
+        let mut signals = self.signals.clone();
+
+        // Non-recursive watches first: re-watching a directory replaces its
+        // mode, and a recursive watch must win.
+        let mut unwatched: Vec<&PathSpec> = self.specs.iter().collect();
+        unwatched.sort_by_key(|spec| spec.recursive);
+        unwatched.retain(|spec| !watch(&mut watcher, spec));
+        session.scan_all().await?;
+
+        let closed = session.tx.clone();
+        let mut ticker = tokio::time::interval(POLL_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                event = events.recv() => {
+                    let Some(event) = event else { break };
+                    if matches!(event.kind, EventKind::Access(_)) {
+                        continue;
+                    }
+                    for path in &event.paths {
+                        session.touch(path).await?;
+                    }
+                }
+                _ = ticker.tick() => {
+                    let before = unwatched.len();
+                    unwatched.retain(|spec| !watch(&mut watcher, spec));
+                    if unwatched.len() < before {
+                        session.scan_all().await?;
+                    }
+                    session.poll_open().await?;
+                    session.checkpoint().await;
+                }
+                request = signals.changed() => session.control(request).await?,
+                _ = closed.closed() => break,
+            }
+        }
+        session.checkpoint().await;
+        Ok(())
+    }
+
+    fn spec_for(&self, path: &Path) -> Option<&PathSpec> {
+        let name = path.file_name()?.to_string_lossy();
+        let excluded = self.exclude.iter().any(|glob| {
+            if glob.as_str().contains('/') {
+                glob.matches_path_with(path, MATCH)
+            } else {
+                glob.matches_with(&name, MATCH)
+            }
+        });
+        if excluded {
+            return None;
+        }
+        self.specs.iter().find(|spec| spec.matches(path))
+    }
+}
+
+/// Start watching `spec.base`; `false` (retried later) if it does not exist
+/// yet.
+fn watch(watcher: &mut notify::RecommendedWatcher, spec: &PathSpec) -> bool {
+    let mode = if spec.recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    match watcher.watch(&spec.base, mode) {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::debug!(dir = %spec.base.display(), error = %e, "cannot watch yet");
+            false
+        }
+    }
+}
+
+/// A file matched by the feed, open or not.
+struct Followed {
+    producer: String,
+    tail: Option<Tail>,
+    /// Last modification seen; decides which files stay open.
+    mtime: SystemTime,
+    /// Where to resume after being parked by the open-file limit.
+    parked: Option<Position>,
+    /// Where an earlier run stopped, until the file is first opened.
+    resumed: Option<Position>,
+    /// The file was unlinked; it is followed again if the path comes back.
+    deleted: bool,
+    rotations: u32,
+    /// Rotated archives have been backfilled (or were not wanted).
+    archived: bool,
+    /// Closed by a stop until started again.
+    stopped: bool,
+}
+
+struct Session<'a> {
+    feed: &'a FileFeed,
+    files: BTreeMap<PathBuf, Followed>,
+    /// Files already read to the end under another name, so a rotated copy
+    /// (`app.log.1`) matching the pattern is not read again.
+    retired: HashSet<FileId>,
+    checkpoints: Option<Checkpoints>,
+    /// Positions saved by earlier runs, taken as their paths are found.
+    saved: BTreeMap<PathBuf, Position>,
+    tx: mpsc::Sender<LogEntry>,
+    updates: mpsc::Sender<ProducerUpdate>,
+}
+
+impl Session<'_> {
+    /// Match every spec against the filesystem and open the most recently
+    /// modified files, up to the limit.
+    async fn scan_all(&mut self) -> Result<(), FeedError> {
+        let mut found = Vec::new();
+        for spec in &self.feed.specs {
+            found.extend(glob_files(spec.pattern.as_str()));
+        }
+        self.discover(found).await
+    }
+
+    /// Start following newly found files, most recently modified first.
+    async fn discover(&mut self, paths: Vec<PathBuf>) -> Result<(), FeedError> {
+        let mut found: Vec<(SystemTime, PathBuf)> = Vec::new();
+        for path in paths {
+            if self.files.contains_key(&path) {
+                continue;
+            }
+            let Some(spec) = self.feed.spec_for(&path) else {
+                continue;
+            };
+            let Ok(meta) = std::fs::metadata(&path) else {
+                continue;
+            };
+            if !meta.is_file() {
+                continue;
+            }
+            let id = FileId::of(&meta);
+            if self.retired.contains(&id) {
+                continue;
+            }
+            if let Some(open) = self.open_path(id) {
+                // A rotated copy of a file still being drained.
+                self.poll(&open).await?;
+                continue;
+            }
+            let producer = spec.producer(&path);
+            let mtime = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            self.files.insert(
+                path.clone(),
+                Followed {
+                    producer,
+                    tail: None,
+                    mtime,
+                    parked: None,
+                    resumed: self.saved.remove(&path),
+                    deleted: false,
+                    rotations: 0,
+                    archived: !self.feed.archives,
+                    stopped: false,
+                },
+            );
+            found.push((mtime, path));
+        }
+        found.sort_by_key(|(mtime, _)| std::cmp::Reverse(*mtime));
+        for (_, path) in found {
+            self.activate(&path).await?;
+        }
+        Ok(())
+    }
+
+    /// React to an inotify event on `path`.
+    async fn touch(&mut self, path: &Path) -> Result<(), FeedError> {
+        let Some(followed) = self.files.get_mut(path) else {
+            if path.is_dir() {
+                let glob = format!("{}/**/*", Pattern::escape(&path.to_string_lossy()));
+                return self.discover(glob_files(&glob)).await;
+            }
+            return self.discover(vec![path.to_path_buf()]).await;
+        };
+        if followed.tail.is_some() {
+            return self.poll(path).await;
+        }
+        let Ok(meta) = std::fs::metadata(path) else {
+            return Ok(());
+        };
+        followed.mtime = meta.modified().unwrap_or(followed.mtime);
+        if followed.deleted {
+            followed.deleted = false;
+            followed.parked = None;
+            self.describe(path, ProducerState::Running).await;
+        }
+        self.activate(path).await
+    }
+
+    /// Open a followed file if there is room, or if it was modified no
+    /// earlier than the stalest open file (which is parked to make room).
+    async fn activate(&mut self, path: &Path) -> Result<(), FeedError> {
+        let Some(followed) = self.files.get_mut(path) else {
+            return Ok(());
+        };
+        if followed.stopped || self.feed.signals.stopped(&file_id(&followed.producer)) {
+            followed.stopped = true;
+            return Ok(());
+        }
+        if std::fs::metadata(path).is_ok_and(|m| self.retired.contains(&FileId::of(&m))) {
+            // An archive already read as part of another file's backfill.
+            return Ok(());
+        }
+        let mtime = self.files[path].mtime;
+        let open = self.files.values().filter(|f| f.tail.is_some()).count();
+        if open >= self.feed.max_open {
+            let stalest = self
+                .files
+                .iter()
+                .filter(|(_, f)| f.tail.is_some())
+                .min_by_key(|(_, f)| f.mtime)
+                .map(|(path, f)| (path.clone(), f.mtime));
+            match stalest {
+                Some((stalest, oldest)) if oldest <= mtime => self.park(&stalest).await?,
+                _ => return Ok(()),
+            }
+        }
+
+        let followed = self.files.get_mut(path).expect("checked above");
+        let producer = followed.producer.clone();
+        let mut offset = match followed.parked.take() {
+            Some(parked)
+                if std::fs::metadata(path).is_ok_and(|m| FileId::of(&m) == parked.id()) =>
+            {
+                parked.offset
+            }
+            _ => 0,
+        };
+        if let Some(position) = followed.resumed.take() {
+            // The archives were read by the run that saved the position.
+            followed.archived = true;
+            offset = self.resume(path, &producer, position).await?;
+        }
+        let followed = self.files.get_mut(path).expect("checked above");
+        if !followed.archived {
+            followed.archived = true;
+            self.backfill_archives(path, &producer).await?;
+        }
+        let followed = self.files.get_mut(path).expect("checked above");
+        match Tail::open(path, offset).await {
+            Ok(tail) => followed.tail = Some(tail),
+            Err(e) => {
+                tracing::debug!(path = %path.display(), error = %e, "cannot open");
+                return Ok(());
+            }
+        }
+        let update = ProducerUpdate {
+            source: FeedKind::File,
+            instance: None,
+            producer: followed.producer.clone(),
+            status: ProducerStatus::Streaming,
+        };
+        let _ = self.updates.send(update).await;
+        self.poll(path).await
+    }
+
+    /// Send the lines of `path`'s rotated archives, oldest first, and retire
+    /// them so they are not followed as files of their own.
+    async fn backfill_archives(&mut self, path: &Path, producer: &str) -> Result<(), FeedError> {
+        for archive in archive::siblings(path, self.feed.archive_horizon) {
+            self.read_archive(&archive, producer).await?;
+        }
+        Ok(())
+    }
+
+    /// Send every line of one archive as `producer`'s and retire it.
+    async fn read_archive(
+        &mut self,
+        archive: &archive::Archive,
+        producer: &str,
+    ) -> Result<(), FeedError> {
+        self.retire(&archive.path, archive.id);
+        let mut lines = archive::lines(archive);
+        let mut batch = Vec::new();
+        while let Some(line) = lines.recv().await {
+            match line {
+                Ok(line) => batch.push(line),
+                Err(e) => {
+                    tracing::warn!(archive = %archive.path.display(), error = %e, "cannot read archive");
+                    break;
+                }
+            }
+            if batch.len() == ARCHIVE_BATCH {
+                self.send(&archive.path, producer, std::mem::take(&mut batch))
+                    .await?;
+            }
+        }
+        self.send(&archive.path, producer, batch).await
+    }
+
+    /// Mark a rotated copy as read so it is not followed as a file of its own.
+    fn retire(&mut self, path: &Path, id: FileId) {
+        self.retired.insert(id);
+        if let Some(open) = self.files.get_mut(path) {
+            // Matched by the pattern too; it is read here instead.
+            open.tail = None;
+            open.archived = true;
+        }
+    }
+
+    /// Pick up where an earlier run stopped and return the offset to read
+    /// `path` from. If the file was rotated away meanwhile, the rest of it and
+    /// any archives rotated after it are sent first, and `path` is read whole.
+    async fn resume(
+        &mut self,
+        path: &Path,
+        producer: &str,
+        position: Position,
+    ) -> Result<u64, FeedError> {
+        let archives = archive::siblings(path, None);
+        let candidates = std::iter::once(path).chain(archives.iter().map(|a| a.path.as_path()));
+        let Some(found) = checkpoint::locate(&position, candidates) else {
+            tracing::debug!(path = %path.display(), "checkpointed file is gone, reading from the start");
+            return Ok(0);
+        };
+        if found == path {
+            return Ok(position.offset);
+        }
+        tracing::debug!(path = %path.display(), rotated = %found.display(), "rotated while stopped");
+        let found = found.to_path_buf();
+        let newer = archives
+            .iter()
+            .position(|a| a.path == found)
+            .map_or(0, |i| i + 1);
+
+        let mut tail = match Tail::open(&found, position.offset).await {
+            Ok(tail) => tail,
+            Err(e) => {
+                tracing::debug!(path = %found.display(), error = %e, "cannot open");
+                return Ok(0);
+            }
+        };
+        self.retire(&found, tail.id);
+        let mut lines = tail.read_lines().await?;
+        lines.extend(tail.flush());
+        self.send(&found, producer, lines).await?;
+        for archive in &archives[newer..] {
+            self.read_archive(archive, producer).await?;
+        }
+        Ok(0)
+    }
+
+    /// Read what is left of an open file and close it, remembering where to
+    /// resume.
+    async fn park(&mut self, path: &Path) -> Result<(), FeedError> {
+        self.poll(path).await?;
+        if let Some(followed) = self.files.get_mut(path) {
+            if let Some(mut tail) = followed.tail.take() {
+                tracing::debug!(path = %path.display(), "open-file limit reached, parking");
+                followed.parked = Some(tail.position().await?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Act on a request from [`Signals::changed`]: reopen the open files
+    /// under a reconnected path, or close the files just stopped and reopen
+    /// those just started.
+    async fn control(&mut self, request: Option<String>) -> Result<(), FeedError> {
+        let paths: Vec<PathBuf> = self.files.keys().cloned().collect();
+        for path in paths {
+            let followed = &self.files[&path];
+            let id = file_id(&followed.producer);
+            let open = followed.tail.is_some();
+            match &request {
+                Some(reconnect) if open && covers(reconnect, &id) => {
+                    tracing::debug!(path = %path.display(), "reopening");
+                    self.close(&path).await?;
+                    self.activate(&path).await?;
+                }
+                Some(_) => {}
+                None if open && self.feed.signals.stopped(&id) => {
+                    tracing::debug!(path = %path.display(), "stopped");
+                    self.close(&path).await?;
+                    if let Some(followed) = self.files.get_mut(&path) {
+                        followed.stopped = true;
+                    }
+                }
+                None if followed.stopped && !self.feed.signals.stopped(&id) => {
+                    self.start(&path).await?;
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Close an open file, remembering where to resume.
+    async fn close(&mut self, path: &Path) -> Result<(), FeedError> {
+        if let Some(followed) = self.files.get_mut(path) {
+            if let Some(mut tail) = followed.tail.take() {
+                followed.parked = Some(tail.position().await?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Open a file that was stopped at its current end.
+    async fn start(&mut self, path: &Path) -> Result<(), FeedError> {
+        let Some(followed) = self.files.get_mut(path) else {
+            return Ok(());
+        };
+        followed.stopped = false;
+        followed.parked = None;
+        let end = std::fs::metadata(path).map_or(0, |meta| meta.len());
+        if let Ok(mut tail) = Tail::open(path, end).await {
+            followed.parked = Some(tail.position().await?);
+        }
+        tracing::debug!(path = %path.display(), "started");
+        self.activate(path).await
+    }
+
+    async fn poll_open(&mut self) -> Result<(), FeedError> {
+        let open: Vec<PathBuf> = self
+            .files
+            .iter()
+            .filter(|(_, f)| f.tail.is_some())
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in open {
+            self.poll(&path).await?;
+        }
+        Ok(())
+    }
+
+    /// Read new lines from an open file, then follow it through rotation,
+    /// deletion or truncation.
+    async fn poll(&mut self, path: &Path) -> Result<(), FeedError> {
+        let Some(followed) = self.files.get_mut(path) else {
+            return Ok(());
+        };
+        let Some(tail) = followed.tail.as_mut() else {
+            return Ok(());
+        };
+        let mut lines = tail.read_lines().await?;
+        followed.mtime = tail.modified;
+        let state = tail.path_state().await?;
+        let producer = followed.producer.clone();
+        let mut described = None;
+        match state {
+            PathState::Same | PathState::Missing { unlinked: false } => {}
+            PathState::Replaced => {
+                lines.extend(tail.read_lines().await?);
+                lines.extend(tail.flush());
+                self.retired.insert(tail.id);
+                followed.rotations += 1;
+                tracing::debug!(path = %path.display(), "file rotated");
+                match Tail::open(path, 0).await {
+                    Ok(mut new) => {
+                        lines.extend(new.read_lines().await?);
+                        followed.tail = Some(new);
+                    }
+                    Err(_) => followed.tail = None,
+                }
+                described = Some(ProducerState::Rotated);
+            }
+            PathState::Missing { unlinked: true } => {
+                lines.extend(tail.read_lines().await?);
+                lines.extend(tail.flush());
+                followed.tail = None;
+                followed.deleted = true;
+                tracing::debug!(path = %path.display(), "file deleted");
+                described = Some(ProducerState::Deleted);
+            }
+        }
+        self.send(path, &producer, lines).await?;
+        if let Some(state) = described {
+            self.describe(path, state).await;
+        }
+        Ok(())
+    }
+
+    async fn send(&self, path: &Path, producer: &str, lines: Vec<String>) -> Result<(), FeedError> {
+        let path = path.to_string_lossy();
+        for line in lines {
+            let mut entry = line_entry(FeedKind::File, producer, line);
+            entry
+                .fields
+                .insert("path".into(), serde_json::Value::from(path.as_ref()));
+            self.tx
+                .send(entry)
+                .await
+                .map_err(|_| FeedError::ChannelClosed)?;
+        }
+        Ok(())
+    }
+
+    async fn describe(&self, path: &Path, state: ProducerState) {
+        let (Some(metadata), Some(followed)) = (&self.feed.metadata, self.files.get(path)) else {
+            return;
+        };
+        let fields = BTreeMap::from([
+            ("path".to_string(), path.to_string_lossy().into_owned()),
+            ("rotations".to_string(), followed.rotations.to_string()),
+        ]);
+        let _ = metadata
+            .send(ProducerMeta {
+                source: FeedKind::File,
+                instance: None,
+                producer: followed.producer.clone(),
+                state,
+                fields,
+            })
+            .await;
+    }
+
+    /// Save where every followed file stopped, when checkpointing. Deleted
+    /// files are forgotten; files never opened keep what was saved before.
+    async fn checkpoint(&mut self) {
+        let Some(checkpoints) = self.checkpoints.as_mut() else {
+            return;
+        };
+        let mut positions = BTreeMap::new();
+        for (path, followed) in &mut self.files {
+            let position = match followed.tail.as_mut() {
+                _ if followed.deleted => None,
+                Some(tail) => match tail.position().await {
+                    Ok(position) => Some(position),
+                    Err(_) => continue,
+                },
+                None => match followed.parked.or(followed.resumed) {
+                    Some(position) => Some(position),
+                    None => continue,
+                },
+            };
+            positions.insert(path.clone(), position);
+        }
+        if let Err(e) = checkpoints.save(positions) {
+            tracing::warn!(error = %e, "cannot save file offsets");
+        }
+    }
+
+    /// Path of the open file with this identity, if any.
+    fn open_path(&self, id: FileId) -> Option<PathBuf> {
+        self.files
+            .iter()
+            .find(|(_, f)| f.tail.as_ref().is_some_and(|tail| tail.id == id))
+            .map(|(path, _)| path.clone())
+    }
+}
+
+/// Tree id of the file followed as `producer`.
+fn file_id(producer: &str) -> String {
+    tree_id(&fml_core::feed_root(FeedKind::File, None), producer)
+}
+
+/// Regular files matching a glob pattern.
+fn glob_files(pattern: &str) -> Vec<PathBuf> {
+    match glob::glob_with(pattern, MATCH) {
+        Ok(paths) => paths.flatten().filter(|path| path.is_file()).collect(),
+        Err(e) => {
+            tracing::warn!(pattern, error = %e, "invalid glob");
+            Vec::new()
+        }
+    }
+}
+
+// ----- Tests -----
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spec_splits_base_from_pattern() {
+        let spec = PathSpec::parse("/var/log/app/**/*.log").unwrap();
+        assert_eq!(spec.base, Path::new("/var/log/app"));
+        assert!(spec.recursive);
+        assert_eq!(spec.label, "app");
+        assert!(spec.matches(Path::new("/var/log/app/api/v2/access.log")));
+        assert!(!spec.matches(Path::new("/var/log/other/access.log")));
+
+        let spec = PathSpec::parse("/var/log/*.log").unwrap();
+        assert_eq!(spec.base, Path::new("/var/log"));
+        assert!(!spec.recursive);
+        assert!(spec.matches(Path::new("/var/log/syslog.log")));
+        assert!(!spec.matches(Path::new("/var/log/nginx/error.log")));
+    }
+
+    #[test]
+    fn plain_file_watches_its_directory() {
+        let spec = PathSpec::parse("/srv/./app/../app/[a].log").unwrap();
+        assert_eq!(spec.base, Path::new("/srv/app"));
+        let spec = PathSpec::parse("/srv/app/missing.log").unwrap();
+        assert_eq!(spec.base, Path::new("/srv/app"));
+        assert!(!spec.recursive);
+        assert!(spec.matches(Path::new("/srv/app/missing.log")));
+        assert_eq!(
+            spec.producer(Path::new("/srv/app/missing.log")),
+            "app/missing.log"
+        );
+    }
+
+    #[test]
+    fn producers_mirror_the_directory_tree() {
+        let spec = PathSpec::parse("/var/log/app/**/*.log").unwrap();
+        assert_eq!(
+            spec.producer(Path::new("/var/log/app/api/access.log")),
+            "app/api/access.log"
+        );
+        let spec = PathSpec::parse("/*.log").unwrap();
+        assert_eq!(spec.producer(Path::new("/boot.log")), "boot.log");
+    }
+
+    #[test]
+    fn excludes_match_names_or_paths() {
+        let config = FileConfig {
+            paths: vec!["/var/log/**/*".into()],
+            exclude: vec!["*.gz".into(), "/var/log/private/**".into()],
+            ..FileConfig::default()
+        };
+        let feed = FileFeed::new(&config).unwrap();
+        assert!(feed.spec_for(Path::new("/var/log/app/app.log")).is_some());
+        assert!(feed
+            .spec_for(Path::new("/var/log/app/app.log.2.gz"))
+            .is_none());
+        assert!(feed
+            .spec_for(Path::new("/var/log/private/app.log"))
+            .is_none());
+    }
+}
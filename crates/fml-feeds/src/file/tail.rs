@@ -0,0 +1,232 @@
+//! One followed file: reads what was appended since the last poll and notices
+//! when the path has been rotated, deleted or truncated under it.
+
+use super::checkpoint::{Fingerprint, Position, FINGERPRINT_LEN};
+use std::io::SeekFrom;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// Read buffer size; also caps how much one poll reads before yielding.
+const CHUNK: usize = 64 * 1024;
+
+/// Identity of a file independent of its name, so a rename is not mistaken
+/// for a new file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) struct FileId {
+    pub dev: u64,
+    pub ino: u64,
+}
+
+impl FileId {
+    pub fn of(meta: &std::fs::Metadata) -> Self {
+        Self {
+            dev: meta.dev(),
+            ino: meta.ino(),
+        }
+    }
+}
+
+/// What the watched path refers to now, relative to the open file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum PathState {
+    /// Still the file we have open.
+    Same,
+    /// A different file: the old one was rotated away.
+    Replaced,
+    /// Nothing there. `unlinked` is set when the open file itself was
+    /// deleted rather than renamed.
+    Missing { unlinked: bool },
+}
+
+/// An open file being tailed from `offset`.
+pub(super) struct Tail {
+    pub path: PathBuf,
+    pub id: FileId,
+    /// Modification time as of the last read.
+    pub modified: SystemTime,
+    file: File,
+    offset: u64,
+    /// Bytes after the last `\n`, held until the line is finished.
+    partial: Vec<u8>,
+    /// Taken lazily and retaken until the file is long enough to complete it.
+    fingerprint: Option<Fingerprint>,
+}
+
+impl Tail {
+    /// Open `path` and position it at `offset` (clamped to the file's length).
+    pub async fn open(path: &Path, offset: u64) -> std::io::Result<Self> {
+        let mut file = File::open(path).await?;
+        let meta = file.metadata().await?;
+        let offset = offset.min(meta.len());
+        file.seek(SeekFrom::Start(offset)).await?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            id: FileId::of(&meta),
+            modified: meta.modified()?,
+            file,
+            offset,
+            partial: Vec::new(),
+            fingerprint: None,
+        })
+    }
+
+    /// Where reading stopped, for a checkpoint.
+    pub async fn position(&mut self) -> std::io::Result<Position> {
+        let fingerprint = match self.fingerprint {
+            Some(fingerprint) if fingerprint.complete() => fingerprint,
+            _ => {
+                let file = self.file.try_clone().await?.into_std().await;
+                let fingerprint = Fingerprint::read(&file, FINGERPRINT_LEN)?;
+                *self.fingerprint.insert(fingerprint)
+            }
+        };
+        Ok(Position {
+            dev: self.id.dev,
+            ino: self.id.ino,
+            offset: self.resume_offset(),
+            fingerprint,
+        })
+    }
+
+    /// Offset of the first byte not yet returned as part of a line.
+    pub fn resume_offset(&self) -> u64 {
+        self.offset - self.partial.len() as u64
+    }
+
+    /// Read everything appended since the last call and return the complete
+    /// lines. A file that shrank below the read offset was truncated and is
+    /// re-read from the start.
+    pub async fn read_lines(&mut self) -> std::io::Result<Vec<String>> {
+        let meta = self.file.metadata().await?;
+        self.modified = meta.modified()?;
+        if meta.len() < self.offset {
+            tracing::debug!(path = %self.path.display(), "file truncated, re-reading");
+            self.file.seek(SeekFrom::Start(0)).await?;
+            self.offset = 0;
+            self.partial.clear();
+            self.fingerprint = None;
+        }
+        let mut lines = Vec::new();
+        let mut buf = vec![0; CHUNK];
+        loop {
+            let n = self.file.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            self.offset += n as u64;
+            let mut rest = &buf[..n];
+            while let Some(nl) = rest.iter().position(|&b| b == b'\n') {
+                self.partial.extend_from_slice(&rest[..nl]);
+                lines.push(take_line(&mut self.partial));
+                rest = &rest[nl + 1..];
+            }
+            self.partial.extend_from_slice(rest);
+        }
+        Ok(lines)
+    }
+
+    /// The unterminated last line, if any. Called once the file will not be
+    /// written again (rotated away or deleted).
+    pub fn flush(&mut self) -> Option<String> {
+        (!self.partial.is_empty()).then(|| take_line(&mut self.partial))
+    }
+
+    /// Compare the path against the open file.
+    pub async fn path_state(&self) -> std::io::Result<PathState> {
+        match tokio::fs::metadata(&self.path).await {
+            Ok(meta) if FileId::of(&meta) == self.id => Ok(PathState::Same),
+            Ok(_) => Ok(PathState::Replaced),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let nlink = self.file.metadata().await?.nlink();
+                Ok(PathState::Missing {
+                    unlinked: nlink == 0,
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Drain `buf` into a line, dropping a trailing `\r` and converting invalid
+/// UTF-8 lossily.
+fn take_line(buf: &mut Vec<u8>) -> String {
+    if buf.last() == Some(&b'\r') {
+        buf.pop();
+    }
+    let line = String::from_utf8_lossy(buf).into_owned();
+    buf.clear();
+    line
+}
+
+// ----- Tests -----
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[tokio::test]
+    async fn partial_lines_wait_for_their_newline() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        let mut out = std::fs::File::create(&path).unwrap();
+        out.write_all(b"one\r\ntw").unwrap();
+
+        let mut tail = Tail::open(&path, 0).await.unwrap();
+        assert_eq!(tail.read_lines().await.unwrap(), ["one"]);
+        out.write_all(b"o\nthree").unwrap();
+        assert_eq!(tail.read_lines().await.unwrap(), ["two"]);
+        assert_eq!(tail.resume_offset(), 9);
+        assert_eq!(tail.flush().as_deref(), Some("three"));
+        assert_eq!(tail.resume_offset(), 14);
+    }
+
+    #[tokio::test]
+    async fn position_fingerprints_the_head_without_moving() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        std::fs::write(&path, "one\n").unwrap();
+
+        let mut tail = Tail::open(&path, 0).await.unwrap();
+        assert_eq!(tail.read_lines().await.unwrap(), ["one"]);
+        let short = tail.position().await.unwrap();
+        assert_eq!((short.offset, short.fingerprint.len), (4, 4));
+
+        let mut out = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        out.write_all(&[b'x'; 2000]).unwrap();
+        out.write_all(b"\ntwo\n").unwrap();
+        assert_eq!(tail.read_lines().await.unwrap().last().unwrap(), "two");
+        let long = tail.position().await.unwrap();
+        assert_eq!(long.offset, 2009);
+        assert_eq!(long.fingerprint.len, FINGERPRINT_LEN);
+    }
+
+    #[tokio::test]
+    async fn rename_and_delete_are_told_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        std::fs::write(&path, "").unwrap();
+        let tail = Tail::open(&path, 0).await.unwrap();
+
+        std::fs::rename(&path, dir.path().join("app.log.1")).unwrap();
+        assert_eq!(
+            tail.path_state().await.unwrap(),
+            PathState::Missing { unlinked: false }
+        );
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(tail.path_state().await.unwrap(), PathState::Replaced);
+
+        let other = Tail::open(&path, 0).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            other.path_state().await.unwrap(),
+            PathState::Missing { unlinked: true }
+        );
+    }
+}
@@ -0,0 +1,524 @@
+//! Fluent forward receiver — fml as the target of a Fluent Bit or Fluentd
+//! `forward` output.
+//!
+//! Speaks the [Forward Protocol v1] over TCP. Every event mode is accepted,
+//! decided per message by the shape of its second element:
+//!
+//! | Mode                     | Message                                        |
+//! |--------------------------|------------------------------------------------|
+//! | Message                  | `[tag, time, record, option?]`                 |
+//! | Forward                  | `[tag, [[time, record], …], option?]`          |
+//! | PackedForward            | `[tag, bin(<[time, record]>…), option?]`       |
+//! | CompressedPackedForward  | as PackedForward, gzip, `compressed: "gzip"`   |
+//!
+//! `time` is integer seconds or an `EventTime` (ext type 0, nanosecond
+//! precision). When the option map carries a `chunk` id, the sender asked for
+//! at-least-once delivery and gets `{"ack": chunk}` back once every event of
+//! the message is queued — so a sender configured with `Require_ack_response`
+//! slows down instead of losing lines when fml falls behind.
+//!
+//! The tag becomes the producer, one tree level per dot (`app.web.access` is
+//! `app/web/access`), and record keys become fields through the same mapping
+//! as the other push feeds (`log` / `message` for the message, `level` for
+//! the level). The tag itself is kept as a `tag` field. Shared-key
+//! authentication, TLS and UDP heartbeats are not supported.
+//!
+//! [Forward Protocol v1]: https://github.com/fluent/fluentd/wiki/Forward-Protocol-Specification-v1
+
+use crate::record::record_entry;
+use crate::FeedError;
+use bytes::{Buf, BytesMut};
+use chrono::{DateTime, Utc};
+use fml_core::{FeedKind, LogEntry};
+use rmp::Marker;
+use rmpv::Value as Msg;
+use serde_json::{Map, Value};
+use std::io;
+use std::net::SocketAddr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+/// Largest message accepted. Fluent Bit chunks are a few MiB at most; a
+/// message claiming more than this is treated as a framing error.
+const MAX_MESSAGE_LEN: usize = 64 * 1024 * 1024;
+
+/// Settings for the forward receiver.
+#[derive(Debug, Clone)]
+pub struct ForwardConfig {
+    pub addr: SocketAddr,
+}
+
+impl Default for ForwardConfig {
+    fn default() -> Self {
+        Self {
+            // The standard forward port, loopback only.
+            addr: SocketAddr::from(([127, 0, 0, 1], 24224)),
+        }
+    }
+}
+
+/// A bound forward receiver, ready to [`run`](ForwardReceiver::run).
+pub struct ForwardReceiver {
+    listener: TcpListener,
+}
+
+impl ForwardReceiver {
+    pub async fn bind(config: &ForwardConfig) -> Result<Self, FeedError> {
+        Ok(Self {
+            listener: TcpListener::bind(config.addr).await?,
+        })
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.listener.local_addr().ok()
+    }
+
+    /// Accept senders until `tx` is closed. Each connection is served on its
+    /// own task; one that sends something other than forward messages is
+    /// dropped without affecting the listener.
+    pub async fn run(self, tx: mpsc::Sender<LogEntry>) -> Result<(), FeedError> {
+        let accept = async {
+            loop {
+                let (stream, peer) = self.listener.accept().await?;
+                tracing::debug!(%peer, "forward: connection");
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, &tx).await {
+                        tracing::debug!(%peer, error = %e, "forward: dropping connection");
+                    }
+                });
+            }
+        };
+        tokio::select! {
+            r = accept => r,
+            _ = tx.closed() => Err(FeedError::ChannelClosed),
+        }
+    }
+}
+
+/// Read messages off one connection until it closes, acking those that ask
+/// for it. Returns `Ok` at a clean EOF or once `tx` is closed.
+async fn serve(mut stream: TcpStream, tx: &mpsc::Sender<LogEntry>) -> io::Result<()> {
+    let mut buf = BytesMut::with_capacity(64 * 1024);
+    loop {
+        while let Some(len) = object_len(&buf)? {
+            let frame = buf.split_to(len);
+            let value = rmpv::decode::read_value(&mut frame.reader()).map_err(invalid)?;
+            let message = decode_message(value)?;
+            for entry in message.entries() {
+                if tx.send(entry).await.is_err() {
+                    return Ok(());
+                }
+            }
+            if let Some(chunk) = message.chunk {
+                stream.write_all(&ack(chunk)).await?;
+            }
+        }
+        if stream.read_buf(&mut buf).await? == 0 {
+            return if buf.is_empty() {
+                Ok(())
+            } else {
+                Err(invalid("connection closed mid-message"))
+            };
+        }
+    }
+}
+
+fn invalid(e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// The `{"ack": chunk}` response to a message that carried a chunk id.
+fn ack(chunk: String) -> Vec<u8> {
+    let response = Msg::Map(vec![(Msg::from("ack"), Msg::from(chunk))]);
+    let mut out = Vec::new();
+    rmpv::encode::write_value(&mut out, &response).expect("writing to a Vec cannot fail");
+    out
+}
+
+// ---------------------------------------------------------------------------
+// Framing
+// ---------------------------------------------------------------------------
+
+/// Length of the first complete MessagePack object in `buf`, or `None` while
+/// more bytes are needed. Only markers and lengths are read, so a large
+/// PackedForward payload is skipped over rather than scanned.
+pub(crate) fn object_len(buf: &[u8]) -> io::Result<Option<usize>> {
+    let mut pos = 0;
+    // Objects still to be walked: the top one plus the children of every
+    // array and map seen so far.
+    let mut pending: u64 = 1;
+    while pending > 0 {
+        pending -= 1;
+        let Some(&byte) = buf.get(pos) else {
+            return Ok(None);
+        };
+        pos += 1;
+        // Payload bytes to skip; the byte width of a length to read first;
+        // what that length counts (payload bytes, or children per item); and
+        // children known from the marker alone.
+        let (mut skip, width, per, mut children): (usize, usize, u64, u64) =
+            match Marker::from_u8(byte) {
+                Marker::FixPos(_)
+                | Marker::FixNeg(_)
+                | Marker::Null
+                | Marker::True
+                | Marker::False => (0, 0, 0, 0),
+                Marker::U8 | Marker::I8 => (1, 0, 0, 0),
+                Marker::U16 | Marker::I16 => (2, 0, 0, 0),
+                Marker::U32 | Marker::I32 | Marker::F32 => (4, 0, 0, 0),
+                Marker::U64 | Marker::I64 | Marker::F64 => (8, 0, 0, 0),
+                Marker::FixStr(n) => (usize::from(n), 0, 0, 0),
+                Marker::Str8 | Marker::Bin8 => (0, 1, 0, 0),
+                Marker::Str16 | Marker::Bin16 => (0, 2, 0, 0),
+                Marker::Str32 | Marker::Bin32 => (0, 4, 0, 0),
+                Marker::FixArray(n) => (0, 0, 0, u64::from(n)),
+                Marker::FixMap(n) => (0, 0, 0, 2 * u64::from(n)),
+                Marker::Array16 => (0, 2, 1, 0),
+                Marker::Array32 => (0, 4, 1, 0),
+                Marker::Map16 => (0, 2, 2, 0),
+                Marker::Map32 => (0, 4, 2, 0),
+                // Type byte plus 1–16 bytes of data.
+                Marker::FixExt1 => (2, 0, 0, 0),
+                Marker::FixExt2 => (3, 0, 0, 0),
+                Marker::FixExt4 => (5, 0, 0, 0),
+                Marker::FixExt8 => (9, 0, 0, 0),
+                Marker::FixExt16 => (17, 0, 0, 0),
+                Marker::Ext8 => (1, 1, 0, 0),
+                Marker::Ext16 => (1, 2, 0, 0),
+                Marker::Ext32 => (1, 4, 0, 0),
+                Marker::Reserved => return Err(invalid("not a forward message")),
+            };
+        if width > 0 {
+            let Some(n) = read_len(buf, pos, width) else {
+                return Ok(None);
+            };
+            pos += width;
+            if per == 0 {
+                skip += n;
+            } else {
+                children += per * n as u64;
+            }
+        }
+        pos += skip;
+        pending += children;
+        if pos > MAX_MESSAGE_LEN {
+            return Err(invalid("message too large"));
+        }
+    }
+    Ok((pos <= buf.len()).then_some(pos))
+}
+
+/// A big-endian length of `width` bytes at `pos`, if it has arrived.
+fn read_len(buf: &[u8], pos: usize, width: usize) -> Option<usize> {
+    let bytes = buf.get(pos..pos + width)?;
+    Some(bytes.iter().fold(0, |n, b| (n << 8) | usize::from(*b)))
+}
+
+// ---------------------------------------------------------------------------
+// Messages
+// ---------------------------------------------------------------------------
+
+/// An event's time and record.
+type Event = (DateTime<Utc>, Map<String, Value>);
+
+/// One decoded forward message.
+#[derive(Debug)]
+pub(crate) struct Message {
+    tag: String,
+    events: Vec<Event>,
+    /// Chunk id to acknowledge, when the sender asked for an ack.
+    chunk: Option<String>,
+}
+
+impl Message {
+    fn entries(&self) -> impl Iterator<Item = LogEntry> + '_ {
+        let producer = tag_producer(&self.tag);
+        self.events.iter().map(move |(ts, record)| {
+            let raw = Value::Object(record.clone()).to_string();
+            let mut entry = record_entry(FeedKind::Forward, &producer, raw, record.clone());
+            entry.ts = *ts;
+            entry
+                .fields
+                .entry("tag".to_string())
+                .or_insert_with(|| Value::String(self.tag.clone()));
+            entry
+        })
+    }
+}
+
+/// Decode a message in any of the event modes.
+pub(crate) fn decode_message(value: Msg) -> io::Result<Message> {
+    let Msg::Array(mut parts) = value else {
+        return Err(invalid("message is not an array"));
+    };
+    if !(2..=4).contains(&parts.len()) {
+        return Err(invalid("message has the wrong number of elements"));
+    }
+    let tag = text(&parts[0]).ok_or_else(|| invalid("tag is not a string"))?;
+    let entries = parts.remove(1);
+
+    let (events, option) = match entries {
+        // Message mode: the time, then the record, then the option.
+        time @ (Msg::Integer(_) | Msg::Ext(..) | Msg::F32(_) | Msg::F64(_)) => {
+            if parts.len() < 2 {
+                return Err(invalid("message mode without a record"));
+            }
+            let record = parts.remove(1);
+            let event = event(time, record)?;
+            (vec![event], parts.get(1))
+        }
+        Msg::Array(entries) => {
+            let events = entries
+                .into_iter()
+                .map(entry_event)
+                .collect::<io::Result<_>>()?;
+            (events, parts.get(1))
+        }
+        Msg::String(packed) => (
+            unpack(packed.as_bytes(), gzipped(parts.get(1)))?,
+            parts.get(1),
+        ),
+        Msg::Binary(packed) => (unpack(&packed, gzipped(parts.get(1)))?, parts.get(1)),
+        _ => return Err(invalid("unknown event mode")),
+    };
+
+    let chunk = option.and_then(|o| option_value(o, "chunk")).and_then(text);
+    Ok(Message { tag, events, chunk })
+}
+
+/// Whether a PackedForward option says the payload is gzip-compressed.
+fn gzipped(option: Option<&Msg>) -> bool {
+    option
+        .and_then(|o| option_value(o, "compressed"))
+        .and_then(text)
+        .is_some_and(|c| c == "gzip")
+}
+
+/// The `[time, record]` entries packed back to back in a PackedForward
+/// payload. A gzip payload may inflate to [`MAX_MESSAGE_LEN`] at most.
+fn unpack(packed: &[u8], gzip: bool) -> io::Result<Vec<Event>> {
+    let inflated;
+    let mut bytes = packed;
+    if gzip {
+        inflated = crate::otlp::gunzip(packed, MAX_MESSAGE_LEN as u64)?
+            .ok_or_else(|| invalid("compressed payload too large"))?;
+        bytes = &inflated;
+    }
+    let mut events = Vec::new();
+    while !bytes.is_empty() {
+        let entry = rmpv::decode::read_value(&mut bytes).map_err(invalid)?;
+        events.push(entry_event(entry)?);
+    }
+    Ok(events)
+}
+
+/// A `[time, record]` entry of a Forward or PackedForward message.
+fn entry_event(entry: Msg) -> io::Result<Event> {
+    match entry {
+        Msg::Array(pair) if pair.len() == 2 => {
+            let [time, record]: [Msg; 2] = pair.try_into().expect("length checked");
+            event(time, record)
+        }
+        _ => Err(invalid("entry is not [time, record]")),
+    }
+}
+
+fn event(time: Msg, record: Msg) -> io::Result<Event> {
+    let Value::Object(record) = to_json(record) else {
+        return Err(invalid("record is not a map"));
+    };
+    Ok((event_time(&time).unwrap_or_else(Utc::now), record))
+}
+
+/// Integer seconds, float seconds, or an `EventTime`: ext type 0 holding
+/// big-endian 32-bit seconds and nanoseconds.
+pub(crate) fn event_time(time: &Msg) -> Option<DateTime<Utc>> {
+    match time {
+        Msg::Integer(secs) => DateTime::from_timestamp(secs.as_i64()?, 0),
+        Msg::F32(secs) => DateTime::from_timestamp_millis((f64::from(*secs) * 1000.0) as i64),
+        Msg::F64(secs) => DateTime::from_timestamp_millis((secs * 1000.0) as i64),
+        Msg::Ext(0, data) if data.len() == 8 => {
+            let secs = u32::from_be_bytes(data[..4].try_into().ok()?);
+            let nanos = u32::from_be_bytes(data[4..].try_into().ok()?);
+            DateTime::from_timestamp(i64::from(secs), nanos)
+        }
+        _ => None,
+    }
+}
+
+fn option_value<'a>(option: &'a Msg, key: &str) -> Option<&'a Msg> {
+    match option {
+        Msg::Map(pairs) => pairs
+            .iter()
+            .find(|(k, _)| text(k).as_deref() == Some(key))
+            .map(|(_, v)| v),
+        _ => None,
+    }
+}
+
+/// A string, or binary that older Fluentd versions send in its place.
+fn text(value: &Msg) -> Option<String> {
+    match value {
+        Msg::String(s) => Some(String::from_utf8_lossy(s.as_bytes()).into_owned()),
+        Msg::Binary(b) => Some(String::from_utf8_lossy(b).into_owned()),
+        _ => None,
+    }
+}
+
+/// A decoded MessagePack value as JSON. Binary is read as (lossy) UTF-8
+/// text, non-string map keys are stringified and ext values are dropped.
+fn to_json(value: Msg) -> Value {
+    match value {
+        Msg::Nil | Msg::Ext(..) => Value::Null,
+        Msg::Boolean(b) => Value::Bool(b),
+        Msg::Integer(n) => n
+            .as_u64()
+            .map(Value::from)
+            .or_else(|| n.as_i64().map(Value::from))
+            .unwrap_or(Value::Null),
+        Msg::F32(f) => Value::from(f64::from(f)),
+        Msg::F64(f) => Value::from(f),
+        value @ (Msg::String(_) | Msg::Binary(_)) => {
+            Value::String(text(&value).unwrap_or_default())
+        }
+        Msg::Array(items) => Value::Array(items.into_iter().map(to_json).collect()),
+        Msg::Map(pairs) => Value::Object(
+            pairs
+                .into_iter()
+                .map(|(k, v)| {
+                    let key = text(&k).unwrap_or_else(|| to_json(k).to_string());
+                    (key, to_json(v))
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// `app.web.access` → `app/web/access`. Empty segments are skipped, and a
+/// tag with none left is `forward`.
+pub(crate) fn tag_producer(tag: &str) -> String {
+    let path: Vec<&str> = tag.split('.').filter(|s| !s.is_empty()).collect();
+    if path.is_empty() {
+        "forward".to_string()
+    } else {
+        path.join("/")
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fml_core::LogLevel;
+
+    fn encode(value: &Msg) -> Vec<u8> {
+        let mut out = Vec::new();
+        rmpv::encode::write_value(&mut out, value).unwrap();
+        out
+    }
+
+    fn record(log: &str) -> Msg {
+        Msg::Map(vec![
+            (Msg::from("log"), Msg::from(log)),
+            (Msg::from("level"), Msg::from("warn")),
+        ])
+    }
+
+    fn event_time_ext(secs: u32, nanos: u32) -> Msg {
+        let mut data = secs.to_be_bytes().to_vec();
+        data.extend(nanos.to_be_bytes());
+        Msg::Ext(0, data)
+    }
+
+    #[test]
+    fn message_mode_with_event_time() {
+        let value = Msg::Array(vec![
+            Msg::from("app.web"),
+            event_time_ext(1_705_312_800, 500_000_000),
+            record("GET / 200"),
+        ]);
+        let message = decode_message(value).unwrap();
+        assert!(message.chunk.is_none());
+        let entries: Vec<_> = message.entries().collect();
+        assert_eq!(entries.len(), 1);
+        let e = &entries[0];
+        assert_eq!(e.source, FeedKind::Forward);
+        assert_eq!(e.producer, "app/web");
+        assert_eq!(e.message.as_deref(), Some("GET / 200"));
+        assert_eq!(e.level, Some(LogLevel::Warn));
+        assert_eq!(e.ts.timestamp_millis(), 1_705_312_800_500);
+        assert_eq!(e.fields["tag"], "app.web");
+    }
+
+    #[test]
+    fn forward_mode_carries_a_chunk() {
+        let option = Msg::Map(vec![(Msg::from("chunk"), Msg::from("abc"))]);
+        let value = Msg::Array(vec![
+            Msg::from("app"),
+            Msg::Array(vec![
+                Msg::Array(vec![Msg::from(1_705_312_800), record("one")]),
+                Msg::Array(vec![Msg::from(1_705_312_801), record("two")]),
+            ]),
+            option,
+        ]);
+        let message = decode_message(value).unwrap();
+        assert_eq!(message.chunk.as_deref(), Some("abc"));
+        let logs: Vec<_> = message.entries().map(|e| e.message.unwrap()).collect();
+        assert_eq!(logs, ["one", "two"]);
+    }
+
+    #[test]
+    fn packed_forward_may_be_gzipped() {
+        let mut packed = Vec::new();
+        for log in ["one", "two"] {
+            packed.extend(encode(&Msg::Array(vec![Msg::from(1), record(log)])));
+        }
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        std::io::Write::write_all(&mut gz, &packed).unwrap();
+        let option = Msg::Map(vec![(Msg::from("compressed"), Msg::from("gzip"))]);
+        let value = Msg::Array(vec![
+            Msg::from("app"),
+            Msg::Binary(gz.finish().unwrap()),
+            option,
+        ]);
+        let message = decode_message(value).unwrap();
+        assert_eq!(message.events.len(), 2);
+        assert_eq!(message.events[1].1["log"], "two");
+    }
+
+    #[test]
+    fn malformed_messages_are_rejected() {
+        assert!(decode_message(Msg::from("app")).is_err());
+        assert!(decode_message(Msg::Array(vec![Msg::from("app"), Msg::from(1)])).is_err());
+        let not_a_map = Msg::Array(vec![Msg::from("app"), Msg::from(1), Msg::from("text")]);
+        assert!(decode_message(not_a_map).is_err());
+    }
+
+    #[test]
+    fn object_len_waits_for_the_whole_object() {
+        let bytes = encode(&Msg::Array(vec![
+            Msg::from("app"),
+            Msg::Binary(vec![0; 300]),
+            Msg::Map(vec![(Msg::from("chunk"), Msg::from("x"))]),
+        ]));
+        for cut in 0..bytes.len() {
+            assert_eq!(object_len(&bytes[..cut]).unwrap(), None, "cut at {cut}");
+        }
+        let mut two = bytes.clone();
+        two.extend(&bytes);
+        assert_eq!(object_len(&two).unwrap(), Some(bytes.len()));
+        assert!(object_len(&[0xc1]).is_err());
+    }
+
+    #[test]
+    fn tags_become_paths() {
+        assert_eq!(tag_producer("kube.shop.api"), "kube/shop/api");
+        assert_eq!(tag_producer("app..web."), "app/web");
+        assert_eq!(tag_producer(""), "forward");
+    }
+}
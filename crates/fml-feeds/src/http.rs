@@ -0,0 +1,451 @@
+//! HTTP push feed — lets Vector, Fluent Bit or Promtail ship logs to fml.
+//!
+//! Serves two endpoints on a configurable address:
+//!
+//! | Endpoint                    | Body                                           |
+//! |-----------------------------|------------------------------------------------|
+//! | `POST /ingest`              | newline-delimited JSON objects, or a JSON array |
+//! | `POST /loki/api/v1/push`    | Loki push API JSON (`{"streams": [...]}`)      |
+//!
+//! `/ingest` records are attributed to the `producer` query parameter
+//! (default `http`), so several shippers can share one endpoint. Loki streams
+//! derive their producer from the stream labels named in
+//! [`HttpConfig::label_hierarchy`], joined with `/`; every label also becomes
+//! a field.
+//!
+//! A request is answered `204 No Content` only once every entry in it is in
+//! the store. Each request is handed to the bounded ingest queue as one batch,
+//! in chunks when it is larger than the queue, and its [`Receipt`] settles
+//! when the UI reports the batch stored (see [`Deliveries`]). When fml can't
+//! keep up the answer is `429 Too Many Requests`: if the queue takes no chunk
+//! within [`QUEUE_WAIT`], or if the feed's overflow policy drops any entry of
+//! the batch. A `429` before the first chunk means nothing was ingested; after
+//! it, the entries already queued may still be stored, so a retry can repeat
+//! them. A batch not stored within [`STORE_WAIT`] (say, its producer is
+//! paused) gets `503 Service Unavailable`. Entries of stopped producers are
+//! discarded and count as handled. Bodies over axum's default limit (2 MiB)
+//! are rejected with `413 Payload Too Large`.
+
+use crate::control::{Deliveries, Delivery, Receipt};
+use crate::record::{level_from_value, record_entry};
+use crate::FeedError;
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    routing::post,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use fml_core::{FeedKind, LogEntry};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+/// How long a request waits for the ingest queue to take each chunk of its
+/// entries before it is turned away with `429`.
+pub const QUEUE_WAIT: Duration = Duration::from_secs(2);
+
+/// How long a queued request waits for its entries to be stored before it
+/// is answered `503`.
+pub const STORE_WAIT: Duration = Duration::from_secs(30);
+
+/// Settings for the push endpoint.
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    pub addr: SocketAddr,
+    /// Loki stream labels that form the producer path, outermost first.
+    /// Labels not present on a stream are skipped.
+    pub label_hierarchy: Vec<String>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            addr: SocketAddr::from(([127, 0, 0, 1], 3100)),
+            label_hierarchy: [
+                "cluster",
+                "namespace",
+                "service_name",
+                "job",
+                "app",
+                "pod",
+                "container",
+                "filename",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        }
+    }
+}
+
+struct IngestState {
+    queue: Queue,
+    label_hierarchy: Vec<String>,
+}
+
+/// A bound push endpoint, ready to [`run`](HttpIngest::run).
+pub struct HttpIngest {
+    listener: TcpListener,
+    label_hierarchy: Vec<String>,
+    deliveries: Deliveries,
+}
+
+impl HttpIngest {
+    pub async fn bind(config: &HttpConfig) -> Result<Self, FeedError> {
+        Ok(Self {
+            listener: TcpListener::bind(config.addr).await?,
+            label_hierarchy: config.label_hierarchy.clone(),
+            deliveries: Deliveries::default(),
+        })
+    }
+
+    /// Answer requests once `deliveries` reports their entries stored. The
+    /// default answers as soon as they are queued.
+    pub fn with_deliveries(mut self, deliveries: Deliveries) -> Self {
+        self.deliveries = deliveries;
+        self
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.listener.local_addr().ok()
+    }
+
+    /// Serve requests, forwarding accepted entries to `tx`.
+    pub async fn run(self, tx: mpsc::Sender<LogEntry>) -> Result<(), FeedError> {
+        let state = Arc::new(IngestState {
+            queue: Queue::new(tx, self.deliveries),
+            label_hierarchy: self.label_hierarchy,
+        });
+        axum::serve(self.listener, router(state)).await?;
+        Ok(())
+    }
+}
+
+fn router(state: Arc<IngestState>) -> Router {
+    Router::new()
+        .route("/ingest", post(ingest_json))
+        .route("/loki/api/v1/push", post(loki_push))
+        .with_state(state)
+}
+
+// ---------------------------------------------------------------------------
+// Handlers
+// ---------------------------------------------------------------------------
+
+async fn ingest_json(
+    State(state): State<Arc<IngestState>>,
+    Query(params): Query<HashMap<String, String>>,
+    body: Bytes,
+) -> (StatusCode, String) {
+    let producer = params.get("producer").map_or("http", String::as_str);
+    match parse_json_body(&body, producer) {
+        Ok(entries) => state.queue.commit(entries).await,
+        Err(msg) => (StatusCode::BAD_REQUEST, msg),
+    }
+}
+
+async fn loki_push(
+    State(state): State<Arc<IngestState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> (StatusCode, String) {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/json");
+    if content_type.starts_with("application/x-protobuf") {
+        return (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "only the JSON push format is supported".to_string(),
+        );
+    }
+    match parse_loki_push(&body, &state.label_hierarchy) {
+        Ok(entries) => state.queue.commit(entries).await,
+        Err(msg) => (StatusCode::BAD_REQUEST, msg),
+    }
+}
+
+/// A push feed's way into its ingest queue. Requests are handed over one
+/// batch at a time and answered once the batch is stored.
+pub(crate) struct Queue {
+    tx: mpsc::Sender<LogEntry>,
+    deliveries: Deliveries,
+    /// Held while a batch is sent, so each reaches the relay whole and in the
+    /// order it was announced.
+    sending: tokio::sync::Mutex<()>,
+}
+
+impl Queue {
+    pub(crate) fn new(tx: mpsc::Sender<LogEntry>, deliveries: Deliveries) -> Self {
+        Self {
+            tx,
+            deliveries,
+            sending: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Hand `entries` to the queue in chunks it can hold, each within
+    /// [`QUEUE_WAIT`], then wait up to [`STORE_WAIT`] for them to be stored.
+    pub(crate) async fn commit(&self, entries: Vec<LogEntry>) -> (StatusCode, String) {
+        let total = entries.len();
+        let Ok(sending) = tokio::time::timeout(QUEUE_WAIT, self.sending.lock()).await else {
+            return queue_full(0, total);
+        };
+        let mut receipt = self.deliveries.batch(total);
+        if let Err(response) = self.send(entries, &mut receipt).await {
+            return response;
+        }
+        drop(sending);
+        match tokio::time::timeout(STORE_WAIT, receipt.stored()).await {
+            Ok(Delivery::Stored) => (StatusCode::NO_CONTENT, String::new()),
+            Ok(Delivery::Dropped(dropped)) => {
+                tracing::debug!(dropped, total, "http ingest: entries dropped");
+                (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    format!("{dropped} of {total} entries dropped: fml is not keeping up"),
+                )
+            }
+            Ok(Delivery::Closed) => shutting_down(),
+            Err(_) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("entries not stored within {}s", STORE_WAIT.as_secs()),
+            ),
+        }
+    }
+
+    /// Send `entries` in chunks, abandoning the rest of the batch if a chunk
+    /// cannot be queued.
+    async fn send(
+        &self,
+        entries: Vec<LogEntry>,
+        receipt: &mut Receipt,
+    ) -> Result<(), (StatusCode, String)> {
+        let total = entries.len();
+        let chunk = self.tx.max_capacity();
+        let mut entries = entries.into_iter();
+        while entries.len() > 0 {
+            let reserve = self.tx.reserve_many(entries.len().min(chunk));
+            let permits = match tokio::time::timeout(QUEUE_WAIT, reserve).await {
+                Ok(Ok(permits)) => permits,
+                Ok(Err(_)) => {
+                    receipt.abandon(entries.len());
+                    return Err(shutting_down());
+                }
+                Err(_) => {
+                    receipt.abandon(entries.len());
+                    return Err(queue_full(total - entries.len(), total));
+                }
+            };
+            for (permit, entry) in permits.zip(&mut entries) {
+                permit.send(entry);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn queue_full(queued: usize, total: usize) -> (StatusCode, String) {
+    tracing::debug!(queued, total, "http ingest: queue full, rejecting batch");
+    let msg = if queued == 0 {
+        "ingest queue full".to_string()
+    } else {
+        format!("ingest queue full after {queued} of {total} entries")
+    };
+    (StatusCode::TOO_MANY_REQUESTS, msg)
+}
+
+fn shutting_down() -> (StatusCode, String) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        "fml is shutting down".to_string(),
+    )
+}
+
+// ---------------------------------------------------------------------------
+// Body parsing
+// ---------------------------------------------------------------------------
+
+/// Parse an `/ingest` body: a JSON array of objects, or one object per line.
+pub fn parse_json_body(body: &[u8], producer: &str) -> Result<Vec<LogEntry>, String> {
+    let text = String::from_utf8_lossy(body);
+    let trimmed = text.trim_start();
+
+    // (raw text, parsed value) pairs; NDJSON keeps each line verbatim.
+    let records: Vec<(String, Value)> = if trimmed.starts_with('[') {
+        let array: Vec<Value> =
+            serde_json::from_str(trimmed).map_err(|e| format!("invalid JSON array: {e}"))?;
+        array.into_iter().map(|v| (v.to_string(), v)).collect()
+    } else {
+        trimmed
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .enumerate()
+            .map(|(i, line)| {
+                serde_json::from_str(line)
+                    .map(|v| (line.to_string(), v))
+                    .map_err(|e| format!("line {}: {e}", i + 1))
+            })
+            .collect::<Result<_, _>>()?
+    };
+
+    records
+        .into_iter()
+        .map(|(raw, record)| match record {
+            Value::Object(map) => Ok(record_entry(FeedKind::Http, producer, raw, map)),
+            other => Err(format!("expected a JSON object, got `{other}`")),
+        })
+        .collect()
+}
+
+/// Parse a Loki push API JSON payload.
+pub fn parse_loki_push(body: &[u8], hierarchy: &[String]) -> Result<Vec<LogEntry>, String> {
+    let payload: Value =
+        serde_json::from_slice(body).map_err(|e| format!("invalid push payload: {e}"))?;
+    let streams = payload
+        .get("streams")
+        .and_then(Value::as_array)
+        .ok_or("push payload has no `streams` array")?;
+
+    let mut entries = Vec::new();
+    for stream in streams {
+        let labels: Map<String, Value> = stream
+            .get("stream")
+            .and_then(Value::as_object)
+            .cloned()
+            .unwrap_or_default();
+        let producer = loki_producer(&labels, hierarchy);
+        let values = stream
+            .get("values")
+            .and_then(Value::as_array)
+            .ok_or("stream has no `values` array")?;
+
+        for value in values {
+            let parts = value.as_array().ok_or("stream value is not an array")?;
+            let ts = parts
+                .first()
+                .and_then(Value::as_str)
+                .and_then(|ns| ns.parse::<i64>().ok())
+                .map(DateTime::<Utc>::from_timestamp_nanos)
+                .ok_or("stream value has no nanosecond timestamp")?;
+            let line = parts
+                .get(1)
+                .and_then(Value::as_str)
+                .ok_or("stream value has no log line")?;
+
+            let mut fields: HashMap<String, Value> = labels
+                .iter()
+                .map(|(k, v)| (k.to_ascii_lowercase(), v.clone()))
+                .collect();
+            // Optional third element: structured metadata.
+            if let Some(Value::Object(meta)) = parts.get(2) {
+                for (k, v) in meta {
+                    fields.insert(k.to_ascii_lowercase(), v.clone());
+                }
+            }
+            let level = ["level", "detected_level", "severity"]
+                .iter()
+                .find_map(|k| fields.get(*k).and_then(level_from_value));
+
+            entries.push(LogEntry {
+                seq: 0,
+                raw: line.to_string(),
+                ts,
+                level,
+                source: FeedKind::Http,
+                instance: None,
+                producer: producer.clone(),
+                fields,
+                message: Some(line.to_string()),
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// `/`-joined values of the hierarchy labels present on a stream, or the
+/// stream's full label set when none of them are.
+fn loki_producer(labels: &Map<String, Value>, hierarchy: &[String]) -> String {
+    let label = |k: &str| {
+        labels
+            .get(k)
+            .and_then(Value::as_str)
+            .filter(|v| !v.is_empty())
+    };
+    let path: Vec<&str> = hierarchy.iter().filter_map(|k| label(k)).collect();
+    if !path.is_empty() {
+        return path.join("/");
+    }
+    let mut pairs: Vec<String> = labels
+        .iter()
+        .filter_map(|(k, v)| v.as_str().map(|v| format!("{k}={v}")))
+        .collect();
+    pairs.sort();
+    if pairs.is_empty() {
+        "loki".to_string()
+    } else {
+        pairs.join(",")
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fml_core::LogLevel;
+
+    fn hierarchy() -> Vec<String> {
+        HttpConfig::default().label_hierarchy
+    }
+
+    #[test]
+    fn ndjson_body_yields_one_entry_per_line() {
+        let body = b"{\"msg\":\"a\",\"level\":\"info\"}\n\n{\"msg\":\"b\",\"level\":\"error\"}\n";
+        let entries = parse_json_body(body, "vector/app").unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].producer, "vector/app");
+        assert_eq!(entries[1].level, Some(LogLevel::Error));
+        assert_eq!(entries[1].message.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn json_array_body_is_accepted() {
+        let entries = parse_json_body(br#"[{"log":"x"},{"log":"y"}]"#, "http").unwrap();
+        assert_eq!(entries.len(), 2);
+    }
+
+    #[test]
+    fn bad_line_is_reported_with_its_number() {
+        let err = parse_json_body(b"{\"a\":1}\nnot json\n", "http").unwrap_err();
+        assert!(err.starts_with("line 2"), "{err}");
+    }
+
+    #[test]
+    fn loki_labels_form_producer_and_fields() {
+        let body = br#"{"streams":[{"stream":{"namespace":"shop","pod":"api-1","container":"api","level":"warn"},
+            "values":[["1705312800000000000","slow request",{"trace_id":"abc"}]]}]}"#;
+        let entries = parse_loki_push(body, &hierarchy()).unwrap();
+        assert_eq!(entries.len(), 1);
+        let e = &entries[0];
+        assert_eq!(e.producer, "shop/api-1/api");
+        assert_eq!(e.fields["namespace"], "shop");
+        assert_eq!(e.fields["trace_id"], "abc");
+        assert_eq!(e.level, Some(LogLevel::Warn));
+        assert_eq!(e.ts.timestamp(), 1_705_312_800);
+    }
+
+    #[test]
+    fn loki_stream_without_known_labels_uses_label_set() {
+        let body = br#"{"streams":[{"stream":{"b":"2","a":"1"},"values":[["1","x"]]}]}"#;
+        let entries = parse_loki_push(body, &hierarchy()).unwrap();
+        assert_eq!(entries[0].producer, "a=1,b=2");
+    }
+}
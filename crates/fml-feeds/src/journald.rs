@@ -0,0 +1,443 @@
+//! Journald feed — tails the systemd journal via `journalctl -f -o json`.
+//!
+//! Each journal record is one JSON object per line. The well-known journal
+//! fields are mapped onto [`LogEntry`] directly rather than going through the
+//! generic normalizer:
+//!
+//! | Journal field           | `LogEntry`                          |
+//! |-------------------------|-------------------------------------|
+//! | `MESSAGE`               | `message`                           |
+//! | `PRIORITY`              | `level` (syslog severity 0–7)       |
+//! | `__REALTIME_TIMESTAMP`  | `ts` (µs since the epoch)           |
+//! | `_HOSTNAME` / `_SYSTEMD_UNIT` | `producer` as `host/unit`     |
+//! | `_PID`, `_HOSTNAME`, `_COMM`  | `fields` (`pid`, `hostname`, `comm`) |
+//!
+//! The producer is `host/unit` so the tree groups units under the host that
+//! logged them. Records without a unit (kernel, early boot) fall back to
+//! `SYSLOG_IDENTIFIER`, then `_COMM`.
+//!
+//! One `journalctl` carries every producer, so under [`run_with_signals`] it
+//! is the feed's root that stops and reconnects: stopping `journald` kills
+//! `journalctl` and starting it follows new records only; a reconnect of any
+//! node respawns it after the last record read (`--after-cursor`). Stopping
+//! a single unit only discards its lines.
+
+use crate::control::{Signal, Signals};
+use crate::{lines, FeedError};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use fml_core::{FeedKind, LogEntry, LogLevel};
+use futures::Stream;
+use serde_json::{Map, Value};
+use std::collections::{HashMap, VecDeque};
+use std::process::Stdio;
+use tokio::sync::mpsc;
+use tokio_util::io::ReaderStream;
+
+/// Lines of journalctl's stderr kept for the error when it fails.
+const STDERR_TAIL: usize = 5;
+
+/// Filters passed through to `journalctl`.
+#[derive(Debug, Clone, Default)]
+pub struct JournaldConfig {
+    /// Only follow these units (`-u`). Empty means all units.
+    pub units: Vec<String>,
+    /// Maximum syslog priority to include (`-p`), 0 (emerg) to 7 (debug).
+    pub priority: Option<u8>,
+    /// Start point for backfill (`--since`), in any format journalctl accepts
+    /// (`"-1h"`, `"2024-01-15 10:00"`, `"today"`).
+    pub since: Option<String>,
+}
+
+/// Where a (re)spawned `journalctl` starts reading.
+enum Start {
+    /// At [`JournaldConfig::since`], or journalctl's default without it.
+    Configured,
+    /// Right after the record with this `__CURSOR`.
+    After(String),
+    /// With records logged from now on.
+    Now,
+}
+
+impl JournaldConfig {
+    /// Build the `journalctl` argument list for this configuration.
+    pub fn args(&self) -> Vec<String> {
+        self.args_from(&Start::Configured)
+    }
+
+    fn args_from(&self, start: &Start) -> Vec<String> {
+        let mut args = vec!["-f".to_string(), "-o".to_string(), "json".to_string()];
+        for unit in &self.units {
+            args.push("-u".to_string());
+            args.push(unit.clone());
+        }
+        if let Some(priority) = self.priority {
+            args.push("-p".to_string());
+            args.push(priority.min(7).to_string());
+        }
+        match (start, &self.since) {
+            (Start::Configured, Some(since)) => {
+                args.push("--since".to_string());
+                args.push(since.clone());
+            }
+            (Start::Configured, None) => {}
+            (Start::After(cursor), _) => {
+                args.push("--after-cursor".to_string());
+                args.push(cursor.clone());
+            }
+            (Start::Now, _) => {
+                args.push("-n".to_string());
+                args.push("0".to_string());
+            }
+        }
+        args
+    }
+}
+
+/// Spawn `journalctl` with `config` and forward every record to `tx` until the
+/// process exits or the receiver is dropped. A non-zero exit (no access to
+/// the journal, an unknown option) is returned as [`FeedError::Exited`] with
+/// what journalctl printed on stderr.
+pub async fn run(config: &JournaldConfig, tx: mpsc::Sender<LogEntry>) -> Result<(), FeedError> {
+    run_with_signals(config, Signals::default(), tx).await
+}
+
+/// Like [`run`], killing `journalctl` while `signals` has the feed stopped
+/// and respawning it on a reconnect.
+pub async fn run_with_signals(
+    config: &JournaldConfig,
+    mut signals: Signals,
+    tx: mpsc::Sender<LogEntry>,
+) -> Result<(), FeedError> {
+    let root = fml_core::feed_root(FeedKind::Journald, None);
+    let mut cursor = None;
+    let mut start = Start::Configured;
+    loop {
+        if signals.stopped(&root) {
+            tracing::debug!("journald: stopped");
+            tokio::select! {
+                () = signals.started(&root) => {}
+                () = tx.closed() => return Err(FeedError::ChannelClosed),
+            }
+            start = Start::Now;
+        }
+        let args = config.args_from(&start);
+        // Dropping the follower kills journalctl.
+        let signal = tokio::select! {
+            result = follow("journalctl", &args, tx.clone(), &mut cursor) => return result,
+            asked = signals.next(&root) => asked,
+        };
+        if let (Signal::Reconnect, Some(cursor)) = (signal, &cursor) {
+            tracing::debug!("journald: reconnect requested");
+            start = Start::After(cursor.clone());
+        }
+    }
+}
+
+/// Run `program` and forward its records, keeping the `__CURSOR` of the
+/// last one in `cursor`.
+async fn follow(
+    program: &str,
+    args: &[String],
+    tx: mpsc::Sender<LogEntry>,
+    cursor: &mut Option<String>,
+) -> Result<(), FeedError> {
+    let mut child = tokio::process::Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|source| FeedError::Spawn {
+            program: program.to_string(),
+            source,
+        })?;
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let stderr = tokio::spawn(stderr_tail(ReaderStream::new(stderr)));
+    if let Err(e) = read_records(ReaderStream::new(stdout), tx, cursor).await {
+        let _ = child.kill().await;
+        return Err(e);
+    }
+    let status = child.wait().await?;
+    let stderr = stderr.await.unwrap_or_default();
+    if status.success() {
+        Ok(())
+    } else {
+        Err(FeedError::Exited {
+            program: program.to_string(),
+            status,
+            stderr,
+        })
+    }
+}
+
+/// Log each stderr line as it comes and return the last [`STDERR_TAIL`] of
+/// them, joined with `; `.
+async fn stderr_tail<S>(stream: S) -> String
+where
+    S: Stream<Item = std::io::Result<Bytes>> + Unpin,
+{
+    let mut reader = lines::from_stream(stream);
+    let mut tail = VecDeque::with_capacity(STDERR_TAIL);
+    while let Ok(Some(line)) = reader.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        tracing::warn!(line = %line, "journald: journalctl stderr");
+        if tail.len() == STDERR_TAIL {
+            tail.pop_front();
+        }
+        tail.push_back(line);
+    }
+    Vec::from(tail).join("; ")
+}
+
+/// Read `journalctl -o json` output from `stream` and forward each record to
+/// `tx`. Lines that are not journal JSON objects are skipped.
+pub async fn ingest<S>(stream: S, tx: mpsc::Sender<LogEntry>) -> Result<(), FeedError>
+where
+    S: Stream<Item = std::io::Result<Bytes>> + Unpin,
+{
+    read_records(stream, tx, &mut None).await
+}
+
+async fn read_records<S>(
+    stream: S,
+    tx: mpsc::Sender<LogEntry>,
+    cursor: &mut Option<String>,
+) -> Result<(), FeedError>
+where
+    S: Stream<Item = std::io::Result<Bytes>> + Unpin,
+{
+    let mut reader = lines::from_stream(stream);
+    while let Some(line) = reader.next_line().await? {
+        let Ok(Value::Object(record)) = serde_json::from_str::<Value>(&line) else {
+            tracing::debug!(line = %line, "journald: skipping non-record line");
+            continue;
+        };
+        let entry = journal_entry(&record, &line);
+        tx.send(entry).await.map_err(|_| FeedError::ChannelClosed)?;
+        if let Some(at) = record.get("__CURSOR").and_then(journal_string) {
+            *cursor = Some(at);
+        }
+    }
+    Ok(())
+}
+
+/// Parse a single `journalctl -o json` line into a [`LogEntry`].
+///
+/// Returns `None` if the line is not a JSON object.
+pub fn parse_record(line: &str) -> Option<LogEntry> {
+    let Ok(Value::Object(record)) = serde_json::from_str::<Value>(line) else {
+        return None;
+    };
+    Some(journal_entry(&record, line))
+}
+
+fn journal_entry(record: &Map<String, Value>, line: &str) -> LogEntry {
+    let message = record.get("MESSAGE").and_then(journal_string);
+    let level = record
+        .get("PRIORITY")
+        .and_then(journal_string)
+        .and_then(|p| p.parse::<u8>().ok())
+        .map(priority_to_level);
+    let ts = record
+        .get("__REALTIME_TIMESTAMP")
+        .and_then(journal_string)
+        .and_then(|us| us.parse::<i64>().ok())
+        .and_then(DateTime::<Utc>::from_timestamp_micros)
+        .unwrap_or_else(Utc::now);
+
+    let mut fields = HashMap::new();
+    for (journal_key, field) in [
+        ("_PID", "pid"),
+        ("_HOSTNAME", "hostname"),
+        ("_COMM", "comm"),
+        ("_SYSTEMD_UNIT", "unit"),
+    ] {
+        if let Some(value) = record.get(journal_key).and_then(journal_string) {
+            fields.insert(field.to_string(), Value::String(value));
+        }
+    }
+
+    LogEntry {
+        seq: 0,
+        raw: line.to_string(),
+        ts,
+        level,
+        source: FeedKind::Journald,
+        instance: None,
+        producer: producer_name(record),
+        fields,
+        message,
+    }
+}
+
+/// Map a syslog priority (0 = emerg … 7 = debug) onto [`LogLevel`].
+pub fn priority_to_level(priority: u8) -> LogLevel {
+    match priority {
+        0..=2 => LogLevel::Fatal,
+        3 => LogLevel::Error,
+        4 => LogLevel::Warn,
+        5 | 6 => LogLevel::Info,
+        _ => LogLevel::Debug,
+    }
+}
+
+/// `host/unit` for a journal record, grouping units under their host.
+fn producer_name(record: &Map<String, Value>) -> String {
+    let host = record
+        .get("_HOSTNAME")
+        .and_then(journal_string)
+        .unwrap_or_else(|| "localhost".to_string());
+    let unit = ["_SYSTEMD_UNIT", "SYSLOG_IDENTIFIER", "_COMM"]
+        .iter()
+        .find_map(|key| record.get(*key).and_then(journal_string))
+        .unwrap_or_else(|| "journal".to_string());
+    format!("{host}/{unit}")
+}
+
+/// Journal field values are strings, except for fields containing non-UTF-8
+/// or control bytes which journalctl emits as an array of byte values.
+fn journal_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Array(bytes) => {
+            let bytes: Vec<u8> = bytes
+                .iter()
+                .filter_map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+                .collect();
+            Some(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        _ => None,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECORD: &str = r#"{"__REALTIME_TIMESTAMP":"1705312800123456","PRIORITY":"3","_HOSTNAME":"bastion-1","_SYSTEMD_UNIT":"sshd.service","_PID":"812","_COMM":"sshd","MESSAGE":"Failed password for root"}"#;
+
+    #[test]
+    fn record_maps_well_known_fields() {
+        let entry = parse_record(RECORD).unwrap();
+        assert_eq!(entry.source, FeedKind::Journald);
+        assert_eq!(entry.producer, "bastion-1/sshd.service");
+        assert_eq!(entry.level, Some(LogLevel::Error));
+        assert_eq!(entry.message.as_deref(), Some("Failed password for root"));
+        assert_eq!(entry.ts.timestamp_micros(), 1_705_312_800_123_456);
+        assert_eq!(entry.fields["pid"], "812");
+        assert_eq!(entry.fields["hostname"], "bastion-1");
+        assert_eq!(entry.fields["comm"], "sshd");
+    }
+
+    #[test]
+    fn priorities_map_to_levels() {
+        assert_eq!(priority_to_level(0), LogLevel::Fatal);
+        assert_eq!(priority_to_level(2), LogLevel::Fatal);
+        assert_eq!(priority_to_level(4), LogLevel::Warn);
+        assert_eq!(priority_to_level(5), LogLevel::Info);
+        assert_eq!(priority_to_level(7), LogLevel::Debug);
+    }
+
+    #[test]
+    fn byte_array_message_is_decoded() {
+        let entry = parse_record(r#"{"MESSAGE":[104,105,10],"_HOSTNAME":"h"}"#).unwrap();
+        assert_eq!(entry.message.as_deref(), Some("hi\n"));
+    }
+
+    #[test]
+    fn unitless_record_falls_back_to_identifier() {
+        let entry = parse_record(r#"{"_HOSTNAME":"h","SYSLOG_IDENTIFIER":"kernel","MESSAGE":"x"}"#)
+            .unwrap();
+        assert_eq!(entry.producer, "h/kernel");
+    }
+
+    #[test]
+    fn non_object_lines_are_rejected() {
+        assert!(parse_record("-- No entries --").is_none());
+        assert!(parse_record("[1,2]").is_none());
+    }
+
+    #[test]
+    fn args_include_filters() {
+        let cfg = JournaldConfig {
+            units: vec!["nginx.service".into()],
+            priority: Some(4),
+            since: Some("-1h".into()),
+        };
+        assert_eq!(
+            cfg.args(),
+            [
+                "-f",
+                "-o",
+                "json",
+                "-u",
+                "nginx.service",
+                "-p",
+                "4",
+                "--since",
+                "-1h"
+            ]
+        );
+    }
+
+    fn sh(script: &str) -> Vec<String> {
+        vec!["-c".to_string(), script.to_string()]
+    }
+
+    #[tokio::test]
+    async fn failed_exit_is_an_error_with_stderr() {
+        let (tx, _rx) = mpsc::channel(8);
+        let script =
+            "echo 'No journal files were opened due to insufficient permissions.' >&2; exit 1";
+        let err = follow("sh", &sh(script), tx, &mut None).await.unwrap_err();
+        let FeedError::Exited { status, stderr, .. } = err else {
+            panic!("expected an exit error, got {err}");
+        };
+        assert_eq!(status.code(), Some(1));
+        assert!(stderr.contains("insufficient permissions"), "{stderr}");
+    }
+
+    #[tokio::test]
+    async fn clean_exit_forwards_records() {
+        let (tx, mut rx) = mpsc::channel(8);
+        follow("sh", &sh(&format!("echo '{RECORD}'")), tx, &mut None)
+            .await
+            .unwrap();
+        assert_eq!(rx.recv().await.unwrap().producer, "bastion-1/sshd.service");
+    }
+
+    #[test]
+    fn restarts_resume_after_the_cursor_or_from_now() {
+        let cfg = JournaldConfig {
+            units: Vec::new(),
+            priority: None,
+            since: Some("-1h".into()),
+        };
+        assert_eq!(
+            cfg.args_from(&Start::After("s=1;i=2".into())),
+            ["-f", "-o", "json", "--after-cursor", "s=1;i=2"]
+        );
+        assert_eq!(cfg.args_from(&Start::Now), ["-f", "-o", "json", "-n", "0"]);
+    }
+
+    #[tokio::test]
+    async fn follow_keeps_the_last_cursor() {
+        let (tx, _rx) = mpsc::channel(8);
+        let mut cursor = None;
+        let record = r#"{"__CURSOR":"s=1;i=2","MESSAGE":"x"}"#;
+        follow("sh", &sh(&format!("echo '{record}'")), tx, &mut cursor)
+            .await
+            .unwrap();
+        assert_eq!(cursor.as_deref(), Some("s=1;i=2"));
+    }
+}
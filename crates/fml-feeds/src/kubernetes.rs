@@ -1 +0,0 @@
-//! Kubernetes feed — tails pod logs via `kubectl logs -f`.
@@ -0,0 +1,233 @@
+//! Kubernetes events as a producer.
+//!
+//! OOM kills, failed scheduling, probe failures and image pull errors often
+//! never reach a container's log; they are only recorded as core/v1 `Event`s.
+//! With events enabled, each followed namespace gets a `namespace/events`
+//! producer fed by a watch on its events. Each event becomes one entry:
+//!
+//! - `type` maps to the level: `Warning` → [`LogLevel::Warn`], `Normal` →
+//!   [`LogLevel::Info`].
+//! - `involvedObject` maps to `object_kind` / `object_name`, and for pods to
+//!   the same `pod` and `container` fields that pod log lines carry, so an
+//!   event correlates with the affected pod's output.
+//! - The entry is stamped with the event's last occurrence, so it lands next
+//!   to the log lines around it.
+//!
+//! Events are updated in place when they recur (`count` goes up); every
+//! update is a new occurrence and becomes a new entry. Re-lists after a watch
+//! restart replay unchanged events, which are skipped.
+
+use crate::{line_entry, report, FeedError};
+use chrono::{DateTime, Utc};
+use fml_core::{FeedKind, LogEntry, LogLevel, ProducerStatus, ProducerUpdate};
+use futures::{StreamExt, TryStreamExt};
+use k8s_openapi::api::core::v1::Event;
+use kube::runtime::{watcher, WatchStreamExt};
+use kube::Api;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+
+/// Producer name for `namespace`'s events.
+pub(super) fn producer(namespace: &str) -> String {
+    format!("{namespace}/events")
+}
+
+/// Watch the events in one namespace until `tx` is closed.
+pub(super) async fn watch_events(
+    api: Api<Event>,
+    namespace: String,
+    tx: mpsc::Sender<LogEntry>,
+    updates: mpsc::Sender<ProducerUpdate>,
+) -> Result<(), FeedError> {
+    let producer = producer(&namespace);
+    // Resource version last ingested per event uid.
+    let mut seen: HashMap<String, String> = HashMap::new();
+    let mut events = watcher(api, watcher::Config::default())
+        .default_backoff()
+        .boxed();
+
+    loop {
+        let event = tokio::select! {
+            _ = tx.closed() => break,
+            event = events.try_next() => event,
+        };
+        match event {
+            Ok(Some(watcher::Event::Apply(ev) | watcher::Event::InitApply(ev))) => {
+                let uid = ev.metadata.uid.clone().unwrap_or_default();
+                let version = ev.metadata.resource_version.clone().unwrap_or_default();
+                if seen.get(&uid) == Some(&version) {
+                    continue;
+                }
+                seen.insert(uid, version);
+                if tx.send(event_entry(&producer, &ev)).await.is_err() {
+                    break;
+                }
+            }
+            Ok(Some(watcher::Event::InitDone)) => {
+                report(
+                    &updates,
+                    FeedKind::Kubernetes,
+                    None,
+                    &producer,
+                    ProducerStatus::Streaming,
+                )
+                .await;
+            }
+            Ok(Some(watcher::Event::Delete(ev))) => {
+                // Expired by the API server's event TTL.
+                if let Some(uid) = &ev.metadata.uid {
+                    seen.remove(uid);
+                }
+            }
+            Ok(Some(watcher::Event::Init)) => {}
+            Ok(None) => break,
+            Err(e) => tracing::warn!(%namespace, error = %e, "kubernetes: event watch error"),
+        }
+    }
+    Ok(())
+}
+
+fn event_entry(producer: &str, ev: &Event) -> LogEntry {
+    let object = &ev.involved_object;
+    let kind = object.kind.as_deref().unwrap_or_default();
+    let name = object.name.as_deref().unwrap_or_default();
+    let reason = ev.reason.as_deref().unwrap_or_default();
+    let message = ev.message.as_deref().unwrap_or_default().trim_end();
+
+    let mut entry = line_entry(
+        FeedKind::Kubernetes,
+        producer,
+        format!("{reason} {}/{name}: {message}", kind.to_ascii_lowercase()),
+    );
+    if let Some(ts) = event_time(ev) {
+        entry.ts = ts;
+    }
+    entry.level = match ev.type_.as_deref() {
+        Some("Warning") => Some(LogLevel::Warn),
+        Some("Normal") => Some(LogLevel::Info),
+        _ => None,
+    };
+
+    let mut fields = vec![
+        ("namespace", ev.metadata.namespace.clone()),
+        ("reason", ev.reason.clone()),
+        ("event_type", ev.type_.clone()),
+        ("object_kind", object.kind.clone()),
+        ("object_name", object.name.clone()),
+        (
+            "component",
+            ev.source
+                .as_ref()
+                .and_then(|s| s.component.clone())
+                .or_else(|| ev.reporting_component.clone())
+                .filter(|c| !c.is_empty()),
+        ),
+        ("node", ev.source.as_ref().and_then(|s| s.host.clone())),
+    ];
+    if kind == "Pod" {
+        fields.push(("pod", object.name.clone()));
+        fields.push((
+            "container",
+            object.field_path.as_deref().and_then(container_of),
+        ));
+    }
+    for (key, value) in fields {
+        if let Some(value) = value {
+            entry.fields.insert(key.to_string(), value.into());
+        }
+    }
+    if let Some(count) = ev.count {
+        entry.fields.insert("count".to_string(), count.into());
+    }
+    entry
+}
+
+/// When the event last happened, falling back through the timestamps older
+/// and newer API versions fill in.
+fn event_time(ev: &Event) -> Option<DateTime<Utc>> {
+    ev.last_timestamp
+        .as_ref()
+        .map(|t| t.0)
+        .or_else(|| ev.event_time.as_ref().map(|t| t.0))
+        .or_else(|| ev.first_timestamp.as_ref().map(|t| t.0))
+        .or_else(|| ev.metadata.creation_timestamp.as_ref().map(|t| t.0))
+}
+
+/// The container named by an `involvedObject.fieldPath` such as
+/// `spec.containers{app}`.
+fn container_of(field_path: &str) -> Option<String> {
+    let rest = field_path
+        .strip_prefix("spec.containers{")
+        .or_else(|| field_path.strip_prefix("spec.initContainers{"))?;
+    rest.strip_suffix('}').map(str::to_string)
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(json: serde_json::Value) -> Event {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn warning_about_a_pod_correlates_with_its_logs() {
+        let ev = event(serde_json::json!({
+            "metadata": {"name": "api-1.17a", "namespace": "shop"},
+            "type": "Warning",
+            "reason": "BackOff",
+            "message": "Back-off restarting failed container app in pod api-1",
+            "involvedObject": {"kind": "Pod", "name": "api-1", "namespace": "shop",
+                               "fieldPath": "spec.containers{app}"},
+            "source": {"component": "kubelet", "host": "node-1"},
+            "count": 7,
+            "lastTimestamp": "2024-01-15T10:00:05Z",
+        }));
+        let entry = event_entry("shop/events", &ev);
+        assert_eq!(entry.level, Some(LogLevel::Warn));
+        assert_eq!(entry.ts.to_rfc3339(), "2024-01-15T10:00:05+00:00");
+        assert_eq!(
+            entry.raw,
+            "BackOff pod/api-1: Back-off restarting failed container app in pod api-1"
+        );
+        assert_eq!(entry.fields["pod"], "api-1");
+        assert_eq!(entry.fields["container"], "app");
+        assert_eq!(entry.fields["reason"], "BackOff");
+        assert_eq!(entry.fields["component"], "kubelet");
+        assert_eq!(entry.fields["count"], 7);
+    }
+
+    #[test]
+    fn normal_event_about_a_workload() {
+        let ev = event(serde_json::json!({
+            "metadata": {"name": "api.17b", "namespace": "shop",
+                         "creationTimestamp": "2024-01-15T10:00:00Z"},
+            "type": "Normal",
+            "reason": "ScalingReplicaSet",
+            "message": "Scaled up replica set api-7d9f8b6c5d to 3",
+            "involvedObject": {"kind": "Deployment", "name": "api"},
+            "eventTime": "2024-01-15T10:00:01.000000Z",
+            "reportingComponent": "deployment-controller",
+        }));
+        let entry = event_entry("shop/events", &ev);
+        assert_eq!(entry.level, Some(LogLevel::Info));
+        assert_eq!(entry.ts.to_rfc3339(), "2024-01-15T10:00:01+00:00");
+        assert_eq!(entry.fields["object_kind"], "Deployment");
+        assert_eq!(entry.fields["component"], "deployment-controller");
+        assert!(!entry.fields.contains_key("pod"));
+    }
+
+    #[test]
+    fn container_is_read_from_field_path() {
+        assert_eq!(container_of("spec.containers{app}").as_deref(), Some("app"));
+        assert_eq!(
+            container_of("spec.initContainers{migrate}").as_deref(),
+            Some("migrate")
+        );
+        assert_eq!(container_of("spec"), None);
+    }
+}
@@ -0,0 +1,495 @@
+//! Per-container log streaming for the Kubernetes feed.
+//!
+//! Logs are requested with `timestamps=true`, so every line starts with the
+//! kubelet's RFC 3339 timestamp. That timestamp becomes [`LogEntry::ts`] and
+//! doubles as a resume cursor: when the API server closes a follow stream
+//! (request timeouts, API server restarts) the stream is re-opened with
+//! `sinceTime` and any replayed lines at or before the cursor are dropped, so
+//! a reconnect never duplicates output.
+//!
+//! When a container restarts, the output that explains why (the panic, the
+//! OOM) belongs to the instance that just died, and the live stream only
+//! shows the fresh boot. [`fetch_previous`] pulls that instance's log once
+//! with `previous=true` and tags it `container_instance=previous`. Each
+//! follower publishes its cursor, and lines at or before it were already
+//! streamed live, so only what the follower never saw is ingested.
+
+use super::workload;
+use crate::control::{tree_id, Signal, Signals};
+use crate::resume::{split_timestamp, Cursor};
+use crate::{line_entry, lines::LineReader, report, Backoff};
+use chrono::{DateTime, Utc};
+use fml_core::{FeedKind, LogEntry, ProducerState, ProducerStatus, ProducerUpdate};
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::LogParams;
+use kube::Api;
+use std::collections::BTreeMap;
+use tokio::sync::{mpsc, watch};
+use tokio_util::compat::FuturesAsyncReadCompatExt;
+
+/// One container's log stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct LogTarget {
+    pub namespace: String,
+    pub pod: String,
+    pub container: String,
+    /// Owning workload as `kind/name`, if any.
+    pub workload: Option<String>,
+    /// `namespace/[workload/]pod[/container]`: replicas nest under their
+    /// workload's name, and the container is named in multi-container pods.
+    pub producer: String,
+}
+
+/// Every container declared by `pod`.
+pub(super) fn containers(pod: &Pod) -> Vec<LogTarget> {
+    let namespace = pod.metadata.namespace.clone().unwrap_or_default();
+    let name = pod.metadata.name.clone().unwrap_or_default();
+    let owner = workload::owner(pod);
+    let group = match &owner {
+        Some(owner) => format!("{namespace}/{}/{name}", owner.name),
+        None => format!("{namespace}/{name}"),
+    };
+    let specs = pod
+        .spec
+        .as_ref()
+        .map(|s| s.containers.as_slice())
+        .unwrap_or_default();
+    let multi = specs.len() > 1;
+    specs
+        .iter()
+        .map(|c| LogTarget {
+            namespace: namespace.clone(),
+            pod: name.clone(),
+            container: c.name.clone(),
+            workload: owner.as_ref().map(ToString::to_string),
+            producer: if multi {
+                format!("{group}/{}", c.name)
+            } else {
+                group.clone()
+            },
+        })
+        .collect()
+}
+
+/// The containers of `pod` whose current state is running.
+pub(super) fn running_containers(pod: &Pod) -> Vec<LogTarget> {
+    containers(pod)
+        .into_iter()
+        .filter(|t| container_state(pod, &t.container) == ContainerState::Running)
+        .collect()
+}
+
+/// Times `container` has restarted, as reported in the pod status.
+pub(super) fn restart_count(pod: &Pod, container: &str) -> u32 {
+    pod.status
+        .as_ref()
+        .and_then(|s| s.container_statuses.as_ref())
+        .and_then(|cs| cs.iter().find(|c| c.name == container))
+        .map_or(0, |c| c.restart_count.max(0) as u32)
+}
+
+/// What the kubelet reports for `container`: its current state
+/// (`Running`, `CrashLoopBackOff`, `OOMKilled`, …) plus `restarts` and, when
+/// known, `exit_code`, `last_reason` and `last_exit_code` of the previous
+/// instance. `None` until the container has a status.
+pub(super) fn describe(
+    pod: &Pod,
+    container: &str,
+) -> Option<(ProducerState, BTreeMap<String, String>)> {
+    let status = pod
+        .status
+        .as_ref()
+        .and_then(|s| s.container_statuses.as_ref())
+        .and_then(|cs| cs.iter().find(|c| c.name == container))?;
+    let mut fields = BTreeMap::from([(
+        "restarts".to_string(),
+        status.restart_count.max(0).to_string(),
+    )]);
+    let current = status.state.as_ref();
+    let state = if let Some(terminated) = current.and_then(|s| s.terminated.as_ref()) {
+        fields.insert("exit_code".to_string(), terminated.exit_code.to_string());
+        ProducerState::Terminated {
+            reason: terminated.reason.clone(),
+            code: Some(terminated.exit_code),
+        }
+    } else if let Some(waiting) = current.and_then(|s| s.waiting.as_ref()) {
+        ProducerState::Waiting {
+            reason: waiting
+                .reason
+                .clone()
+                .unwrap_or_else(|| "Waiting".to_string()),
+        }
+    } else {
+        ProducerState::Running
+    };
+    if let Some(last) = status
+        .last_state
+        .as_ref()
+        .and_then(|s| s.terminated.as_ref())
+    {
+        if let Some(reason) = &last.reason {
+            fields.insert("last_reason".to_string(), reason.clone());
+        }
+        fields.insert("last_exit_code".to_string(), last.exit_code.to_string());
+    }
+    Some((state, fields))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContainerState {
+    Running,
+    /// Terminated, or waiting to restart after terminating, with the exit
+    /// code of the last run if known.
+    Stopped(Option<i32>),
+    /// Not started yet, or no status reported.
+    Pending,
+}
+
+fn container_state(pod: &Pod, container: &str) -> ContainerState {
+    let Some(status) = pod
+        .status
+        .as_ref()
+        .and_then(|s| s.container_statuses.as_ref())
+        .and_then(|cs| cs.iter().find(|c| c.name == container))
+    else {
+        return ContainerState::Pending;
+    };
+    let state = status.state.as_ref();
+    if state.and_then(|s| s.running.as_ref()).is_some() {
+        return ContainerState::Running;
+    }
+    if let Some(terminated) = state.and_then(|s| s.terminated.as_ref()) {
+        return ContainerState::Stopped(Some(terminated.exit_code));
+    }
+    match status
+        .last_state
+        .as_ref()
+        .and_then(|s| s.terminated.as_ref())
+    {
+        Some(last) => ContainerState::Stopped(Some(last.exit_code)),
+        None => ContainerState::Pending,
+    }
+}
+
+/// Stream `target`'s log until its container stops, its pod is deleted, or
+/// `tx` is closed. Lifecycle changes are reported on `updates`, and the
+/// timestamp of the newest line forwarded on `seen`. A reconnect on
+/// `signals` reopens the stream at once; a stop closes it until the container
+/// is started again, leaving out what it logged between.
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn follow(
+    api: Api<Pod>,
+    target: LogTarget,
+    tail_lines: Option<i64>,
+    mut backoff: Backoff,
+    mut signals: Signals,
+    tx: mpsc::Sender<LogEntry>,
+    updates: mpsc::Sender<ProducerUpdate>,
+    seen: watch::Sender<Option<DateTime<Utc>>>,
+) {
+    let root = fml_core::feed_root(FeedKind::Kubernetes, None);
+    let id = tree_id(&root, &target.producer);
+    let mut cursor = Cursor::default();
+    loop {
+        if signals.stopped(&id) {
+            tracing::debug!(producer = %target.producer, "kubernetes: stopped");
+            tokio::select! {
+                () = signals.started(&id) => {}
+                () = tx.closed() => return,
+            }
+            backoff.reset();
+            cursor.restart_at(chrono::Utc::now());
+        }
+        let mut signal = None;
+        let params = log_params(&cursor, &target.container, tail_lines);
+        report(
+            &updates,
+            FeedKind::Kubernetes,
+            None,
+            &target.producer,
+            ProducerStatus::Connecting,
+        )
+        .await;
+        match api.log_stream(&target.pod, &params).await {
+            Ok(stream) => {
+                report(
+                    &updates,
+                    FeedKind::Kubernetes,
+                    None,
+                    &target.producer,
+                    ProducerStatus::Streaming,
+                )
+                .await;
+                let mut reader = LineReader::new(stream.compat());
+                loop {
+                    let line = tokio::select! {
+                        line = reader.next_line() => line,
+                        asked = signals.next(&id) => {
+                            signal = Some(asked);
+                            break;
+                        }
+                    };
+                    match line {
+                        Ok(Some(line)) => {
+                            let (ts, message) = split_timestamp(&line);
+                            if !cursor.admit(ts) {
+                                continue;
+                            }
+                            backoff.reset();
+                            seen.send_replace(cursor.last());
+                            let entry = target_entry(&target, ts, message);
+                            if tx.send(entry).await.is_err() {
+                                return;
+                            }
+                        }
+                        Ok(None) => break,
+                        Err(e) => {
+                            tracing::debug!(producer = %target.producer, error = %e, "kubernetes: log stream error");
+                            break;
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::warn!(producer = %target.producer, error = %e, "kubernetes: log request failed");
+            }
+        }
+
+        match signal {
+            Some(Signal::Reconnect) => {
+                tracing::debug!(producer = %target.producer, "kubernetes: reconnect requested");
+                backoff.reset();
+                cursor.resume();
+                continue;
+            }
+            // Closed until started, at the top of the loop.
+            Some(Signal::Stop) => continue,
+            None => {}
+        }
+
+        // The stream ended. Only reconnect if the container is still up.
+        match api.get_opt(&target.pod).await {
+            Ok(None) => {
+                report(
+                    &updates,
+                    FeedKind::Kubernetes,
+                    None,
+                    &target.producer,
+                    ProducerStatus::Ended { code: None },
+                )
+                .await;
+                return;
+            }
+            Ok(Some(pod)) => match container_state(&pod, &target.container) {
+                ContainerState::Running => {}
+                ContainerState::Stopped(code) => {
+                    report(
+                        &updates,
+                        FeedKind::Kubernetes,
+                        None,
+                        &target.producer,
+                        ProducerStatus::Ended { code },
+                    )
+                    .await;
+                    return;
+                }
+                ContainerState::Pending => {
+                    report(
+                        &updates,
+                        FeedKind::Kubernetes,
+                        None,
+                        &target.producer,
+                        ProducerStatus::Ended { code: None },
+                    )
+                    .await;
+                    return;
+                }
+            },
+            Err(e) => {
+                tracing::debug!(producer = %target.producer, error = %e, "kubernetes: pod lookup failed");
+            }
+        }
+        if tx.is_closed() {
+            return;
+        }
+
+        cursor.resume();
+        let delay = backoff.next_delay();
+        report(
+            &updates,
+            FeedKind::Kubernetes,
+            None,
+            &target.producer,
+            ProducerStatus::Backoff {
+                attempt: backoff.attempt(),
+            },
+        )
+        .await;
+        tokio::select! {
+            () = tokio::time::sleep(delay) => {}
+            _ = signals.next(&id) => backoff.reset(),
+        }
+    }
+}
+
+/// Ingest the log of `target`'s previous container instance, once. Lines are
+/// tagged `container_instance=previous` so they can be told apart from the
+/// live stream. Lines stamped at or before `seen`, the cursor of the follower
+/// that streamed that instance, are skipped as already ingested.
+pub(super) async fn fetch_previous(
+    api: Api<Pod>,
+    target: LogTarget,
+    tail_lines: Option<i64>,
+    seen: Option<watch::Receiver<Option<DateTime<Utc>>>>,
+    tx: mpsc::Sender<LogEntry>,
+) {
+    let params = LogParams {
+        container: Some(target.container.clone()),
+        previous: true,
+        timestamps: true,
+        tail_lines,
+        ..LogParams::default()
+    };
+    let text = match api.logs(&target.pod, &params).await {
+        Ok(text) => text,
+        Err(e) => {
+            // Nothing to fetch if the old instance has already been
+            // garbage-collected.
+            tracing::debug!(producer = %target.producer, error = %e, "kubernetes: no previous log");
+            return;
+        }
+    };
+    // Read once the log is in hand, so the follower has had as long as
+    // possible to drain the instance's last lines.
+    let streamed = seen.and_then(|seen| *seen.borrow());
+    for line in text.lines() {
+        let (ts, message) = split_timestamp(line);
+        if ts
+            .zip(streamed)
+            .is_some_and(|(ts, streamed)| ts <= streamed)
+        {
+            continue;
+        }
+        let mut entry = target_entry(&target, ts, message);
+        entry
+            .fields
+            .insert("container_instance".to_string(), "previous".into());
+        if tx.send(entry).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn target_entry(target: &LogTarget, ts: Option<DateTime<Utc>>, message: &str) -> LogEntry {
+    let mut entry = line_entry(FeedKind::Kubernetes, &target.producer, message.to_string());
+    if let Some(ts) = ts {
+        entry.ts = ts;
+    }
+    for (key, value) in [
+        ("namespace", &target.namespace),
+        ("pod", &target.pod),
+        ("container", &target.container),
+    ] {
+        entry.fields.insert(key.to_string(), value.as_str().into());
+    }
+    if let Some(workload) = &target.workload {
+        entry
+            .fields
+            .insert("workload".to_string(), workload.as_str().into());
+    }
+    entry
+}
+
+/// Follow-mode parameters: backfill `tail_lines` on the first connect, then
+/// re-open from the cursor.
+fn log_params(cursor: &Cursor, container: &str, tail_lines: Option<i64>) -> LogParams {
+    LogParams {
+        container: Some(container.to_string()),
+        follow: true,
+        timestamps: true,
+        since_time: cursor.last(),
+        tail_lines: if cursor.last().is_some() {
+            None
+        } else {
+            tail_lines
+        },
+        ..LogParams::default()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(s: &str) -> Option<DateTime<Utc>> {
+        Some(DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc))
+    }
+
+    #[test]
+    fn params_switch_from_tail_to_since_time() {
+        let mut cursor = Cursor::default();
+        let first = log_params(&cursor, "app", Some(50));
+        assert_eq!(first.tail_lines, Some(50));
+        assert!(first.since_time.is_none());
+
+        cursor.admit(ts("2024-01-15T10:00:00Z"));
+        let again = log_params(&cursor, "app", Some(50));
+        assert_eq!(again.tail_lines, None);
+        assert_eq!(again.since_time, ts("2024-01-15T10:00:00Z"));
+        assert!(again.follow && again.timestamps);
+    }
+
+    #[test]
+    fn multi_container_pods_name_the_container() {
+        let pod: Pod = serde_json::from_value(serde_json::json!({
+            "metadata": {"name": "api-1", "namespace": "shop"},
+            "spec": {"containers": [{"name": "app"}, {"name": "proxy"}]},
+            "status": {"containerStatuses": [
+                {"name": "app", "ready": true, "restartCount": 0, "image": "app", "imageID": "",
+                 "state": {"running": {}}},
+                {"name": "proxy", "ready": false, "restartCount": 2, "image": "envoy", "imageID": "",
+                 "state": {"waiting": {"reason": "CrashLoopBackOff"}},
+                 "lastState": {"terminated": {"exitCode": 137}}}
+            ]}
+        }))
+        .unwrap();
+        let all: Vec<_> = containers(&pod).into_iter().map(|t| t.producer).collect();
+        assert_eq!(all, ["shop/api-1/app", "shop/api-1/proxy"]);
+        let running = running_containers(&pod);
+        assert_eq!(running.len(), 1);
+        assert_eq!(running[0].container, "app");
+        assert_eq!(
+            container_state(&pod, "proxy"),
+            ContainerState::Stopped(Some(137))
+        );
+        assert_eq!(restart_count(&pod, "proxy"), 2);
+        assert_eq!(restart_count(&pod, "app"), 0);
+
+        let (state, fields) = describe(&pod, "proxy").unwrap();
+        assert_eq!(state.to_string(), "CrashLoopBackOff");
+        assert_eq!(fields["restarts"], "2");
+        assert_eq!(fields["last_exit_code"], "137");
+        assert_eq!(describe(&pod, "app").unwrap().0, ProducerState::Running);
+    }
+
+    #[test]
+    fn replicas_nest_under_their_workload() {
+        let pod: Pod = serde_json::from_value(serde_json::json!({
+            "metadata": {
+                "name": "api-7d9f8b6c5d-x2x4q", "namespace": "shop",
+                "labels": {"pod-template-hash": "7d9f8b6c5d"},
+                "ownerReferences": [{"apiVersion": "apps/v1", "kind": "ReplicaSet",
+                    "name": "api-7d9f8b6c5d", "uid": "u", "controller": true}],
+            },
+            "spec": {"containers": [{"name": "app"}]},
+        }))
+        .unwrap();
+        let targets = containers(&pod);
+        assert_eq!(targets[0].producer, "shop/api/api-7d9f8b6c5d-x2x4q");
+        assert_eq!(targets[0].workload.as_deref(), Some("deployment/api"));
+    }
+}
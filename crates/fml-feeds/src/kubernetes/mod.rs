@@ -0,0 +1,373 @@
+//! Kubernetes feed — streams pod logs straight from the API server.
+//!
+//! No `kubectl` is involved. Credentials come from kubeconfig (contexts,
+//! bearer tokens, client certificates and exec credential plugins are all
+//! honoured) or, when running inside a cluster, the service account.
+//!
+//! For every selected namespace the feed watches the pod list and opens one
+//! `GET /api/v1/namespaces/{ns}/pods/{pod}/log?follow=true&timestamps=true`
+//! stream per running container (see [`logs`]). Pods appearing in the watch
+//! are attached as they start; deleted pods are reported as
+//! [`ProducerStatus::Ended`].
+//!
+//! Producers are `namespace/pod`, or `namespace/pod/container` for pods with
+//! more than one container, so the tree groups pods under their namespace.
+//! Pods owned by a workload nest one level deeper, under the workload's name
+//! (`shop/api/api-7d9f8b6c5d-x2x4q`), so replicas from successive rollouts
+//! share a node (see [`workload`]). Every entry carries `namespace`, `pod`
+//! and `container` fields, plus `workload` (`deployment/api`) when owned.
+//!
+//! When a container's restart count goes up, the log of the instance that
+//! just exited is fetched once (`previous=true`) and the lines the live
+//! stream did not already deliver are ingested with a
+//! `container_instance=previous` field, and the restart is reported as
+//! [`ProducerStatus::Restarted`] so the tree can flag it. Containers that
+//! have already restarted when first seen get the same treatment, which is
+//! what makes a pod in `CrashLoopBackOff` debuggable after the fact.
+//!
+//! With a metadata channel ([`KubernetesFeed::with_metadata`]) every
+//! container's [`ProducerState`](fml_core::ProducerState) is reported as the
+//! pod watch sees it change — `running`, the kubelet's waiting reason
+//! (`CrashLoopBackOff`, `ImagePullBackOff`) or termination reason
+//! (`OOMKilled`, `Error`, `Completed`) — with `restarts`, `exit_code`,
+//! `last_reason` and `last_exit_code` fields.
+//!
+//! Streams can also be reopened on demand ([`KubernetesFeed::with_signals`]);
+//! the `sinceTime` cursor keeps that from repeating lines. The same signals
+//! close a container's stream while it is stopped in the UI.
+//!
+//! Optionally, each namespace also gets a `namespace/events` producer
+//! carrying its Kubernetes events (see [`events`]).
+//!
+//! The pods followed can be narrowed to a label selector, evaluated by the
+//! API server, and to a set of workloads. Either way the feed keeps watching,
+//! so replacement pods are picked up across rollouts and restarts.
+
+mod events;
+mod logs;
+mod workload;
+
+pub use workload::{Workload, WorkloadKind};
+
+use crate::control::Signals;
+use crate::{report, Backoff, FeedError};
+use chrono::{DateTime, Utc};
+use fml_core::{FeedKind, LogEntry, ProducerMeta, ProducerStatus, ProducerUpdate};
+use futures::future::BoxFuture;
+use futures::{FutureExt, StreamExt, TryStreamExt};
+use k8s_openapi::api::core::v1::{Event, Pod};
+use kube::config::{KubeConfigOptions, Kubeconfig};
+use kube::runtime::{watcher, WatchStreamExt};
+use kube::{Api, Client};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+
+/// Where to connect and what to follow.
+#[derive(Debug, Clone, Default)]
+pub struct KubernetesConfig {
+    /// Kubeconfig file to read instead of `$KUBECONFIG` / `~/.kube/config`.
+    pub kubeconfig: Option<PathBuf>,
+    /// Kubeconfig context to use. `None` uses the current context.
+    pub context: Option<String>,
+    /// Namespaces to follow. Empty follows the context's default namespace.
+    pub namespaces: Vec<String>,
+    /// Lines of existing output to backfill per container on attach. `None`
+    /// backfills the container's whole log.
+    pub tail_lines: Option<i64>,
+    /// Only follow pods matching this label selector (`app=api,tier!=cache`).
+    pub label_selector: Option<String>,
+    /// Only follow pods owned by one of these workloads. Empty follows every
+    /// pod.
+    pub workloads: Vec<Workload>,
+    /// Also stream each namespace's events as a `namespace/events` producer.
+    pub events: bool,
+}
+
+/// A connected Kubernetes feed, ready to [`run`](KubernetesFeed::run).
+pub struct KubernetesFeed {
+    client: Client,
+    namespaces: Vec<String>,
+    tail_lines: Option<i64>,
+    label_selector: Option<String>,
+    workloads: Vec<Workload>,
+    events: bool,
+    metadata: Option<mpsc::Sender<ProducerMeta>>,
+    signals: Signals,
+}
+
+impl KubernetesFeed {
+    /// Load credentials as described by `config` and build an API client.
+    ///
+    /// With neither a kubeconfig path nor a context, this falls back to the
+    /// in-cluster service account when no kubeconfig exists.
+    pub async fn connect(config: &KubernetesConfig) -> Result<Self, FeedError> {
+        let options = KubeConfigOptions {
+            context: config.context.clone(),
+            ..KubeConfigOptions::default()
+        };
+        let kube_config = match (&config.kubeconfig, &config.context) {
+            (Some(path), _) => {
+                let kubeconfig = Kubeconfig::read_from(path)
+                    .map_err(|e| FeedError::KubeConfig(format!("{}: {e}", path.display())))?;
+                kube::Config::from_custom_kubeconfig(kubeconfig, &options)
+                    .await
+                    .map_err(config_error)?
+            }
+            (None, Some(_)) => kube::Config::from_kubeconfig(&options)
+                .await
+                .map_err(config_error)?,
+            (None, None) => kube::Config::infer().await.map_err(config_error)?,
+        };
+
+        let namespaces = if config.namespaces.is_empty() {
+            vec![kube_config.default_namespace.clone()]
+        } else {
+            config.namespaces.clone()
+        };
+        let client = Client::try_from(kube_config)?;
+        Ok(Self::new(client, namespaces)
+            .with_tail_lines(config.tail_lines)
+            .with_selection(config.label_selector.clone(), config.workloads.clone())
+            .with_events(config.events))
+    }
+
+    /// Build a feed from an existing client.
+    pub fn new(client: Client, namespaces: Vec<String>) -> Self {
+        Self {
+            client,
+            namespaces,
+            tail_lines: None,
+            label_selector: None,
+            workloads: Vec::new(),
+            events: false,
+            metadata: None,
+            signals: Signals::default(),
+        }
+    }
+
+    pub fn with_tail_lines(mut self, tail_lines: Option<i64>) -> Self {
+        self.tail_lines = tail_lines;
+        self
+    }
+
+    /// Narrow the pods followed to a label selector and/or a set of owning
+    /// workloads.
+    pub fn with_selection(
+        mut self,
+        label_selector: Option<String>,
+        workloads: Vec<Workload>,
+    ) -> Self {
+        self.label_selector = label_selector;
+        self.workloads = workloads;
+        self
+    }
+
+    /// Also stream each namespace's events.
+    pub fn with_events(mut self, events: bool) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Report each container's state on `metadata`.
+    pub fn with_metadata(mut self, metadata: mpsc::Sender<ProducerMeta>) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Reopen or close a container's log stream when `signals` asks.
+    pub fn with_signals(mut self, signals: Signals) -> Self {
+        self.signals = signals;
+        self
+    }
+
+    /// The namespaces this feed follows.
+    pub fn namespaces(&self) -> &[String] {
+        &self.namespaces
+    }
+
+    /// Watch every namespace and stream its containers' logs until `tx` is
+    /// closed.
+    pub async fn run(
+        self,
+        tx: mpsc::Sender<LogEntry>,
+        updates: mpsc::Sender<ProducerUpdate>,
+    ) -> Result<(), FeedError> {
+        let mut watch_config = watcher::Config::default();
+        if let Some(selector) = &self.label_selector {
+            watch_config = watch_config.labels(selector);
+        }
+        let mut watches: Vec<BoxFuture<'_, Result<(), FeedError>>> = Vec::new();
+        for ns in &self.namespaces {
+            let api: Api<Pod> = Api::namespaced(self.client.clone(), ns);
+            let selection = Selection {
+                watch: watch_config.clone(),
+                workloads: &self.workloads,
+                tail_lines: self.tail_lines,
+                signals: self.signals.clone(),
+            };
+            let watch = watch_pods(
+                api,
+                selection,
+                tx.clone(),
+                updates.clone(),
+                self.metadata.clone(),
+            );
+            watches.push(watch.boxed());
+            if self.events {
+                let api: Api<Event> = Api::namespaced(self.client.clone(), ns);
+                let watch = events::watch_events(api, ns.clone(), tx.clone(), updates.clone());
+                watches.push(watch.boxed());
+            }
+        }
+        futures::future::try_join_all(watches).await?;
+        Ok(())
+    }
+}
+
+fn config_error(e: impl std::fmt::Display) -> FeedError {
+    FeedError::KubeConfig(e.to_string())
+}
+
+/// Which pods a namespace watch follows.
+struct Selection<'a> {
+    watch: watcher::Config,
+    workloads: &'a [Workload],
+    tail_lines: Option<i64>,
+    signals: Signals,
+}
+
+impl Selection<'_> {
+    fn matches(&self, pod: &Pod) -> bool {
+        self.workloads.is_empty()
+            || workload::owner(pod).is_some_and(|owner| self.workloads.contains(&owner))
+    }
+}
+
+/// Follow the pod list in one namespace, attaching a log stream to every
+/// running container of the selected pods and reporting deleted pods as
+/// ended. Container state changes go to `metadata`, if given.
+async fn watch_pods(
+    api: Api<Pod>,
+    selection: Selection<'_>,
+    tx: mpsc::Sender<LogEntry>,
+    updates: mpsc::Sender<ProducerUpdate>,
+    metadata: Option<mpsc::Sender<ProducerMeta>>,
+) -> Result<(), FeedError> {
+    let mut streams: HashMap<String, JoinHandle<()>> = HashMap::new();
+    // Newest line timestamp each producer's latest follower has forwarded.
+    let mut streamed: HashMap<String, watch::Receiver<Option<DateTime<Utc>>>> = HashMap::new();
+    // Restart count last seen per producer.
+    let mut restarts: HashMap<String, u32> = HashMap::new();
+    // Metadata last sent per producer.
+    let mut described: HashMap<String, ProducerMeta> = HashMap::new();
+    let mut events = watcher(api.clone(), selection.watch.clone())
+        .default_backoff()
+        .boxed();
+
+    loop {
+        let event = tokio::select! {
+            _ = tx.closed() => break,
+            event = events.try_next() => event,
+        };
+        match event {
+            Ok(Some(watcher::Event::Apply(pod) | watcher::Event::InitApply(pod))) => {
+                streams.retain(|_, task| !task.is_finished());
+                if !selection.matches(&pod) {
+                    continue;
+                }
+                if let Some(metadata) = &metadata {
+                    for target in logs::containers(&pod) {
+                        let Some((state, fields)) = logs::describe(&pod, &target.container) else {
+                            continue;
+                        };
+                        let meta = ProducerMeta {
+                            source: FeedKind::Kubernetes,
+                            instance: None,
+                            producer: target.producer,
+                            state,
+                            fields,
+                        };
+                        if described.get(&meta.producer) != Some(&meta) {
+                            described.insert(meta.producer.clone(), meta.clone());
+                            // Advisory, like status.
+                            let _ = metadata.send(meta).await;
+                        }
+                    }
+                }
+                for target in logs::containers(&pod) {
+                    let count = logs::restart_count(&pod, &target.container);
+                    let seen = restarts.insert(target.producer.clone(), count).unwrap_or(0);
+                    if count <= seen {
+                        continue;
+                    }
+                    tracing::debug!(producer = %target.producer, count, "kubernetes: container restarted");
+                    let producer = target.producer.clone();
+                    tokio::spawn(logs::fetch_previous(
+                        api.clone(),
+                        target,
+                        selection.tail_lines,
+                        streamed.get(&producer).cloned(),
+                        tx.clone(),
+                    ));
+                    report(
+                        &updates,
+                        FeedKind::Kubernetes,
+                        None,
+                        &producer,
+                        ProducerStatus::Restarted { count },
+                    )
+                    .await;
+                }
+                for target in logs::running_containers(&pod) {
+                    if streams.contains_key(&target.producer) {
+                        continue;
+                    }
+                    tracing::debug!(producer = %target.producer, "kubernetes: attaching");
+                    let producer = target.producer.clone();
+                    let (seen_tx, seen_rx) = watch::channel(None);
+                    streamed.insert(producer.clone(), seen_rx);
+                    let follower = logs::follow(
+                        api.clone(),
+                        target,
+                        selection.tail_lines,
+                        Backoff::new(Duration::from_secs(1), Duration::from_secs(30)),
+                        selection.signals.clone(),
+                        tx.clone(),
+                        updates.clone(),
+                        seen_tx,
+                    );
+                    streams.insert(producer, tokio::spawn(follower));
+                }
+            }
+            Ok(Some(watcher::Event::Delete(pod))) if selection.matches(&pod) => {
+                for target in logs::containers(&pod) {
+                    restarts.remove(&target.producer);
+                    described.remove(&target.producer);
+                    streamed.remove(&target.producer);
+                    if let Some(task) = streams.remove(&target.producer) {
+                        task.abort();
+                    }
+                    report(
+                        &updates,
+                        FeedKind::Kubernetes,
+                        None,
+                        &target.producer,
+                        ProducerStatus::Ended { code: None },
+                    )
+                    .await;
+                }
+            }
+            Ok(Some(_)) => {}
+            Ok(None) => break,
+            Err(e) => tracing::warn!(error = %e, "kubernetes: pod watch error"),
+        }
+    }
+
+    for (_, task) in streams {
+        task.abort();
+    }
+    Ok(())
+}
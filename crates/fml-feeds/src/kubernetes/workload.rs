@@ -0,0 +1,207 @@
+//! Workloads — the controllers that own pods.
+//!
+//! Pods come and go across rollouts, reschedules and job retries, but the
+//! workload that owns them stays put. The feed resolves each pod's owner from
+//! its `ownerReferences` (no extra API calls: a ReplicaSet created by a
+//! Deployment is recognised by its `pod-template-hash` suffix) so replicas can
+//! be grouped under one tree node and selected by workload instead of by pod
+//! name.
+
+use k8s_openapi::api::core::v1::Pod;
+use std::fmt;
+use std::str::FromStr;
+
+/// Controller kinds a pod can be owned by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WorkloadKind {
+    Deployment,
+    StatefulSet,
+    DaemonSet,
+    Job,
+    /// A ReplicaSet not managed by a Deployment.
+    ReplicaSet,
+}
+
+impl WorkloadKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            WorkloadKind::Deployment => "deployment",
+            WorkloadKind::StatefulSet => "statefulset",
+            WorkloadKind::DaemonSet => "daemonset",
+            WorkloadKind::Job => "job",
+            WorkloadKind::ReplicaSet => "replicaset",
+        }
+    }
+}
+
+impl fmt::Display for WorkloadKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WorkloadKind {
+    type Err = String;
+
+    /// Parse a kind the way `kubectl` does: case-insensitive, singular or
+    /// plural, with the usual short names (`deploy`, `sts`, `ds`, `rs`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "deployment" | "deployments" | "deploy" => Ok(WorkloadKind::Deployment),
+            "statefulset" | "statefulsets" | "sts" => Ok(WorkloadKind::StatefulSet),
+            "daemonset" | "daemonsets" | "ds" => Ok(WorkloadKind::DaemonSet),
+            "job" | "jobs" => Ok(WorkloadKind::Job),
+            "replicaset" | "replicasets" | "rs" => Ok(WorkloadKind::ReplicaSet),
+            other => Err(format!(
+                "unknown workload kind `{other}` (expected deployment, statefulset, daemonset, job or replicaset)"
+            )),
+        }
+    }
+}
+
+/// A named workload, written `kind/name` (`deployment/api`, `sts/db`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Workload {
+    pub kind: WorkloadKind,
+    pub name: String,
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.kind, self.name)
+    }
+}
+
+impl FromStr for Workload {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, name) = s
+            .split_once('/')
+            .ok_or_else(|| format!("workload `{s}` must be written kind/name"))?;
+        if name.is_empty() || name.contains('/') {
+            return Err(format!("workload `{s}` must be written kind/name"));
+        }
+        Ok(Workload {
+            kind: kind.parse()?,
+            name: name.to_string(),
+        })
+    }
+}
+
+/// The workload that owns `pod`, following a ReplicaSet up to its Deployment.
+/// Bare pods and pods owned by anything else (static pods, operators'
+/// custom resources) have none.
+pub(super) fn owner(pod: &Pod) -> Option<Workload> {
+    let reference = pod
+        .metadata
+        .owner_references
+        .as_ref()?
+        .iter()
+        .find(|r| r.controller == Some(true))?;
+    let kind = match reference.kind.as_str() {
+        "ReplicaSet" => {
+            return Some(replica_set_owner(pod, &reference.name));
+        }
+        "StatefulSet" => WorkloadKind::StatefulSet,
+        "DaemonSet" => WorkloadKind::DaemonSet,
+        "Job" => WorkloadKind::Job,
+        _ => return None,
+    };
+    Some(Workload {
+        kind,
+        name: reference.name.clone(),
+    })
+}
+
+/// Deployments name their ReplicaSets `<deployment>-<pod-template-hash>` and
+/// stamp the hash on every pod, so the Deployment name is recoverable without
+/// fetching the ReplicaSet.
+fn replica_set_owner(pod: &Pod, replica_set: &str) -> Workload {
+    let hash = pod
+        .metadata
+        .labels
+        .as_ref()
+        .and_then(|l| l.get("pod-template-hash"));
+    match hash.and_then(|h| replica_set.strip_suffix(h.as_str())?.strip_suffix('-')) {
+        Some(deployment) if !deployment.is_empty() => Workload {
+            kind: WorkloadKind::Deployment,
+            name: deployment.to_string(),
+        },
+        _ => Workload {
+            kind: WorkloadKind::ReplicaSet,
+            name: replica_set.to_string(),
+        },
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pod(owner_kind: &str, owner_name: &str, hash: Option<&str>) -> Pod {
+        let mut labels = serde_json::Map::new();
+        if let Some(hash) = hash {
+            labels.insert("pod-template-hash".into(), hash.into());
+        }
+        serde_json::from_value(serde_json::json!({
+            "metadata": {
+                "name": "p",
+                "labels": labels,
+                "ownerReferences": [{
+                    "apiVersion": "apps/v1", "kind": owner_kind, "name": owner_name,
+                    "uid": "u", "controller": true,
+                }],
+            },
+        }))
+        .unwrap()
+    }
+
+    fn owner_of(kind: &str, name: &str, hash: Option<&str>) -> Workload {
+        owner(&pod(kind, name, hash)).unwrap()
+    }
+
+    #[test]
+    fn replica_set_resolves_to_its_deployment() {
+        let deployment = owner_of("ReplicaSet", "api-7d9f8b6c5d", Some("7d9f8b6c5d"));
+        assert_eq!(deployment.to_string(), "deployment/api");
+
+        let bare = owner_of("ReplicaSet", "legacy-rs", None);
+        assert_eq!(bare.to_string(), "replicaset/legacy-rs");
+    }
+
+    #[test]
+    fn direct_owners_are_kept() {
+        assert_eq!(
+            owner_of("StatefulSet", "db", None).to_string(),
+            "statefulset/db"
+        );
+        assert_eq!(
+            owner_of("DaemonSet", "fluent", None).to_string(),
+            "daemonset/fluent"
+        );
+        assert_eq!(
+            owner_of("Job", "migrate-42", None).to_string(),
+            "job/migrate-42"
+        );
+        assert!(owner(&pod("Node", "node-1", None)).is_none());
+    }
+
+    #[test]
+    fn workloads_parse_kubectl_style() {
+        let w: Workload = "deploy/api".parse().unwrap();
+        assert_eq!(w.kind, WorkloadKind::Deployment);
+        assert_eq!(w.name, "api");
+        assert_eq!(
+            "STS/db".parse::<Workload>().unwrap().to_string(),
+            "statefulset/db"
+        );
+        assert!("api".parse::<Workload>().is_err());
+        assert!("pod/api".parse::<Workload>().is_err());
+        assert!("deploy/".parse::<Workload>().is_err());
+    }
+}
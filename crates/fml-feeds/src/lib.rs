@@ -2,12 +2,118 @@
 //!
 //! Each feed adapter connects to a log source, reads raw bytes, and pushes
 //! normalised [`fml_core::LogEntry`] structs onto an async channel for the store.
+//!
+//! Producer names may contain `/` separators (e.g. `project/service`,
+//! `host/unit`); the TUI splits on them to build the producer tree hierarchy.
 
+pub mod backoff;
+pub mod control;
+pub mod demo;
 pub mod docker;
+pub mod exec;
 pub mod file;
+pub mod forward;
+pub mod http;
+pub mod journald;
 pub mod kubernetes;
+pub mod otlp;
+pub mod pipe;
+pub mod replay;
 pub mod stdin;
+pub mod syslog;
+
+mod lines;
+mod record;
+mod resume;
+
+pub use backoff::Backoff;
+pub use control::{Deliveries, Delivery, FeedControl, Receipt, Signal, Signals};
+pub use fml_core::handle::{FeedHandle, ProducerCounters, ProducerHealth};
+
+/// Errors surfaced by feed adapters.
+#[derive(Debug, thiserror::Error)]
+pub enum FeedError {
+    /// The feed's backing process could not be started.
+    #[error("failed to spawn `{program}`: {source}")]
+    Spawn {
+        program: String,
+        #[source]
+        source: std::io::Error,
+    },
+    /// The feed's backing process exited unsuccessfully; `stderr` holds the
+    /// last of what it printed there.
+    #[error("`{program}` failed ({status}): {stderr}")]
+    Exited {
+        program: String,
+        status: std::process::ExitStatus,
+        stderr: String,
+    },
+    /// Reading from the underlying source failed.
+    #[error("feed I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// No usable kubeconfig or in-cluster configuration was found.
+    #[error("kubernetes config: {0}")]
+    KubeConfig(String),
+    /// A Kubernetes API request failed.
+    #[error("kubernetes API error: {0}")]
+    Kube(#[from] kube::Error),
+    /// A Docker host, context or TLS setting could not be resolved.
+    #[error("docker config: {0}")]
+    DockerConfig(String),
+    /// A Docker Engine API request failed.
+    #[error("docker API error: {0}")]
+    Docker(String),
+    /// A file feed path or exclude pattern is invalid.
+    #[error("file feed: {0}")]
+    FileConfig(String),
+    /// A replayed file cannot be paced.
+    #[error("replay feed: {0}")]
+    ReplayConfig(String),
+    /// A demo scenario is missing or invalid.
+    #[error("demo scenario: {0}")]
+    DemoScenario(String),
+    /// A named pipe or Unix socket path cannot be used.
+    #[error("pipe feed: {0}")]
+    PipeConfig(String),
+    /// The receiving end of the entry channel was dropped.
+    #[error("entry channel closed")]
+    ChannelClosed,
+}
+
+/// Send a producer status update. Status is advisory, so a closed
+/// receiver must not stop the feed.
+pub(crate) async fn report(
+    updates: &tokio::sync::mpsc::Sender<fml_core::ProducerUpdate>,
+    source: fml_core::FeedKind,
+    instance: Option<&str>,
+    producer: &str,
+    status: fml_core::ProducerStatus,
+) {
+    let update = fml_core::ProducerUpdate {
+        source,
+        instance: instance.map(str::to_string),
+        producer: producer.to_string(),
+        status,
+    };
+    let _ = updates.send(update).await;
+}
 
-/// Trait implemented by each log feed source.
-/// Full definition comes in Phase 4.
-pub trait FeedHandle: Send + Sync {}
+/// Build an entry for an unstructured line: the line is both `raw` and
+/// `message`, stamped with the ingest time.
+pub(crate) fn line_entry(
+    source: fml_core::FeedKind,
+    producer: &str,
+    line: String,
+) -> fml_core::LogEntry {
+    fml_core::LogEntry {
+        seq: 0,
+        raw: line.clone(),
+        ts: chrono::Utc::now(),
+        level: None,
+        source,
+        instance: None,
+        producer: producer.to_string(),
+        fields: Default::default(),
+        message: Some(line),
+    }
+}
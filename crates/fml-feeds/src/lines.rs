@@ -0,0 +1,80 @@
+//! Newline framing shared by the line-oriented feeds.
+//!
+//! Feeds read either from a child process pipe or from a byte [`Stream`]
+//! (which is how the test harnesses fake process output). Both are funnelled
+//! through [`LineReader`], which strips line terminators and converts invalid
+//! UTF-8 lossily so a single bad byte never stalls a feed.
+
+use bytes::Bytes;
+use futures::Stream;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use tokio_util::io::StreamReader;
+
+/// Reads `\n`-terminated lines from an async buffered reader.
+pub(crate) struct LineReader<R> {
+    inner: R,
+    buf: Vec<u8>,
+}
+
+impl<R: AsyncBufRead + Unpin> LineReader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Return the next line without its trailing `\n` / `\r\n`, or `None` at
+    /// EOF. A final unterminated line is still returned.
+    pub(crate) async fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        self.buf.clear();
+        let n = self.inner.read_until(b'\n', &mut self.buf).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        if self.buf.last() == Some(&b'\n') {
+            self.buf.pop();
+            if self.buf.last() == Some(&b'\r') {
+                self.buf.pop();
+            }
+        }
+        Ok(Some(String::from_utf8_lossy(&self.buf).into_owned()))
+    }
+}
+
+/// Wrap a byte stream (process output, fake process, HTTP body) in a
+/// [`LineReader`].
+pub(crate) fn from_stream<S>(stream: S) -> LineReader<StreamReader<S, Bytes>>
+where
+    S: Stream<Item = std::io::Result<Bytes>> + Unpin,
+{
+    LineReader::new(StreamReader::new(stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn splits_and_strips_terminators() {
+        let chunks = vec![
+            Ok(Bytes::from_static(b"one\r\ntw")),
+            Ok(Bytes::from_static(b"o\nthree")),
+        ];
+        let mut lines = from_stream(futures::stream::iter(chunks));
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("one"));
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("two"));
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("three"));
+        assert_eq!(lines.next_line().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn invalid_utf8_is_lossy() {
+        let chunks = vec![Ok(Bytes::from_static(b"bad \xff byte\n"))];
+        let mut lines = from_stream(futures::stream::iter(chunks));
+        assert_eq!(
+            lines.next_line().await.unwrap().as_deref(),
+            Some("bad \u{fffd} byte")
+        );
+    }
+}
@@ -0,0 +1,702 @@
+//! OTLP/HTTP logs receiver — fml as a local debug collector.
+//!
+//! Accepts `POST /v1/logs` in both OTLP/HTTP encodings:
+//!
+//! | `Content-Type`            | Body                                         |
+//! |---------------------------|----------------------------------------------|
+//! | `application/x-protobuf`  | binary `ExportLogsServiceRequest`            |
+//! | `application/json`        | OTLP JSON (lowerCamelCase, hex trace ids)    |
+//!
+//! `Content-Encoding: gzip` is honoured for either. Both encodings decode into
+//! the same [`proto`] messages, so the mapping onto [`LogEntry`] is shared:
+//!
+//! | OTLP                                   | `LogEntry`                       |
+//! |----------------------------------------|----------------------------------|
+//! | resource `service.name` / instance     | `producer` as `service/instance` |
+//! | `timeUnixNano` (else observed time)    | `ts`                             |
+//! | `severityNumber` (else `severityText`) | `level`                          |
+//! | `body`                                 | `message` and `raw`              |
+//! | `traceId` / `spanId`                   | `trace_id` / `span_id` (hex)     |
+//! | resource + record attributes           | `fields`, keys lowercased        |
+//!
+//! The instance is `service.instance.id`, falling back to `host.name` and then
+//! `k8s.pod.name`; a resource with none of them is just `service`. As with the
+//! [`http`](crate::http) feed, a request is answered once all of its records
+//! are in the store, or with `429` when fml can't keep up. A
+//! gzip body that inflates past [`MAX_INFLATED`] bytes is rejected with `413`
+//! before any of it is decoded.
+
+use crate::control::Deliveries;
+use crate::http::Queue;
+use crate::FeedError;
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use fml_core::{FeedKind, LogEntry, LogLevel};
+use prost::Message;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+/// Largest request body accepted once gzip is inflated.
+pub const MAX_INFLATED: u64 = 32 * 1024 * 1024;
+
+/// Settings for the OTLP receiver.
+#[derive(Debug, Clone)]
+pub struct OtlpConfig {
+    pub addr: SocketAddr,
+}
+
+impl Default for OtlpConfig {
+    fn default() -> Self {
+        Self {
+            // The standard OTLP/HTTP port, loopback only.
+            addr: SocketAddr::from(([127, 0, 0, 1], 4318)),
+        }
+    }
+}
+
+/// A bound OTLP receiver, ready to [`run`](OtlpReceiver::run).
+pub struct OtlpReceiver {
+    listener: TcpListener,
+    deliveries: Deliveries,
+}
+
+impl OtlpReceiver {
+    pub async fn bind(config: &OtlpConfig) -> Result<Self, FeedError> {
+        Ok(Self {
+            listener: TcpListener::bind(config.addr).await?,
+            deliveries: Deliveries::default(),
+        })
+    }
+
+    /// Answer exports once `deliveries` reports their records stored. The
+    /// default answers as soon as they are queued.
+    pub fn with_deliveries(mut self, deliveries: Deliveries) -> Self {
+        self.deliveries = deliveries;
+        self
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.listener.local_addr().ok()
+    }
+
+    /// Serve export requests, forwarding accepted records to `tx`.
+    pub async fn run(self, tx: mpsc::Sender<LogEntry>) -> Result<(), FeedError> {
+        let app = Router::new()
+            .route("/v1/logs", post(export_logs))
+            .with_state(Arc::new(Queue::new(tx, self.deliveries)));
+        axum::serve(self.listener, app).await?;
+        Ok(())
+    }
+}
+
+/// Request body encoding, which also selects the response encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Protobuf,
+    Json,
+}
+
+async fn export_logs(State(queue): State<Arc<Queue>>, headers: HeaderMap, body: Bytes) -> Response {
+    let header_str = |name| headers.get(name).and_then(|v| v.to_str().ok());
+    let encoding = match header_str(header::CONTENT_TYPE) {
+        Some(ct) if ct.starts_with("application/x-protobuf") => Encoding::Protobuf,
+        Some(ct) if ct.starts_with("application/json") => Encoding::Json,
+        _ => {
+            return (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "expected application/x-protobuf or application/json",
+            )
+                .into_response()
+        }
+    };
+
+    let body = match header_str(header::CONTENT_ENCODING) {
+        Some("gzip") => match gunzip(&body, MAX_INFLATED) {
+            Ok(Some(body)) => body,
+            Ok(None) => {
+                return (
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    format!("gzip body inflates past {MAX_INFLATED} bytes"),
+                )
+                    .into_response()
+            }
+            Err(e) => {
+                return (StatusCode::BAD_REQUEST, format!("bad gzip body: {e}")).into_response()
+            }
+        },
+        _ => body.to_vec(),
+    };
+
+    let request = match encoding {
+        Encoding::Protobuf => proto::ExportLogsServiceRequest::decode(body.as_slice())
+            .map_err(|e| format!("invalid protobuf: {e}")),
+        Encoding::Json => serde_json::from_slice::<Value>(&body)
+            .map_err(|e| format!("invalid JSON: {e}"))
+            .and_then(|v| json::export_request(&v)),
+    };
+    let request = match request {
+        Ok(request) => request,
+        Err(msg) => return (StatusCode::BAD_REQUEST, msg).into_response(),
+    };
+
+    match queue.commit(request_entries(&request)).await {
+        // An empty ExportLogsServiceResponse means full success.
+        (StatusCode::NO_CONTENT, _) => match encoding {
+            Encoding::Protobuf => (
+                [(header::CONTENT_TYPE, "application/x-protobuf")],
+                Vec::<u8>::new(),
+            )
+                .into_response(),
+            Encoding::Json => ([(header::CONTENT_TYPE, "application/json")], "{}").into_response(),
+        },
+        rejected => rejected.into_response(),
+    }
+}
+
+/// Inflate a gzip body (one or more members), or `None` if it inflates past
+/// `limit` bytes. Reading stops at the limit, so a small gzip bomb cannot
+/// exhaust memory.
+pub(crate) fn gunzip(body: &[u8], limit: u64) -> std::io::Result<Option<Vec<u8>>> {
+    let mut decoder = flate2::read::MultiGzDecoder::new(body);
+    let mut out = Vec::new();
+    (&mut decoder).take(limit).read_to_end(&mut out)?;
+    if decoder.read(&mut [0u8])? > 0 {
+        return Ok(None);
+    }
+    Ok(Some(out))
+}
+
+// ---------------------------------------------------------------------------
+// Mapping
+// ---------------------------------------------------------------------------
+
+/// Flatten an export request into entries, one per log record.
+pub fn request_entries(request: &proto::ExportLogsServiceRequest) -> Vec<LogEntry> {
+    let mut entries = Vec::new();
+    for resource_logs in &request.resource_logs {
+        let resource_attrs = resource_logs
+            .resource
+            .as_ref()
+            .map(|r| r.attributes.as_slice())
+            .unwrap_or_default();
+        let producer = resource_producer(resource_attrs);
+
+        for scope_logs in &resource_logs.scope_logs {
+            let scope = scope_logs.scope.as_ref().map(|s| s.name.as_str());
+            for record in &scope_logs.log_records {
+                let mut entry = record_entry(record, &producer, resource_attrs);
+                if let Some(scope) = scope.filter(|s| !s.is_empty()) {
+                    entry.fields.insert("scope".to_string(), scope.into());
+                }
+                entries.push(entry);
+            }
+        }
+    }
+    entries
+}
+
+fn record_entry(
+    record: &proto::LogRecord,
+    producer: &str,
+    resource_attrs: &[proto::KeyValue],
+) -> LogEntry {
+    let mut fields: HashMap<String, Value> = resource_attrs
+        .iter()
+        .chain(&record.attributes)
+        .map(|kv| (kv.key.to_ascii_lowercase(), any_to_json(kv.value.as_ref())))
+        .collect();
+    if !record.trace_id.is_empty() {
+        fields.insert("trace_id".to_string(), hex(&record.trace_id).into());
+    }
+    if !record.span_id.is_empty() {
+        fields.insert("span_id".to_string(), hex(&record.span_id).into());
+    }
+
+    let body = match record.body.as_ref().and_then(|b| b.value.as_ref()) {
+        Some(proto::any_value::Value::StringValue(s)) => s.clone(),
+        None => String::new(),
+        Some(_) => any_to_json(record.body.as_ref()).to_string(),
+    };
+    let nanos = match record.time_unix_nano {
+        0 => record.observed_time_unix_nano,
+        t => t,
+    };
+    let ts = match i64::try_from(nanos) {
+        Ok(n) if n > 0 => DateTime::<Utc>::from_timestamp_nanos(n),
+        _ => Utc::now(),
+    };
+    let level =
+        severity_to_level(record.severity_number).or_else(|| record.severity_text.parse().ok());
+
+    LogEntry {
+        seq: 0,
+        raw: body.clone(),
+        ts,
+        level,
+        source: FeedKind::Otlp,
+        instance: None,
+        producer: producer.to_string(),
+        fields,
+        message: Some(body),
+    }
+}
+
+/// `service.name/instance` for a resource.
+fn resource_producer(attrs: &[proto::KeyValue]) -> String {
+    let attr = |key: &str| {
+        attrs.iter().find(|kv| kv.key == key).and_then(|kv| {
+            match kv.value.as_ref()?.value.as_ref()? {
+                proto::any_value::Value::StringValue(s) if !s.is_empty() => Some(s.as_str()),
+                _ => None,
+            }
+        })
+    };
+    let service = attr("service.name").unwrap_or("unknown_service");
+    match ["service.instance.id", "host.name", "k8s.pod.name"]
+        .iter()
+        .find_map(|k| attr(k))
+    {
+        Some(instance) => format!("{service}/{instance}"),
+        None => service.to_string(),
+    }
+}
+
+/// Map an OTLP `SeverityNumber` (1–24, four steps per level) onto
+/// [`LogLevel`]. `0` (unspecified) yields `None`.
+pub fn severity_to_level(number: i32) -> Option<LogLevel> {
+    match number {
+        1..=4 => Some(LogLevel::Trace),
+        5..=8 => Some(LogLevel::Debug),
+        9..=12 => Some(LogLevel::Info),
+        13..=16 => Some(LogLevel::Warn),
+        17..=20 => Some(LogLevel::Error),
+        21..=24 => Some(LogLevel::Fatal),
+        _ => None,
+    }
+}
+
+fn any_to_json(value: Option<&proto::AnyValue>) -> Value {
+    use proto::any_value::Value as V;
+    match value.and_then(|v| v.value.as_ref()) {
+        None => Value::Null,
+        Some(V::StringValue(s)) => s.clone().into(),
+        Some(V::BoolValue(b)) => (*b).into(),
+        Some(V::IntValue(i)) => (*i).into(),
+        Some(V::DoubleValue(d)) => (*d).into(),
+        Some(V::BytesValue(b)) => hex(b).into(),
+        Some(V::ArrayValue(a)) => a.values.iter().map(|v| any_to_json(Some(v))).collect(),
+        Some(V::KvlistValue(kv)) => kv
+            .values
+            .iter()
+            .map(|kv| (kv.key.clone(), any_to_json(kv.value.as_ref())))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+// ---------------------------------------------------------------------------
+// Protobuf messages
+// ---------------------------------------------------------------------------
+
+/// The subset of `opentelemetry.proto` needed to decode a logs export.
+///
+/// Field numbers follow `opentelemetry/proto/logs/v1/logs.proto` and
+/// `common/v1/common.proto`; fields fml does not use are omitted, which prost
+/// skips on decode.
+pub mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ExportLogsServiceRequest {
+        #[prost(message, repeated, tag = "1")]
+        pub resource_logs: Vec<ResourceLogs>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ResourceLogs {
+        #[prost(message, optional, tag = "1")]
+        pub resource: Option<Resource>,
+        #[prost(message, repeated, tag = "2")]
+        pub scope_logs: Vec<ScopeLogs>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Resource {
+        #[prost(message, repeated, tag = "1")]
+        pub attributes: Vec<KeyValue>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ScopeLogs {
+        #[prost(message, optional, tag = "1")]
+        pub scope: Option<InstrumentationScope>,
+        #[prost(message, repeated, tag = "2")]
+        pub log_records: Vec<LogRecord>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct InstrumentationScope {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(string, tag = "2")]
+        pub version: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct LogRecord {
+        #[prost(fixed64, tag = "1")]
+        pub time_unix_nano: u64,
+        #[prost(fixed64, tag = "11")]
+        pub observed_time_unix_nano: u64,
+        #[prost(int32, tag = "2")]
+        pub severity_number: i32,
+        #[prost(string, tag = "3")]
+        pub severity_text: String,
+        #[prost(message, optional, tag = "5")]
+        pub body: Option<AnyValue>,
+        #[prost(message, repeated, tag = "6")]
+        pub attributes: Vec<KeyValue>,
+        #[prost(bytes = "vec", tag = "9")]
+        pub trace_id: Vec<u8>,
+        #[prost(bytes = "vec", tag = "10")]
+        pub span_id: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct KeyValue {
+        #[prost(string, tag = "1")]
+        pub key: String,
+        #[prost(message, optional, tag = "2")]
+        pub value: Option<AnyValue>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AnyValue {
+        #[prost(oneof = "any_value::Value", tags = "1, 2, 3, 4, 5, 6, 7")]
+        pub value: Option<any_value::Value>,
+    }
+
+    pub mod any_value {
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Value {
+            #[prost(string, tag = "1")]
+            StringValue(String),
+            #[prost(bool, tag = "2")]
+            BoolValue(bool),
+            #[prost(int64, tag = "3")]
+            IntValue(i64),
+            #[prost(double, tag = "4")]
+            DoubleValue(f64),
+            #[prost(message, tag = "5")]
+            ArrayValue(super::ArrayValue),
+            #[prost(message, tag = "6")]
+            KvlistValue(super::KeyValueList),
+            #[prost(bytes = "vec", tag = "7")]
+            BytesValue(Vec<u8>),
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ArrayValue {
+        #[prost(message, repeated, tag = "1")]
+        pub values: Vec<AnyValue>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct KeyValueList {
+        #[prost(message, repeated, tag = "1")]
+        pub values: Vec<KeyValue>,
+    }
+
+    /// Convenience constructor for a string attribute.
+    pub fn string_attr(key: &str, value: &str) -> KeyValue {
+        KeyValue {
+            key: key.to_string(),
+            value: Some(AnyValue {
+                value: Some(any_value::Value::StringValue(value.to_string())),
+            }),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// JSON encoding
+// ---------------------------------------------------------------------------
+
+/// OTLP JSON → [`proto`] messages.
+///
+/// Per the OTLP/HTTP spec, field names are lowerCamelCase (the original
+/// snake_case names are accepted too), 64-bit integers may be strings, and
+/// trace / span ids are hex strings rather than base64.
+mod json {
+    use super::proto;
+    use serde_json::Value;
+
+    fn get<'a>(obj: &'a Value, camel: &str, snake: &str) -> Option<&'a Value> {
+        obj.get(camel).or_else(|| obj.get(snake))
+    }
+
+    fn array<'a>(obj: &'a Value, camel: &str, snake: &str) -> &'a [Value] {
+        get(obj, camel, snake)
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    fn string(obj: &Value, camel: &str, snake: &str) -> String {
+        get(obj, camel, snake)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    /// A uint64 / int64 given as a JSON number or decimal string.
+    fn int(obj: &Value, camel: &str, snake: &str) -> Option<i128> {
+        match get(obj, camel, snake)? {
+            Value::Number(n) => n.as_i64().map(i128::from).or(n.as_u64().map(i128::from)),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn hex_bytes(obj: &Value, camel: &str, snake: &str) -> Result<Vec<u8>, String> {
+        let s = string(obj, camel, snake);
+        if !s.len().is_multiple_of(2) {
+            return Err(format!("`{camel}` is not a hex string"));
+        }
+        (0..s.len())
+            .step_by(2)
+            .map(|i| {
+                u8::from_str_radix(&s[i..i + 2], 16)
+                    .map_err(|_| format!("`{camel}` is not a hex string"))
+            })
+            .collect()
+    }
+
+    pub(super) fn export_request(v: &Value) -> Result<proto::ExportLogsServiceRequest, String> {
+        if !v.is_object() {
+            return Err("export request must be a JSON object".to_string());
+        }
+        Ok(proto::ExportLogsServiceRequest {
+            resource_logs: array(v, "resourceLogs", "resource_logs")
+                .iter()
+                .map(resource_logs)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn resource_logs(v: &Value) -> Result<proto::ResourceLogs, String> {
+        Ok(proto::ResourceLogs {
+            resource: v.get("resource").map(|r| proto::Resource {
+                attributes: key_values(array(r, "attributes", "attributes")),
+            }),
+            scope_logs: array(v, "scopeLogs", "scope_logs")
+                .iter()
+                .map(scope_logs)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn scope_logs(v: &Value) -> Result<proto::ScopeLogs, String> {
+        Ok(proto::ScopeLogs {
+            scope: v.get("scope").map(|s| proto::InstrumentationScope {
+                name: string(s, "name", "name"),
+                version: string(s, "version", "version"),
+            }),
+            log_records: array(v, "logRecords", "log_records")
+                .iter()
+                .map(log_record)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn log_record(v: &Value) -> Result<proto::LogRecord, String> {
+        let nanos = |camel, snake| {
+            int(v, camel, snake)
+                .and_then(|n| u64::try_from(n).ok())
+                .unwrap_or(0)
+        };
+        Ok(proto::LogRecord {
+            time_unix_nano: nanos("timeUnixNano", "time_unix_nano"),
+            observed_time_unix_nano: nanos("observedTimeUnixNano", "observed_time_unix_nano"),
+            severity_number: int(v, "severityNumber", "severity_number")
+                .and_then(|n| i32::try_from(n).ok())
+                .unwrap_or(0),
+            severity_text: string(v, "severityText", "severity_text"),
+            body: v.get("body").map(any_value),
+            attributes: key_values(array(v, "attributes", "attributes")),
+            trace_id: hex_bytes(v, "traceId", "trace_id")?,
+            span_id: hex_bytes(v, "spanId", "span_id")?,
+        })
+    }
+
+    fn key_values(values: &[Value]) -> Vec<proto::KeyValue> {
+        values
+            .iter()
+            .map(|kv| proto::KeyValue {
+                key: string(kv, "key", "key"),
+                value: kv.get("value").map(any_value),
+            })
+            .collect()
+    }
+
+    fn any_value(v: &Value) -> proto::AnyValue {
+        use proto::any_value::Value as V;
+        let value = if let Some(s) = get(v, "stringValue", "string_value").and_then(Value::as_str) {
+            Some(V::StringValue(s.to_string()))
+        } else if let Some(b) = get(v, "boolValue", "bool_value").and_then(Value::as_bool) {
+            Some(V::BoolValue(b))
+        } else if let Some(i) = int(v, "intValue", "int_value") {
+            i64::try_from(i).ok().map(V::IntValue)
+        } else if let Some(d) = get(v, "doubleValue", "double_value").and_then(Value::as_f64) {
+            Some(V::DoubleValue(d))
+        } else if let Some(a) = get(v, "arrayValue", "array_value") {
+            Some(V::ArrayValue(proto::ArrayValue {
+                values: array(a, "values", "values").iter().map(any_value).collect(),
+            }))
+        } else if let Some(kv) = get(v, "kvlistValue", "kvlist_value") {
+            Some(V::KvlistValue(proto::KeyValueList {
+                values: key_values(array(kv, "values", "values")),
+            }))
+        } else {
+            // bytesValue is base64 in OTLP JSON; keep it as text.
+            get(v, "bytesValue", "bytes_value")
+                .and_then(Value::as_str)
+                .map(|s| V::StringValue(s.to_string()))
+        };
+        proto::AnyValue { value }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON_EXPORT: &str = r#"{"resourceLogs":[{
+        "resource":{"attributes":[
+            {"key":"service.name","value":{"stringValue":"checkout"}},
+            {"key":"service.instance.id","value":{"stringValue":"checkout-7d9f"}}]},
+        "scopeLogs":[{"scope":{"name":"io.opentelemetry.jdbc"},"logRecords":[{
+            "timeUnixNano":"1705312800000000000",
+            "severityNumber":17,"severityText":"ERROR",
+            "body":{"stringValue":"connection refused"},
+            "attributes":[{"key":"db.system","value":{"stringValue":"postgresql"}},
+                          {"key":"retry","value":{"intValue":"3"}}],
+            "traceId":"5b8efff798038103d269b633813fc60c","spanId":"eee19b7ec3c1b174"}]}]}]}"#;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn gunzip_stops_at_the_limit() {
+        let body = gzip(&[0u8; 4096]);
+        assert_eq!(gunzip(&body, 4096).unwrap().map(|b| b.len()), Some(4096));
+        assert_eq!(gunzip(&body, 4095).unwrap(), None);
+    }
+
+    fn json_entries(text: &str) -> Vec<LogEntry> {
+        let request = json::export_request(&serde_json::from_str(text).unwrap()).unwrap();
+        request_entries(&request)
+    }
+
+    #[test]
+    fn json_export_maps_resource_and_record() {
+        let entries = json_entries(JSON_EXPORT);
+        assert_eq!(entries.len(), 1);
+        let e = &entries[0];
+        assert_eq!(e.source, FeedKind::Otlp);
+        assert_eq!(e.producer, "checkout/checkout-7d9f");
+        assert_eq!(e.level, Some(LogLevel::Error));
+        assert_eq!(e.message.as_deref(), Some("connection refused"));
+        assert_eq!(e.ts.timestamp(), 1_705_312_800);
+        assert_eq!(e.fields["trace_id"], "5b8efff798038103d269b633813fc60c");
+        assert_eq!(e.fields["span_id"], "eee19b7ec3c1b174");
+        assert_eq!(e.fields["db.system"], "postgresql");
+        assert_eq!(e.fields["retry"], 3);
+        assert_eq!(e.fields["scope"], "io.opentelemetry.jdbc");
+    }
+
+    #[test]
+    fn protobuf_round_trip_matches_json() {
+        let request = json::export_request(&serde_json::from_str(JSON_EXPORT).unwrap()).unwrap();
+        let decoded =
+            proto::ExportLogsServiceRequest::decode(request.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(decoded, request);
+    }
+
+    #[test]
+    fn attribute_keys_are_lowercased() {
+        let entries = json_entries(
+            r#"{"resourceLogs":[{"resource":{"attributes":[
+                    {"key":"service.name","value":{"stringValue":"cart"}},
+                    {"key":"Deployment.Environment","value":{"stringValue":"prod"}}]},
+                "scopeLogs":[{"logRecords":[{"body":{"stringValue":"added"},
+                    "attributes":[{"key":"HTTP.Method","value":{"stringValue":"POST"}},
+                                  {"key":"User","value":{"kvlistValue":{"values":[
+                                      {"key":"ID","value":{"intValue":"7"}}]}}}]}]}]}]}"#,
+        );
+        let fields = &entries[0].fields;
+        assert_eq!(fields["deployment.environment"], "prod");
+        assert_eq!(fields["http.method"], "POST");
+        // Only field names are keys; a map inside a value keeps its own.
+        assert_eq!(fields["user"]["ID"], 7);
+        assert!(!fields.contains_key("HTTP.Method"));
+    }
+
+    #[test]
+    fn service_without_instance_is_single_node() {
+        let entries = json_entries(
+            r#"{"resourceLogs":[{"resource":{"attributes":[{"key":"service.name","value":{"stringValue":"cron"}}]},
+                "scopeLogs":[{"logRecords":[{"body":{"stringValue":"tick"}}]}]}]}"#,
+        );
+        assert_eq!(entries[0].producer, "cron");
+    }
+
+    #[test]
+    fn severity_text_is_used_when_number_is_unset() {
+        let entries = json_entries(
+            r#"{"resourceLogs":[{"scopeLogs":[{"logRecords":[{"severityText":"warning","body":{"stringValue":"x"}}]}]}]}"#,
+        );
+        assert_eq!(entries[0].producer, "unknown_service");
+        assert_eq!(entries[0].level, Some(LogLevel::Warn));
+    }
+
+    #[test]
+    fn structured_body_is_rendered_as_json() {
+        let entries = json_entries(
+            r#"{"resourceLogs":[{"scopeLogs":[{"logRecords":[{"body":{"kvlistValue":{"values":[{"key":"event","value":{"stringValue":"login"}}]}}}]}]}]}"#,
+        );
+        assert_eq!(entries[0].message.as_deref(), Some(r#"{"event":"login"}"#));
+    }
+
+    #[test]
+    fn severity_numbers_map_to_levels() {
+        assert_eq!(severity_to_level(0), None);
+        assert_eq!(severity_to_level(1), Some(LogLevel::Trace));
+        assert_eq!(severity_to_level(9), Some(LogLevel::Info));
+        assert_eq!(severity_to_level(16), Some(LogLevel::Warn));
+        assert_eq!(severity_to_level(24), Some(LogLevel::Fatal));
+    }
+}
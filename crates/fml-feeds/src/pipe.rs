@@ -0,0 +1,441 @@
+//! Pipe feed — named pipes and Unix sockets local tools write lines to.
+//!
+//! Three kinds of endpoint, any number of each:
+//!
+//! | Endpoint           | Producer                              | Framing                 |
+//! |--------------------|---------------------------------------|-------------------------|
+//! | named pipe (FIFO)  | pipe file name (`app.fifo`)           | newline-delimited       |
+//! | stream socket      | `socket/client` per connection        | newline-delimited       |
+//! | datagram socket    | socket name, `/sender` if it is bound | one or more lines each  |
+//!
+//! Pipes and sockets that do not exist are created, and removed again when
+//! the listener is dropped. A pipe is opened for reading *and* writing, so
+//! writers can open and close it at will without the feed ever seeing EOF.
+//! A stale socket file — one nothing is listening on any more — is replaced;
+//! a live one is refused.
+//!
+//! Stream connections are named after the connecting process
+//! (`/proc/<pid>/comm`), numbered `-2`, `-3`, … while several with the same
+//! name are connected. A client that disconnects and reconnects therefore
+//! carries on as the same producer: [`ProducerStatus::Ended`] when it goes,
+//! [`ProducerStatus::Streaming`] again when it is back.
+//!
+//! With [`PipeListener::with_signals`], stopping a pipe closes it, so writers
+//! get `EPIPE` until it is started and reopened; a reconnect reopens it at
+//! once. Stopping a stream client closes its connection and refuses new ones
+//! under its name until started; a reconnect closes the connection for the
+//! client to open again. A datagram socket has no stream per sender, so
+//! stopping one only discards its lines.
+
+use crate::control::{tree_id, Signal, Signals};
+use crate::{line_entry, lines::LineReader, report, FeedError};
+use fml_core::{FeedKind, LogEntry, ProducerStatus, ProducerUpdate};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use std::collections::HashSet;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::io::BufReader;
+use tokio::net::unix::pipe;
+use tokio::net::{UnixDatagram, UnixListener, UnixStream};
+use tokio::sync::mpsc;
+
+/// Largest datagram accepted; longer ones are truncated by the kernel.
+const MAX_DATAGRAM_LEN: usize = 64 * 1024;
+
+/// Paths to listen on. At least one should be set.
+#[derive(Debug, Clone, Default)]
+pub struct PipeConfig {
+    /// Named pipes.
+    pub fifos: Vec<PathBuf>,
+    /// `SOCK_STREAM` Unix sockets.
+    pub streams: Vec<PathBuf>,
+    /// `SOCK_DGRAM` Unix sockets.
+    pub datagrams: Vec<PathBuf>,
+}
+
+/// Opened pipes and bound sockets, ready to [`run`](PipeListener::run).
+pub struct PipeListener {
+    fifos: Vec<(String, PathBuf, pipe::Receiver)>,
+    streams: Vec<(String, UnixListener)>,
+    datagrams: Vec<(String, UnixDatagram)>,
+    created: Created,
+    signals: Signals,
+}
+
+/// Paths the listener created, removed when it goes away.
+struct Created(Vec<PathBuf>);
+
+impl Drop for Created {
+    fn drop(&mut self) {
+        for path in &self.0 {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+impl PipeListener {
+    /// Create or open every pipe and bind every socket in `config`.
+    pub async fn bind(config: &PipeConfig) -> Result<Self, FeedError> {
+        let mut created = Created(Vec::new());
+        let mut fifos = Vec::new();
+        for path in &config.fifos {
+            if open_fifo(path)? {
+                created.0.push(path.clone());
+            }
+            fifos.push((name(path), path.clone(), open_receiver(path)?));
+        }
+        let mut streams = Vec::new();
+        for path in &config.streams {
+            clear_stale_socket(path, |path| {
+                std::os::unix::net::UnixStream::connect(path).map(drop)
+            })?;
+            let listener = UnixListener::bind(path)?;
+            created.0.push(path.clone());
+            streams.push((name(path), listener));
+        }
+        let mut datagrams = Vec::new();
+        for path in &config.datagrams {
+            clear_stale_socket(path, |path| {
+                std::os::unix::net::UnixDatagram::unbound()?.connect(path)
+            })?;
+            let socket = UnixDatagram::bind(path)?;
+            created.0.push(path.clone());
+            datagrams.push((name(path), socket));
+        }
+        Ok(Self {
+            fifos,
+            streams,
+            datagrams,
+            created,
+            signals: Signals::default(),
+        })
+    }
+
+    /// Close or reopen pipes and connections when `signals` asks.
+    pub fn with_signals(mut self, signals: Signals) -> Self {
+        self.signals = signals;
+        self
+    }
+
+    /// Read lines until `tx` is closed. Each stream connection is served on
+    /// its own task; one that fails is dropped without affecting the rest.
+    pub async fn run(
+        self,
+        tx: mpsc::Sender<LogEntry>,
+        updates: mpsc::Sender<ProducerUpdate>,
+    ) -> Result<(), FeedError> {
+        let _created = self.created;
+        let mut servers: Vec<BoxFuture<'_, Result<(), FeedError>>> = Vec::new();
+        for (producer, path, receiver) in self.fifos {
+            let signals = self.signals.clone();
+            servers.push(serve_fifo(producer, path, receiver, signals, &tx, &updates).boxed());
+        }
+        for (socket, listener) in self.streams {
+            let signals = self.signals.clone();
+            servers.push(serve_stream(socket, listener, signals, &tx, &updates).boxed());
+        }
+        for (socket, datagram) in self.datagrams {
+            servers.push(serve_datagram(socket, datagram, &tx, &updates).boxed());
+        }
+        if servers.is_empty() {
+            return Ok(());
+        }
+        tokio::select! {
+            r = futures::future::try_join_all(servers) => r.map(drop),
+            _ = tx.closed() => Err(FeedError::ChannelClosed),
+        }
+    }
+}
+
+/// Make sure `path` is a named pipe, creating it if needed. Returns whether
+/// it was created.
+fn open_fifo(path: &Path) -> Result<bool, FeedError> {
+    match std::fs::metadata(path) {
+        Ok(meta) if meta.file_type().is_fifo() => Ok(false),
+        Ok(_) => Err(FeedError::PipeConfig(format!(
+            "{} exists and is not a named pipe",
+            path.display()
+        ))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            nix::unistd::mkfifo(path, nix::sys::stat::Mode::from_bits_truncate(0o600))
+                .map_err(|e| FeedError::PipeConfig(format!("{}: {e}", path.display())))?;
+            Ok(true)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Open a named pipe for reading, and for writing so it never sees EOF.
+fn open_receiver(path: &Path) -> std::io::Result<pipe::Receiver> {
+    pipe::OpenOptions::new()
+        .read_write(true)
+        .open_receiver(path)
+}
+
+/// Remove a socket file left behind by a listener that is gone. `probe`
+/// connects to it; a refused connection means nobody is listening.
+fn clear_stale_socket(
+    path: &Path,
+    probe: impl Fn(&Path) -> std::io::Result<()>,
+) -> Result<(), FeedError> {
+    let meta = match std::fs::symlink_metadata(path) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let refuse = |detail: &str| FeedError::PipeConfig(format!("{} {detail}", path.display()));
+    if !meta.file_type().is_socket() {
+        return Err(refuse("exists and is not a socket"));
+    }
+    match probe(path) {
+        Ok(()) => Err(refuse("is in use by another listener")),
+        Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+            tracing::debug!(path = %path.display(), "pipe: replacing stale socket");
+            std::fs::remove_file(path)?;
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn serve_fifo(
+    producer: String,
+    path: PathBuf,
+    receiver: pipe::Receiver,
+    mut signals: Signals,
+    tx: &mpsc::Sender<LogEntry>,
+    updates: &mpsc::Sender<ProducerUpdate>,
+) -> Result<(), FeedError> {
+    let id = tree_id(&fml_core::feed_root(FeedKind::Pipe, None), &producer);
+    let mut receiver = Some(receiver);
+    loop {
+        if signals.stopped(&id) {
+            tracing::debug!(producer = %producer, "pipe: stopped");
+            receiver = None;
+            tokio::select! {
+                () = signals.started(&id) => {}
+                () = tx.closed() => return Err(FeedError::ChannelClosed),
+            }
+        }
+        let receiver = match receiver.take() {
+            Some(receiver) => receiver,
+            None => open_receiver(&path)?,
+        };
+        report(
+            updates,
+            FeedKind::Pipe,
+            None,
+            &producer,
+            ProducerStatus::Streaming,
+        )
+        .await;
+        let mut lines = LineReader::new(BufReader::new(receiver));
+        // Holding the write end too, this only ends on an error or a signal.
+        let signal = loop {
+            let line = tokio::select! {
+                line = lines.next_line() => line?,
+                asked = signals.next(&id) => break asked,
+            };
+            let Some(line) = line else {
+                return Ok(());
+            };
+            tx.send(line_entry(FeedKind::Pipe, &producer, line))
+                .await
+                .map_err(|_| FeedError::ChannelClosed)?;
+        };
+        if signal == Signal::Reconnect {
+            tracing::debug!(producer = %producer, "pipe: reopening");
+        }
+    }
+}
+
+async fn serve_stream(
+    socket: String,
+    listener: UnixListener,
+    signals: Signals,
+    tx: &mpsc::Sender<LogEntry>,
+    updates: &mpsc::Sender<ProducerUpdate>,
+) -> Result<(), FeedError> {
+    let root = fml_core::feed_root(FeedKind::Pipe, None);
+    let connected = Arc::new(Mutex::new(HashSet::new()));
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let client = Client::claim(&socket, &stream, &connected);
+        let id = tree_id(&root, &client.producer);
+        if signals.stopped(&id) {
+            tracing::debug!(producer = %client.producer, "pipe: refusing stopped client");
+            continue;
+        }
+        tracing::debug!(producer = %client.producer, "pipe: connection");
+        let tx = tx.clone();
+        let updates = updates.clone();
+        let mut signals = signals.clone();
+        tokio::spawn(async move {
+            report(
+                &updates,
+                FeedKind::Pipe,
+                None,
+                &client.producer,
+                ProducerStatus::Streaming,
+            )
+            .await;
+            let mut lines = LineReader::new(BufReader::new(stream));
+            loop {
+                let line = tokio::select! {
+                    line = lines.next_line() => line,
+                    _ = signals.next(&id) => {
+                        tracing::debug!(producer = %client.producer, "pipe: closing connection");
+                        break;
+                    }
+                };
+                match line {
+                    Ok(Some(line)) => {
+                        let entry = line_entry(FeedKind::Pipe, &client.producer, line);
+                        if tx.send(entry).await.is_err() {
+                            return;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        tracing::debug!(producer = %client.producer, error = %e, "pipe: dropping connection");
+                        break;
+                    }
+                }
+            }
+            report(
+                &updates,
+                FeedKind::Pipe,
+                None,
+                &client.producer,
+                ProducerStatus::Ended { code: None },
+            )
+            .await;
+        });
+    }
+}
+
+/// A stream connection's producer name, held until it disconnects.
+struct Client {
+    producer: String,
+    connected: Arc<Mutex<HashSet<String>>>,
+}
+
+impl Client {
+    /// Name the connection after its process, numbered if that name is
+    /// already connected.
+    fn claim(socket: &str, stream: &UnixStream, connected: &Arc<Mutex<HashSet<String>>>) -> Self {
+        let process = stream
+            .peer_cred()
+            .ok()
+            .and_then(|cred| cred.pid())
+            .and_then(|pid| std::fs::read_to_string(format!("/proc/{pid}/comm")).ok())
+            .map(|comm| comm.trim().to_string())
+            .filter(|comm| !comm.is_empty())
+            .unwrap_or_else(|| "client".to_string());
+        let mut taken = connected.lock().expect("client names poisoned");
+        let producer = (1..)
+            .map(|n| match n {
+                1 => format!("{socket}/{process}"),
+                n => format!("{socket}/{process}-{n}"),
+            })
+            .find(|producer| !taken.contains(producer))
+            .expect("an unused name");
+        taken.insert(producer.clone());
+        Self {
+            producer,
+            connected: connected.clone(),
+        }
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        if let Ok(mut taken) = self.connected.lock() {
+            taken.remove(&self.producer);
+        }
+    }
+}
+
+async fn serve_datagram(
+    socket: String,
+    datagram: UnixDatagram,
+    tx: &mpsc::Sender<LogEntry>,
+    updates: &mpsc::Sender<ProducerUpdate>,
+) -> Result<(), FeedError> {
+    let mut seen = HashSet::new();
+    let mut buf = vec![0u8; MAX_DATAGRAM_LEN];
+    loop {
+        let (n, peer) = datagram.recv_from(&mut buf).await?;
+        let producer = match peer.as_pathname() {
+            Some(path) => format!("{socket}/{}", name(path)),
+            None => socket.clone(),
+        };
+        if seen.insert(producer.clone()) {
+            report(
+                updates,
+                FeedKind::Pipe,
+                None,
+                &producer,
+                ProducerStatus::Streaming,
+            )
+            .await;
+        }
+        let text = String::from_utf8_lossy(&buf[..n]);
+        for line in text.lines().filter(|line| !line.is_empty()) {
+            tx.send(line_entry(FeedKind::Pipe, &producer, line.to_string()))
+                .await
+                .map_err(|_| FeedError::ChannelClosed)?;
+        }
+    }
+}
+
+/// The file name of a pipe or socket, its producer name.
+fn name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+// ----- Tests -----
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn existing_files_are_not_taken_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.log");
+        std::fs::write(&file, "").unwrap();
+        let err = open_fifo(&file).unwrap_err();
+        assert!(err.to_string().contains("not a named pipe"), "{err}");
+        let err = clear_stale_socket(&file, |_| Ok(())).unwrap_err();
+        assert!(err.to_string().contains("not a socket"), "{err}");
+        assert!(file.exists());
+    }
+
+    #[test]
+    fn fifos_are_created_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.fifo");
+        assert!(open_fifo(&path).unwrap());
+        assert!(!open_fifo(&path).unwrap());
+        assert!(std::fs::metadata(&path).unwrap().file_type().is_fifo());
+    }
+
+    #[test]
+    fn stale_sockets_are_replaced_and_live_ones_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.sock");
+        let live = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        let connect = |path: &Path| std::os::unix::net::UnixStream::connect(path).map(drop);
+        let err = clear_stale_socket(&path, connect).unwrap_err();
+        assert!(err.to_string().contains("in use"), "{err}");
+
+        drop(live);
+        clear_stale_socket(&path, connect).unwrap();
+        assert!(!path.exists());
+    }
+}
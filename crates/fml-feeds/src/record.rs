@@ -0,0 +1,142 @@
+//! Mapping of structured records (JSON objects, decoded MessagePack maps)
+//! onto [`LogEntry`].
+//!
+//! Push-based feeds receive records that are already structured, so they map
+//! the conventional message / level / timestamp keys here instead of
+//! re-serialising and re-parsing each record as a raw line.
+
+use chrono::{DateTime, Utc};
+use fml_core::{FeedKind, LogEntry, LogLevel};
+use serde_json::{Map, Value};
+
+/// Keys checked, in order, for the human-readable message.
+const MESSAGE_KEYS: &[&str] = &["message", "msg", "log", "short_message"];
+/// Keys checked, in order, for the severity.
+const LEVEL_KEYS: &[&str] = &["level", "severity", "lvl", "log.level"];
+/// Keys checked, in order, for the event time.
+const TS_KEYS: &[&str] = &["timestamp", "@timestamp", "ts", "time"];
+
+/// Build an entry from a structured record. All top-level keys become fields
+/// (lowercased); `raw` is kept verbatim.
+pub(crate) fn record_entry(
+    source: FeedKind,
+    producer: &str,
+    raw: String,
+    record: Map<String, Value>,
+) -> LogEntry {
+    let fields: std::collections::HashMap<String, Value> = record
+        .into_iter()
+        .map(|(k, v)| (k.to_ascii_lowercase(), v))
+        .collect();
+
+    let message = MESSAGE_KEYS
+        .iter()
+        .find_map(|k| fields.get(*k).and_then(Value::as_str))
+        .map(str::to_string)
+        .or_else(|| Some(raw.clone()));
+    let level = LEVEL_KEYS
+        .iter()
+        .find_map(|k| fields.get(*k).and_then(level_from_value));
+    let ts = TS_KEYS
+        .iter()
+        .find_map(|k| fields.get(*k).and_then(ts_from_value))
+        .unwrap_or_else(Utc::now);
+
+    LogEntry {
+        seq: 0,
+        raw,
+        ts,
+        level,
+        source,
+        instance: None,
+        producer: producer.to_string(),
+        fields,
+        message,
+    }
+}
+
+/// The event time a record carries under one of the conventional keys.
+pub(crate) fn record_ts(record: &Map<String, Value>) -> Option<DateTime<Utc>> {
+    TS_KEYS
+        .iter()
+        .find_map(|k| record.get(*k).and_then(ts_from_value))
+}
+
+/// A level given as a name (`"warn"`) or a syslog severity number (`4`).
+pub(crate) fn level_from_value(value: &Value) -> Option<LogLevel> {
+    match value {
+        Value::String(s) => s.parse().ok(),
+        Value::Number(n) => n
+            .as_u64()
+            .and_then(|n| u8::try_from(n).ok())
+            .filter(|n| *n <= 7)
+            .map(crate::journald::priority_to_level),
+        _ => None,
+    }
+}
+
+/// A timestamp given as RFC 3339 text or as epoch seconds / milliseconds.
+pub(crate) fn ts_from_value(value: &Value) -> Option<DateTime<Utc>> {
+    match value {
+        Value::String(s) => DateTime::parse_from_rfc3339(s)
+            .ok()
+            .map(|t| t.with_timezone(&Utc)),
+        Value::Number(n) => {
+            let secs = n.as_f64()?;
+            // Anything past the year 5138 in seconds is really milliseconds.
+            let millis = if secs > 1e11 { secs } else { secs * 1000.0 };
+            DateTime::from_timestamp_millis(millis as i64)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn obj(v: Value) -> Map<String, Value> {
+        v.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn conventional_keys_are_mapped() {
+        let e = record_entry(
+            FeedKind::Http,
+            "p",
+            "raw".into(),
+            obj(json!({"Msg": "hi", "Level": "warning", "ts": "2024-01-15T10:00:00Z"})),
+        );
+        assert_eq!(e.message.as_deref(), Some("hi"));
+        assert_eq!(e.level, Some(LogLevel::Warn));
+        assert_eq!(e.ts.timestamp(), 1_705_312_800);
+        assert!(e.fields.contains_key("msg"));
+    }
+
+    #[test]
+    fn epoch_seconds_and_millis_are_accepted() {
+        assert_eq!(
+            ts_from_value(&json!(1_705_312_800)).unwrap().timestamp(),
+            1_705_312_800
+        );
+        assert_eq!(
+            ts_from_value(&json!(1_705_312_800_500u64))
+                .unwrap()
+                .timestamp_millis(),
+            1_705_312_800_500
+        );
+    }
+
+    #[test]
+    fn numeric_level_is_syslog_severity() {
+        assert_eq!(level_from_value(&json!(3)), Some(LogLevel::Error));
+        assert_eq!(level_from_value(&json!(42)), None);
+    }
+
+    #[test]
+    fn missing_message_falls_back_to_raw() {
+        let e = record_entry(FeedKind::Http, "p", "{}".into(), Map::new());
+        assert_eq!(e.message.as_deref(), Some("{}"));
+    }
+}
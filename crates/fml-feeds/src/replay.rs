@@ -0,0 +1,411 @@
+//! Replay feed — plays exported or archived logs back at their original pace.
+//!
+//! Two kinds of line are read, mixed freely within a file:
+//!
+//! | Line                                           | Entry                                        |
+//! |------------------------------------------------|----------------------------------------------|
+//! | fml `jsonl` export ([`JsonlRecord`])           | as exported: source, producer, level, fields |
+//! | JSON with a `ts`, `timestamp` or `time` key    | fields mapped as the HTTP feed maps them     |
+//! | `2024-01-15T10:32:05Z …`, `2024-01-15 10:32:05,250 …` | the rest of the line is the message   |
+//!
+//! Non-export lines are `replay` entries whose producer is the file name,
+//! without compression or rotation suffixes (`app.log.2.gz` → `app.log`).
+//! Lines without a timestamp (stack traces) take the one before them.
+//! Compressed files are read like the file feed's archives.
+//!
+//! All files are merged by timestamp. The first entry is sent at once and the
+//! rest after the same gaps as originally, divided by the [`Speed`]. With a
+//! control channel ([`ReplayFeed::with_control`]) the TUI can pause, resume,
+//! change speed or jump ahead with [`ReplayCommand`]s. Producers are reported
+//! as [`ProducerStatus::Streaming`] with their first line and all of them as
+//! [`ProducerStatus::Ended`] once the last line is sent.
+
+use crate::record::{record_entry, record_ts};
+use crate::resume::split_timestamp;
+use crate::{file, line_entry, FeedError};
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use fml_core::export::JsonlRecord;
+use fml_core::{
+    FeedKind, LogEntry, ProducerStatus, ProducerUpdate, ReplayCommand, ReplayTarget, Speed,
+};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{self, Instant};
+
+/// What to replay.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayConfig {
+    /// Files to read; merged by timestamp.
+    pub paths: Vec<PathBuf>,
+    pub speed: Speed,
+}
+
+/// Entries loaded from the replayed files, ready to be played.
+pub struct ReplayFeed {
+    entries: Vec<LogEntry>,
+    speed: Speed,
+    control: Option<mpsc::Receiver<ReplayCommand>>,
+}
+
+impl ReplayFeed {
+    /// Read and merge every file in `config`. Fails on unreadable files and
+    /// on files with lines but no timestamps to pace them by.
+    pub fn load(config: &ReplayConfig) -> Result<Self, FeedError> {
+        if config.paths.is_empty() {
+            return Err(FeedError::ReplayConfig("no files to replay".into()));
+        }
+        let mut entries = Vec::new();
+        for path in &config.paths {
+            entries.extend(load_file(path)?);
+        }
+        entries.sort_by_key(|entry| entry.ts);
+        tracing::debug!(
+            entries = entries.len(),
+            files = config.paths.len(),
+            "replay: loaded"
+        );
+        Ok(Self {
+            entries,
+            speed: config.speed,
+            control: None,
+        })
+    }
+
+    /// Take [`ReplayCommand`]s from `control` while playing.
+    pub fn with_control(mut self, control: mpsc::Receiver<ReplayCommand>) -> Self {
+        self.control = Some(control);
+        self
+    }
+
+    /// Play every entry, then return.
+    pub async fn run(
+        self,
+        tx: mpsc::Sender<LogEntry>,
+        updates: mpsc::Sender<ProducerUpdate>,
+    ) -> Result<(), FeedError> {
+        let Some(first) = self.entries.first() else {
+            return Ok(());
+        };
+        let mut clock = Clock {
+            origin: first.ts,
+            at: Instant::now(),
+            speed: self.speed,
+            paused: false,
+        };
+        let mut control = self.control;
+        let mut seen = HashSet::new();
+        let mut producers = Vec::new();
+
+        for entry in self.entries {
+            loop {
+                let due = clock.due(entry.ts);
+                if due.is_some_and(|due| due <= Instant::now()) {
+                    break;
+                }
+                let Some(rx) = control.as_mut() else {
+                    // Only a command pauses the clock, so `due` is set.
+                    time::sleep_until(due.unwrap_or_else(Instant::now)).await;
+                    break;
+                };
+                let command = tokio::select! {
+                    _ = time::sleep_until(due.unwrap_or_else(Instant::now)), if due.is_some() => break,
+                    command = rx.recv() => command,
+                };
+                match command {
+                    Some(command) => clock.apply(command),
+                    None => {
+                        // The TUI is gone; nothing could resume a pause.
+                        control = None;
+                        clock.apply(ReplayCommand::Resume);
+                    }
+                }
+            }
+
+            let key = (entry.source, entry.instance.clone(), entry.producer.clone());
+            if !entry.producer.is_empty() && seen.insert(key.clone()) {
+                producers.push(key);
+                let _ = updates
+                    .send(update(&entry, ProducerStatus::Streaming))
+                    .await;
+            }
+            tx.send(entry).await.map_err(|_| FeedError::ChannelClosed)?;
+        }
+
+        for (source, instance, producer) in producers {
+            let _ = updates
+                .send(ProducerUpdate {
+                    source,
+                    instance,
+                    producer,
+                    status: ProducerStatus::Ended { code: None },
+                })
+                .await;
+        }
+        Ok(())
+    }
+}
+
+fn update(entry: &LogEntry, status: ProducerStatus) -> ProducerUpdate {
+    ProducerUpdate {
+        source: entry.source,
+        instance: entry.instance.clone(),
+        producer: entry.producer.clone(),
+        status,
+    }
+}
+
+/// Maps log time onto wall time: the log reached `origin` at `at`, and has
+/// moved on at `speed` since unless paused.
+struct Clock {
+    origin: DateTime<Utc>,
+    at: Instant,
+    speed: Speed,
+    paused: bool,
+}
+
+impl Clock {
+    /// The log time being played.
+    fn now(&self) -> DateTime<Utc> {
+        if self.paused {
+            return self.origin;
+        }
+        let played = self.at.elapsed().mul_f64(self.speed.factor());
+        TimeDelta::from_std(played)
+            .ok()
+            .and_then(|played| self.origin.checked_add_signed(played))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+
+    /// When an entry stamped `ts` is due: already, if the clock is past it;
+    /// never while paused.
+    fn due(&self, ts: DateTime<Utc>) -> Option<Instant> {
+        if ts <= self.origin {
+            return Some(self.at);
+        }
+        if self.paused {
+            return None;
+        }
+        let ahead = (ts - self.origin)
+            .to_std()
+            .unwrap_or_default()
+            .div_f64(self.speed.factor());
+        Some(
+            self.at
+                .checked_add(ahead)
+                .unwrap_or_else(|| Instant::now() + FAR_FUTURE),
+        )
+    }
+
+    fn rebase(&mut self, origin: DateTime<Utc>) {
+        self.origin = origin;
+        self.at = Instant::now();
+    }
+
+    fn apply(&mut self, command: ReplayCommand) {
+        tracing::debug!(?command, "replay: command");
+        match command {
+            ReplayCommand::Pause if !self.paused => {
+                self.rebase(self.now());
+                self.paused = true;
+            }
+            ReplayCommand::Resume if self.paused => {
+                self.at = Instant::now();
+                self.paused = false;
+            }
+            ReplayCommand::Pause | ReplayCommand::Resume => {}
+            ReplayCommand::Speed(speed) => {
+                self.rebase(self.now());
+                self.speed = speed;
+            }
+            ReplayCommand::Jump(target) => {
+                let now = self.now();
+                let target = match target {
+                    ReplayTarget::At(ts) => ts,
+                    ReplayTarget::TimeOfDay(time) => {
+                        let ts = now.date_naive().and_time(time).and_utc();
+                        if ts < now {
+                            ts + TimeDelta::days(1)
+                        } else {
+                            ts
+                        }
+                    }
+                };
+                if target > now {
+                    self.rebase(target);
+                } else {
+                    tracing::debug!(%target, "replay: jump target already played");
+                }
+            }
+        }
+    }
+}
+
+/// Stand-in deadline for gaps too long to represent.
+const FAR_FUTURE: Duration = Duration::from_secs(86_400 * 365 * 30);
+
+/// The entries of one file, each with a timestamp.
+fn load_file(path: &Path) -> Result<Vec<LogEntry>, FeedError> {
+    let reader = std::io::BufReader::new(file::open_decompressed(path)?);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let producer = producer_of(&name);
+    let path_field = Value::from(path.to_string_lossy().into_owned());
+
+    let mut entries: Vec<LogEntry> = Vec::new();
+    // Lines before the first timestamp, stamped once it is found.
+    let mut undated = 0;
+    let mut last = None;
+    for line in reader.split(b'\n') {
+        let mut bytes = line?;
+        if bytes.last() == Some(&b'\r') {
+            bytes.pop();
+        }
+        let line = String::from_utf8_lossy(&bytes).into_owned();
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (ts, mut entry) = parse_line(producer, line);
+        if entry.source == FeedKind::Replay {
+            entry
+                .fields
+                .entry("path".into())
+                .or_insert_with(|| path_field.clone());
+        }
+        match ts.or(last) {
+            Some(ts) => {
+                if last.is_none() {
+                    for earlier in &mut entries[..undated] {
+                        earlier.ts = ts;
+                    }
+                    undated = 0;
+                }
+                entry.ts = ts;
+                last = Some(ts);
+            }
+            None => undated += 1,
+        }
+        entries.push(entry);
+    }
+    if last.is_none() && !entries.is_empty() {
+        return Err(FeedError::ReplayConfig(format!(
+            "{}: no timestamped lines to pace the replay by",
+            path.display()
+        )));
+    }
+    Ok(entries)
+}
+
+/// The entry for one line and the timestamp it carries, if any.
+fn parse_line(producer: &str, line: String) -> (Option<DateTime<Utc>>, LogEntry) {
+    if line.starts_with('{') {
+        if let Ok(record) = serde_json::from_str::<JsonlRecord>(&line) {
+            let entry = record.into_entry();
+            return (Some(entry.ts), entry);
+        }
+        if let Ok(Value::Object(record)) = serde_json::from_str(&line) {
+            let ts = record_ts(&record);
+            return (ts, record_entry(FeedKind::Replay, producer, line, record));
+        }
+    }
+    let (ts, message) = split_plain_timestamp(&line);
+    let message = message.to_string();
+    let mut entry = line_entry(FeedKind::Replay, producer, line);
+    entry.message = Some(message);
+    (ts, entry)
+}
+
+/// Split a leading timestamp off a plain log line: RFC 3339, or a date and
+/// time (`2024-01-15 10:32:05.250`, `,250` as Python writes it), optionally
+/// in brackets. Times without an offset are taken as UTC.
+fn split_plain_timestamp(line: &str) -> (Option<DateTime<Utc>>, &str) {
+    let (ts, rest) = split_timestamp(line);
+    if ts.is_some() {
+        return (ts, rest);
+    }
+    let mut parts = line.splitn(3, ' ');
+    if let (Some(date), Some(time)) = (parts.next(), parts.next()) {
+        let date = date.strip_prefix('[').unwrap_or(date);
+        let time = time.strip_suffix(']').unwrap_or(time).replace(',', ".");
+        let stamp = format!("{date} {time}");
+        if let Ok(ts) = NaiveDateTime::parse_from_str(&stamp, "%Y-%m-%d %H:%M:%S%.f") {
+            return (Some(ts.and_utc()), parts.next().unwrap_or(""));
+        }
+    }
+    (None, line)
+}
+
+/// A file's producer: its name without compression or a numeric rotation
+/// suffix, so `app.log.1` and `app.log.2.gz` join `app.log`.
+fn producer_of(name: &str) -> &str {
+    let name = file::uncompressed_name(name);
+    match name.rsplit_once('.') {
+        Some((stem, index)) if !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()) => {
+            stem
+        }
+        _ => name,
+    }
+}
+
+// ----- Tests -----
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn plain_timestamps_split() {
+        let (at, rest) = split_plain_timestamp("2024-01-15T10:32:05Z GET / 200");
+        assert_eq!((at, rest), (Some(ts("2024-01-15T10:32:05Z")), "GET / 200"));
+        let (at, rest) = split_plain_timestamp("2024-01-15 10:32:05,250 ERROR boom");
+        assert_eq!(
+            (at, rest),
+            (Some(ts("2024-01-15T10:32:05.250Z")), "ERROR boom")
+        );
+        let (at, rest) = split_plain_timestamp("[2024-01-15 10:32:05] ready");
+        assert_eq!((at, rest), (Some(ts("2024-01-15T10:32:05Z")), "ready"));
+        assert_eq!(
+            split_plain_timestamp("    at Foo.bar(Foo.java:42)"),
+            (None, "    at Foo.bar(Foo.java:42)")
+        );
+    }
+
+    #[test]
+    fn rotated_files_share_a_producer() {
+        assert_eq!(producer_of("app.log"), "app.log");
+        assert_eq!(producer_of("app.log.1"), "app.log");
+        assert_eq!(producer_of("app.log.2.gz"), "app.log");
+        assert_eq!(producer_of("app.log-20240115.zst"), "app.log-20240115");
+    }
+
+    #[test]
+    fn paused_clock_only_releases_what_it_passed() {
+        let mut clock = Clock {
+            origin: ts("2024-01-15T10:00:00Z"),
+            at: Instant::now(),
+            speed: Speed::REALTIME,
+            paused: false,
+        };
+        clock.apply(ReplayCommand::Pause);
+        clock.apply(ReplayCommand::Jump(ReplayTarget::TimeOfDay(
+            chrono::NaiveTime::from_hms_opt(10, 5, 0).unwrap(),
+        )));
+        assert!(clock.due(ts("2024-01-15T10:04:59Z")).is_some());
+        assert!(clock.due(ts("2024-01-15T10:05:01Z")).is_none());
+
+        // Jumping back is a no-op.
+        clock.apply(ReplayCommand::Jump(ReplayTarget::At(ts(
+            "2024-01-15T09:00:00Z",
+        ))));
+        assert_eq!(clock.now(), ts("2024-01-15T10:05:00Z"));
+    }
+}
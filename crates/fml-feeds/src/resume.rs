@@ -0,0 +1,129 @@
+//! Resuming timestamped log streams without duplicates.
+//!
+//! The Kubernetes and Docker log APIs can both prefix every line with an
+//! RFC 3339 timestamp and re-open a stream from a given time. Feeds use the
+//! newest timestamp seen as a resume [`Cursor`]: after a disconnect the
+//! stream is re-opened from that time and the lines it replays are dropped.
+
+use chrono::{DateTime, Utc};
+
+/// Split a leading RFC 3339 timestamp (and the space after it) off `line`.
+pub(crate) fn split_timestamp(line: &str) -> (Option<DateTime<Utc>>, &str) {
+    if let Some((prefix, rest)) = line.split_once(' ') {
+        if let Ok(ts) = DateTime::parse_from_rfc3339(prefix) {
+            return (Some(ts.with_timezone(&Utc)), rest);
+        }
+    }
+    (None, line)
+}
+
+/// Resume position within one timestamped log.
+///
+/// APIs replay lines stamped at or after the resume time, so the cursor
+/// remembers how many lines it has already forwarded at exactly the newest
+/// timestamp and skips that many replays.
+#[derive(Debug, Default)]
+pub(crate) struct Cursor {
+    /// Timestamp of the newest line forwarded so far.
+    last: Option<DateTime<Utc>>,
+    /// Lines forwarded with exactly `last` as their timestamp.
+    at_last: usize,
+    /// Replayed lines at `last` still to be skipped after a reconnect.
+    skip: usize,
+}
+
+impl Cursor {
+    /// Timestamp to re-open the stream from, if any line has been seen.
+    pub(crate) fn last(&self) -> Option<DateTime<Utc>> {
+        self.last
+    }
+
+    /// Prepare to skip what the next stream replays.
+    pub(crate) fn resume(&mut self) {
+        self.skip = self.at_last;
+    }
+
+    /// Skip ahead to `now`, so the next stream leaves out what was logged
+    /// while it was closed on purpose.
+    pub(crate) fn restart_at(&mut self, now: DateTime<Utc>) {
+        if self.last.is_none_or(|last| last < now) {
+            self.last = Some(now);
+            self.at_last = 0;
+            self.skip = 0;
+        }
+    }
+
+    /// Whether a line stamped `ts` is new. Lines without a timestamp are
+    /// always forwarded.
+    pub(crate) fn admit(&mut self, ts: Option<DateTime<Utc>>) -> bool {
+        let Some(ts) = ts else {
+            return true;
+        };
+        match self.last {
+            Some(last) if ts < last => false,
+            Some(last) if ts == last => {
+                if self.skip > 0 {
+                    self.skip -= 1;
+                    false
+                } else {
+                    self.at_last += 1;
+                    true
+                }
+            }
+            _ => {
+                self.last = Some(ts);
+                self.at_last = 1;
+                self.skip = 0;
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(s: &str) -> Option<DateTime<Utc>> {
+        Some(DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc))
+    }
+
+    #[test]
+    fn timestamp_prefix_is_split_off() {
+        let (stamp, message) = split_timestamp("2024-01-15T10:00:00.123456789Z GET /health 200");
+        assert_eq!(stamp, ts("2024-01-15T10:00:00.123456789Z"));
+        assert_eq!(message, "GET /health 200");
+
+        let (stamp, message) = split_timestamp("no timestamp here");
+        assert_eq!(stamp, None);
+        assert_eq!(message, "no timestamp here");
+    }
+
+    #[test]
+    fn cursor_drops_replayed_lines_after_resume() {
+        let mut cursor = Cursor::default();
+        assert!(cursor.admit(ts("2024-01-15T10:00:00Z")));
+        assert!(cursor.admit(ts("2024-01-15T10:00:01Z")));
+        assert!(cursor.admit(ts("2024-01-15T10:00:01Z")));
+
+        cursor.resume();
+        // The server replays from the resume time (second precision here).
+        assert!(!cursor.admit(ts("2024-01-15T10:00:00Z")));
+        assert!(!cursor.admit(ts("2024-01-15T10:00:01Z")));
+        assert!(!cursor.admit(ts("2024-01-15T10:00:01Z")));
+        // A third line in the same instant is new.
+        assert!(cursor.admit(ts("2024-01-15T10:00:01Z")));
+        assert!(cursor.admit(ts("2024-01-15T10:00:02Z")));
+    }
+
+    #[test]
+    fn restart_drops_what_came_before() {
+        let mut cursor = Cursor::default();
+        assert!(cursor.admit(ts("2024-01-15T10:00:00Z")));
+        cursor.restart_at(ts("2024-01-15T10:00:05Z").unwrap());
+        assert_eq!(cursor.last(), ts("2024-01-15T10:00:05Z"));
+        assert!(!cursor.admit(ts("2024-01-15T10:00:03Z")));
+        assert!(cursor.admit(ts("2024-01-15T10:00:05Z")));
+        assert!(cursor.admit(ts("2024-01-15T10:00:06Z")));
+    }
+}
@@ -1 +1,309 @@
 //! Stdin feed — reads raw log lines from standard input.
+//!
+//! Tools that multiplex several sources into one stream prefix each line with
+//! where it came from. With a [`Prefix`] the feed splits that back off, so
+//! each source becomes its own producer and only the payload is the message:
+//!
+//! | Prefix    | Tool                                   | Line                          | Producer      |
+//! |-----------|----------------------------------------|-------------------------------|---------------|
+//! | `compose` | `docker compose logs -f`               | `api-1  \| listening on :80`  | `api-1`       |
+//! | `kubectl` | `kubectl logs -l app=api --prefix`     | `[pod/api-7f9/api] ready`     | `api-7f9/api` |
+//! | `stern`   | `stern api`                            | `api-7f9 api ready`           | `api-7f9/api` |
+//! | regex     | anything, via a `producer` group       | `(?P<producer>\w+): `         | the group     |
+//!
+//! [`Prefix::Detect`] (the default) tries `compose` and `kubectl` on the
+//! first lines and keeps the one most of them match, or none. Colour codes
+//! are stripped before matching, since these tools colour their prefixes.
+//! Lines without a prefix go to [`StdinConfig::producer`]. New producers are
+//! reported as [`ProducerStatus::Streaming`] and all of them as
+//! [`ProducerStatus::Ended`] at EOF.
+
+use crate::{line_entry, lines, FeedError};
+use bytes::Bytes;
+use fml_core::{FeedKind, LogEntry, ProducerStatus, ProducerUpdate};
+use futures::Stream;
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use std::sync::LazyLock;
+use tokio::sync::mpsc;
+use tokio_util::io::ReaderStream;
+
+/// Lines [`Prefix::Detect`] samples before settling on a format.
+const DETECT_LINES: usize = 8;
+
+static COMPOSE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?P<producer>[A-Za-z0-9][\w.-]*)\s+\| ?(?P<message>.*)$").unwrap()
+});
+static KUBECTL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\[(?:pod/)?(?P<producer>[^\]\s]+)\] ?(?P<message>.*)$").unwrap()
+});
+static STERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?P<pod>[a-z0-9][a-z0-9.-]*) (?P<container>[a-z0-9][a-z0-9-]*) (?P<message>.*)$")
+        .unwrap()
+});
+/// SGR escapes (`ESC [ … m`).
+static COLOUR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\x1b\[[0-9;]*m").unwrap());
+
+/// How each line names the source it came from.
+#[derive(Debug, Clone, Default)]
+pub enum Prefix {
+    /// Lines are not prefixed; everything is one producer.
+    None,
+    /// Pick `compose` or `kubectl` from the first lines, if either fits.
+    #[default]
+    Detect,
+    /// `service | message`, padded (`docker compose logs`).
+    Compose,
+    /// `[pod/name/container] message` (`kubectl logs --prefix`).
+    Kubectl,
+    /// `pod container message` (`stern`'s default template).
+    Stern,
+    /// A regex with a `producer` group. The message is its `message` group,
+    /// or else whatever follows the match.
+    Pattern(Regex),
+}
+
+impl FromStr for Prefix {
+    type Err = String;
+
+    /// `none`, `auto`, `compose`, `kubectl`, `stern`, or a regex with a
+    /// `producer` group.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Prefix::None),
+            "auto" => Ok(Prefix::Detect),
+            "compose" => Ok(Prefix::Compose),
+            "kubectl" => Ok(Prefix::Kubectl),
+            "stern" => Ok(Prefix::Stern),
+            pattern => {
+                let regex = Regex::new(pattern).map_err(|e| e.to_string())?;
+                if !regex.capture_names().any(|name| name == Some("producer")) {
+                    return Err(format!(
+                        "`{pattern}` is not none, auto, compose, kubectl or stern, \
+                         nor a regex with a `producer` group"
+                    ));
+                }
+                Ok(Prefix::Pattern(regex))
+            }
+        }
+    }
+}
+
+impl Prefix {
+    /// Split `line` into producer and payload, if it carries this prefix.
+    fn split<'a>(&self, line: &'a str) -> Option<(String, &'a str)> {
+        let (regex, producer): (&Regex, fn(&regex::Captures) -> String) = match self {
+            Prefix::None | Prefix::Detect => return None,
+            Prefix::Compose => (&COMPOSE, |c| c["producer"].to_string()),
+            Prefix::Kubectl => (&KUBECTL, |c| c["producer"].to_string()),
+            Prefix::Stern => (&STERN, |c| format!("{}/{}", &c["pod"], &c["container"])),
+            Prefix::Pattern(regex) => (regex, |c| c["producer"].to_string()),
+        };
+        let captures = regex.captures(line)?;
+        let whole = captures.get(0).expect("group 0 always matches");
+        let message = match captures.name("message") {
+            Some(message) => message.as_str(),
+            None => line[whole.end()..].trim_start(),
+        };
+        let producer = producer(&captures);
+        (!producer.is_empty()).then_some((producer, message))
+    }
+}
+
+/// What to read from stdin.
+#[derive(Debug, Clone, Default)]
+pub struct StdinConfig {
+    /// Producer of lines without a prefix. Empty puts them at the feed root.
+    pub producer: String,
+    pub prefix: Prefix,
+}
+
+/// [`Prefix::Detect`] while it samples: match counts per candidate.
+struct Detector {
+    seen: usize,
+    compose: usize,
+    kubectl: usize,
+}
+
+impl Detector {
+    /// Count `line` and return the format to split it with. Once enough lines
+    /// are seen, the result is final: the candidate at least half of them
+    /// matched, or [`Prefix::None`].
+    fn sample(&mut self, line: &str) -> (Prefix, bool) {
+        self.seen += 1;
+        let compose = COMPOSE.is_match(line);
+        let kubectl = KUBECTL.is_match(line);
+        self.compose += usize::from(compose);
+        self.kubectl += usize::from(kubectl);
+        if self.seen < DETECT_LINES {
+            let guess = match (kubectl, compose) {
+                (true, _) => Prefix::Kubectl,
+                (_, true) => Prefix::Compose,
+                _ => Prefix::None,
+            };
+            return (guess, false);
+        }
+        let settled = if self.kubectl * 2 >= self.seen && self.kubectl >= self.compose {
+            Prefix::Kubectl
+        } else if self.compose * 2 >= self.seen {
+            Prefix::Compose
+        } else {
+            Prefix::None
+        };
+        tracing::debug!(prefix = ?settled, "stdin: prefix detected");
+        (settled, true)
+    }
+}
+
+/// Read the process's stdin until EOF.
+pub async fn run(
+    config: &StdinConfig,
+    tx: mpsc::Sender<LogEntry>,
+    updates: mpsc::Sender<ProducerUpdate>,
+) -> Result<(), FeedError> {
+    ingest(config, ReaderStream::new(tokio::io::stdin()), tx, updates).await
+}
+
+/// Forward lines from `stream` until EOF, split by `config.prefix`.
+pub async fn ingest<S>(
+    config: &StdinConfig,
+    stream: S,
+    tx: mpsc::Sender<LogEntry>,
+    updates: mpsc::Sender<ProducerUpdate>,
+) -> Result<(), FeedError>
+where
+    S: Stream<Item = std::io::Result<Bytes>> + Unpin,
+{
+    let report = |producer: String, status: ProducerStatus| {
+        let updates = updates.clone();
+        async move {
+            let update = ProducerUpdate {
+                source: FeedKind::Stdin,
+                instance: None,
+                producer,
+                status,
+            };
+            let _ = updates.send(update).await;
+        }
+    };
+
+    let mut prefix = config.prefix.clone();
+    let mut detector = matches!(prefix, Prefix::Detect).then_some(Detector {
+        seen: 0,
+        compose: 0,
+        kubectl: 0,
+    });
+    let mut producers = BTreeSet::new();
+    let mut reader = lines::from_stream(stream);
+    while let Some(line) = reader.next_line().await? {
+        let plain = match prefix {
+            Prefix::None => Cow::Borrowed(line.as_str()),
+            _ => COLOUR.replace_all(&line, ""),
+        };
+        let split = match detector.as_mut() {
+            Some(detector) if !plain.is_empty() => {
+                let (format, settled) = detector.sample(&plain);
+                let split = format.split(&plain);
+                if settled {
+                    prefix = format;
+                }
+                split
+            }
+            _ => prefix.split(&plain),
+        };
+        if !matches!(prefix, Prefix::Detect) {
+            detector = None;
+        }
+        let (producer, message) = match split {
+            Some((producer, message)) => (producer, message.to_string()),
+            None => (config.producer.clone(), line.clone()),
+        };
+        if !producer.is_empty() && producers.insert(producer.clone()) {
+            report(producer.clone(), ProducerStatus::Streaming).await;
+        }
+        tx.send(line_entry(FeedKind::Stdin, &producer, message))
+            .await
+            .map_err(|_| FeedError::ChannelClosed)?;
+    }
+    for producer in producers {
+        report(producer, ProducerStatus::Ended { code: None }).await;
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(prefix: &str, line: &str) -> Option<(String, String)> {
+        let prefix: Prefix = prefix.parse().unwrap();
+        prefix
+            .split(line)
+            .map(|(producer, message)| (producer, message.to_string()))
+    }
+
+    fn pair(producer: &str, message: &str) -> Option<(String, String)> {
+        Some((producer.to_string(), message.to_string()))
+    }
+
+    #[test]
+    fn known_prefixes_split() {
+        assert_eq!(
+            split("compose", "api-1  | listening on :80"),
+            pair("api-1", "listening on :80")
+        );
+        assert_eq!(
+            split("kubectl", "[pod/api-7f9/api] ready"),
+            pair("api-7f9/api", "ready")
+        );
+        assert_eq!(
+            split("stern", "api-7f9 api GET / 200"),
+            pair("api-7f9/api", "GET / 200")
+        );
+        assert_eq!(split("compose", "no prefix here"), None);
+    }
+
+    #[test]
+    fn patterns_need_a_producer_group() {
+        assert_eq!(
+            split(r"^(?P<producer>\w+): ", "worker: job done"),
+            pair("worker", "job done")
+        );
+        assert_eq!(
+            split(
+                r"^<(?P<producer>\w+)> (?P<message>.*) !$",
+                "<db> slow query !"
+            ),
+            pair("db", "slow query")
+        );
+        assert!(r"^(\w+): ".parse::<Prefix>().is_err());
+        assert!("(?P<producer>".parse::<Prefix>().is_err());
+    }
+
+    #[test]
+    fn detection_settles_on_the_majority() {
+        let mut detector = Detector {
+            seen: 0,
+            compose: 0,
+            kubectl: 0,
+        };
+        let mut last = None;
+        for i in 0..DETECT_LINES {
+            let line = if i % 4 == 0 {
+                "Attaching to api-1, db-1".to_string()
+            } else {
+                format!("db-1  | checkpoint {i}")
+            };
+            last = Some(detector.sample(&line));
+        }
+        let (prefix, settled) = last.unwrap();
+        assert!(settled);
+        assert!(matches!(prefix, Prefix::Compose));
+    }
+}
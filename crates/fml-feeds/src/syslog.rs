@@ -0,0 +1,501 @@
+//! Syslog feed — a throwaway network syslog sink.
+//!
+//! Listens on a UDP and/or TCP port and parses each message as RFC 5424 or
+//! RFC 3164 (BSD) syslog. Over TCP both framings from RFC 6587 are accepted,
+//! decided per message: octet-counted (`<len> <msg>`) when the frame starts
+//! with a digit, newline-delimited otherwise. Each UDP datagram is one message.
+//!
+//! The producer is `hostname/app-name`, falling back to the sender's IP when
+//! the message carries no hostname and to `syslog` when it has no app name.
+//! `PRI` is split into `facility` and `severity` fields, and the severity
+//! sets the entry level the same way journald priorities do. RFC 3164
+//! timestamps carry no year or zone, so they are read as local time in the
+//! latest year that does not put them in the future.
+
+use crate::journald::priority_to_level;
+use crate::FeedError;
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, TimeDelta, TimeZone, Utc};
+use fml_core::{FeedKind, LogEntry};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::net::{TcpListener, UdpSocket};
+use tokio::sync::mpsc;
+
+/// Largest message accepted over either transport. Octet-counted frames
+/// claiming more than this are treated as a framing error.
+const MAX_MESSAGE_LEN: usize = 64 * 1024;
+
+/// Addresses to listen on. At least one should be set.
+#[derive(Debug, Clone, Default)]
+pub struct SyslogConfig {
+    pub udp: Option<SocketAddr>,
+    pub tcp: Option<SocketAddr>,
+}
+
+/// Bound syslog sockets, ready to [`run`](SyslogListener::run).
+///
+/// Binding is separate from running so callers (and tests) can bind to port 0
+/// and read back the chosen port with [`SyslogListener::udp_addr`] /
+/// [`SyslogListener::tcp_addr`].
+pub struct SyslogListener {
+    udp: Option<UdpSocket>,
+    tcp: Option<TcpListener>,
+}
+
+impl SyslogListener {
+    pub async fn bind(config: &SyslogConfig) -> Result<Self, FeedError> {
+        let udp = match config.udp {
+            Some(addr) => Some(UdpSocket::bind(addr).await?),
+            None => None,
+        };
+        let tcp = match config.tcp {
+            Some(addr) => Some(TcpListener::bind(addr).await?),
+            None => None,
+        };
+        Ok(Self { udp, tcp })
+    }
+
+    pub fn udp_addr(&self) -> Option<SocketAddr> {
+        self.udp.as_ref().and_then(|s| s.local_addr().ok())
+    }
+
+    pub fn tcp_addr(&self) -> Option<SocketAddr> {
+        self.tcp.as_ref().and_then(|s| s.local_addr().ok())
+    }
+
+    /// Receive messages until `tx` is closed. Each TCP connection is served on
+    /// its own task; a connection that sends malformed framing is dropped
+    /// without affecting the listener.
+    pub async fn run(self, tx: mpsc::Sender<LogEntry>) -> Result<(), FeedError> {
+        let udp = async {
+            match &self.udp {
+                Some(socket) => serve_udp(socket, &tx).await,
+                None => std::future::pending().await,
+            }
+        };
+        let tcp = async {
+            match &self.tcp {
+                Some(listener) => serve_tcp(listener, &tx).await,
+                None => std::future::pending().await,
+            }
+        };
+        if self.udp.is_none() && self.tcp.is_none() {
+            return Ok(());
+        }
+        tokio::select! {
+            r = udp => r,
+            r = tcp => r,
+            _ = tx.closed() => Err(FeedError::ChannelClosed),
+        }
+    }
+}
+
+async fn serve_udp(socket: &UdpSocket, tx: &mpsc::Sender<LogEntry>) -> Result<(), FeedError> {
+    let mut buf = vec![0u8; MAX_MESSAGE_LEN];
+    loop {
+        let (n, peer) = socket.recv_from(&mut buf).await?;
+        let text = String::from_utf8_lossy(&buf[..n]);
+        let text = text.trim_end_matches(['\r', '\n']);
+        if text.is_empty() {
+            continue;
+        }
+        tx.send(parse_message(text, Some(peer.ip())))
+            .await
+            .map_err(|_| FeedError::ChannelClosed)?;
+    }
+}
+
+async fn serve_tcp(listener: &TcpListener, tx: &mpsc::Sender<LogEntry>) -> Result<(), FeedError> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        tracing::debug!(%peer, "syslog: tcp connection");
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(stream);
+            loop {
+                match read_frame(&mut reader).await {
+                    Ok(Some(frame)) if frame.is_empty() => continue,
+                    Ok(Some(frame)) => {
+                        if tx
+                            .send(parse_message(&frame, Some(peer.ip())))
+                            .await
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                    Ok(None) => return,
+                    Err(e) => {
+                        tracing::debug!(%peer, error = %e, "syslog: dropping connection");
+                        return;
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Read one RFC 6587 frame: octet-counted if it starts with a digit,
+/// newline-delimited otherwise. Returns `None` at a clean EOF. Either way a
+/// frame longer than [`MAX_MESSAGE_LEN`] is a framing error.
+pub(crate) async fn read_frame<R>(reader: &mut R) -> std::io::Result<Option<String>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let first = match reader.fill_buf().await?.first() {
+        Some(b) => *b,
+        None => return Ok(None),
+    };
+
+    let mut buf = Vec::new();
+    if first.is_ascii_digit() {
+        read_until_bounded(reader, b' ', &mut buf).await?;
+        let len: usize = std::str::from_utf8(&buf)
+            .ok()
+            .and_then(|s| s.trim_end().parse().ok())
+            .filter(|len| *len <= MAX_MESSAGE_LEN)
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "bad octet count")
+            })?;
+        buf.resize(len, 0);
+        reader.read_exact(&mut buf).await?;
+    } else {
+        read_until_bounded(reader, b'\n', &mut buf).await?;
+    }
+
+    let text = String::from_utf8_lossy(&buf);
+    Ok(Some(text.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+/// `read_until` that gives up once [`MAX_MESSAGE_LEN`] bytes have passed
+/// without `delim`, so a peer that never sends it cannot grow `buf` forever.
+async fn read_until_bounded<R>(reader: &mut R, delim: u8, buf: &mut Vec<u8>) -> std::io::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let limit = MAX_MESSAGE_LEN as u64 + 1;
+    let n = reader.take(limit).read_until(delim, buf).await?;
+    if n as u64 == limit && buf.last() != Some(&delim) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "frame too long",
+        ));
+    }
+    Ok(())
+}
+
+/// Parse one syslog message. Anything that does not start with a `<PRI>`
+/// header is kept as an unstructured message from the sender.
+pub fn parse_message(text: &str, peer: Option<IpAddr>) -> LogEntry {
+    let mut fields = HashMap::new();
+    if let Some(peer) = peer {
+        fields.insert("peer".to_string(), Value::String(peer.to_string()));
+    }
+
+    let parsed = split_pri(text).map(|(pri, rest)| {
+        fields.insert("facility".to_string(), Value::from(pri / 8));
+        fields.insert("severity".to_string(), Value::from(pri % 8));
+        let header = match rest.strip_prefix("1 ") {
+            Some(rest) => parse_5424(rest),
+            None => parse_3164(rest),
+        };
+        (pri, header)
+    });
+
+    let (level, header) = match parsed {
+        Some((pri, header)) => (Some(priority_to_level(pri % 8)), header),
+        None => (None, Header::unstructured(text)),
+    };
+
+    for (key, value) in [
+        ("hostname", &header.hostname),
+        ("appname", &header.app),
+        ("procid", &header.procid),
+        ("msgid", &header.msgid),
+        ("structured_data", &header.structured_data),
+    ] {
+        if let Some(value) = value {
+            fields.insert(key.to_string(), Value::String(value.clone()));
+        }
+    }
+
+    let host = header
+        .hostname
+        .clone()
+        .or_else(|| peer.map(|p| p.to_string()))
+        .unwrap_or_else(|| "unknown".to_string());
+    let app = header.app.clone().unwrap_or_else(|| "syslog".to_string());
+
+    LogEntry {
+        seq: 0,
+        raw: text.to_string(),
+        ts: header.ts.unwrap_or_else(Utc::now),
+        level,
+        source: FeedKind::Syslog,
+        instance: None,
+        producer: format!("{host}/{app}"),
+        fields,
+        message: Some(header.message),
+    }
+}
+
+/// Parsed syslog header. `None` fields were absent or the RFC 5424 nil value.
+#[derive(Debug, Default)]
+struct Header {
+    ts: Option<DateTime<Utc>>,
+    hostname: Option<String>,
+    app: Option<String>,
+    procid: Option<String>,
+    msgid: Option<String>,
+    structured_data: Option<String>,
+    message: String,
+}
+
+impl Header {
+    fn unstructured(text: &str) -> Self {
+        Self {
+            message: text.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Split `<PRI>` off the front of a message.
+fn split_pri(text: &str) -> Option<(u8, &str)> {
+    let rest = text.strip_prefix('<')?;
+    let end = rest.find('>')?;
+    let pri: u8 = rest[..end].parse().ok().filter(|p| *p <= 191)?;
+    Some((pri, &rest[end + 1..]))
+}
+
+fn nil(token: &str) -> Option<String> {
+    (token != "-" && !token.is_empty()).then(|| token.to_string())
+}
+
+/// `TIMESTAMP HOSTNAME APP-NAME PROCID MSGID STRUCTURED-DATA [MSG]`
+fn parse_5424(rest: &str) -> Header {
+    let mut parts = rest.splitn(6, ' ');
+    let ts = parts
+        .next()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Utc));
+    let hostname = parts.next().and_then(nil);
+    let app = parts.next().and_then(nil);
+    let procid = parts.next().and_then(nil);
+    let msgid = parts.next().and_then(nil);
+    let (sd, msg) = split_structured_data(parts.next().unwrap_or(""));
+    let msg = msg.strip_prefix('\u{feff}').unwrap_or(msg);
+
+    Header {
+        ts,
+        hostname,
+        app,
+        procid,
+        msgid,
+        structured_data: nil(sd),
+        message: msg.to_string(),
+    }
+}
+
+/// Split `STRUCTURED-DATA [SP MSG]`, honouring `\]` escapes inside param values.
+fn split_structured_data(s: &str) -> (&str, &str) {
+    if !s.starts_with('[') {
+        return match s.split_once(' ') {
+            Some((sd, msg)) => (sd, msg),
+            None => (s, ""),
+        };
+    }
+    let bytes = s.as_bytes();
+    let (mut i, mut in_quotes) = (0, false);
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'"' => in_quotes = !in_quotes,
+            b']' if !in_quotes && bytes.get(i + 1) != Some(&b'[') => {
+                let sd = &s[..=i];
+                return (sd, s[i + 1..].strip_prefix(' ').unwrap_or(&s[i + 1..]));
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    (s, "")
+}
+
+/// `Mmm dd hh:mm:ss HOSTNAME TAG[pid]: MSG`. Devices that omit the hostname
+/// are common, so a token ending in `:` right after the timestamp is taken as
+/// the tag instead.
+fn parse_3164(rest: &str) -> Header {
+    let (ts, rest) = match rest.get(..15).and_then(parse_bsd_timestamp) {
+        Some(ts) => (Some(ts), rest[15..].trim_start()),
+        None => (None, rest),
+    };
+
+    let (hostname, rest) = match rest.split_once(' ') {
+        Some((host, after)) if ts.is_some() && !host.ends_with(':') => (Some(host), after),
+        _ => (None, rest),
+    };
+
+    let (tag, message) = match rest.split_once(": ") {
+        Some((tag, msg)) if !tag.contains(' ') => (Some(tag), msg),
+        _ => (None, rest),
+    };
+    let (app, procid) = match tag {
+        Some(tag) => match tag.split_once('[') {
+            Some((app, pid)) => (Some(app), Some(pid.trim_end_matches(']'))),
+            None => (Some(tag), None),
+        },
+        None => (None, None),
+    };
+
+    Header {
+        ts,
+        hostname: hostname.map(str::to_string),
+        app: app.map(str::to_string),
+        procid: procid.map(str::to_string),
+        message: message.to_string(),
+        ..Default::default()
+    }
+}
+
+/// BSD timestamps carry no year or zone; assume the sender shares our local
+/// zone and the most recent year that does not put the message in the future
+/// (a December line read in January is from last year).
+fn parse_bsd_timestamp(s: &str) -> Option<DateTime<Utc>> {
+    bsd_timestamp(s, Local::now())
+}
+
+/// [`parse_bsd_timestamp`] as seen at `now`, in `now`'s zone.
+fn bsd_timestamp<Tz: TimeZone>(s: &str, now: DateTime<Tz>) -> Option<DateTime<Utc>> {
+    let s = s.replace("  ", " 0");
+    let year = now.year();
+    [year, year - 1].into_iter().find_map(|year| {
+        let naive =
+            NaiveDateTime::parse_from_str(&format!("{year} {s}"), "%Y %b %d %H:%M:%S").ok()?;
+        let ts = now.timezone().from_local_datetime(&naive).earliest()?;
+        // Allow for clock skew between the sender and us.
+        (ts <= now.clone() + TimeDelta::days(1)).then(|| ts.with_timezone(&Utc))
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fml_core::LogLevel;
+
+    #[test]
+    fn rfc5424_message_is_parsed() {
+        let e = parse_message(
+            r#"<165>1 2024-01-15T10:00:00.123Z fw01 pf 4711 ID47 [exampleSDID@32473 iut="3" note="a\]b"] packet dropped"#,
+            None,
+        );
+        assert_eq!(e.producer, "fw01/pf");
+        assert_eq!(e.level, Some(LogLevel::Info));
+        assert_eq!(e.message.as_deref(), Some("packet dropped"));
+        assert_eq!(e.fields["facility"], 20);
+        assert_eq!(e.fields["severity"], 5);
+        assert_eq!(e.fields["procid"], "4711");
+        assert_eq!(e.fields["msgid"], "ID47");
+        assert_eq!(
+            e.fields["structured_data"],
+            r#"[exampleSDID@32473 iut="3" note="a\]b"]"#
+        );
+        assert_eq!(e.ts.timestamp_subsec_millis(), 123);
+    }
+
+    #[test]
+    fn rfc5424_nil_values_are_absent() {
+        let e = parse_message("<11>1 - - - - - - oops", None);
+        assert_eq!(e.level, Some(LogLevel::Error));
+        assert_eq!(e.producer, "unknown/syslog");
+        assert!(!e.fields.contains_key("hostname"));
+        assert_eq!(e.message.as_deref(), Some("oops"));
+    }
+
+    #[test]
+    fn rfc3164_message_is_parsed() {
+        let e = parse_message(
+            "<38>Jan  5 10:00:02 myhost sshd[12345]: Failed password for admin",
+            None,
+        );
+        assert_eq!(e.producer, "myhost/sshd");
+        assert_eq!(e.level, Some(LogLevel::Info));
+        assert_eq!(e.fields["procid"], "12345");
+        assert_eq!(e.message.as_deref(), Some("Failed password for admin"));
+        let local = e.ts.with_timezone(&Local);
+        assert_eq!(local.format("%m-%d %H:%M:%S").to_string(), "01-05 10:00:02");
+    }
+
+    #[test]
+    fn rfc3164_without_hostname_uses_peer() {
+        let peer: IpAddr = "10.0.0.9".parse().unwrap();
+        let e = parse_message("<28>Jan 15 10:00:02 switchd: port 7 down", Some(peer));
+        assert_eq!(e.producer, "10.0.0.9/switchd");
+        assert_eq!(e.level, Some(LogLevel::Warn));
+        assert_eq!(e.fields["peer"], "10.0.0.9");
+    }
+
+    #[test]
+    fn missing_pri_is_kept_verbatim() {
+        let e = parse_message("just some text", None);
+        assert_eq!(e.level, None);
+        assert_eq!(e.message.as_deref(), Some("just some text"));
+    }
+
+    #[tokio::test]
+    async fn frames_mixed_octet_counted_and_newline() {
+        let input: &[u8] = b"11 <14>1 - - -\n<14>plain line\n5 hello";
+        let mut reader = BufReader::new(input);
+        assert_eq!(
+            read_frame(&mut reader).await.unwrap().as_deref(),
+            Some("<14>1 - - -")
+        );
+        assert_eq!(read_frame(&mut reader).await.unwrap().as_deref(), Some(""));
+        assert_eq!(
+            read_frame(&mut reader).await.unwrap().as_deref(),
+            Some("<14>plain line")
+        );
+        assert_eq!(
+            read_frame(&mut reader).await.unwrap().as_deref(),
+            Some("hello")
+        );
+        assert_eq!(read_frame(&mut reader).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn newline_frame_without_newline_is_bounded() {
+        let input = vec![b'x'; MAX_MESSAGE_LEN * 2];
+        let mut reader = BufReader::new(input.as_slice());
+        let err = read_frame(&mut reader).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let mut line = vec![b'x'; MAX_MESSAGE_LEN];
+        line.push(b'\n');
+        let mut reader = BufReader::new(line.as_slice());
+        let frame = read_frame(&mut reader).await.unwrap().unwrap();
+        assert_eq!(frame.len(), MAX_MESSAGE_LEN);
+    }
+
+    #[test]
+    fn bsd_timestamps_are_local_time() {
+        let zone = chrono::FixedOffset::east_opt(2 * 3600).unwrap();
+        let now = zone.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let ts = bsd_timestamp("Jun  1 10:00:00", now).unwrap();
+        assert_eq!(ts.to_rfc3339(), "2024-06-01T08:00:00+00:00");
+    }
+
+    #[test]
+    fn bsd_timestamps_in_the_future_are_from_last_year() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 2, 0, 5, 0).unwrap();
+        let ts = bsd_timestamp("Dec 31 23:59:00", now).unwrap();
+        assert_eq!(ts.to_rfc3339(), "2024-12-31T23:59:00+00:00");
+        let ts = bsd_timestamp("Jan  2 00:04:00", now).unwrap();
+        assert_eq!(ts.year(), 2025);
+    }
+}
@@ -3,14 +3,19 @@
 //! [`App::run`] sets up the terminal, drives the crossterm event loop, and
 //! tears everything down cleanly on exit or panic.
 
+use crate::FeedChannels;
 use crate::{
     event::AppEvent,
     theme::Theme,
     widgets::{
         command_bar::{CommandBar, CommandBarState},
+        feed_health::{FeedPanel, FeedPanelState},
         help::HelpPopup,
         log_stream::{LogStream, LogStreamState},
-        producer_tree::{ProducerTree, ProducerTreeState, TreeNode},
+        producer_tree::{
+            build_producer_tree, producer_path, NodeSelection, ProducerTree, ProducerTreeState,
+            TreeNode,
+        },
         query_bar::{QueryBar, QueryBarState},
         tab_bar::TabBar,
     },
@@ -20,13 +25,25 @@ use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use fml_core::{config::Config, LogEntry};
+use fml_core::{config::Config, FeedHandle, LogEntry, ProducerMeta, ProducerStatus};
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction as LayoutDir, Layout, Rect},
     Frame, Terminal,
 };
-use std::{io, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc::error::TrySendError;
+
+/// Upper bound on entries moved from the feed channel per frame, so a burst
+/// cannot starve input handling.
+const MAX_DRAIN_PER_FRAME: usize = 10_000;
+
+/// How often feed handles are asked for their counters.
+const HEALTH_INTERVAL: Duration = Duration::from_millis(500);
 
 // ---------------------------------------------------------------------------
 // Focus + tab types
@@ -74,6 +91,9 @@ pub struct AppState {
     pub theme: Theme,
     pub config: Config,
     pub show_help: bool,
+    /// The `:feeds` panel is open.
+    pub show_feeds: bool,
+    pub feed_panel: FeedPanelState,
     pub command_bar: CommandBarState,
     pub quit: bool,
 }
@@ -84,6 +104,18 @@ pub struct AppState {
 
 pub struct App {
     state: AppState,
+    /// Live feed channels; `None` when running on static mock data.
+    feeds: Option<FeedChannels>,
+    /// Sequence number for the next live entry.
+    next_seq: u64,
+    /// Latest metadata per producer node id, stamped onto that producer's
+    /// entries as they arrive.
+    producer_meta: HashMap<String, ProducerMeta>,
+    /// Producer node ids stopped because the main tree's selection leaves
+    /// them out.
+    stopped: HashSet<String>,
+    /// When feed health was last polled.
+    health_at: Option<Instant>,
 }
 
 impl App {
@@ -106,10 +138,7 @@ impl App {
                 .collect()
         };
 
-        let children: Vec<TreeNode> = producers
-            .into_iter()
-            .map(|p| TreeNode::new(p.clone(), p))
-            .collect();
+        let children = build_producer_tree(producers.iter().map(String::as_str));
 
         let root = TreeNode::new("__root__", "fml-demo").with_children(children);
 
@@ -118,6 +147,24 @@ impl App {
             ..Default::default()
         };
 
+        Self::with_tree(entries, tree, config, theme)
+    }
+
+    /// Create an App that starts empty and is populated from running feeds.
+    /// Producers are added to the tree as their first line or status arrives.
+    pub fn live(feeds: FeedChannels, config: Config, theme: Theme) -> Self {
+        let mut app = Self::with_tree(Vec::new(), ProducerTreeState::default(), config, theme);
+        app.feeds = Some(feeds);
+        app
+    }
+
+    fn with_tree(
+        entries: Vec<LogEntry>,
+        tree: ProducerTreeState,
+        config: Config,
+        theme: Theme,
+    ) -> Self {
+        let next_seq = entries.iter().map(|e| e.seq).max().unwrap_or(0) + 1;
         let show_timestamps = config.ui.show_timestamps;
         let mut stream = LogStreamState::new(entries);
         stream.show_timestamps = show_timestamps;
@@ -139,11 +186,162 @@ impl App {
             theme,
             config,
             show_help: false,
+            show_feeds: false,
+            feed_panel: FeedPanelState::default(),
             command_bar: CommandBarState::default(),
             quit: false,
         };
 
-        App { state }
+        App {
+            state,
+            feeds: None,
+            next_seq,
+            producer_meta: HashMap::new(),
+            stopped: HashSet::new(),
+            health_at: None,
+        }
+    }
+
+    /// Move pending entries, status updates and metadata from the feed
+    /// channels into the main tab's stream and producer tree.
+    ///
+    /// Each entry is stamped with its producer's current state and metadata
+    /// (`state=CrashLoopBackOff`, `restarts=3`) so they can be searched like
+    /// any other field. Fields the line itself set are left alone.
+    fn drain_feeds(&mut self) {
+        let Some(feeds) = self.feeds.as_mut() else {
+            return;
+        };
+        let main = &mut self.state.tabs[0];
+        let mut arrived = false;
+
+        while let Ok(update) = feeds.updates.try_recv() {
+            arrived = true;
+            tracing::debug!(producer = %update.producer, status = %update.status, "producer status");
+            main.tree
+                .set_status(update.feed_root(), &update.producer, update.status);
+        }
+
+        while let Ok(meta) = feeds.metadata.try_recv() {
+            arrived = true;
+            tracing::debug!(producer = %meta.producer, state = %meta.state, "producer state");
+            main.tree.set_state(
+                meta.feed_root(),
+                &meta.producer,
+                meta.state.clone(),
+                meta.fields.clone(),
+            );
+            let id = producer_path(meta.feed_root(), &meta.producer);
+            self.producer_meta.insert(id, meta);
+        }
+
+        let mut batch = Vec::new();
+        let mut stored: HashMap<String, u64> = HashMap::new();
+        for _ in 0..MAX_DRAIN_PER_FRAME {
+            let Ok(mut entry) = feeds.entries.try_recv() else {
+                break;
+            };
+            arrived = true;
+            entry.seq = self.next_seq;
+            self.next_seq += 1;
+            main.tree
+                .insert_producer(entry.feed_root(), &entry.producer);
+            let id = producer_path(entry.feed_root(), &entry.producer);
+            if let Some(meta) = self.producer_meta.get(&id) {
+                for (key, value) in meta.searchable() {
+                    entry
+                        .fields
+                        .entry(key.to_string())
+                        .or_insert_with(|| value.into());
+                }
+            }
+            *stored.entry(entry.feed_root()).or_default() += 1;
+            batch.push(entry);
+        }
+        main.stream.extend(batch);
+        for (root, lines) in &stored {
+            for handle in &feeds.handles {
+                handle.stored(root, *lines);
+            }
+        }
+
+        if arrived {
+            self.sync_selection();
+        }
+        if self
+            .health_at
+            .is_none_or(|at| at.elapsed() >= HEALTH_INTERVAL)
+        {
+            self.poll_health();
+        }
+    }
+
+    /// Total the lines every feed has lost so each tab can warn about them,
+    /// and refresh the feed health panel.
+    fn poll_health(&mut self) {
+        let Some(feeds) = self.feeds.as_ref() else {
+            return;
+        };
+        let now = Instant::now();
+        self.health_at = Some(now);
+        let health: Vec<_> = feeds
+            .handles
+            .iter()
+            .flat_map(|handle| handle.health())
+            .collect();
+        let dropped = health.iter().map(|health| health.counters.dropped).sum();
+        for tab in &mut self.state.tabs {
+            tab.stream.dropped = dropped;
+        }
+        self.state.feed_panel.refresh(health, now);
+    }
+
+    /// Once anything in the main tree is selected, stop every producer left
+    /// out of the selection and start the rest. With nothing selected every
+    /// producer runs.
+    fn sync_selection(&mut self) {
+        let Some(feeds) = self.feeds.as_ref() else {
+            return;
+        };
+        let leaves = self.state.tabs[0].tree.leaves();
+        let any = leaves
+            .iter()
+            .any(|node| node.selection == NodeSelection::Selected);
+        for node in leaves {
+            let stop = any && node.selection != NodeSelection::Selected;
+            if stop == self.stopped.contains(&node.id) {
+                continue;
+            }
+            tracing::debug!(producer = %node.id, stop, "selection changed");
+            for handle in &feeds.handles {
+                if stop {
+                    handle.stop(&node.id);
+                } else {
+                    handle.start(&node.id);
+                }
+            }
+            if stop {
+                self.stopped.insert(node.id.clone());
+            } else {
+                self.stopped.remove(&node.id);
+            }
+        }
+    }
+
+    /// Send a command to every feed handle, or reopen the command bar to say
+    /// there are none.
+    fn control(&mut self, command: impl Fn(&dyn FeedHandle)) {
+        match self.feeds.as_ref().map(|feeds| &feeds.handles) {
+            Some(handles) if !handles.is_empty() => {
+                for handle in handles {
+                    command(handle.as_ref());
+                }
+            }
+            _ => {
+                self.state.focus = Focus::Command;
+                self.state.command_bar.error = Some("no feeds running".to_string());
+            }
+        }
     }
 
     /// Set up the terminal, run the event loop, and restore the terminal on exit.
@@ -170,6 +368,7 @@ impl App {
         terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     ) -> anyhow::Result<()> {
         loop {
+            self.drain_feeds();
             {
                 let s = &self.state;
                 terminal.draw(|frame| draw(frame, s))?;
@@ -229,6 +428,12 @@ impl App {
             return;
         }
 
+        // The feed panel intercepts all events too.
+        if s.show_feeds {
+            self.handle_feed_panel(event);
+            return;
+        }
+
         // Command mode intercepts all events.
         if s.focus == Focus::Command {
             match event {
@@ -329,8 +534,89 @@ impl App {
             AppEvent::Greed(n) => {
                 s.tabs[s.active_tab].query.greed = n;
             }
+            AppEvent::Replay(command) => {
+                let replay = self.feeds.as_ref().and_then(|feeds| feeds.replay.as_ref());
+                let error = match replay.map(|tx| tx.try_send(command)) {
+                    Some(Ok(())) => None,
+                    Some(Err(TrySendError::Full(_))) => Some("replay is busy"),
+                    Some(Err(TrySendError::Closed(_))) => Some("replay has finished"),
+                    None => Some("no replay feed running"),
+                };
+                if let Some(error) = error {
+                    // Reopen the bar to show why nothing happened.
+                    s.focus = Focus::Command;
+                    s.command_bar.error = Some(error.to_string());
+                }
+            }
+            AppEvent::Pause(path) => self.control(|handle| handle.pause(&path)),
+            AppEvent::Resume(path) => self.control(|handle| handle.resume(&path)),
+            AppEvent::Feeds => {
+                if self.feeds.as_ref().is_some_and(|f| !f.handles.is_empty()) {
+                    tracing::debug!("feed panel opened");
+                    self.poll_health();
+                    self.state.show_feeds = true;
+                } else {
+                    self.control(|_| {});
+                }
+            }
+            AppEvent::Char('p') if s.focus == Focus::Tree => {
+                let tree = &s.tabs[s.active_tab].tree;
+                if let Some(node) = tree.cursor_node() {
+                    let id = node.id.clone();
+                    if node.status == Some(ProducerStatus::Paused) {
+                        self.handle(AppEvent::Resume(id));
+                    } else {
+                        self.handle(AppEvent::Pause(id));
+                    }
+                }
+            }
             AppEvent::NoOp => tracing::debug!("received no-op app event"),
-            other => dispatch_to_focused(s, other),
+            other => {
+                let selecting = s.focus == Focus::Tree && s.active_tab == 0;
+                dispatch_to_focused(s, other);
+                if selecting {
+                    self.sync_selection();
+                }
+            }
+        }
+    }
+}
+
+impl App {
+    /// Keys while the feed panel is open: move, reconnect or stop/start the
+    /// producer under the cursor, or close the panel. Stops made here are
+    /// independent of the tree selection.
+    fn handle_feed_panel(&mut self, event: AppEvent) {
+        let path = self.state.feed_panel.selected().map(|row| {
+            (
+                row.health.path.clone(),
+                row.health.stopped,
+                row.health.reopens,
+            )
+        });
+        match (event, path) {
+            (AppEvent::Escape | AppEvent::Quit | AppEvent::Feeds, _) => {
+                tracing::debug!("feed panel closed");
+                self.state.show_feeds = false;
+            }
+            (AppEvent::Char('r'), Some((path, _, false))) => {
+                tracing::debug!(producer = %path, "reconnect not supported by its feed");
+            }
+            (AppEvent::Char('r'), Some((path, _, true))) => {
+                tracing::debug!(producer = %path, "reconnect requested");
+                self.control(|handle| handle.reconnect(&path));
+                self.poll_health();
+            }
+            (AppEvent::Char('s'), Some((path, stopped, _))) => {
+                tracing::debug!(producer = %path, stop = !stopped, "stop toggled from feed panel");
+                if stopped {
+                    self.control(|handle| handle.start(&path));
+                } else {
+                    self.control(|handle| handle.stop(&path));
+                }
+                self.poll_health();
+            }
+            (event, _) => self.state.feed_panel.handle(&event),
         }
     }
 }
@@ -395,6 +681,10 @@ fn draw(frame: &mut Frame, state: &AppState) {
         vert[2],
     );
 
+    if state.show_feeds {
+        frame.render_widget(FeedPanel::new(&state.feed_panel, &state.theme), area);
+    }
+
     if state.show_help {
         frame.render_widget(HelpPopup::new(&state.theme), area);
     }
@@ -432,3 +722,245 @@ fn install_panic_hook() {
         original(info);
     }));
 }
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Direction;
+    use fml_core::{FeedKind, ProducerState};
+    use tokio::sync::mpsc;
+
+    fn entry(producer: &str, fields: &[(&str, &str)]) -> LogEntry {
+        LogEntry {
+            seq: 0,
+            raw: "line".into(),
+            ts: chrono::Utc::now(),
+            level: None,
+            source: FeedKind::Kubernetes,
+            instance: None,
+            producer: producer.into(),
+            fields: fields
+                .iter()
+                .map(|(k, v)| (k.to_string(), (*v).into()))
+                .collect(),
+            message: Some("line".into()),
+        }
+    }
+
+    #[test]
+    fn entries_carry_their_producers_state() {
+        let (tx, entries) = mpsc::channel(8);
+        let (_utx, updates) = mpsc::channel(8);
+        let (mtx, metadata) = mpsc::channel(8);
+        let feeds = FeedChannels {
+            entries,
+            updates,
+            metadata,
+            replay: None,
+            handles: Vec::new(),
+        };
+        let mut app = App::live(feeds, Config::defaults(), Theme::load_default());
+
+        mtx.try_send(ProducerMeta {
+            source: FeedKind::Kubernetes,
+            instance: None,
+            producer: "shop/api-1".into(),
+            state: ProducerState::Waiting {
+                reason: "CrashLoopBackOff".into(),
+            },
+            fields: [("restarts".to_string(), "4".to_string())].into(),
+        })
+        .unwrap();
+        tx.try_send(entry("shop/api-1", &[("state", "ready")]))
+            .unwrap();
+        tx.try_send(entry("shop/web-1", &[])).unwrap();
+        app.drain_feeds();
+
+        let stamped = &app.state.tabs[0].stream.entries[0];
+        // The line's own field wins over the producer's state.
+        assert_eq!(stamped.fields["state"], "ready");
+        assert_eq!(stamped.fields["restarts"], "4");
+        assert!(app.state.tabs[0].stream.entries[1].fields.is_empty());
+
+        let pod = &app.state.tabs[0].tree.nodes[0].children[0].children[0];
+        assert_eq!(pod.state.as_ref().unwrap().to_string(), "CrashLoopBackOff");
+    }
+
+    #[test]
+    fn replay_commands_reach_the_replay_feed() {
+        let (_tx, entries) = mpsc::channel(8);
+        let (_utx, updates) = mpsc::channel(8);
+        let (_mtx, metadata) = mpsc::channel(8);
+        let (rtx, mut replay) = mpsc::channel(8);
+        let feeds = FeedChannels {
+            entries,
+            updates,
+            metadata,
+            replay: Some(rtx),
+            handles: Vec::new(),
+        };
+        let mut app = App::live(feeds, Config::defaults(), Theme::load_default());
+        app.handle(AppEvent::Replay(fml_core::ReplayCommand::Pause));
+        assert_eq!(replay.try_recv(), Ok(fml_core::ReplayCommand::Pause));
+        assert_ne!(app.state.focus, Focus::Command);
+
+        drop(replay);
+        app.handle(AppEvent::Replay(fml_core::ReplayCommand::Resume));
+        assert_eq!(app.state.focus, Focus::Command);
+        assert_eq!(
+            app.state.command_bar.error.as_deref(),
+            Some("replay has finished")
+        );
+    }
+
+    /// Records the commands it is sent, as `verb path`, and reports one
+    /// producer that has lost `dropped` lines.
+    #[derive(Default)]
+    struct Recorder(std::sync::Mutex<Vec<String>>, u64);
+
+    impl Recorder {
+        fn take(&self) -> Vec<String> {
+            std::mem::take(&mut self.0.lock().unwrap())
+        }
+    }
+
+    impl FeedHandle for Recorder {
+        fn pause(&self, path: &str) {
+            self.0.lock().unwrap().push(format!("pause {path}"));
+        }
+        fn resume(&self, path: &str) {
+            self.0.lock().unwrap().push(format!("resume {path}"));
+        }
+        fn stop(&self, path: &str) {
+            self.0.lock().unwrap().push(format!("stop {path}"));
+        }
+        fn start(&self, path: &str) {
+            self.0.lock().unwrap().push(format!("start {path}"));
+        }
+        fn reconnect(&self, path: &str) {
+            self.0.lock().unwrap().push(format!("reconnect {path}"));
+        }
+        fn health(&self) -> Vec<fml_core::ProducerHealth> {
+            vec![fml_core::ProducerHealth {
+                path: "docker/api".into(),
+                status: None,
+                paused: false,
+                stopped: false,
+                closes: true,
+                reopens: true,
+                counters: fml_core::ProducerCounters {
+                    dropped: self.1,
+                    ..Default::default()
+                },
+                lag: None,
+                last_line: None,
+                last_error: None,
+            }]
+        }
+    }
+
+    #[test]
+    fn selection_and_pause_drive_the_feed_handles() {
+        let (tx, entries) = mpsc::channel(8);
+        let (utx, updates) = mpsc::channel(8);
+        let (_mtx, metadata) = mpsc::channel(8);
+        let recorder = std::sync::Arc::new(Recorder::default());
+        let feeds = FeedChannels {
+            entries,
+            updates,
+            metadata,
+            replay: None,
+            handles: vec![recorder.clone()],
+        };
+        let mut app = App::live(feeds, Config::defaults(), Theme::load_default());
+        tx.try_send(entry("shop/api-1", &[])).unwrap();
+        tx.try_send(entry("shop/web-1", &[])).unwrap();
+        app.drain_feeds();
+        assert!(recorder.take().is_empty(), "nothing selected, all run");
+
+        // Cursor: kubernetes, shop, api-1.
+        app.handle(AppEvent::TreeNav(Direction::Down));
+        app.handle(AppEvent::TreeNav(Direction::Down));
+        app.handle(AppEvent::Char(' '));
+        assert_eq!(recorder.take(), ["stop kubernetes/shop/web-1"]);
+        app.handle(AppEvent::Char(' '));
+        assert_eq!(recorder.take(), ["start kubernetes/shop/web-1"]);
+
+        app.handle(AppEvent::Char('p'));
+        assert_eq!(recorder.take(), ["pause kubernetes/shop/api-1"]);
+        utx.try_send(fml_core::ProducerUpdate {
+            source: FeedKind::Kubernetes,
+            instance: None,
+            producer: "shop/api-1".into(),
+            status: ProducerStatus::Paused,
+        })
+        .unwrap();
+        app.drain_feeds();
+        app.handle(AppEvent::Char('p'));
+        assert_eq!(recorder.take(), ["resume kubernetes/shop/api-1"]);
+    }
+
+    #[test]
+    fn lost_lines_are_counted_from_every_feed() {
+        let (_tx, entries) = mpsc::channel(8);
+        let (_utx, updates) = mpsc::channel(8);
+        let (_mtx, metadata) = mpsc::channel(8);
+        let feeds = FeedChannels {
+            entries,
+            updates,
+            metadata,
+            replay: None,
+            handles: vec![
+                std::sync::Arc::new(Recorder(Default::default(), 3)),
+                std::sync::Arc::new(Recorder(Default::default(), 4)),
+            ],
+        };
+        let mut app = App::live(feeds, Config::defaults(), Theme::load_default());
+        app.drain_feeds();
+        assert_eq!(app.state.tabs[0].stream.dropped, 7);
+    }
+
+    #[test]
+    fn feed_panel_reconnects_and_stops_producers() {
+        let (_tx, entries) = mpsc::channel(8);
+        let (_utx, updates) = mpsc::channel(8);
+        let (_mtx, metadata) = mpsc::channel(8);
+        let recorder = std::sync::Arc::new(Recorder::default());
+        let feeds = FeedChannels {
+            entries,
+            updates,
+            metadata,
+            replay: None,
+            handles: vec![recorder.clone()],
+        };
+        let mut app = App::live(feeds, Config::defaults(), Theme::load_default());
+        app.handle(AppEvent::Feeds);
+        assert!(app.state.show_feeds);
+        assert_eq!(app.state.feed_panel.rows.len(), 1);
+
+        app.handle(AppEvent::Char('r'));
+        app.handle(AppEvent::Char('s'));
+        // Tree keys are not passed through while the panel is open.
+        app.handle(AppEvent::Char('p'));
+        assert_eq!(recorder.take(), ["reconnect docker/api", "stop docker/api"]);
+        assert!(app.stopped.is_empty());
+
+        app.handle(AppEvent::Escape);
+        assert!(!app.state.show_feeds);
+    }
+
+    #[test]
+    fn feed_panel_needs_a_feed() {
+        let mut app = App::new(Vec::new(), Config::defaults(), Theme::load_default());
+        app.handle(AppEvent::Feeds);
+        assert!(!app.state.show_feeds);
+        assert_eq!(
+            app.state.command_bar.error.as_deref(),
+            Some("no feeds running")
+        );
+    }
+}
@@ -41,6 +41,7 @@
 //!   special bindings
 
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use fml_core::ReplayCommand;
 
 /// Cardinal direction for producer tree and log-stream navigation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -96,6 +97,14 @@ pub enum AppEvent {
     Timestamps,
     /// Set greed value directly
     Greed(u8),
+    /// Control the replay feed
+    Replay(ReplayCommand),
+    /// Hold back lines from the producers under a tree path (`""` for all)
+    Pause(String),
+    /// Release producers paused under a tree path (`""` for all)
+    Resume(String),
+    /// Toggle the feed health panel
+    Feeds,
     /// Emitted when no handling is required
     NoOp,
 }
@@ -134,6 +143,27 @@ impl AppEvent {
                 Ok(_) => Err("greed must be 0-10".to_string()),
                 Err(_) => Err("usage: greed <0-10>".to_string()),
             },
+            "replay" => {
+                let (action, arg) = rest
+                    .split_once(char::is_whitespace)
+                    .map(|(a, r)| (a, r.trim()))
+                    .unwrap_or((rest, ""));
+                let command = match (action, arg) {
+                    ("pause", "") => ReplayCommand::Pause,
+                    ("resume", "") => ReplayCommand::Resume,
+                    ("speed", speed) if !speed.is_empty() => ReplayCommand::Speed(speed.parse()?),
+                    ("jump", time) if !time.is_empty() => ReplayCommand::Jump(time.parse()?),
+                    _ => {
+                        return Err(
+                            "usage: replay <pause|resume|speed <n>x|jump <time>>".to_string()
+                        )
+                    }
+                };
+                Ok(AppEvent::Replay(command))
+            }
+            "pause" => Ok(AppEvent::Pause(rest.to_string())),
+            "resume" => Ok(AppEvent::Resume(rest.to_string())),
+            "feeds" => Ok(AppEvent::Feeds),
             other => Err(format!("unknown command: {other}")),
         }
     }
@@ -572,4 +602,53 @@ mod tests {
         let err = AppEvent::parse_str("frobnicate").unwrap_err();
         assert!(err.contains("frobnicate"));
     }
+
+    #[test]
+    fn parse_str_replay_commands() {
+        assert_eq!(
+            AppEvent::parse_str("replay pause"),
+            Ok(AppEvent::Replay(ReplayCommand::Pause))
+        );
+        assert_eq!(
+            AppEvent::parse_str("replay resume"),
+            Ok(AppEvent::Replay(ReplayCommand::Resume))
+        );
+        assert_eq!(
+            AppEvent::parse_str("replay speed 10x"),
+            Ok(AppEvent::Replay(ReplayCommand::Speed(
+                "10".parse().unwrap()
+            )))
+        );
+        assert_eq!(
+            AppEvent::parse_str("replay jump 10:32:05"),
+            Ok(AppEvent::Replay(ReplayCommand::Jump(
+                "10:32:05".parse().unwrap()
+            )))
+        );
+    }
+
+    #[test]
+    fn parse_str_replay_bad_args_are_err() {
+        assert!(AppEvent::parse_str("replay").unwrap_err().contains("usage"));
+        assert!(AppEvent::parse_str("replay speed").is_err());
+        assert!(AppEvent::parse_str("replay speed fast").is_err());
+        assert!(AppEvent::parse_str("replay jump soon").is_err());
+    }
+
+    #[test]
+    fn parse_str_pause_and_resume_take_a_path() {
+        assert_eq!(
+            AppEvent::parse_str("pause docker/shop"),
+            Ok(AppEvent::Pause("docker/shop".to_string()))
+        );
+        assert_eq!(
+            AppEvent::parse_str("resume"),
+            Ok(AppEvent::Resume(String::new()))
+        );
+    }
+
+    #[test]
+    fn parse_str_feeds() {
+        assert_eq!(AppEvent::parse_str("feeds"), Ok(AppEvent::Feeds));
+    }
 }
@@ -7,6 +7,24 @@ pub mod widgets;
 
 pub use app::App;
 
+use fml_core::{FeedHandle, LogEntry, ProducerMeta, ProducerUpdate, ReplayCommand};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// Receiving ends of the channels running feeds write to. The binary owns the
+/// feed tasks; the TUI only drains these between frames.
+pub struct FeedChannels {
+    pub entries: mpsc::Receiver<LogEntry>,
+    pub updates: mpsc::Receiver<ProducerUpdate>,
+    pub metadata: mpsc::Receiver<ProducerMeta>,
+    /// Control channel of the replay feed, when one runs; `:replay` commands
+    /// are sent here.
+    pub replay: Option<mpsc::Sender<ReplayCommand>>,
+    /// One handle per running feed. Pause, resume and tree selection are
+    /// sent to every handle; each acts on the producers it owns.
+    pub handles: Vec<Arc<dyn FeedHandle>>,
+}
+
 /// Start the TUI with hardcoded mock data (Phase 2 entry point).
 pub fn run() -> anyhow::Result<()> {
     let config =
@@ -15,6 +33,12 @@ pub fn run() -> anyhow::Result<()> {
     App::new(mock_entries(), config, theme).run()
 }
 
+/// Start the TUI fed by live feeds instead of mock data.
+pub fn run_live(config: fml_core::config::Config, feeds: FeedChannels) -> anyhow::Result<()> {
+    let theme = theme::Theme::load_default();
+    App::live(feeds, config, theme).run()
+}
+
 // ---------------------------------------------------------------------------
 // Mock data — replaced by real feeds in Phase 4
 // ---------------------------------------------------------------------------
@@ -207,6 +231,7 @@ fn mock_entries() -> Vec<fml_core::LogEntry> {
                 ts,
                 level: Some(*level),
                 source: FeedKind::Docker,
+                instance: None,
                 producer: producer.to_string(),
                 fields: Default::default(),
                 message: Some(message),
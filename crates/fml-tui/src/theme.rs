@@ -12,7 +12,7 @@
 //! of the order in which producers appear.
 
 use config::{Config, File, FileFormat};
-use fml_core::LogLevel;
+use fml_core::{LogLevel, StateSeverity};
 use ratatui::style::{Color, Modifier, Style};
 use serde::Deserialize;
 
@@ -88,6 +88,13 @@ struct RawSearch {
     highlight: RawStyle,
 }
 
+#[derive(Debug, Deserialize)]
+struct RawStates {
+    ok: RawStyle,
+    warn: RawStyle,
+    error: RawStyle,
+}
+
 #[derive(Debug, Deserialize)]
 struct RawProducers {
     palette: Vec<String>,
@@ -98,6 +105,7 @@ struct RawTheme {
     levels: RawLevels,
     borders: RawBorders,
     search: RawSearch,
+    states: RawStates,
     producers: RawProducers,
 }
 
@@ -130,6 +138,11 @@ pub struct Theme {
     /// Inline highlight applied to matched search spans.
     pub search_highlight: Style,
 
+    /// Styles for producer states in the tree, by severity.
+    pub state_ok: Style,
+    pub state_warn: Style,
+    pub state_error: Style,
+
     /// Ordered colour palette used for producer colour cycling.
     producer_palette: Vec<Color>,
 }
@@ -178,6 +191,9 @@ impl Theme {
             border_command_bar: raw.borders.command_bar.into_style(),
             border_unfocused: raw.borders.unfocused.into_style(),
             search_highlight: raw.search.highlight.into_style(),
+            state_ok: raw.states.ok.into_style(),
+            state_warn: raw.states.warn.into_style(),
+            state_error: raw.states.error.into_style(),
             producer_palette: raw
                 .producers
                 .palette
@@ -201,6 +217,15 @@ impl Theme {
         }
     }
 
+    /// Return the [`Style`] for a producer state of the given severity.
+    pub fn state_style(&self, severity: StateSeverity) -> Style {
+        match severity {
+            StateSeverity::Ok => self.state_ok,
+            StateSeverity::Warn => self.state_warn,
+            StateSeverity::Error => self.state_error,
+        }
+    }
+
     /// Return a stable [`Style`] for a producer name.
     ///
     /// The colour is determined by hashing the name and taking the result
@@ -280,6 +305,7 @@ mod tests {
         assert_ne!(theme.level_error, Style::default());
         assert_ne!(theme.border_focused, Style::default());
         assert_ne!(theme.search_highlight, Style::default());
+        assert_ne!(theme.state_error, Style::default());
         assert!(!theme.producer_palette.is_empty());
     }
 
@@ -289,6 +315,7 @@ mod tests {
         assert_ne!(theme.level_error, Style::default());
         assert_ne!(theme.border_focused, Style::default());
         assert_ne!(theme.search_highlight, Style::default());
+        assert_ne!(theme.state_error, Style::default());
         assert!(!theme.producer_palette.is_empty());
     }
 
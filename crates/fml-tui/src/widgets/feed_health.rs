@@ -0,0 +1,300 @@
+//! Feed health panel — centred overlay listing every producer the running
+//! feeds have reported on.
+//!
+//! Open with `:feeds`; close with `Escape` or `q`. `↑`/`k` and `↓`/`j` move
+//! the cursor, `r` reconnects the producer under it and `s` stops or starts
+//! it. Each row lists those keys, greyed where its feed cannot act on them:
+//! `r` does nothing where the feed cannot reopen the producer's stream, and
+//! `s` only discards the lines of a producer with no stream of its own to
+//! close (push feeds, stdin).
+//!
+//! Each row shows the producer's connection state, how long ago its last
+//! line arrived, its current lines per second, reconnects, lines dropped and
+//! the last error its feed reported.
+
+use crate::event::{AppEvent, Direction};
+use crate::theme::Theme;
+use chrono::{DateTime, Utc};
+use fml_core::{ProducerHealth, ProducerStatus};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Cell, Clear, Row, StatefulWidget, Table, TableState, Widget},
+};
+use std::collections::HashMap;
+use std::time::Instant;
+
+// ---------------------------------------------------------------------------
+// State
+// ---------------------------------------------------------------------------
+
+/// One producer as the panel shows it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedRow {
+    pub health: ProducerHealth,
+    /// Lines per second since the previous refresh.
+    pub rate: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct FeedPanelState {
+    /// Producers sorted by tree id.
+    pub rows: Vec<FeedRow>,
+    pub cursor: usize,
+    /// Line count and time of the previous refresh, per producer.
+    sampled: HashMap<String, (u64, Instant)>,
+}
+
+impl FeedPanelState {
+    /// Replace the rows with `health`, working out each producer's line rate
+    /// from how far its counter moved since the last refresh.
+    pub fn refresh(&mut self, mut health: Vec<ProducerHealth>, now: Instant) {
+        health.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut sampled = HashMap::with_capacity(health.len());
+        self.rows = health
+            .into_iter()
+            .map(|health| {
+                let lines = health.counters.lines;
+                let rate = match self.sampled.get(&health.path) {
+                    Some(&(before, at)) if now > at => {
+                        lines.saturating_sub(before) as f64 / (now - at).as_secs_f64()
+                    }
+                    _ => 0.0,
+                };
+                sampled.insert(health.path.clone(), (lines, now));
+                FeedRow { health, rate }
+            })
+            .collect();
+        self.sampled = sampled;
+        self.cursor = self.cursor.min(self.rows.len().saturating_sub(1));
+    }
+
+    /// The row under the cursor.
+    pub fn selected(&self) -> Option<&FeedRow> {
+        self.rows.get(self.cursor)
+    }
+
+    pub fn handle(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::TreeNav(Direction::Up) => self.cursor = self.cursor.saturating_sub(1),
+            AppEvent::TreeNav(Direction::Down) if self.cursor + 1 < self.rows.len() => {
+                self.cursor += 1;
+            }
+            _ => {}
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Widget
+// ---------------------------------------------------------------------------
+
+pub struct FeedPanel<'a> {
+    state: &'a FeedPanelState,
+    theme: &'a Theme,
+}
+
+impl<'a> FeedPanel<'a> {
+    pub fn new(state: &'a FeedPanelState, theme: &'a Theme) -> Self {
+        Self { state, theme }
+    }
+
+    fn row(&self, row: &FeedRow, now: DateTime<Utc>) -> Row<'a> {
+        let health = &row.health;
+        let (state, style) = if health.stopped {
+            ("stopped".to_string(), self.theme.state_warn)
+        } else if health.paused {
+            ("paused".to_string(), self.theme.state_warn)
+        } else {
+            match &health.status {
+                None => ("-".to_string(), Style::default()),
+                Some(status @ ProducerStatus::Streaming) => {
+                    (status.to_string(), self.theme.state_ok)
+                }
+                Some(ProducerStatus::Error { .. }) => ("error".to_string(), self.theme.state_error),
+                Some(status) => (status.to_string(), self.theme.state_warn),
+            }
+        };
+        let dropped = match health.counters.dropped {
+            0 => Cell::from("0"),
+            n => Cell::from(n.to_string()).style(self.theme.state_error),
+        };
+        let key = |key: &'static str, works: bool| {
+            if works {
+                Span::raw(key)
+            } else {
+                Span::styled(key, Style::default().add_modifier(Modifier::DIM))
+            }
+        };
+        let keys = Line::from(vec![
+            key("r", health.reopens),
+            Span::raw(" "),
+            key("s", health.closes),
+        ]);
+        Row::new(vec![
+            Cell::from(health.path.clone()),
+            Cell::from(keys),
+            Cell::from(state).style(style),
+            Cell::from(age(health.last_line, now)),
+            Cell::from(format!("{:.1}", row.rate)),
+            Cell::from(health.counters.reconnects.to_string()),
+            dropped,
+            Cell::from(health.last_error.clone().unwrap_or_default()).style(self.theme.state_error),
+        ])
+    }
+}
+
+impl Widget for FeedPanel<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let height = self.state.rows.len().max(1) as u16 + 3;
+        let popup = centered_rect(area.width.saturating_sub(8).max(60), height, area);
+        Clear.render(popup, buf);
+
+        let block = Block::bordered()
+            .title(" feeds — r reconnect · s stop/start · Esc close ")
+            .border_style(Style::default().add_modifier(Modifier::BOLD));
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        if self.state.rows.is_empty() {
+            Line::from("  no producers yet").render(inner, buf);
+            return;
+        }
+
+        let now = Utc::now();
+        let rows: Vec<Row> = self
+            .state
+            .rows
+            .iter()
+            .map(|row| self.row(row, now))
+            .collect();
+        let header = Row::new([
+            "producer",
+            "keys",
+            "state",
+            "last line",
+            "lines/s",
+            "reconn",
+            "dropped",
+            "last error",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+        let widths = [
+            Constraint::Fill(2),
+            Constraint::Length(4),
+            Constraint::Length(12),
+            Constraint::Length(10),
+            Constraint::Length(8),
+            Constraint::Length(6),
+            Constraint::Length(8),
+            Constraint::Fill(2),
+        ];
+        let table = Table::new(rows, widths)
+            .header(header)
+            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+        let mut table_state = TableState::default().with_selected(Some(self.state.cursor));
+        StatefulWidget::render(table, inner, buf, &mut table_state);
+    }
+}
+
+/// How long before `now` the last line arrived, as `4s ago` / `3m ago`.
+fn age(last: Option<DateTime<Utc>>, now: DateTime<Utc>) -> String {
+    let Some(last) = last else {
+        return "never".to_string();
+    };
+    let secs = (now - last).num_seconds().max(0);
+    match secs {
+        0..60 => format!("{secs}s ago"),
+        60..3600 => format!("{}m ago", secs / 60),
+        _ => format!("{}h ago", secs / 3600),
+    }
+}
+
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let x = area.x + area.width.saturating_sub(width) / 2;
+    let y = area.y + area.height.saturating_sub(height) / 2;
+    Rect {
+        x,
+        y,
+        width: width.min(area.width),
+        height: height.min(area.height),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn health(path: &str, lines: u64) -> ProducerHealth {
+        ProducerHealth {
+            path: path.into(),
+            status: Some(ProducerStatus::Streaming),
+            paused: false,
+            stopped: false,
+            closes: true,
+            reopens: true,
+            counters: fml_core::ProducerCounters {
+                lines,
+                ..Default::default()
+            },
+            lag: None,
+            last_line: None,
+            last_error: None,
+        }
+    }
+
+    #[test]
+    fn rates_come_from_the_line_counter() {
+        let mut panel = FeedPanelState::default();
+        let start = Instant::now();
+        panel.refresh(
+            vec![health("docker/web", 5), health("docker/api", 10)],
+            start,
+        );
+        assert_eq!(panel.rows[0].health.path, "docker/api");
+        assert_eq!(panel.rows[0].rate, 0.0);
+
+        let later = start + Duration::from_secs(2);
+        panel.refresh(
+            vec![health("docker/api", 30), health("docker/web", 5)],
+            later,
+        );
+        assert_eq!(panel.rows[0].rate, 10.0);
+        assert_eq!(panel.rows[1].rate, 0.0);
+    }
+
+    #[test]
+    fn cursor_stays_on_a_row() {
+        let mut panel = FeedPanelState::default();
+        let now = Instant::now();
+        panel.refresh(vec![health("a", 0), health("b", 0)], now);
+        panel.handle(&AppEvent::TreeNav(Direction::Down));
+        panel.handle(&AppEvent::TreeNav(Direction::Down));
+        assert_eq!(panel.selected().unwrap().health.path, "b");
+
+        panel.refresh(vec![health("a", 0)], now);
+        assert_eq!(panel.selected().unwrap().health.path, "a");
+        panel.handle(&AppEvent::TreeNav(Direction::Up));
+        assert_eq!(panel.cursor, 0);
+    }
+
+    #[test]
+    fn ages_read_in_the_largest_unit() {
+        let now = Utc::now();
+        assert_eq!(age(None, now), "never");
+        assert_eq!(age(Some(now - chrono::Duration::seconds(4)), now), "4s ago");
+        assert_eq!(
+            age(Some(now - chrono::Duration::seconds(200)), now),
+            "3m ago"
+        );
+        assert_eq!(age(Some(now + chrono::Duration::seconds(1)), now), "0s ago");
+    }
+}
@@ -42,6 +42,7 @@ impl Widget for HelpPopup<'_> {
             ("← h  /  → l", "Collapse / expand tree node"),
             ("Space", "Toggle producer selection"),
             ("Enter", "Expand/collapse tree node"),
+            ("p", "Pause / resume the focused producer"),
             ("PageUp  /  Ctrl+u", "Scroll log stream up"),
             ("PageDown / Ctrl+d", "Scroll log stream down"),
             ("G", "Jump to log tail and resume"),
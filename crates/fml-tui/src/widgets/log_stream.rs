@@ -15,6 +15,14 @@
 //! `scroll_offset` = number of entries hidden at the bottom (0 = live tail).
 //! `cursor` = absolute index into `entries` (0 = oldest). The cursor is always
 //! kept within the visible window; moving it past the edge auto-scrolls.
+//!
+//! # Ordering and retention
+//!
+//! `entries` is kept in timestamp order. Each frame's arrivals are added in
+//! one [`LogStreamState::extend`]: in-order lines are appended, and late ones
+//! are sorted and merged in a single pass, so a large backfill costs one merge
+//! rather than an insert per line. Only the newest [`MAX_ENTRIES`] are kept;
+//! the oldest are trimmed once the buffer grows [`TRIM_SLACK`] past that.
 
 use std::cell::Cell;
 
@@ -33,6 +41,11 @@ use ratatui::{
 use tracing;
 
 const PAGE_STEP: usize = 10;
+/// Entries kept in the stream; older ones are dropped from the top.
+pub const MAX_ENTRIES: usize = 100_000;
+/// How far past [`MAX_ENTRIES`] the stream may grow before it is trimmed, so
+/// trimming happens once per batch of this size instead of on every frame.
+pub const TRIM_SLACK: usize = MAX_ENTRIES / 10;
 
 // ---------------------------------------------------------------------------
 // State
@@ -50,6 +63,9 @@ pub struct LogStreamState {
     pub buffered_new: usize,
     /// Whether timestamps are shown on each log line.
     pub show_timestamps: bool,
+    /// Lines the feeds reported lost before reaching the stream; shown as a
+    /// warning in the title once non-zero.
+    pub dropped: u64,
     /// Cached from the last render so `handle()` can do cursor-aware scrolling.
     last_height: Cell<usize>,
 }
@@ -64,10 +80,93 @@ impl LogStreamState {
             paused: false,
             buffered_new: 0,
             show_timestamps: true,
+            dropped: 0,
             last_height: Cell::new(40),
         }
     }
 
+    /// Add a newly ingested entry in timestamp order; see [`Self::extend`].
+    pub fn push(&mut self, entry: LogEntry) {
+        self.extend(std::iter::once(entry));
+    }
+
+    /// Add newly ingested entries in timestamp order. Entries usually arrive
+    /// in order and are appended; late ones (backfill, Kubernetes events, a
+    /// previous container's log) are slotted in after every entry stamped at
+    /// or before them, so feeds interleave by time. Late entries are merged in
+    /// one pass per call, so callers should hand over a whole batch at once.
+    ///
+    /// While paused the view stays put: entries landing below it are counted
+    /// in `buffered_new`. Otherwise the cursor follows the tail.
+    pub fn extend(&mut self, batch: impl IntoIterator<Item = LogEntry>) {
+        let old_len = self.entries.len();
+        let mut late = Vec::new();
+        for entry in batch {
+            match self.entries.last() {
+                Some(last) if entry.ts < last.ts => late.push(entry),
+                _ => self.entries.push(entry),
+            }
+        }
+        let appended = self.entries.len() - old_len;
+        // Late entries slot in before the first existing entry stamped after
+        // them; count those landing above the cursor and above the view's
+        // bottom line so a paused view can be kept in place.
+        let end = old_len.saturating_sub(self.scroll_offset);
+        let bottom = end.checked_sub(1).map(|i| self.entries[i].ts);
+        let (above_cursor, above_view) = match (self.entries.get(self.cursor), bottom) {
+            (Some(cursor), Some(bottom)) => (
+                late.iter().filter(|e| e.ts < cursor.ts).count(),
+                late.iter().filter(|e| e.ts < bottom).count(),
+            ),
+            _ => (0, 0),
+        };
+        let added = appended + late.len();
+        if !late.is_empty() {
+            self.merge(late);
+        }
+
+        if !self.paused {
+            self.cursor = self.entries.len().saturating_sub(1);
+        } else {
+            let below = added - above_view;
+            self.buffered_new += below;
+            self.scroll_offset += below;
+            self.cursor += above_cursor;
+        }
+        self.trim();
+    }
+
+    /// Merge `late` into `entries`, each after every entry stamped at or
+    /// before it.
+    fn merge(&mut self, mut late: Vec<LogEntry>) {
+        late.sort_by_key(|e| e.ts);
+        let existing = std::mem::take(&mut self.entries);
+        let mut merged = Vec::with_capacity(existing.len() + late.len());
+        let mut late = late.into_iter().peekable();
+        for entry in existing {
+            while let Some(next) = late.next_if(|l| l.ts < entry.ts) {
+                merged.push(next);
+            }
+            merged.push(entry);
+        }
+        merged.extend(late);
+        self.entries = merged;
+    }
+
+    /// Drop the oldest entries once the stream is [`TRIM_SLACK`] past
+    /// [`MAX_ENTRIES`].
+    fn trim(&mut self) {
+        if self.entries.len() <= MAX_ENTRIES + TRIM_SLACK {
+            return;
+        }
+        let excess = self.entries.len() - MAX_ENTRIES;
+        self.entries.drain(..excess);
+        self.cursor = self.cursor.saturating_sub(excess);
+        self.scroll_offset = self.scroll_offset.min(self.entries.len());
+        self.buffered_new = self.buffered_new.min(self.scroll_offset);
+        tracing::debug!(dropped = excess, "stream: trimmed oldest entries");
+    }
+
     fn height(&self) -> usize {
         self.last_height.get().max(1)
     }
@@ -201,7 +300,16 @@ impl Widget for LogStream<'_> {
             self.theme.border_unfocused
         };
 
-        let block = Block::bordered().title("Logs").border_style(border_style);
+        let mut title = vec![Span::raw("Logs")];
+        if self.state.dropped > 0 {
+            title.push(Span::styled(
+                format!(" ⚠ {} lines dropped ", self.state.dropped),
+                self.theme.state_error.add_modifier(Modifier::BOLD),
+            ));
+        }
+        let block = Block::bordered()
+            .title(Line::from(title))
+            .border_style(border_style);
         let inner = block.inner(area);
         block.render(area, buf);
 
@@ -323,3 +431,95 @@ fn render_entry(entry: &LogEntry, show_ts: bool, theme: &Theme) -> Line<'static>
 
     Line::from(spans)
 }
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use fml_core::FeedKind;
+
+    fn at(secs: i64, message: &str) -> LogEntry {
+        LogEntry {
+            seq: 0,
+            raw: message.to_string(),
+            ts: Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap(),
+            level: None,
+            source: FeedKind::Kubernetes,
+            instance: None,
+            producer: "shop/api-1".to_string(),
+            fields: Default::default(),
+            message: Some(message.to_string()),
+        }
+    }
+
+    fn messages(state: &LogStreamState) -> Vec<&str> {
+        state.entries.iter().map(|e| e.raw.as_str()).collect()
+    }
+
+    #[test]
+    fn late_entries_interleave_by_timestamp() {
+        let mut state = LogStreamState::new(Vec::new());
+        state.push(at(1, "a"));
+        state.push(at(3, "c"));
+        state.push(at(2, "b"));
+        state.push(at(3, "c2"));
+        state.push(at(0, "first"));
+        assert_eq!(messages(&state), ["first", "a", "b", "c", "c2"]);
+        assert_eq!(state.cursor, 4);
+    }
+
+    #[test]
+    fn paused_view_stays_on_the_same_entries() {
+        let mut state = LogStreamState::new((0..5).map(|i| at(i * 10, "x")).collect());
+        state.paused = true;
+        state.scroll_offset = 2;
+        state.cursor = 2;
+
+        // Lands below the view: buffered.
+        state.push(at(35, "late below"));
+        assert_eq!((state.scroll_offset, state.buffered_new), (3, 1));
+        // Lands above the cursor: cursor moves with its entry.
+        state.push(at(5, "late above"));
+        assert_eq!(state.entries[state.cursor].ts, at(20, "").ts);
+        assert_eq!((state.scroll_offset, state.buffered_new), (3, 1));
+    }
+
+    #[test]
+    fn late_batch_merges_in_one_pass() {
+        let mut state = LogStreamState::new(vec![at(10, "b"), at(20, "d")]);
+        state.extend([
+            at(30, "e"),
+            at(15, "c"),
+            at(0, "a"),
+            at(20, "d2"),
+            at(40, "f"),
+        ]);
+        assert_eq!(messages(&state), ["a", "b", "c", "d", "d2", "e", "f"]);
+        assert_eq!(state.cursor, 6);
+    }
+
+    #[test]
+    fn paused_batch_keeps_the_view() {
+        let mut state = LogStreamState::new((0..5).map(|i| at(i * 10, "x")).collect());
+        state.paused = true;
+        state.scroll_offset = 2;
+        state.cursor = 2;
+
+        state.extend([at(35, "late below"), at(5, "late above"), at(50, "new")]);
+        assert_eq!(state.entries[state.cursor].ts, at(20, "").ts);
+        assert_eq!((state.scroll_offset, state.buffered_new), (4, 2));
+    }
+
+    #[test]
+    fn oldest_entries_are_trimmed() {
+        let mut state = LogStreamState::new(Vec::new());
+        state.extend((0..(MAX_ENTRIES + TRIM_SLACK + 1) as i64).map(|i| at(i, "x")));
+        assert_eq!(state.entries.len(), MAX_ENTRIES);
+        assert_eq!(state.entries[0].ts, at(TRIM_SLACK as i64 + 1, "").ts);
+        assert_eq!(state.cursor, MAX_ENTRIES - 1);
+    }
+}
@@ -1,6 +1,7 @@
 //! Ratatui widgets for the fml TUI.
 
 pub mod command_bar;
+pub mod feed_health;
 pub mod help;
 pub mod log_stream;
 pub mod producer_tree;
@@ -4,15 +4,22 @@
 //! - `↑`/`k` and `↓`/`j` move the cursor up and down the visible list.
 //! - `→`/`l` or `Enter` expands the focused node; `←`/`h` collapses it.
 //! - `Space` toggles the selection state of the focused node.
+//!
+//! Each node shows the feed's stream status and, when the feed reports one,
+//! the [`ProducerState`] of the pod, container or file behind it, coloured by
+//! the theme's `[states]` styles.
 
 use crate::event::{AppEvent, Direction};
+use fml_core::{ProducerState, ProducerStatus};
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
     style::{Modifier, Style},
-    text::Line,
+    text::{Line, Span},
     widgets::{Block, List, ListItem, ListState, StatefulWidget, Widget},
 };
+use std::collections::BTreeMap;
+use std::fmt;
 use tracing;
 
 // ---------------------------------------------------------------------------
@@ -41,6 +48,15 @@ pub struct TreeNode {
     pub label: String,
     pub expanded: bool,
     pub selection: NodeSelection,
+    /// Last lifecycle status reported by the feed, if any.
+    pub status: Option<ProducerStatus>,
+    /// Restarts reported by the feed; shown as a marker once non-zero.
+    pub restarts: u32,
+    /// State of the pod, container or file behind the producer, if its feed
+    /// reports one.
+    pub state: Option<ProducerState>,
+    /// Facts reported alongside `state` (`restarts`, `exit_code`, …).
+    pub meta: BTreeMap<String, String>,
     pub children: Vec<TreeNode>,
 }
 
@@ -51,6 +67,10 @@ impl TreeNode {
             label: label.into(),
             expanded: true,
             selection: NodeSelection::Unselected,
+            status: None,
+            restarts: 0,
+            state: None,
+            meta: BTreeMap::new(),
             children: Vec::new(),
         }
     }
@@ -61,6 +81,59 @@ impl TreeNode {
     }
 }
 
+/// Build a forest from producer names, splitting each on `/` so that
+/// hierarchical names (`host/unit`, `project/service`) nest under their
+/// parents. Node ids are the full path prefix, so leaf ids equal the
+/// producer name. Insertion order is preserved at every level.
+pub fn build_producer_tree<'a>(producers: impl IntoIterator<Item = &'a str>) -> Vec<TreeNode> {
+    let mut roots: Vec<TreeNode> = Vec::new();
+    for producer in producers {
+        insert_path(&mut roots, producer);
+    }
+    roots
+}
+
+/// Insert the `/`-separated `producer` path into `nodes`, creating any missing
+/// nodes. Returns `true` if a node was created.
+///
+/// Nodes created beneath a fully selected parent start selected, so selecting
+/// a group (a namespace, a workload) keeps covering producers that join it
+/// later — a rollout's new pods, a restarted container.
+fn insert_path(nodes: &mut Vec<TreeNode>, producer: &str) -> bool {
+    let mut level = nodes;
+    let mut path = String::new();
+    let mut created = false;
+    let mut inherited = NodeSelection::Unselected;
+    for segment in producer.split('/').filter(|s| !s.is_empty()) {
+        if !path.is_empty() {
+            path.push('/');
+        }
+        path.push_str(segment);
+        let idx = match level.iter().position(|n| n.id == path) {
+            Some(idx) => idx,
+            None => {
+                let mut node = TreeNode::new(path.clone(), segment);
+                node.selection = inherited;
+                level.push(node);
+                created = true;
+                level.len() - 1
+            }
+        };
+        if level[idx].selection == NodeSelection::Selected {
+            inherited = NodeSelection::Selected;
+        }
+        level = &mut level[idx].children;
+    }
+    created
+}
+
+/// Node id for `producer` under its feed's root node. `feed` is a
+/// [`FeedKind`](fml_core::FeedKind), or a [`feed_root`](fml_core::feed_root) name when a feed runs
+/// as several instances.
+pub fn producer_path(feed: impl fmt::Display, producer: &str) -> String {
+    format!("{feed}/{producer}")
+}
+
 // ---------------------------------------------------------------------------
 // Tree state
 // ---------------------------------------------------------------------------
@@ -75,10 +148,94 @@ pub struct ProducerTreeState {
 impl ProducerTreeState {
     /// Return the id of the node at the cursor, if any.
     fn cursor_id(&self) -> Option<String> {
+        self.cursor_node().map(|n| n.id.clone())
+    }
+
+    /// Add `producer` (and any missing ancestors) under the top-level node for
+    /// its feed. Returns `true` if the producer was not already present.
+    ///
+    /// Every feed gets its own root (`docker`, `kubernetes`, …, or
+    /// `docker@podman` for one of several instances), so producers from
+    /// several feeds running at once never collide.
+    pub fn insert_producer(&mut self, feed: impl fmt::Display, producer: &str) -> bool {
+        insert_path(&mut self.nodes, &producer_path(feed, producer))
+    }
+
+    /// Record the latest lifecycle status for `producer`, inserting the node
+    /// if the feed reports on it before any of its lines arrive. Restart
+    /// counts are kept alongside the status rather than replacing it, and a
+    /// removed producer is dropped from the tree.
+    pub fn set_status(&mut self, feed: impl fmt::Display, producer: &str, status: ProducerStatus) {
+        let id = producer_path(feed, producer);
+        if status == ProducerStatus::Removed {
+            self.remove_node(&id);
+            return;
+        }
+        insert_path(&mut self.nodes, &id);
+        if let Some(node) = find_mut(&mut self.nodes, &id) {
+            match status {
+                ProducerStatus::Restarted { count } => node.restarts = count,
+                status => node.status = Some(status),
+            }
+        }
+    }
+
+    /// Record the latest state of the object behind `producer`, replacing
+    /// any earlier state and fields. Inserts the node if needed.
+    pub fn set_state(
+        &mut self,
+        feed: impl fmt::Display,
+        producer: &str,
+        state: ProducerState,
+        meta: BTreeMap<String, String>,
+    ) {
+        let id = producer_path(feed, producer);
+        insert_path(&mut self.nodes, &id);
+        if let Some(node) = find_mut(&mut self.nodes, &id) {
+            node.state = Some(state);
+            node.meta = meta;
+        }
+    }
+
+    /// Drop `producer`, and any groups left empty by its removal. Feed roots
+    /// stay. Returns `true` if the producer was present.
+    pub fn remove_producer(&mut self, feed: impl fmt::Display, producer: &str) -> bool {
+        self.remove_node(&producer_path(feed, producer))
+    }
+
+    fn remove_node(&mut self, id: &str) -> bool {
+        let removed = self.nodes.iter_mut().any(|root| {
+            let removed = remove_node(&mut root.children, id);
+            if removed && !root.children.is_empty() {
+                root.selection = compute_selection_from_children(&root.children);
+            }
+            removed
+        });
+        self.clamp_cursor();
+        removed
+    }
+
+    /// The node at the cursor, if any.
+    pub fn cursor_node(&self) -> Option<&TreeNode> {
         self.visible()
             .into_iter()
             .nth(self.cursor)
-            .map(|(_, n)| n.id.clone())
+            .map(|(_, node)| node)
+    }
+
+    /// Every leaf, expanded or not, in tree order.
+    pub fn leaves(&self) -> Vec<&TreeNode> {
+        fn walk<'a>(nodes: &'a [TreeNode], out: &mut Vec<&'a TreeNode>) {
+            for node in nodes {
+                if node.children.is_empty() {
+                    out.push(node);
+                }
+                walk(&node.children, out);
+            }
+        }
+        let mut out = Vec::new();
+        walk(&self.nodes, &mut out);
+        out
     }
 
     /// Flatten the tree into `(depth, &node)` pairs, respecting expanded state.
@@ -158,6 +315,38 @@ fn flatten(nodes: &[TreeNode], depth: usize) -> Vec<(usize, &TreeNode)> {
     out
 }
 
+fn find_mut<'a>(nodes: &'a mut [TreeNode], id: &str) -> Option<&'a mut TreeNode> {
+    for node in nodes.iter_mut() {
+        if node.id == id {
+            return Some(node);
+        }
+        if let Some(found) = find_mut(&mut node.children, id) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// Remove the node with `id`. On the way back up, ancestors left without
+/// children are removed too and the rest recompute their selection state.
+fn remove_node(nodes: &mut Vec<TreeNode>, id: &str) -> bool {
+    if let Some(idx) = nodes.iter().position(|n| n.id == id) {
+        nodes.remove(idx);
+        return true;
+    }
+    for idx in 0..nodes.len() {
+        if remove_node(&mut nodes[idx].children, id) {
+            if nodes[idx].children.is_empty() {
+                nodes.remove(idx);
+            } else {
+                nodes[idx].selection = compute_selection_from_children(&nodes[idx].children);
+            }
+            return true;
+        }
+    }
+    false
+}
+
 /// Set the `expanded` flag on the node with `id`. Returns `true` if found.
 #[allow(clippy::ptr_arg)] // Vec retained for future dynamic-size tree operations
 fn set_expanded(nodes: &mut Vec<TreeNode>, id: &str, expanded: bool) -> bool {
@@ -324,10 +513,25 @@ impl Widget for ProducerTree<'_> {
                     NodeSelection::Unselected => " ○",
                     NodeSelection::Partial => " ◐",
                 };
-                ListItem::new(Line::from(format!(
-                    "{}{}{}{}",
-                    indent, expand, node.label, sel
-                )))
+                // Streaming is the normal state; only surface the others.
+                let status = match &node.status {
+                    Some(ProducerStatus::Streaming) | None => String::new(),
+                    Some(status) => format!(" [{status}]"),
+                };
+                let restarts = match node.restarts {
+                    0 => String::new(),
+                    n => format!(" ↻{n}"),
+                };
+                let mut spans = vec![Span::raw(format!(
+                    "{}{}{}{}{}{}",
+                    indent, expand, node.label, sel, restarts, status
+                ))];
+                if let Some(state) = &node.state {
+                    let style = self.theme.state_style(state.severity());
+                    spans.push(Span::raw(" "));
+                    spans.push(Span::styled(state.to_string(), style));
+                }
+                ListItem::new(Line::from(spans))
             })
             .collect();
 
@@ -346,6 +550,7 @@ impl Widget for ProducerTree<'_> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use fml_core::FeedKind;
 
     /// Build a small tree: root → [a (leaf), b (leaf), c (leaf)]
     fn three_leaf_tree() -> Vec<TreeNode> {
@@ -433,6 +638,132 @@ mod tests {
         assert_eq!(find_sel(&nodes, "a"), NodeSelection::Unselected);
     }
 
+    #[test]
+    fn slash_separated_producers_nest() {
+        let nodes = build_producer_tree(["host-a/sshd", "host-a/nginx", "host-b/sshd", "flat"]);
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[0].id, "host-a");
+        let children: Vec<_> = nodes[0].children.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(children, ["host-a/sshd", "host-a/nginx"]);
+        assert_eq!(nodes[1].children[0].label, "sshd");
+        assert!(nodes[2].children.is_empty());
+    }
+
+    #[test]
+    fn status_is_recorded_on_interior_node() {
+        let mut state = ProducerTreeState::default();
+        assert!(state.insert_producer(FeedKind::Exec, "tests/stdout"));
+        assert!(!state.insert_producer(FeedKind::Exec, "tests/stdout"));
+        state.set_status(
+            FeedKind::Exec,
+            "tests",
+            ProducerStatus::Ended { code: Some(1) },
+        );
+        let command = &state.nodes[0].children[0];
+        assert_eq!(
+            command.status,
+            Some(ProducerStatus::Ended { code: Some(1) })
+        );
+        assert_eq!(command.children.len(), 1);
+    }
+
+    #[test]
+    fn restart_marker_survives_status_changes() {
+        let mut state = ProducerTreeState::default();
+        let restarted = ProducerStatus::Restarted { count: 2 };
+        state.set_status(FeedKind::Kubernetes, "shop/api-1", restarted);
+        state.set_status(
+            FeedKind::Kubernetes,
+            "shop/api-1",
+            ProducerStatus::Streaming,
+        );
+        let pod = &state.nodes[0].children[0].children[0];
+        assert_eq!(pod.restarts, 2);
+        assert_eq!(pod.status, Some(ProducerStatus::Streaming));
+    }
+
+    #[test]
+    fn state_replaces_earlier_state_and_fields() {
+        let mut state = ProducerTreeState::default();
+        let crashing = ProducerState::Waiting {
+            reason: "CrashLoopBackOff".into(),
+        };
+        let fields = BTreeMap::from([
+            ("restarts".to_string(), "3".to_string()),
+            ("last_reason".to_string(), "OOMKilled".to_string()),
+        ]);
+        state.set_state(FeedKind::Kubernetes, "shop/api-1", crashing.clone(), fields);
+        let pod = &state.nodes[0].children[0].children[0];
+        assert_eq!(pod.state, Some(crashing));
+        assert_eq!(pod.meta["last_reason"], "OOMKilled");
+
+        state.set_state(
+            FeedKind::Kubernetes,
+            "shop/api-1",
+            ProducerState::Running,
+            BTreeMap::from([("restarts".to_string(), "3".to_string())]),
+        );
+        let pod = &state.nodes[0].children[0].children[0];
+        assert_eq!(pod.state, Some(ProducerState::Running));
+        assert!(!pod.meta.contains_key("last_reason"));
+    }
+
+    #[test]
+    fn removed_producer_takes_empty_groups_with_it() {
+        let mut state = ProducerTreeState::default();
+        state.insert_producer(FeedKind::Docker, "shop/api");
+        state.insert_producer(FeedKind::Docker, "shop/web");
+        state.insert_producer(FeedKind::Docker, "postgres");
+        toggle_selection(&mut state.nodes, "docker/shop/web");
+
+        state.set_status(FeedKind::Docker, "shop/api", ProducerStatus::Removed);
+        assert_eq!(find_sel_opt(&state.nodes, "docker/shop/api"), None);
+        assert_eq!(
+            find_sel(&state.nodes, "docker/shop"),
+            NodeSelection::Selected
+        );
+
+        assert!(state.remove_producer(FeedKind::Docker, "shop/web"));
+        assert_eq!(find_sel_opt(&state.nodes, "docker/shop"), None);
+        assert!(!state.remove_producer(FeedKind::Docker, "shop/web"));
+        assert_eq!(state.nodes[0].children.len(), 1);
+    }
+
+    #[test]
+    fn each_feed_gets_its_own_root() {
+        let mut state = ProducerTreeState::default();
+        state.insert_producer(FeedKind::Kubernetes, "shop/api-1");
+        state.insert_producer(FeedKind::Docker, "postgres");
+        state.insert_producer(FeedKind::Kubernetes, "shop/api-2");
+        let roots: Vec<_> = state.nodes.iter().map(|n| n.label.as_str()).collect();
+        assert_eq!(roots, ["kubernetes", "docker"]);
+        assert_eq!(state.nodes[0].children[0].children.len(), 2);
+        assert_eq!(state.nodes[1].children[0].id, "docker/postgres");
+    }
+
+    #[test]
+    fn producers_joining_a_selected_group_are_selected() {
+        let mut state = ProducerTreeState::default();
+        state.insert_producer(FeedKind::Kubernetes, "shop/api/api-1111-aaaaa");
+        state.insert_producer(FeedKind::Kubernetes, "shop/cart-0");
+        toggle_selection(&mut state.nodes, "kubernetes/shop/api");
+
+        state.insert_producer(FeedKind::Kubernetes, "shop/api/api-2222-bbbbb");
+        state.insert_producer(FeedKind::Kubernetes, "shop/web-0");
+        assert_eq!(
+            find_sel(&state.nodes, "kubernetes/shop/api/api-2222-bbbbb"),
+            NodeSelection::Selected
+        );
+        assert_eq!(
+            find_sel(&state.nodes, "kubernetes/shop/api"),
+            NodeSelection::Selected
+        );
+        assert_eq!(
+            find_sel(&state.nodes, "kubernetes/shop/web-0"),
+            NodeSelection::Unselected
+        );
+    }
+
     #[test]
     fn deselecting_last_child_makes_parent_unselected() {
         let mut nodes = three_leaf_tree();
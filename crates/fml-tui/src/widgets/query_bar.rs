@@ -48,52 +48,42 @@ impl QueryBarState {
                 self.cursor += c.len_utf8();
                 tracing::debug!(query = %self.query, cursor = self.cursor, "query: char inserted");
             }
-            AppEvent::Backspace => {
-                if self.cursor > 0 {
-                    // Walk back one char boundary
-                    let prev = self.query[..self.cursor]
-                        .char_indices()
-                        .last()
-                        .map(|(i, _)| i)
-                        .unwrap_or(0);
-                    self.query.remove(prev);
-                    self.cursor = prev;
-                    tracing::debug!(query = %self.query, cursor = self.cursor, "query: backspace");
-                }
+            AppEvent::Backspace if self.cursor > 0 => {
+                // Walk back one char boundary
+                let prev = self.query[..self.cursor]
+                    .char_indices()
+                    .last()
+                    .map(|(i, _)| i)
+                    .unwrap_or(0);
+                self.query.remove(prev);
+                self.cursor = prev;
+                tracing::debug!(query = %self.query, cursor = self.cursor, "query: backspace");
             }
             // Left/right arrows re-mapped from TreeNav by the App shell
-            AppEvent::TreeNav(Direction::Left) => {
-                if self.cursor > 0 {
-                    self.cursor = self.query[..self.cursor]
-                        .char_indices()
-                        .last()
-                        .map(|(i, _)| i)
-                        .unwrap_or(0);
-                    tracing::debug!(cursor = self.cursor, "query: cursor left");
-                }
+            AppEvent::TreeNav(Direction::Left) if self.cursor > 0 => {
+                self.cursor = self.query[..self.cursor]
+                    .char_indices()
+                    .last()
+                    .map(|(i, _)| i)
+                    .unwrap_or(0);
+                tracing::debug!(cursor = self.cursor, "query: cursor left");
             }
-            AppEvent::TreeNav(Direction::Right) => {
-                if self.cursor < self.query.len() {
-                    let next = self.query[self.cursor..]
-                        .char_indices()
-                        .nth(1)
-                        .map(|(i, _)| self.cursor + i)
-                        .unwrap_or(self.query.len());
-                    self.cursor = next;
-                    tracing::debug!(cursor = self.cursor, "query: cursor right");
-                }
+            AppEvent::TreeNav(Direction::Right) if self.cursor < self.query.len() => {
+                let next = self.query[self.cursor..]
+                    .char_indices()
+                    .nth(1)
+                    .map(|(i, _)| self.cursor + i)
+                    .unwrap_or(self.query.len());
+                self.cursor = next;
+                tracing::debug!(cursor = self.cursor, "query: cursor right");
             }
-            AppEvent::GreedUp => {
-                if self.greed < GREED_MAX {
-                    self.greed += 1;
-                    tracing::debug!(greed = self.greed, "query: greed up");
-                }
+            AppEvent::GreedUp if self.greed < GREED_MAX => {
+                self.greed += 1;
+                tracing::debug!(greed = self.greed, "query: greed up");
             }
-            AppEvent::GreedDown => {
-                if self.greed > 0 {
-                    self.greed -= 1;
-                    tracing::debug!(greed = self.greed, "query: greed down");
-                }
+            AppEvent::GreedDown if self.greed > 0 => {
+                self.greed -= 1;
+                tracing::debug!(greed = self.greed, "query: greed down");
             }
             _ => {}
         }
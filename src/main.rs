@@ -1,4 +1,20 @@
-use clap::Parser;
+use anyhow::{bail, Context};
+use clap::{Parser, ValueEnum};
+use fml_core::config::Config;
+use fml_core::{FeedKind, LogEntry, ProducerMeta, ProducerStatus, ProducerUpdate, ReplayCommand};
+use fml_feeds::exec::ExecConfig;
+use fml_feeds::{FeedControl, FeedHandle};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// Capacity of the channel between feed tasks and the UI.
+const ENTRY_CHANNEL_CAPACITY: usize = 8_192;
+const UPDATE_CHANNEL_CAPACITY: usize = 256;
+const REPLAY_CONTROL_CAPACITY: usize = 16;
+/// Unprivileged stand-in for port 514 when no syslog address is given.
+const DEFAULT_SYSLOG_ADDR: &str = "0.0.0.0:5514";
+/// Scenario `--demo` plays when none is named.
+const DEFAULT_SCENARIO: &str = "redis-outage";
 
 #[derive(Parser)]
 #[command(name = "fml", about = "Feed Me Logs — terminal log triage")]
@@ -6,6 +22,220 @@ struct Cli {
     /// Write debug logs to /tmp/fml-debug.log (tail -f to inspect).
     #[arg(long)]
     debug: bool,
+
+    /// Feed to open (repeatable, once per kind; all feeds merge into one
+    /// session). Without one, fml starts on mock data.
+    #[arg(long = "feed", value_enum, value_name = "FEED")]
+    feeds: Vec<FeedArg>,
+
+    /// Named command from `[feeds.exec.commands]` to run (repeatable). With
+    /// `--feed exec` and neither this nor a trailing command, every configured
+    /// command runs.
+    #[arg(long = "exec", value_name = "NAME")]
+    exec_names: Vec<String>,
+
+    /// Restart ad-hoc exec commands with backoff when they exit.
+    #[arg(long)]
+    restart: bool,
+
+    /// Systemd unit for `--feed journald` to follow (repeatable). Default:
+    /// `[feeds.journald] units`, then the whole journal.
+    #[arg(long = "unit", value_name = "UNIT")]
+    units: Vec<String>,
+
+    /// Maximum syslog priority `--feed journald` includes, 0 (emerg) to 7
+    /// (debug). Default: `[feeds.journald] priority`.
+    #[arg(long, value_name = "0-7", value_parser = clap::value_parser!(u8).range(0..=7))]
+    journal_priority: Option<u8>,
+
+    /// Where `--feed journald` starts its backfill, in any format
+    /// `journalctl --since` accepts (`-1h`, `today`). Default:
+    /// `[feeds.journald] since`.
+    #[arg(long, value_name = "WHEN")]
+    journal_since: Option<String>,
+
+    /// UDP address for `--feed syslog` to listen on.
+    #[arg(long, value_name = "ADDR")]
+    syslog_udp: Option<std::net::SocketAddr>,
+
+    /// TCP address for `--feed syslog` to listen on (octet-counted or
+    /// newline-framed).
+    #[arg(long, value_name = "ADDR")]
+    syslog_tcp: Option<std::net::SocketAddr>,
+
+    /// Address for `--feed http` to accept pushes on (default 127.0.0.1:3100).
+    #[arg(long, value_name = "ADDR")]
+    http_listen: Option<std::net::SocketAddr>,
+
+    /// Address for `--feed otlp` to receive OTLP/HTTP logs on
+    /// (default 127.0.0.1:4318).
+    #[arg(long, value_name = "ADDR")]
+    otlp_listen: Option<std::net::SocketAddr>,
+
+    /// Address for `--feed forward` to receive Fluent forward messages on
+    /// (default 127.0.0.1:24224).
+    #[arg(long, value_name = "ADDR")]
+    forward_listen: Option<std::net::SocketAddr>,
+
+    /// Named pipe for `--feed pipe` to read lines from, created if missing
+    /// (repeatable).
+    #[arg(long = "fifo", value_name = "PATH")]
+    fifos: Vec<std::path::PathBuf>,
+
+    /// Unix stream socket for `--feed pipe` to accept connections on; each
+    /// connection is its own producer (repeatable).
+    #[arg(long = "unix-socket", value_name = "PATH")]
+    unix_sockets: Vec<std::path::PathBuf>,
+
+    /// Unix datagram socket for `--feed pipe` to receive lines on
+    /// (repeatable).
+    #[arg(long = "unix-dgram", value_name = "PATH")]
+    unix_dgrams: Vec<std::path::PathBuf>,
+
+    /// Engine for `--feed docker`, as a socket path or `unix://` / `tcp://`
+    /// URL (repeatable). Without this or `--docker-context`: `[feeds.docker]
+    /// socket`, then `DOCKER_HOST`, the current Docker context, and the first
+    /// Docker or Podman socket found.
+    #[arg(long = "docker-host", value_name = "URL")]
+    docker_hosts: Vec<fml_feeds::docker::Endpoint>,
+
+    /// Docker context (from `~/.docker/contexts`) for `--feed docker` to
+    /// follow (repeatable).
+    #[arg(long = "docker-context", value_name = "NAME")]
+    docker_contexts: Vec<String>,
+
+    /// Only follow containers matching this filter with `--feed docker`:
+    /// `name=SUBSTR`, `label=KEY` or `label=KEY=VALUE` (repeatable; a
+    /// container matching any filter is followed).
+    #[arg(long = "docker-filter", value_name = "FILTER")]
+    docker_filters: Vec<fml_feeds::docker::ContainerFilter>,
+
+    /// Lines of existing output `--feed docker` loads per container on
+    /// attach (default: the whole log).
+    #[arg(long, value_name = "N")]
+    docker_tail: Option<u64>,
+
+    /// Kubeconfig for `--feed kubernetes` (default `$KUBECONFIG`, then
+    /// `~/.kube/config`, then the in-cluster service account).
+    #[arg(long, value_name = "PATH")]
+    kubeconfig: Option<std::path::PathBuf>,
+
+    /// Kubeconfig context for `--feed kubernetes` (default:
+    /// `[feeds.kubernetes] context`, then the current context).
+    #[arg(long, value_name = "NAME")]
+    kube_context: Option<String>,
+
+    /// Namespace for `--feed kubernetes` to follow (repeatable; default:
+    /// `[feeds.kubernetes] default_namespace`, then the context's namespace).
+    #[arg(long = "namespace", short = 'n', value_name = "NS")]
+    namespaces: Vec<String>,
+
+    /// Workload for `--feed kubernetes` to follow across rollouts, as
+    /// `kind/name` (`deployment/api`, `sts/db`; repeatable). Without one,
+    /// every pod is followed.
+    #[arg(long = "workload", value_name = "KIND/NAME")]
+    workloads: Vec<fml_feeds::kubernetes::Workload>,
+
+    /// Label selector narrowing the pods `--feed kubernetes` follows
+    /// (`app=api,tier!=cache`).
+    #[arg(long = "selector", short = 'l', value_name = "SELECTOR")]
+    label_selector: Option<String>,
+
+    /// Also stream Kubernetes events, as one `<namespace>/events` producer per
+    /// followed namespace.
+    #[arg(long)]
+    kube_events: bool,
+
+    /// Lines of existing output `--feed kubernetes` loads per container on
+    /// attach (default: the whole log).
+    #[arg(long, value_name = "N")]
+    kube_tail: Option<i64>,
+
+    /// File, directory or glob pattern for `--feed file` to follow
+    /// (repeatable; `'/var/log/app/**/*.log'` matches at any depth). Default:
+    /// `[feeds.file] paths`.
+    #[arg(long = "path", value_name = "PATH")]
+    paths: Vec<String>,
+
+    /// Glob pattern of files `--feed file` never opens (repeatable; without a
+    /// `/` it matches the file name, e.g. `'*.gz'`).
+    #[arg(long = "exclude", value_name = "GLOB")]
+    excludes: Vec<String>,
+
+    /// Most files `--feed file` holds open at once; the most recently
+    /// modified win (default 256).
+    #[arg(long, value_name = "N")]
+    max_open_files: Option<usize>,
+
+    /// Have `--feed file` backfill each file's rotated archives
+    /// (`app.log.1`, `app.log.2.gz`, `.zst`, `.bz2`, dated suffixes) first.
+    #[arg(long)]
+    archives: bool,
+
+    /// How far back `--archives` reads: an age (`24h`, `7d`) or a size on
+    /// disk (`500MB`). Implies `--archives`. Default: every archive.
+    #[arg(long, value_name = "AGE|SIZE")]
+    archive_horizon: Option<fml_feeds::file::ArchiveHorizon>,
+
+    /// Have `--feed file` save how far it read each file and, on the next
+    /// run, resume from there instead of the start.
+    #[arg(long)]
+    resume: bool,
+
+    /// How `--feed stdin` lines name their producer: `auto` (default),
+    /// `none`, `compose` (`api-1  | …`), `kubectl` (`[pod/name/container] …`),
+    /// `stern` (`pod container …`), or a regex with a `producer` group.
+    #[arg(long, value_name = "FORMAT")]
+    prefix: Option<fml_feeds::stdin::Prefix>,
+
+    /// File for `--feed replay` to play back: an fml `jsonl` export or a log
+    /// whose lines start with a timestamp, optionally compressed (repeatable;
+    /// files are merged by timestamp).
+    #[arg(long = "replay", value_name = "PATH")]
+    replays: Vec<std::path::PathBuf>,
+
+    /// Pace of `--feed replay` relative to the original: `10x`, `0.5x`
+    /// (default 1x).
+    #[arg(long, value_name = "N")]
+    speed: Option<fml_core::Speed>,
+
+    /// Play a scripted incident: a shipped scenario (`redis-outage`,
+    /// `auth-storm`, `oom-crashloop`) or a scenario file. Implies
+    /// `--feed demo`; without a value, `redis-outage`.
+    #[arg(long, value_name = "SCENARIO", num_args = 0..=1, default_missing_value = DEFAULT_SCENARIO)]
+    demo: Option<String>,
+
+    /// What a feed does with new lines when the UI falls behind: `block`
+    /// (default), `drop-oldest` or `sample`. Lost lines are counted and
+    /// shown.
+    #[arg(long, value_name = "POLICY")]
+    overflow: Option<fml_core::Overflow>,
+
+    /// Show producers under the names their feeds give them, without
+    /// `[producers]` rules or replica grouping.
+    #[arg(long)]
+    raw_producers: bool,
+
+    /// Ad-hoc command for `--feed exec`, e.g. `fml --feed exec -- stern api`.
+    #[arg(last = true, value_name = "COMMAND")]
+    command: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum FeedArg {
+    Docker,
+    Kubernetes,
+    File,
+    Stdin,
+    Journald,
+    Exec,
+    Syslog,
+    Http,
+    Otlp,
+    Forward,
+    Pipe,
+    Replay,
+    Demo,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -27,5 +257,491 @@ fn main() -> anyhow::Result<()> {
         tracing::info!("fml debug log started — tail -f /tmp/fml-debug.log");
     }
 
-    fml_tui::run()
+    let mut feeds = cli.feeds.clone();
+    if cli.demo.is_some() && !feeds.contains(&FeedArg::Demo) {
+        feeds.push(FeedArg::Demo);
+    }
+    // Feeds take their settings from the shared flags, so a second
+    // instance of a kind could only repeat the first.
+    for (i, feed) in feeds.iter().enumerate() {
+        if feeds[..i].contains(feed) {
+            let name = feed.to_possible_value().expect("no skipped variants");
+            bail!(
+                "`--feed {0}` is given twice; one {0} feed covers every source its flags name",
+                name.get_name()
+            );
+        }
+    }
+    if feeds.is_empty() {
+        return fml_tui::run();
+    }
+
+    let config = Config::load().unwrap_or_else(|_| Config::defaults());
+    let runtime = tokio::runtime::Runtime::new()?;
+    let (tx, entries) = mpsc::channel(ENTRY_CHANNEL_CAPACITY);
+    let (update_tx, updates) = mpsc::channel(UPDATE_CHANNEL_CAPACITY);
+    let (meta_tx, metadata) = mpsc::channel(UPDATE_CHANNEL_CAPACITY);
+
+    let overflow = match (cli.overflow, &config.ingest.overflow) {
+        (Some(overflow), _) => overflow,
+        (None, Some(overflow)) => overflow
+            .parse()
+            .map_err(anyhow::Error::msg)
+            .context("`[ingest] overflow`")?,
+        (None, None) => fml_core::Overflow::default(),
+    };
+    let buffer = config
+        .ingest
+        .buffer
+        .unwrap_or(fml_feeds::control::DEFAULT_BUFFER);
+    let rules = if cli.raw_producers {
+        fml_core::ProducerRules::default()
+    } else {
+        fml_core::ProducerRules::from_config(&config.producers)
+            .map_err(anyhow::Error::msg)
+            .context("`[producers]` rules")?
+    };
+
+    let mut replay = None;
+    let mut handles: Vec<Arc<dyn FeedHandle>> = Vec::new();
+    for &feed in &feeds {
+        let (control, senders, relay) =
+            FeedControl::attach(tx.clone(), update_tx.clone(), meta_tx.clone());
+        let outputs = FeedOutputs {
+            tx: &senders.entries,
+            update_tx: &senders.updates,
+            meta_tx: &senders.metadata,
+            replay: &mut replay,
+            signals: control.signals(),
+            deliveries: control.deliveries(),
+        };
+        spawn_feed(feed, &cli, &config, &runtime, outputs)?;
+        runtime.spawn(
+            relay
+                .with_overflow(overflow, buffer)
+                .with_rules(rules.clone())
+                .run(),
+        );
+        handles.push(Arc::new(control));
+    }
+    drop((tx, update_tx, meta_tx));
+
+    let channels = fml_tui::FeedChannels {
+        entries,
+        updates,
+        metadata,
+        replay,
+        handles,
+    };
+    let result = fml_tui::run_live(config, channels);
+    runtime.shutdown_background();
+    result
+}
+
+/// Where a feed's tasks send their output: entries, lifecycle updates and
+/// producer metadata all go through the feed's own [`FeedControl`]. A feed
+/// the TUI controls leaves its control sender behind, and feeds that can
+/// close or reopen a producer's stream listen on `signals`. Push feeds
+/// answer their senders once `deliveries` reports a batch stored.
+struct FeedOutputs<'a> {
+    tx: &'a mpsc::Sender<LogEntry>,
+    update_tx: &'a mpsc::Sender<ProducerUpdate>,
+    meta_tx: &'a mpsc::Sender<ProducerMeta>,
+    replay: &'a mut Option<mpsc::Sender<ReplayCommand>>,
+    signals: fml_feeds::Signals,
+    deliveries: fml_feeds::Deliveries,
+}
+
+/// Start the task(s) for one feed on `runtime`.
+fn spawn_feed(
+    feed: FeedArg,
+    cli: &Cli,
+    config: &Config,
+    runtime: &tokio::runtime::Runtime,
+    outputs: FeedOutputs<'_>,
+) -> anyhow::Result<()> {
+    let FeedOutputs {
+        tx,
+        update_tx,
+        meta_tx,
+        replay,
+        signals,
+        deliveries,
+    } = outputs;
+    match feed {
+        FeedArg::Exec => {
+            for exec in exec_configs(cli, config)? {
+                let tx = tx.clone();
+                let update_tx = update_tx.clone();
+                let signals = signals.clone();
+                runtime.spawn(async move {
+                    let run =
+                        fml_feeds::exec::run_with_signals(&exec, signals, tx, update_tx.clone());
+                    if let Err(e) = run.await {
+                        tracing::warn!(name = %exec.name, error = %e, "exec feed stopped");
+                        report_error(&update_tx, FeedKind::Exec, None, &exec.name, e).await;
+                    }
+                });
+            }
+        }
+        FeedArg::Stdin => {
+            let prefix = match (&cli.prefix, &config.feeds.stdin.prefix) {
+                (Some(prefix), _) => prefix.clone(),
+                (None, Some(prefix)) => prefix
+                    .parse()
+                    .map_err(anyhow::Error::msg)
+                    .context("`[feeds.stdin] prefix`")?,
+                (None, None) => fml_feeds::stdin::Prefix::default(),
+            };
+            let stdin = fml_feeds::stdin::StdinConfig {
+                prefix,
+                ..Default::default()
+            };
+            let tx = tx.clone();
+            let update_tx = update_tx.clone();
+            runtime.spawn(async move {
+                if let Err(e) = fml_feeds::stdin::run(&stdin, tx, update_tx.clone()).await {
+                    tracing::warn!(error = %e, "stdin feed stopped");
+                    report_error(&update_tx, FeedKind::Stdin, None, "", e).await;
+                }
+            });
+        }
+        FeedArg::Journald => {
+            let defaults = &config.feeds.journald;
+            let journald = fml_feeds::journald::JournaldConfig {
+                units: if cli.units.is_empty() {
+                    defaults.units.clone()
+                } else {
+                    cli.units.clone()
+                },
+                priority: cli.journal_priority.or(defaults.priority),
+                since: cli.journal_since.clone().or_else(|| defaults.since.clone()),
+            };
+            let tx = tx.clone();
+            let update_tx = update_tx.clone();
+            runtime.spawn(async move {
+                let run = fml_feeds::journald::run_with_signals(&journald, signals, tx);
+                if let Err(e) = run.await {
+                    tracing::warn!(error = %e, "journald feed stopped");
+                    report_error(&update_tx, FeedKind::Journald, None, "", e).await;
+                }
+            });
+        }
+        FeedArg::Syslog => {
+            let mut syslog = fml_feeds::syslog::SyslogConfig {
+                udp: cli.syslog_udp,
+                tcp: cli.syslog_tcp,
+            };
+            if syslog.udp.is_none() && syslog.tcp.is_none() {
+                syslog.udp = Some(DEFAULT_SYSLOG_ADDR.parse()?);
+            }
+            let listener = runtime.block_on(fml_feeds::syslog::SyslogListener::bind(&syslog))?;
+            let tx = tx.clone();
+            let update_tx = update_tx.clone();
+            runtime.spawn(async move {
+                if let Err(e) = listener.run(tx).await {
+                    tracing::warn!(error = %e, "syslog feed stopped");
+                    report_error(&update_tx, FeedKind::Syslog, None, "", e).await;
+                }
+            });
+        }
+        FeedArg::Http => {
+            let mut http = fml_feeds::http::HttpConfig::default();
+            if let Some(addr) = cli.http_listen {
+                http.addr = addr;
+            }
+            let ingest = runtime
+                .block_on(fml_feeds::http::HttpIngest::bind(&http))?
+                .with_deliveries(deliveries);
+            let tx = tx.clone();
+            let update_tx = update_tx.clone();
+            runtime.spawn(async move {
+                if let Err(e) = ingest.run(tx).await {
+                    tracing::warn!(error = %e, "http feed stopped");
+                    report_error(&update_tx, FeedKind::Http, None, "", e).await;
+                }
+            });
+        }
+        FeedArg::Otlp => {
+            let mut otlp = fml_feeds::otlp::OtlpConfig::default();
+            if let Some(addr) = cli.otlp_listen {
+                otlp.addr = addr;
+            }
+            let receiver = runtime
+                .block_on(fml_feeds::otlp::OtlpReceiver::bind(&otlp))?
+                .with_deliveries(deliveries);
+            let tx = tx.clone();
+            let update_tx = update_tx.clone();
+            runtime.spawn(async move {
+                if let Err(e) = receiver.run(tx).await {
+                    tracing::warn!(error = %e, "otlp feed stopped");
+                    report_error(&update_tx, FeedKind::Otlp, None, "", e).await;
+                }
+            });
+        }
+        FeedArg::Forward => {
+            let mut forward = fml_feeds::forward::ForwardConfig::default();
+            if let Some(addr) = cli.forward_listen {
+                forward.addr = addr;
+            }
+            let receiver = runtime.block_on(fml_feeds::forward::ForwardReceiver::bind(&forward))?;
+            let tx = tx.clone();
+            let update_tx = update_tx.clone();
+            runtime.spawn(async move {
+                if let Err(e) = receiver.run(tx).await {
+                    tracing::warn!(error = %e, "forward feed stopped");
+                    report_error(&update_tx, FeedKind::Forward, None, "", e).await;
+                }
+            });
+        }
+        FeedArg::Pipe => {
+            let pipe = fml_feeds::pipe::PipeConfig {
+                fifos: cli.fifos.clone(),
+                streams: cli.unix_sockets.clone(),
+                datagrams: cli.unix_dgrams.clone(),
+            };
+            if pipe.fifos.is_empty() && pipe.streams.is_empty() && pipe.datagrams.is_empty() {
+                bail!(
+                    "`--feed pipe` needs a path: `--fifo <PATH>`, `--unix-socket <PATH>` \
+                     or `--unix-dgram <PATH>`"
+                );
+            }
+            let listener = runtime
+                .block_on(fml_feeds::pipe::PipeListener::bind(&pipe))?
+                .with_signals(signals);
+            let tx = tx.clone();
+            let update_tx = update_tx.clone();
+            runtime.spawn(async move {
+                if let Err(e) = listener.run(tx, update_tx.clone()).await {
+                    tracing::warn!(error = %e, "pipe feed stopped");
+                    report_error(&update_tx, FeedKind::Pipe, None, "", e).await;
+                }
+            });
+        }
+        FeedArg::Docker => {
+            let engines = docker_engines(cli, config)?;
+            let several = engines.len() > 1;
+            for engine in engines {
+                let docker = fml_feeds::docker::DockerConfig {
+                    endpoint: engine.endpoint,
+                    instance: several.then(|| engine.name.clone()),
+                    filters: cli.docker_filters.clone(),
+                    tail: cli.docker_tail,
+                };
+                let instance = docker.instance.clone();
+                let feed = fml_feeds::docker::DockerFeed::new(&docker)
+                    .with_context(|| format!("docker engine `{}`", engine.name))?
+                    .with_metadata(meta_tx.clone())
+                    .with_signals(signals.clone());
+                let tx = tx.clone();
+                let update_tx = update_tx.clone();
+                runtime.spawn(async move {
+                    if let Err(e) = feed.run(tx, update_tx.clone()).await {
+                        tracing::warn!(engine = %engine.name, error = %e, "docker feed stopped");
+                        report_error(&update_tx, FeedKind::Docker, instance, "", e).await;
+                    }
+                });
+            }
+        }
+        FeedArg::Kubernetes => {
+            let defaults = &config.feeds.kubernetes;
+            let non_empty = |s: &String| (!s.is_empty()).then(|| s.clone());
+            let mut namespaces = cli.namespaces.clone();
+            if namespaces.is_empty() {
+                namespaces.extend(non_empty(&defaults.default_namespace));
+            }
+            let kubernetes = fml_feeds::kubernetes::KubernetesConfig {
+                kubeconfig: cli.kubeconfig.clone(),
+                context: cli
+                    .kube_context
+                    .clone()
+                    .or_else(|| non_empty(&defaults.context)),
+                namespaces,
+                tail_lines: cli.kube_tail,
+                label_selector: cli.label_selector.clone(),
+                workloads: cli.workloads.clone(),
+                events: cli.kube_events,
+            };
+            let feed = runtime
+                .block_on(fml_feeds::kubernetes::KubernetesFeed::connect(&kubernetes))?
+                .with_metadata(meta_tx.clone())
+                .with_signals(signals);
+            let tx = tx.clone();
+            let update_tx = update_tx.clone();
+            runtime.spawn(async move {
+                if let Err(e) = feed.run(tx, update_tx.clone()).await {
+                    tracing::warn!(error = %e, "kubernetes feed stopped");
+                    report_error(&update_tx, FeedKind::Kubernetes, None, "", e).await;
+                }
+            });
+        }
+        FeedArg::File => {
+            let defaults = &config.feeds.file;
+            let pick = |cli: &[String], config: &[String]| {
+                if cli.is_empty() { config } else { cli }.to_vec()
+            };
+            let file = fml_feeds::file::FileConfig {
+                paths: pick(&cli.paths, &defaults.paths),
+                exclude: pick(&cli.excludes, &defaults.exclude),
+                max_open: cli
+                    .max_open_files
+                    .or(defaults.max_open_files)
+                    .unwrap_or(fml_feeds::file::DEFAULT_MAX_OPEN),
+                archives: cli.archives || cli.archive_horizon.is_some() || defaults.archives,
+                archive_horizon: match (cli.archive_horizon, &defaults.archive_horizon) {
+                    (Some(horizon), _) => Some(horizon),
+                    (None, Some(horizon)) => Some(
+                        horizon
+                            .parse()
+                            .map_err(anyhow::Error::msg)
+                            .context("`[feeds.file] archive_horizon`")?,
+                    ),
+                    (None, None) => None,
+                },
+                checkpoint: (cli.resume || defaults.resume)
+                    .then(|| fml_core::config::state_dir().join("file-offsets.json")),
+            };
+            if file.paths.is_empty() {
+                bail!("`--feed file` needs a path: `fml --feed file --path <PATH>`");
+            }
+            let feed = fml_feeds::file::FileFeed::new(&file)?
+                .with_metadata(meta_tx.clone())
+                .with_signals(signals);
+            let tx = tx.clone();
+            let update_tx = update_tx.clone();
+            runtime.spawn(async move {
+                if let Err(e) = feed.run(tx, update_tx.clone()).await {
+                    tracing::warn!(error = %e, "file feed stopped");
+                    report_error(&update_tx, FeedKind::File, None, "", e).await;
+                }
+            });
+        }
+        FeedArg::Replay => {
+            if cli.replays.is_empty() {
+                bail!("`--feed replay` needs a file: `fml --feed replay --replay <PATH>`");
+            }
+            let config = fml_feeds::replay::ReplayConfig {
+                paths: cli.replays.clone(),
+                speed: cli.speed.unwrap_or_default(),
+            };
+            let (control_tx, control) = mpsc::channel(REPLAY_CONTROL_CAPACITY);
+            let feed = fml_feeds::replay::ReplayFeed::load(&config)?.with_control(control);
+            *replay = Some(control_tx);
+            let tx = tx.clone();
+            let update_tx = update_tx.clone();
+            runtime.spawn(async move {
+                if let Err(e) = feed.run(tx, update_tx.clone()).await {
+                    tracing::warn!(error = %e, "replay feed stopped");
+                    report_error(&update_tx, FeedKind::Replay, None, "", e).await;
+                }
+            });
+        }
+        FeedArg::Demo => {
+            let scenario = cli.demo.as_deref().unwrap_or(DEFAULT_SCENARIO);
+            let scenario = fml_feeds::demo::Scenario::load(scenario)?;
+            let feed = fml_feeds::demo::DemoFeed::new(scenario).with_metadata(meta_tx.clone());
+            let tx = tx.clone();
+            let update_tx = update_tx.clone();
+            runtime.spawn(async move {
+                if let Err(e) = feed.run(tx, update_tx.clone()).await {
+                    tracing::warn!(error = %e, "demo feed stopped");
+                    report_error(&update_tx, FeedKind::Demo, None, "", e).await;
+                }
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Show why a feed gave up on its node in the producer tree: the feed root,
+/// or `producer` under it.
+async fn report_error(
+    updates: &mpsc::Sender<ProducerUpdate>,
+    source: FeedKind,
+    instance: Option<String>,
+    producer: &str,
+    error: fml_feeds::FeedError,
+) {
+    let update = ProducerUpdate {
+        source,
+        instance,
+        producer: producer.to_string(),
+        status: ProducerStatus::Error {
+            message: error.to_string(),
+        },
+    };
+    let _ = updates.send(update).await;
+}
+
+/// Resolve the engines `--feed docker` should follow: every `--docker-host`
+/// and `--docker-context`, else the configured socket, else whatever
+/// [`detect`](fml_feeds::docker::engine::detect) finds. Names are made unique
+/// so each engine gets its own tree root.
+fn docker_engines(cli: &Cli, config: &Config) -> anyhow::Result<Vec<fml_feeds::docker::Engine>> {
+    use fml_feeds::docker::engine::{self, Engine, Environment};
+
+    let env = Environment::from_process();
+    let mut engines: Vec<Engine> = cli
+        .docker_hosts
+        .iter()
+        .map(|endpoint| Engine::from_endpoint(env.with_tls(endpoint.clone())))
+        .collect();
+    for name in &cli.docker_contexts {
+        engines.push(engine::context(&env, name)?);
+    }
+    if engines.is_empty() {
+        let socket = &config.feeds.docker.socket;
+        engines.push(if socket.is_empty() {
+            engine::detect(&env)?
+        } else {
+            let endpoint = socket
+                .parse()
+                .map_err(|e: String| anyhow::anyhow!("[feeds.docker] socket: {e}"))?;
+            Engine::from_endpoint(endpoint)
+        });
+    }
+
+    let mut seen = std::collections::HashMap::<String, u32>::new();
+    for engine in &mut engines {
+        let count = seen.entry(engine.name.clone()).or_default();
+        *count += 1;
+        if *count > 1 {
+            engine.name = format!("{}-{count}", engine.name);
+        }
+    }
+    Ok(engines)
+}
+
+/// Resolve the commands `--feed exec` should run: the trailing ad-hoc command,
+/// the `--exec` names, or every configured command when neither is given.
+fn exec_configs(cli: &Cli, config: &Config) -> anyhow::Result<Vec<ExecConfig>> {
+    let commands = &config.feeds.exec.commands;
+    let mut out = Vec::new();
+
+    if let Some(mut adhoc) = ExecConfig::from_argv(&cli.command) {
+        adhoc.restart = cli.restart;
+        out.push(adhoc);
+    }
+
+    let names: Vec<&String> = if cli.exec_names.is_empty() && out.is_empty() {
+        commands.keys().collect()
+    } else {
+        cli.exec_names.iter().collect()
+    };
+    for name in names {
+        let cmd = commands
+            .get(name)
+            .with_context(|| format!("no `[feeds.exec.commands.{name}]` in config"))?;
+        let Some((program, args)) = cmd.command.split_first() else {
+            bail!("`[feeds.exec.commands.{name}]` has an empty `command`");
+        };
+        let mut exec = ExecConfig::named(name.clone(), program.clone(), args.to_vec());
+        exec.restart = cmd.restart;
+        out.push(exec);
+    }
+
+    if out.is_empty() {
+        bail!("`--feed exec` needs a command: `fml --feed exec -- <cmd> [args…]`");
+    }
+    Ok(out)
 }
@@ -90,6 +90,7 @@ impl LogEntryBuilder {
             ts: self.ts,
             level: self.level,
             source: self.source,
+            instance: None,
             producer: self.producer,
             fields: self.fields,
             message: self.message,
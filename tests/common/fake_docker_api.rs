@@ -1,14 +1,21 @@
 //! Fake Docker Engine API server for integration tests.
 //!
 //! Spins up a minimal `axum` HTTP server on a random TCP port bound to
-//! 127.0.0.1. Serves:
-//! - `GET /containers/json` — list of configured containers
-//! - `GET /containers/{id}/logs` — streaming log output from a channel
+//! 127.0.0.1. Serves the subset of the Engine API the Docker feed uses:
+//! - `GET /containers/json` — running containers
+//! - `GET /containers/{id}/json` — inspect output (name, labels, image, state)
+//! - `GET /containers/{id}/logs` — buffered lines as multiplexed frames
+//!   (stdout = 1, stderr = 2), honouring `timestamps`, `tail` and `since`;
+//!   with `follow=1` the response stays open while the container runs
+//! - `GET /events` — container lifecycle events (`start`, `die`, `oom`,
+//!   `health_status: …`, `destroy`)
+//!   as a JSON stream; events at or after `since` are replayed first
 //!
-//! In production, the Docker API is served over a Unix socket. For tests we
-//! use TCP for simplicity (axum 0.7 does not support UnixListener with
-//! `axum::serve`). The ingestor under test should accept a configurable base
-//! URL so it can be pointed at the test server.
+//! The server listens on a random TCP port ([`FakeDockerApi::start`]), a Unix
+//! socket ([`FakeDockerApi::start_unix`]) or a TCP port behind mutual TLS
+//! ([`FakeDockerApi::start_tls`], using the certificates in
+//! `tests/fixtures/docker-tls`). [`FakeDockerApi::endpoint`] is the matching
+//! `DOCKER_HOST`-style URL.
 //!
 //! # Example
 //!
@@ -20,37 +27,151 @@
 //! api.add_container("abc123", "myapp_api_1").await;
 //! api.stream_log("abc123", r#"{"level":"INFO","msg":"hello"}"#).await;
 //!
-//! // Point your ingestor at api.base_url()
-//! let url = api.base_url();
+//! // Point the feed at api.endpoint()
+//! let endpoint = api.endpoint();
 //! # });
 //! ```
 
 use axum::{
-    extract::{Path, State},
-    response::IntoResponse,
+    body::Body,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
     routing::get,
     Router,
 };
+use bytes::Bytes;
+use chrono::{DateTime, Duration as ChronoDuration, SecondsFormat, Utc};
+use futures::StreamExt;
+use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::convert::Infallible;
 use std::net::SocketAddr;
+use std::path::{Path as FsPath, PathBuf};
 use std::sync::Arc;
-use tokio::net::TcpListener;
-use tokio::sync::{mpsc, Mutex};
+use tokio::net::{TcpListener, TcpStream, UnixListener};
+use tokio::sync::{broadcast, mpsc, Mutex};
+
+/// Multiplexed stream type for stdout.
+const STDOUT: u8 = 1;
+/// Multiplexed stream type for stderr.
+const STDERR: u8 = 2;
+
+/// One buffered log line.
+#[derive(Clone)]
+struct LogLine {
+    ts: DateTime<Utc>,
+    stream: u8,
+    text: String,
+}
+
+struct FakeContainer {
+    id: String,
+    name: String,
+    labels: HashMap<String, String>,
+    running: bool,
+    exit_code: i32,
+    oom_killed: bool,
+    /// Health check status, for containers with a health check.
+    health: Option<String>,
+    lines: Vec<LogLine>,
+    /// Open `follow=1` responses.
+    followers: Vec<mpsc::UnboundedSender<LogLine>>,
+}
+
+impl FakeContainer {
+    fn inspect(&self) -> Value {
+        json!({
+            "Id": self.id,
+            "Name": format!("/{}", self.name),
+            "Config": {"Image": "fake/image:latest", "Tty": false, "Labels": self.labels},
+            "RestartCount": 0,
+            "State": {
+                "Status": if self.running { "running" } else { "exited" },
+                "Running": self.running,
+                "ExitCode": self.exit_code,
+                "OOMKilled": self.oom_killed,
+                "Health": self.health.as_ref().map(|h| json!({"Status": h})),
+            },
+        })
+    }
+}
+
+/// An event as sent on the wire, with its time for `since` replay.
+#[derive(Clone)]
+struct EngineEvent {
+    time: i64,
+    event: Value,
+}
 
 /// State shared between the router and test code.
 #[derive(Default)]
 struct ApiState {
-    containers: Vec<serde_json::Value>,
-    /// Per-container log lines buffered for /containers/{id}/logs.
-    log_lines: HashMap<String, Vec<String>>,
-    /// Senders for live streaming (future use).
-    log_senders: HashMap<String, mpsc::UnboundedSender<String>>,
+    /// Containers in creation order.
+    containers: Vec<FakeContainer>,
+    events: Vec<EngineEvent>,
+    last_log_ts: Option<DateTime<Utc>>,
+}
+
+impl ApiState {
+    fn container(&mut self, id: &str) -> &mut FakeContainer {
+        self.containers
+            .iter_mut()
+            .find(|c| c.id == id)
+            .expect("container exists")
+    }
+
+    /// Record a container event with the container's name and labels as
+    /// actor attributes, as the Engine does.
+    fn record(&mut self, action: &str, id: &str, extra: &[(&str, String)]) -> EngineEvent {
+        let container = self.container(id);
+        let mut attributes: serde_json::Map<String, Value> = container
+            .labels
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone().into()))
+            .collect();
+        attributes.insert("name".into(), container.name.clone().into());
+        attributes.insert("image".into(), "fake/image:latest".into());
+        for (k, v) in extra {
+            attributes.insert(k.to_string(), v.clone().into());
+        }
+        let now = Utc::now();
+        let event = EngineEvent {
+            time: now.timestamp(),
+            event: json!({
+                "Type": "container",
+                "Action": action,
+                "status": action,
+                "id": id,
+                "Actor": {"ID": id, "Attributes": attributes},
+                "scope": "local",
+                "time": now.timestamp(),
+                "timeNano": now.timestamp_nanos_opt().unwrap_or_default(),
+            }),
+        };
+        self.events.push(event.clone());
+        event
+    }
+}
+
+/// Where the server listens.
+enum Transport {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+    Tls(SocketAddr),
 }
 
 /// Handle to the running fake Docker API server.
 pub struct FakeDockerApi {
-    addr: SocketAddr,
+    transport: Transport,
     state: Arc<Mutex<ApiState>>,
+    events_tx: broadcast::Sender<EngineEvent>,
+}
+
+#[derive(Clone)]
+struct Shared {
+    state: Arc<Mutex<ApiState>>,
+    events_tx: broadcast::Sender<EngineEvent>,
 }
 
 impl FakeDockerApi {
@@ -59,72 +180,414 @@ impl FakeDockerApi {
     pub async fn start() -> std::io::Result<Self> {
         let listener = TcpListener::bind("127.0.0.1:0").await?;
         let addr = listener.local_addr()?;
-        let state = Arc::new(Mutex::new(ApiState::default()));
-
-        let app = Router::new()
-            .route("/containers/json", get(list_containers))
-            .route("/containers/:id/logs", get(stream_logs))
-            .with_state(state.clone());
+        let (api, app) = Self::new(Transport::Tcp(addr));
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        Ok(api)
+    }
 
+    /// Start the server on a Unix socket at `path`.
+    pub async fn start_unix(path: &FsPath) -> std::io::Result<Self> {
+        let listener = UnixListener::bind(path)?;
+        let (api, app) = Self::new(Transport::Unix(path.to_path_buf()));
         tokio::spawn(async move {
             axum::serve(listener, app).await.unwrap();
         });
+        Ok(api)
+    }
 
-        // Give the task a moment to register.
-        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    /// Start the server on a random port behind TLS, presenting
+    /// `server-cert.pem` from `fixtures` and requiring a client certificate
+    /// signed by its `ca.pem`.
+    pub async fn start_tls(fixtures: &FsPath) -> std::io::Result<Self> {
+        let listener = TlsListener {
+            tcp: TcpListener::bind("127.0.0.1:0").await?,
+            acceptor: tls_acceptor(fixtures),
+        };
+        let addr = listener.tcp.local_addr()?;
+        let (api, app) = Self::new(Transport::Tls(addr));
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        Ok(api)
+    }
 
-        Ok(Self { addr, state })
+    fn new(transport: Transport) -> (Self, Router) {
+        let state = Arc::new(Mutex::new(ApiState::default()));
+        let (events_tx, _) = broadcast::channel(1024);
+        let app = Router::new()
+            .route("/containers/json", get(list_containers))
+            .route("/containers/{id}/json", get(inspect_container))
+            .route("/containers/{id}/logs", get(container_logs))
+            .route("/events", get(events))
+            .with_state(Shared {
+                state: state.clone(),
+                events_tx: events_tx.clone(),
+            });
+        let api = Self {
+            transport,
+            state,
+            events_tx,
+        };
+        (api, app)
     }
 
-    /// Base URL for the API (e.g. `http://127.0.0.1:PORT`).
-    pub fn base_url(&self) -> String {
-        format!("http://{}", self.addr)
+    /// `DOCKER_HOST`-style endpoint for the feed: `tcp://127.0.0.1:PORT` or
+    /// `unix:///path`. TLS servers need [`Tls`](fml_feeds::docker::Tls)
+    /// attached to the parsed endpoint.
+    pub fn endpoint(&self) -> String {
+        match &self.transport {
+            Transport::Tcp(addr) | Transport::Tls(addr) => format!("tcp://{addr}"),
+            Transport::Unix(path) => format!("unix://{}", path.display()),
+        }
     }
 
-    /// Register a container in the /containers/json response.
+    /// Register a container that was already running before the feed
+    /// started. No event is published.
     pub async fn add_container(&self, id: &str, name: &str) {
-        let (tx, _rx) = mpsc::unbounded_channel::<String>();
+        self.add_container_with(id, name, &[]).await;
+    }
+
+    /// [`add_container`](Self::add_container) with labels.
+    pub async fn add_container_with(&self, id: &str, name: &str, labels: &[(&str, &str)]) {
+        self.create(id, name, labels, true).await;
+    }
+
+    /// Register a Compose service container: `project`, `service` and
+    /// `container-number` labels, named `project-service-N`.
+    pub async fn add_compose_container(&self, id: &str, project: &str, service: &str, n: u32) {
+        let number = n.to_string();
+        let labels = [
+            ("com.docker.compose.project", project),
+            ("com.docker.compose.service", service),
+            ("com.docker.compose.container-number", number.as_str()),
+        ];
+        self.add_container_with(id, &format!("{project}-{service}-{n}"), &labels)
+            .await;
+    }
+
+    /// Create a container and start it, publishing a `start` event.
+    pub async fn run_container(&self, id: &str, name: &str, labels: &[(&str, &str)]) {
+        self.create(id, name, labels, false).await;
+        self.start_container(id).await;
+    }
+
+    async fn create(&self, id: &str, name: &str, labels: &[(&str, &str)], running: bool) {
         let mut state = self.state.lock().await;
-        state.containers.push(serde_json::json!({
-            "Id": id,
-            "Names": [format!("/{}", name)],
-            "State": "running",
-            "Labels": {}
-        }));
-        state.log_lines.insert(id.to_string(), vec![]);
-        state.log_senders.insert(id.to_string(), tx);
+        state.containers.push(FakeContainer {
+            id: id.to_string(),
+            name: name.to_string(),
+            labels: labels
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            running,
+            exit_code: 0,
+            oom_killed: false,
+            health: None,
+            lines: Vec::new(),
+            followers: Vec::new(),
+        });
+    }
+
+    /// (Re)start a stopped container and publish a `start` event.
+    pub async fn start_container(&self, id: &str) {
+        let event = {
+            let mut state = self.state.lock().await;
+            state.container(id).running = true;
+            state.record("start", id, &[])
+        };
+        let _ = self.events_tx.send(event);
     }
 
-    /// Buffer a log line to be returned by /containers/{id}/logs.
+    /// Append a stdout line to a container's log, delivering it to followers.
     pub async fn stream_log(&self, container_id: &str, line: &str) {
+        self.append(container_id, STDOUT, line).await;
+    }
+
+    /// Append a stderr line to a container's log.
+    pub async fn stream_stderr(&self, container_id: &str, line: &str) {
+        self.append(container_id, STDERR, line).await;
+    }
+
+    /// Each line gets a strictly increasing timestamp.
+    async fn append(&self, id: &str, stream: u8, text: &str) {
+        let mut state = self.state.lock().await;
+        let now = Utc::now();
+        let ts = match state.last_log_ts {
+            Some(last) if now <= last => last + ChronoDuration::microseconds(1),
+            _ => now,
+        };
+        state.last_log_ts = Some(ts);
+        let line = LogLine {
+            ts,
+            stream,
+            text: text.to_string(),
+        };
+        let container = state.container(id);
+        container.lines.push(line.clone());
+        container.followers.retain(|f| f.send(line.clone()).is_ok());
+    }
+
+    /// Stop a container with `exit_code`: its log streams end and a `die`
+    /// event is published.
+    pub async fn stop_container(&self, id: &str, exit_code: i32) {
+        let event = {
+            let mut state = self.state.lock().await;
+            let container = state.container(id);
+            container.running = false;
+            container.exit_code = exit_code;
+            container.followers.clear();
+            state.record("die", id, &[("exitCode", exit_code.to_string())])
+        };
+        let _ = self.events_tx.send(event);
+    }
+
+    /// Kill a container for running out of memory: an `oom` event, then it
+    /// stops with exit code 137.
+    pub async fn oom_kill(&self, id: &str) {
+        let event = {
+            let mut state = self.state.lock().await;
+            state.container(id).oom_killed = true;
+            state.record("oom", id, &[])
+        };
+        let _ = self.events_tx.send(event);
+        self.stop_container(id, 137).await;
+    }
+
+    /// Set a container's health check status (`starting`, `healthy`,
+    /// `unhealthy`) and publish a `health_status` event.
+    pub async fn set_health(&self, id: &str, health: &str) {
+        let event = {
+            let mut state = self.state.lock().await;
+            state.container(id).health = Some(health.to_string());
+            state.record(&format!("health_status: {health}"), id, &[])
+        };
+        let _ = self.events_tx.send(event);
+    }
+
+    /// Delete a container and publish a `destroy` event.
+    pub async fn remove_container(&self, id: &str) {
+        let event = {
+            let mut state = self.state.lock().await;
+            let event = state.record("destroy", id, &[]);
+            state.containers.retain(|c| c.id != id);
+            event
+        };
+        let _ = self.events_tx.send(event);
+    }
+
+    /// Close every open log stream for a container while it keeps running,
+    /// as happens when the daemon restarts its log driver.
+    pub async fn drop_log_streams(&self, id: &str) {
+        self.state.lock().await.container(id).followers.clear();
+    }
+
+    /// Number of open `/events` streams.
+    pub fn event_subscribers(&self) -> usize {
+        self.events_tx.receiver_count()
+    }
+
+    /// Number of currently open `follow=1` streams for a container.
+    pub async fn follower_count(&self, id: &str) -> usize {
         let mut state = self.state.lock().await;
-        if let Some(lines) = state.log_lines.get_mut(container_id) {
-            lines.push(line.to_string());
+        let container = state.container(id);
+        container.followers.retain(|f| !f.is_closed());
+        container.followers.len()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// TLS
+// ---------------------------------------------------------------------------
+
+/// A TCP listener that completes a TLS handshake before handing the
+/// connection to axum. Failed handshakes are dropped.
+struct TlsListener {
+    tcp: TcpListener,
+    acceptor: tokio_rustls::TlsAcceptor,
+}
+
+impl axum::serve::Listener for TlsListener {
+    type Io = tokio_rustls::server::TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        loop {
+            let Ok((stream, addr)) = self.tcp.accept().await else {
+                continue;
+            };
+            if let Ok(tls) = self.acceptor.accept(stream).await {
+                return (tls, addr);
+            }
         }
     }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        self.tcp.local_addr()
+    }
+}
+
+fn tls_acceptor(fixtures: &FsPath) -> tokio_rustls::TlsAcceptor {
+    use rustls_pki_types::pem::PemObject;
+    use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+    use tokio_rustls::rustls::{self, server::WebPkiClientVerifier, RootCertStore};
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut roots = RootCertStore::empty();
+    for cert in CertificateDer::pem_file_iter(fixtures.join("ca.pem")).unwrap() {
+        roots.add(cert.unwrap()).unwrap();
+    }
+    let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+        .build()
+        .unwrap();
+    let certs = CertificateDer::pem_file_iter(fixtures.join("server-cert.pem"))
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let key = PrivateKeyDer::from_pem_file(fixtures.join("server-key.pem")).unwrap();
+    let config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(certs, key)
+        .unwrap();
+    tokio_rustls::TlsAcceptor::from(Arc::new(config))
 }
 
 // ---------------------------------------------------------------------------
 // Route handlers
 // ---------------------------------------------------------------------------
 
-async fn list_containers(State(state): State<Arc<Mutex<ApiState>>>) -> impl IntoResponse {
-    let state = state.lock().await;
-    axum::Json(state.containers.clone())
+fn not_found(id: &str) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        axum::Json(json!({"message": format!("No such container: {id}")})),
+    )
+        .into_response()
+}
+
+async fn list_containers(State(shared): State<Shared>) -> Response {
+    let state = shared.state.lock().await;
+    let list: Vec<Value> = state
+        .containers
+        .iter()
+        .filter(|c| c.running)
+        .map(|c| {
+            json!({
+                "Id": c.id,
+                "Names": [format!("/{}", c.name)],
+                "Image": "fake/image:latest",
+                "State": "running",
+                "Labels": c.labels,
+            })
+        })
+        .collect();
+    axum::Json(list).into_response()
 }
 
-async fn stream_logs(
+async fn inspect_container(Path(id): Path<String>, State(shared): State<Shared>) -> Response {
+    let state = shared.state.lock().await;
+    match state.containers.iter().find(|c| c.id == id) {
+        Some(container) => axum::Json(container.inspect()).into_response(),
+        None => not_found(&id),
+    }
+}
+
+async fn container_logs(
     Path(id): Path<String>,
-    State(state): State<Arc<Mutex<ApiState>>>,
-) -> impl IntoResponse {
-    let lines = {
-        let state = state.lock().await;
-        state.log_lines.get(&id).cloned().unwrap_or_default()
+    Query(params): Query<HashMap<String, String>>,
+    State(shared): State<Shared>,
+) -> Response {
+    let flag = |key: &str| matches!(params.get(key).map(String::as_str), Some("1" | "true"));
+    let timestamps = flag("timestamps");
+    let follow = flag("follow");
+
+    let mut state = shared.state.lock().await;
+    let Some(container) = state.containers.iter_mut().find(|c| c.id == id) else {
+        return not_found(&id);
     };
 
-    if lines.is_empty() {
-        return (axum::http::StatusCode::NOT_FOUND, String::new());
+    let mut lines = container.lines.clone();
+    if let Some(since) = params.get("since").and_then(|s| parse_since(s)) {
+        lines.retain(|l| l.ts >= since);
+    }
+    if let Some(tail) = params.get("tail").and_then(|n| n.parse::<usize>().ok()) {
+        let skip = lines.len().saturating_sub(tail);
+        lines.drain(..skip);
     }
 
-    (axum::http::StatusCode::OK, lines.join("\n"))
+    let render = move |line: LogLine| -> Result<Bytes, Infallible> { Ok(frame(&line, timestamps)) };
+    let backlog = futures::stream::iter(lines.into_iter().map(render).collect::<Vec<_>>());
+
+    if !follow || !container.running {
+        return Body::from_stream(backlog).into_response();
+    }
+    let (tx, rx) = mpsc::unbounded_channel();
+    container.followers.push(tx);
+    let live = futures::stream::unfold(rx, move |mut rx| async move {
+        rx.recv().await.map(|line| (render(line), rx))
+    });
+    Body::from_stream(backlog.chain(live)).into_response()
+}
+
+/// `since` as `SECONDS[.NANOS]`.
+fn parse_since(s: &str) -> Option<DateTime<Utc>> {
+    let (secs, nanos) = match s.split_once('.') {
+        Some((secs, nanos)) => (secs.parse().ok()?, format!("{nanos:0<9}").parse().ok()?),
+        None => (s.parse().ok()?, 0),
+    };
+    DateTime::from_timestamp(secs, nanos)
+}
+
+/// One multiplexed frame: 8-byte header, then the (timestamped) line.
+fn frame(line: &LogLine, timestamps: bool) -> Bytes {
+    let payload = if timestamps {
+        format!(
+            "{} {}\n",
+            line.ts.to_rfc3339_opts(SecondsFormat::Nanos, true),
+            line.text
+        )
+    } else {
+        format!("{}\n", line.text)
+    };
+    let mut out = vec![line.stream, 0, 0, 0];
+    out.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    out.extend_from_slice(payload.as_bytes());
+    Bytes::from(out)
+}
+
+async fn events(
+    Query(params): Query<HashMap<String, String>>,
+    State(shared): State<Shared>,
+) -> Response {
+    let since: Option<i64> = params.get("since").and_then(|s| s.parse().ok());
+    // Snapshot the backlog and subscribe under one lock so nothing is missed.
+    let (backlog, rx) = {
+        let state = shared.state.lock().await;
+        let backlog: Vec<Value> = match since {
+            Some(since) => state
+                .events
+                .iter()
+                .filter(|e| e.time >= since)
+                .map(|e| e.event.clone())
+                .collect(),
+            None => Vec::new(),
+        };
+        (backlog, shared.events_tx.subscribe())
+    };
+
+    let json_line = |event: &Value| -> Result<String, Infallible> { Ok(format!("{event}\n")) };
+    let live = futures::stream::unfold(rx, move |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(e) => return Some((json_line(&e.event), rx)),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    let body = futures::stream::iter(backlog.iter().map(json_line).collect::<Vec<_>>()).chain(live);
+    Body::from_stream(body).into_response()
 }
@@ -0,0 +1,703 @@
+//! Fake Kubernetes API server for integration tests.
+//!
+//! Spins up a minimal `axum` HTTP server on a random TCP port bound to
+//! 127.0.0.1, in the style of [`fake_docker_api`](super::fake_docker_api).
+//! Serves the subset of the core/v1 API the Kubernetes feed uses:
+//! - `GET /api/v1/namespaces/{ns}/pods` — pod list, or a watch stream with
+//!   `?watch=true` (events after `resourceVersion` are replayed first);
+//!   equality-based `labelSelector`s (`k=v`, `k!=v`, `k`) are honoured
+//! - `GET /api/v1/namespaces/{ns}/events` — core/v1 events, listed or
+//!   watched the same way
+//! - `GET /api/v1/namespaces/{ns}/pods/{pod}` — a single pod
+//! - `GET /api/v1/namespaces/{ns}/pods/{pod}/log` — buffered lines, honouring
+//!   `container`, `timestamps`, `tailLines` and `sinceTime`; with
+//!   `follow=true` the response stays open and receives lines logged later;
+//!   `previous=true` serves the log of the instance before the last restart
+//!
+//! The feed reaches the server through a kubeconfig written by
+//! [`FakeKubeApi::write_kubeconfig`], so every test also exercises kubeconfig
+//! loading. `Authorization` headers are recorded for auth assertions.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # tokio_test::block_on(async {
+//! use common::fake_kube_api::{token_user, FakeKubeApi};
+//!
+//! let api = FakeKubeApi::start().await.unwrap();
+//! api.add_pod("shop", "api-1", &["app"]).await;
+//! api.log("shop", "api-1", "app", "GET /health 200").await;
+//! let dir = tempfile::tempdir().unwrap();
+//! let kubeconfig = api.write_kubeconfig(dir.path(), &token_user("t0ken"));
+//! # });
+//! ```
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Duration as ChronoDuration, SecondsFormat, Utc};
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, mpsc, Mutex};
+
+type ContainerKey = (String, String, String);
+
+/// One buffered log line.
+#[derive(Clone)]
+struct LogLine {
+    ts: DateTime<Utc>,
+    text: String,
+}
+
+#[derive(Default)]
+struct ContainerLog {
+    lines: Vec<LogLine>,
+    /// Log of the previous instance, served for `previous=true`.
+    previous: Option<Vec<LogLine>>,
+    /// Open `follow=true` responses.
+    followers: Vec<mpsc::UnboundedSender<LogLine>>,
+}
+
+/// A watch event as sent on the wire, tagged for replay and filtering.
+#[derive(Clone)]
+struct WatchEvent {
+    resource_version: u64,
+    /// `pods` or `events`.
+    resource: &'static str,
+    namespace: String,
+    event: Value,
+}
+
+impl WatchEvent {
+    fn visible(&self, resource: &str, ns: &str, selector: Option<&str>) -> bool {
+        self.resource == resource
+            && self.namespace == ns
+            && matches_selector(&self.event["object"], selector)
+    }
+}
+
+/// Evaluate an equality-based label selector against a pod.
+fn matches_selector(pod: &Value, selector: Option<&str>) -> bool {
+    let labels = &pod["metadata"]["labels"];
+    selector
+        .into_iter()
+        .flat_map(|s| s.split(','))
+        .filter(|term| !term.is_empty())
+        .all(|term| {
+            if let Some((k, v)) = term.split_once("!=") {
+                labels[k].as_str() != Some(v)
+            } else if let Some((k, v)) = term.split_once('=') {
+                labels[k].as_str() == Some(v.trim_start_matches('='))
+            } else if let Some(k) = term.strip_prefix('!') {
+                labels.get(k).is_none()
+            } else {
+                labels.get(term).is_some()
+            }
+        })
+}
+
+/// State shared between the router and test code.
+#[derive(Default)]
+struct ApiState {
+    resource_version: u64,
+    pods: BTreeMap<(String, String), Value>,
+    /// core/v1 `Event` objects.
+    k8s_events: BTreeMap<(String, String), Value>,
+    /// Watch history, replayed to watches from their `resourceVersion`.
+    events: Vec<WatchEvent>,
+    logs: HashMap<ContainerKey, ContainerLog>,
+    last_log_ts: Option<DateTime<Utc>>,
+    authorization: Vec<String>,
+}
+
+impl ApiState {
+    /// Bump the resource version, stamp it on the pod and record the event.
+    fn publish(&mut self, kind: &str, ns: &str, name: &str) -> WatchEvent {
+        self.resource_version += 1;
+        let rv = self.resource_version;
+        let pod = self
+            .pods
+            .get_mut(&(ns.to_string(), name.to_string()))
+            .expect("pod exists");
+        pod["metadata"]["resourceVersion"] = rv.to_string().into();
+        let object = pod.clone();
+        self.record("pods", kind, ns, object)
+    }
+
+    fn record(
+        &mut self,
+        resource: &'static str,
+        kind: &str,
+        ns: &str,
+        object: Value,
+    ) -> WatchEvent {
+        let event = WatchEvent {
+            resource_version: self.resource_version,
+            resource,
+            namespace: ns.to_string(),
+            event: json!({"type": kind, "object": object}),
+        };
+        self.events.push(event.clone());
+        event
+    }
+}
+
+/// Handle to the running fake Kubernetes API server.
+pub struct FakeKubeApi {
+    addr: SocketAddr,
+    state: Arc<Mutex<ApiState>>,
+    watch_tx: broadcast::Sender<WatchEvent>,
+}
+
+#[derive(Clone)]
+struct Shared {
+    state: Arc<Mutex<ApiState>>,
+    watch_tx: broadcast::Sender<WatchEvent>,
+}
+
+impl FakeKubeApi {
+    /// Start the fake API server on a random port. Returns once the server is
+    /// listening.
+    pub async fn start() -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(ApiState::default()));
+        let (watch_tx, _) = broadcast::channel(1024);
+
+        let app = Router::new()
+            .route("/api/v1/namespaces/{ns}/pods", get(list_or_watch_pods))
+            .route("/api/v1/namespaces/{ns}/events", get(list_or_watch_events))
+            .route("/api/v1/namespaces/{ns}/pods/{pod}", get(get_pod))
+            .route("/api/v1/namespaces/{ns}/pods/{pod}/log", get(pod_log))
+            .with_state(Shared {
+                state: state.clone(),
+                watch_tx: watch_tx.clone(),
+            });
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        Ok(Self {
+            addr,
+            state,
+            watch_tx,
+        })
+    }
+
+    /// Base URL for the API (e.g. `http://127.0.0.1:PORT`).
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Write a kubeconfig with a single `fake` context pointing at this server
+    /// and return its path. `user` is the YAML body of the `user:` entry; see
+    /// [`token_user`] and [`exec_user`].
+    pub fn write_kubeconfig(&self, dir: &FsPath, user: &str) -> PathBuf {
+        let user = user
+            .lines()
+            .map(|l| format!("      {l}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let yaml = format!(
+            "apiVersion: v1\n\
+             kind: Config\n\
+             current-context: fake\n\
+             clusters:\n\
+             - name: fake\n  cluster:\n    server: {}\n\
+             contexts:\n\
+             - name: fake\n  context:\n    cluster: fake\n    user: fake\n    namespace: default\n\
+             users:\n\
+             - name: fake\n  user:\n{user}\n",
+            self.base_url()
+        );
+        let path = dir.join("kubeconfig");
+        std::fs::write(&path, yaml).unwrap();
+        path
+    }
+
+    /// Add a running pod with the given containers and announce it to
+    /// watchers.
+    pub async fn add_pod(&self, ns: &str, name: &str, containers: &[&str]) {
+        self.add_pod_with(ns, name, containers, &[], None).await;
+    }
+
+    /// Add a running pod with labels and, optionally, a controlling owner
+    /// given as `(kind, name)`, e.g. `("ReplicaSet", "api-7d9f8b6c5d")`.
+    pub async fn add_pod_with(
+        &self,
+        ns: &str,
+        name: &str,
+        containers: &[&str],
+        labels: &[(&str, &str)],
+        owner: Option<(&str, &str)>,
+    ) {
+        let labels: serde_json::Map<String, Value> = labels
+            .iter()
+            .map(|(k, v)| (k.to_string(), Value::from(*v)))
+            .collect();
+        let owner_references: Vec<Value> = owner
+            .into_iter()
+            .map(|(kind, owner)| {
+                json!({
+                    "apiVersion": "apps/v1", "kind": kind, "name": owner,
+                    "uid": format!("uid-{ns}-{owner}"), "controller": true,
+                })
+            })
+            .collect();
+        let pod = json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": {
+                "name": name,
+                "namespace": ns,
+                "uid": format!("uid-{ns}-{name}"),
+                "labels": labels,
+                "ownerReferences": owner_references,
+            },
+            "spec": {
+                "containers": containers.iter().map(|c| json!({"name": c})).collect::<Vec<_>>(),
+                "nodeName": "node-1",
+            },
+            "status": {
+                "phase": "Running",
+                "containerStatuses": containers.iter().map(|c| json!({
+                    "name": c,
+                    "ready": true,
+                    "restartCount": 0,
+                    "image": format!("{c}:latest"),
+                    "imageID": "",
+                    "state": {"running": {"startedAt": "2024-01-15T10:00:00Z"}},
+                })).collect::<Vec<_>>(),
+            },
+        });
+        let mut state = self.state.lock().await;
+        state.pods.insert((ns.to_string(), name.to_string()), pod);
+        for c in containers {
+            state
+                .logs
+                .entry((ns.to_string(), name.to_string(), c.to_string()))
+                .or_default();
+        }
+        let event = state.publish("ADDED", ns, name);
+        let _ = self.watch_tx.send(event);
+    }
+
+    /// Delete a pod: watchers see `DELETED`, open log streams end.
+    pub async fn delete_pod(&self, ns: &str, name: &str) {
+        let mut state = self.state.lock().await;
+        let event = state.publish("DELETED", ns, name);
+        state.pods.remove(&(ns.to_string(), name.to_string()));
+        for ((lns, lpod, _), log) in state.logs.iter_mut() {
+            if lns == ns && lpod == name {
+                log.followers.clear();
+            }
+        }
+        let _ = self.watch_tx.send(event);
+    }
+
+    /// Mark a container as terminated with `exit_code`; its log streams end.
+    pub async fn stop_container(&self, ns: &str, pod: &str, container: &str, exit_code: i32) {
+        let mut state = self.state.lock().await;
+        if let Some(p) = state.pods.get_mut(&(ns.to_string(), pod.to_string())) {
+            if let Some(statuses) = p["status"]["containerStatuses"].as_array_mut() {
+                for s in statuses.iter_mut().filter(|s| s["name"] == container) {
+                    s["ready"] = false.into();
+                    s["state"] = json!({"terminated": {"exitCode": exit_code}});
+                }
+            }
+        }
+        if let Some(log) =
+            state
+                .logs
+                .get_mut(&(ns.to_string(), pod.to_string(), container.to_string()))
+        {
+            log.followers.clear();
+        }
+        let event = state.publish("MODIFIED", ns, pod);
+        let _ = self.watch_tx.send(event);
+    }
+
+    /// Restart a container: its current log becomes the previous instance's,
+    /// the restart count goes up, open log streams end, and the new instance
+    /// starts running with an empty log.
+    pub async fn restart_container(&self, ns: &str, pod: &str, container: &str, exit_code: i32) {
+        let mut state = self.state.lock().await;
+        if let Some(p) = state.pods.get_mut(&(ns.to_string(), pod.to_string())) {
+            if let Some(statuses) = p["status"]["containerStatuses"].as_array_mut() {
+                for s in statuses.iter_mut().filter(|s| s["name"] == container) {
+                    let count = s["restartCount"].as_i64().unwrap_or(0) + 1;
+                    s["restartCount"] = count.into();
+                    s["lastState"] = json!({"terminated": {"exitCode": exit_code}});
+                    s["state"] = json!({"running": {"startedAt": Utc::now().to_rfc3339()}});
+                }
+            }
+        }
+        let log = state
+            .logs
+            .entry((ns.to_string(), pod.to_string(), container.to_string()))
+            .or_default();
+        log.previous = Some(std::mem::take(&mut log.lines));
+        log.followers.clear();
+        let event = state.publish("MODIFIED", ns, pod);
+        let _ = self.watch_tx.send(event);
+    }
+
+    /// Put a container into `CrashLoopBackOff` after its instance died with
+    /// `reason` (`OOMKilled`, `Error`) and `exit_code`: the restart count goes
+    /// up, open log streams end and no new instance is running.
+    pub async fn crash_loop(
+        &self,
+        ns: &str,
+        pod: &str,
+        container: &str,
+        reason: &str,
+        exit_code: i32,
+    ) {
+        let mut state = self.state.lock().await;
+        if let Some(p) = state.pods.get_mut(&(ns.to_string(), pod.to_string())) {
+            if let Some(statuses) = p["status"]["containerStatuses"].as_array_mut() {
+                for s in statuses.iter_mut().filter(|s| s["name"] == container) {
+                    let count = s["restartCount"].as_i64().unwrap_or(0) + 1;
+                    s["restartCount"] = count.into();
+                    s["ready"] = false.into();
+                    s["lastState"] =
+                        json!({"terminated": {"exitCode": exit_code, "reason": reason}});
+                    s["state"] = json!({"waiting": {"reason": "CrashLoopBackOff"}});
+                }
+            }
+        }
+        let log = state
+            .logs
+            .entry((ns.to_string(), pod.to_string(), container.to_string()))
+            .or_default();
+        log.previous = Some(std::mem::take(&mut log.lines));
+        log.followers.clear();
+        let event = state.publish("MODIFIED", ns, pod);
+        let _ = self.watch_tx.send(event);
+    }
+
+    /// Record a core/v1 `Event` in `ns`. `event` needs at least
+    /// `metadata.name`; re-recording a name updates it (as the kubelet does
+    /// when bumping `count`).
+    pub async fn record_event(&self, ns: &str, mut event: Value) {
+        let name = event["metadata"]["name"]
+            .as_str()
+            .expect("event needs metadata.name")
+            .to_string();
+        let mut state = self.state.lock().await;
+        state.resource_version += 1;
+        event["apiVersion"] = "v1".into();
+        event["kind"] = "Event".into();
+        event["metadata"]["namespace"] = ns.into();
+        event["metadata"]["uid"] = format!("uid-event-{ns}-{name}").into();
+        event["metadata"]["resourceVersion"] = state.resource_version.to_string().into();
+        let kind = match state
+            .k8s_events
+            .insert((ns.to_string(), name), event.clone())
+        {
+            Some(_) => "MODIFIED",
+            None => "ADDED",
+        };
+        let watch_event = state.record("events", kind, ns, event);
+        let _ = self.watch_tx.send(watch_event);
+    }
+
+    /// Append a line to a container's log, delivering it to followers. Each
+    /// line gets a strictly increasing timestamp.
+    pub async fn log(&self, ns: &str, pod: &str, container: &str, text: &str) {
+        let mut state = self.state.lock().await;
+        let now = Utc::now();
+        let ts = match state.last_log_ts {
+            Some(last) if now <= last => last + ChronoDuration::microseconds(1),
+            _ => now,
+        };
+        state.last_log_ts = Some(ts);
+        let line = LogLine {
+            ts,
+            text: text.to_string(),
+        };
+        let log = state
+            .logs
+            .entry((ns.to_string(), pod.to_string(), container.to_string()))
+            .or_default();
+        log.lines.push(line.clone());
+        log.followers.retain(|f| f.send(line.clone()).is_ok());
+    }
+
+    /// Close every open log stream for a container, as the API server does
+    /// on request timeouts. The container keeps running.
+    pub async fn drop_log_streams(&self, ns: &str, pod: &str, container: &str) {
+        let mut state = self.state.lock().await;
+        if let Some(log) =
+            state
+                .logs
+                .get_mut(&(ns.to_string(), pod.to_string(), container.to_string()))
+        {
+            log.followers.clear();
+        }
+    }
+
+    /// Number of currently open `follow=true` streams for a container.
+    pub async fn follower_count(&self, ns: &str, pod: &str, container: &str) -> usize {
+        let mut state = self.state.lock().await;
+        state
+            .logs
+            .get_mut(&(ns.to_string(), pod.to_string(), container.to_string()))
+            .map(|log| {
+                log.followers.retain(|f| !f.is_closed());
+                log.followers.len()
+            })
+            .unwrap_or(0)
+    }
+
+    /// Every `Authorization` header received so far.
+    pub async fn authorization_headers(&self) -> Vec<String> {
+        self.state.lock().await.authorization.clone()
+    }
+}
+
+/// Kubeconfig `user:` body authenticating with a static bearer token.
+pub fn token_user(token: &str) -> String {
+    format!("token: {token}")
+}
+
+/// Kubeconfig `user:` body using an exec credential plugin (`sh -c echo`)
+/// that returns `token`.
+pub fn exec_user(token: &str) -> String {
+    let credential = json!({
+        "apiVersion": "client.authentication.k8s.io/v1beta1",
+        "kind": "ExecCredential",
+        "status": {"token": token},
+    });
+    format!(
+        "exec:\n  apiVersion: client.authentication.k8s.io/v1beta1\n  command: sh\n  args:\n  - -c\n  - \"echo '{}'\"",
+        credential.to_string().replace('"', "\\\"")
+    )
+}
+
+// ---------------------------------------------------------------------------
+// Route handlers
+// ---------------------------------------------------------------------------
+
+async fn record_auth(shared: &Shared, headers: &HeaderMap) {
+    if let Some(auth) = headers.get("authorization").and_then(|v| v.to_str().ok()) {
+        shared
+            .state
+            .lock()
+            .await
+            .authorization
+            .push(auth.to_string());
+    }
+}
+
+fn not_found(what: &str) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        axum::Json(json!({
+            "kind": "Status", "apiVersion": "v1", "status": "Failure",
+            "reason": "NotFound", "code": 404, "message": format!("{what} not found"),
+        })),
+    )
+        .into_response()
+}
+
+fn ndjson_line(value: &Value) -> Result<String, Infallible> {
+    Ok(format!("{value}\n"))
+}
+
+async fn list_or_watch_pods(
+    Path(ns): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    State(shared): State<Shared>,
+) -> Response {
+    list_or_watch("pods", ns, params, headers, shared).await
+}
+
+async fn list_or_watch_events(
+    Path(ns): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    State(shared): State<Shared>,
+) -> Response {
+    list_or_watch("events", ns, params, headers, shared).await
+}
+
+async fn list_or_watch(
+    resource: &'static str,
+    ns: String,
+    params: HashMap<String, String>,
+    headers: HeaderMap,
+    shared: Shared,
+) -> Response {
+    record_auth(&shared, &headers).await;
+    let watch = matches!(params.get("watch").map(String::as_str), Some("true" | "1"));
+    let selector = params.get("labelSelector").cloned();
+
+    if !watch {
+        let state = shared.state.lock().await;
+        let (objects, list_kind) = match resource {
+            "pods" => (&state.pods, "PodList"),
+            _ => (&state.k8s_events, "EventList"),
+        };
+        let items: Vec<Value> = objects
+            .iter()
+            .filter(|((ons, _), o)| *ons == ns && matches_selector(o, selector.as_deref()))
+            .map(|(_, o)| o.clone())
+            .collect();
+        return axum::Json(json!({
+            "apiVersion": "v1",
+            "kind": list_kind,
+            "metadata": {"resourceVersion": state.resource_version.to_string()},
+            "items": items,
+        }))
+        .into_response();
+    }
+
+    let since: u64 = params
+        .get("resourceVersion")
+        .and_then(|rv| rv.parse().ok())
+        .unwrap_or(0);
+    // Snapshot the backlog and subscribe under one lock so nothing is missed.
+    let (backlog, rx) = {
+        let state = shared.state.lock().await;
+        let backlog: Vec<Value> = state
+            .events
+            .iter()
+            .filter(|e| e.resource_version > since && e.visible(resource, &ns, selector.as_deref()))
+            .map(|e| e.event.clone())
+            .collect();
+        (backlog, shared.watch_tx.subscribe())
+    };
+
+    let live = futures::stream::unfold((rx, ns), move |(mut rx, ns)| {
+        let selector = selector.clone();
+        async move {
+            loop {
+                match rx.recv().await {
+                    Ok(e) if e.visible(resource, &ns, selector.as_deref()) => {
+                        return Some((ndjson_line(&e.event), (rx, ns)))
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        }
+    });
+    let body =
+        futures::stream::iter(backlog.iter().map(ndjson_line).collect::<Vec<_>>()).chain(live);
+    Body::from_stream(body).into_response()
+}
+
+async fn get_pod(
+    Path((ns, name)): Path<(String, String)>,
+    headers: HeaderMap,
+    State(shared): State<Shared>,
+) -> Response {
+    record_auth(&shared, &headers).await;
+    let state = shared.state.lock().await;
+    match state.pods.get(&(ns, name.clone())) {
+        Some(pod) => axum::Json(pod.clone()).into_response(),
+        None => not_found(&format!("pods \"{name}\"")),
+    }
+}
+
+async fn pod_log(
+    Path((ns, name)): Path<(String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    State(shared): State<Shared>,
+) -> Response {
+    record_auth(&shared, &headers).await;
+    let flag = |key: &str| params.get(key).map(String::as_str) == Some("true");
+    let timestamps = flag("timestamps");
+    let follow = flag("follow");
+
+    let mut state = shared.state.lock().await;
+    let Some(pod) = state.pods.get(&(ns.clone(), name.clone())) else {
+        return not_found(&format!("pods \"{name}\""));
+    };
+    let container = match params.get("container") {
+        Some(c) => c.clone(),
+        None => pod["spec"]["containers"][0]["name"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+    };
+    let Some(log) = state.logs.get_mut(&(ns, name, container)) else {
+        return not_found("container");
+    };
+
+    if flag("previous") {
+        let Some(previous) = &log.previous else {
+            return (
+                StatusCode::BAD_REQUEST,
+                "previous terminated container not found",
+            )
+                .into_response();
+        };
+        let mut lines = previous.clone();
+        if let Some(tail) = params
+            .get("tailLines")
+            .and_then(|n| n.parse::<usize>().ok())
+        {
+            let skip = lines.len().saturating_sub(tail);
+            lines.drain(..skip);
+        }
+        let body: String = lines.iter().map(|l| render_line(l, timestamps)).collect();
+        return body.into_response();
+    }
+
+    let mut lines: Vec<LogLine> = log.lines.clone();
+    if let Some(since) = params
+        .get("sinceTime")
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+    {
+        lines.retain(|l| l.ts >= since);
+    }
+    if let Some(tail) = params
+        .get("tailLines")
+        .and_then(|n| n.parse::<usize>().ok())
+    {
+        let skip = lines.len().saturating_sub(tail);
+        lines.drain(..skip);
+    }
+
+    let render =
+        move |line: LogLine| -> Result<String, Infallible> { Ok(render_line(&line, timestamps)) };
+    let backlog = futures::stream::iter(lines.into_iter().map(render).collect::<Vec<_>>());
+
+    if !follow {
+        return Body::from_stream(backlog).into_response();
+    }
+    let (tx, rx) = mpsc::unbounded_channel();
+    log.followers.push(tx);
+    let live = futures::stream::unfold(rx, move |mut rx| async move {
+        rx.recv().await.map(|line| (render(line), rx))
+    });
+    Body::from_stream(backlog.chain(live)).into_response()
+}
+
+fn render_line(line: &LogLine, timestamps: bool) -> String {
+    if timestamps {
+        format!(
+            "{} {}\n",
+            line.ts.to_rfc3339_opts(SecondsFormat::Nanos, true),
+            line.text
+        )
+    } else {
+        format!("{}\n", line.text)
+    }
+}
@@ -7,9 +7,12 @@
 pub mod assertions;
 pub mod builders;
 pub mod fake_docker_api;
+pub mod fake_kube_api;
 pub mod fake_process;
 pub mod fixtures;
+pub mod http_client;
 
 pub use assertions::*;
 pub use builders::*;
 pub use fixtures::*;
+pub use http_client::*;
@@ -0,0 +1,250 @@
+#![allow(unused)]
+//! Demo feed integration harness.
+//!
+//! # What this covers
+//!
+//! - **Shipped scenarios**: between them they write every line format (JSON,
+//!   logfmt, plain, access log) and hit every search domain (auth, error,
+//!   network, database, performance, lifecycle, resource).
+//! - **Loading**: scenarios load by name or from a file; unknown names and
+//!   invalid files are refused with the reason.
+//! - **Feed**: producers stream, lines arrive at their rate with the
+//!   `demo` source, event states reach the metadata channel, and a
+//!   non-repeating scenario ends its producers.
+//!
+//! # What this does NOT cover
+//!
+//! - Event effects and seeding in detail (unit-tested in `fml-feeds`)
+//!
+//! # Running
+//!
+//! ```sh
+//! cargo test --test demo_harness
+//! ```
+
+mod common;
+use common::*;
+use fml_core::{FeedKind, LogLevel, ProducerState, ProducerStatus};
+use fml_feeds::demo::{DemoFeed, Format, Scenario, Simulation, BUILTIN};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Terms from each search domain family (docs/search/GREEDY_ALGORITHM.md).
+const DOMAINS: &[(&str, &[&str])] = &[
+    (
+        "auth",
+        &[
+            "login",
+            "token",
+            "session",
+            "jwt",
+            "oauth",
+            "permission",
+            "credential",
+        ],
+    ),
+    (
+        "error",
+        &[
+            "exception",
+            "failure",
+            "failed",
+            "panic",
+            "fatal",
+            "crash",
+            "caused_by",
+        ],
+    ),
+    (
+        "network",
+        &["timeout", "connection", "refused", "unreachable", "reset"],
+    ),
+    (
+        "database",
+        &["query", "deadlock", "transaction", "rolled back", "pool"],
+    ),
+    (
+        "performance",
+        &[
+            "slow",
+            "latency",
+            "elapsed",
+            "duration",
+            "threshold",
+            "backpressure",
+        ],
+    ),
+    (
+        "lifecycle",
+        &["startup", "shutdown", "init", "ready", "healthy", "restart"],
+    ),
+    (
+        "resource",
+        &[
+            "oom",
+            "memory",
+            "disk",
+            "limit",
+            "exhausted",
+            "leak",
+            "gc",
+            "heap",
+        ],
+    ),
+];
+
+fn builtin() -> Vec<Scenario> {
+    BUILTIN
+        .iter()
+        .map(|(name, _)| Scenario::load(name).unwrap())
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Shipped scenarios
+// ---------------------------------------------------------------------------
+
+/// Every line format is written by some shipped scenario.
+#[test]
+fn shipped_scenarios_cover_every_format() {
+    let formats: Vec<Format> = builtin()
+        .iter()
+        .flat_map(|s| s.services.iter().map(|service| service.format))
+        .collect();
+    for format in [Format::Json, Format::Logfmt, Format::Plain, Format::Access] {
+        assert!(formats.contains(&format), "no scenario writes {format:?}");
+    }
+}
+
+/// Playing every shipped scenario once through produces lines from every
+/// search domain, at every level from debug to fatal.
+#[test]
+fn shipped_scenarios_cover_every_search_domain() {
+    let start: chrono::DateTime<chrono::Utc> = "2024-01-15T10:00:00Z".parse().unwrap();
+    let mut text = String::new();
+    let mut levels = HashSet::new();
+    for scenario in builtin() {
+        let duration = scenario.duration.expect("shipped scenarios are bounded");
+        let mut simulation = Simulation::new(scenario);
+        let step = Duration::from_secs(1);
+        for i in 1..=duration.as_secs() {
+            let now = start + chrono::TimeDelta::seconds(i as i64);
+            for entry in simulation.advance(step, now).entries {
+                text.push_str(&entry.raw.to_lowercase());
+                text.push('\n');
+                levels.extend(entry.level);
+            }
+        }
+    }
+    for (domain, terms) in DOMAINS {
+        assert!(
+            terms.iter().any(|term| text.contains(term)),
+            "no line in the {domain} domain"
+        );
+    }
+    for level in [
+        LogLevel::Debug,
+        LogLevel::Info,
+        LogLevel::Warn,
+        LogLevel::Error,
+        LogLevel::Fatal,
+    ] {
+        assert!(levels.contains(&level), "no {level} line");
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Loading
+// ---------------------------------------------------------------------------
+
+/// A scenario file loads by path; bad ones say what is wrong.
+#[test]
+fn scenarios_load_from_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mine.toml");
+    std::fs::write(
+        &path,
+        "name = 'mine'\n[[service]]\nname = 'api'\nrate = 1\nlines = [{ message = 'ok' }]\n",
+    )
+    .unwrap();
+    let scenario = Scenario::load(path.to_str().unwrap()).unwrap();
+    assert_eq!(scenario.name, "mine");
+
+    std::fs::write(&path, "name = 'mine'\n").unwrap();
+    let err = Scenario::load(path.to_str().unwrap()).err().unwrap();
+    assert!(err.to_string().contains("service"), "{err}");
+
+    let err = Scenario::load("no-such-outage").err().unwrap();
+    assert!(err.to_string().contains("redis-outage"), "{err}");
+}
+
+// ---------------------------------------------------------------------------
+// Feed
+// ---------------------------------------------------------------------------
+
+/// The feed streams its producers, writes lines at their rate, reports event
+/// states, and ends a non-repeating scenario.
+#[tokio::test(start_paused = true)]
+async fn feed_plays_a_scenario_to_the_end() {
+    let scenario = Scenario::from_toml(
+        r#"
+        name = "short"
+        duration = "2s"
+        repeat = false
+
+        [[service]]
+        name = "api"
+        replicas = 2
+        format = "json"
+        rate = 5
+        lines = [{ message = "ok" }]
+
+        [[event]]
+        at = "1s"
+        services = ["api-2"]
+        state = "OOMKilled"
+        restart = true
+        "#,
+    )
+    .unwrap();
+    let (tx, mut entries) = mpsc::channel(1024);
+    let (utx, mut updates) = mpsc::channel(1024);
+    let (mtx, mut metadata) = mpsc::channel(1024);
+    let feed = DemoFeed::new(scenario).with_metadata(mtx);
+    feed.run(tx, utx).await.unwrap();
+
+    let mut lines = Vec::new();
+    while let Ok(entry) = entries.try_recv() {
+        lines.push(entry);
+    }
+    assert_eq!(lines.len(), 2 * 5 * 2);
+    assert!(lines.iter().all(|e| e.source == FeedKind::Demo));
+    assert!(lines[0].raw.contains(r#""msg":"ok""#), "{}", lines[0].raw);
+
+    let mut statuses = Vec::new();
+    while let Ok(update) = updates.try_recv() {
+        statuses.push((update.producer, update.status));
+    }
+    assert_eq!(
+        statuses,
+        [
+            ("api-1".to_string(), ProducerStatus::Streaming),
+            ("api-2".to_string(), ProducerStatus::Streaming),
+            ("api-2".to_string(), ProducerStatus::Restarted { count: 1 }),
+            ("api-1".to_string(), ProducerStatus::Ended { code: None }),
+            ("api-2".to_string(), ProducerStatus::Ended { code: None }),
+        ]
+    );
+
+    let mut states = Vec::new();
+    while let Ok(meta) = metadata.try_recv() {
+        states.push((meta.producer, meta.state.to_string()));
+    }
+    let api2: Vec<_> = states
+        .iter()
+        .filter(|(producer, _)| producer == "api-2")
+        .map(|(_, state)| state.as_str())
+        .collect();
+    assert_eq!(api2, ["running", "OOMKilled", "running"]);
+}
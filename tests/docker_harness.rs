@@ -11,13 +11,25 @@
 //!   (`project/service`). Unlabelled containers appear at the top level.
 //! - **stderr tagging**: lines from stderr (frame type = 2) must carry a
 //!   synthetic field `stream=stderr`; stdout lines get `stream=stdout`.
-//! - **Container exit and removal**: when a container exits or is removed, the
-//!   log stream closes gracefully without panicking.
+//! - **Events stream**: containers started after the feed are attached from
+//!   `start` events; `die` reports the exit code and `destroy` removes the
+//!   producer from the tree.
+//! - **Filters**: only containers matching `name=` / `label=` filters are
+//!   followed.
+//! - **Reconnect**: a dropped log stream is re-opened with `since` and no line
+//!   is duplicated.
+//! - **Container state**: `running`, health check `healthy` / `unhealthy`,
+//!   `exited(code)` and `OOMKilled` on the metadata channel.
+//! - **Engines**: several engines become separate `docker@<name>` tree roots;
+//!   `tcp://` endpoints with client certificates speak mutual TLS; a rootless
+//!   Podman socket under `$XDG_RUNTIME_DIR` is auto-detected.
 //!
 //! # What this does NOT cover
 //!
-//! - Real Docker daemon interaction (uses `FakeDockerApi` Unix socket)
+//! - Real Docker daemon interaction (uses `FakeDockerApi` over TCP, TLS or a
+//!   Unix socket)
 //! - Swarm / overlay network containers
+//! - TTY containers (raw, unframed output)
 //!
 //! # Running
 //!
@@ -26,7 +38,144 @@
 //! ```
 
 mod common;
+use common::fake_docker_api::FakeDockerApi;
 use common::*;
+use fml_core::{FeedKind, LogEntry, ProducerMeta, ProducerState, ProducerStatus, ProducerUpdate};
+use fml_feeds::docker::engine::{self, Environment};
+use fml_feeds::docker::{ContainerFilter, DockerConfig, DockerFeed, Endpoint, Tls};
+use fml_feeds::{FeedControl, FeedHandle};
+use fml_tui::widgets::producer_tree::ProducerTreeState;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// A running feed against a fake Engine.
+struct Harness {
+    api: FakeDockerApi,
+    entries: mpsc::Receiver<LogEntry>,
+    updates: mpsc::Receiver<ProducerUpdate>,
+    metadata: mpsc::Receiver<ProducerMeta>,
+}
+
+async fn start_with(api: FakeDockerApi, filters: &[&str]) -> Harness {
+    let config = DockerConfig {
+        endpoint: api.endpoint().parse().unwrap(),
+        instance: None,
+        filters: filters.iter().map(|f| f.parse().unwrap()).collect(),
+        tail: None,
+    };
+    start_config(api, config).await
+}
+
+async fn start_config(api: FakeDockerApi, config: DockerConfig) -> Harness {
+    let (tx, entries) = mpsc::channel(256);
+    let (utx, updates) = mpsc::channel(256);
+    let (mtx, metadata) = mpsc::channel(256);
+    let feed = DockerFeed::new(&config).unwrap().with_metadata(mtx);
+    tokio::spawn(feed.run(tx, utx));
+    // Containers started from here on must be seen through the events stream.
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while api.event_subscribers() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("feed should subscribe to events");
+    Harness {
+        api,
+        entries,
+        updates,
+        metadata,
+    }
+}
+
+/// A feed for `api` whose producers live under `docker@<instance>`.
+async fn start_instance(api: FakeDockerApi, instance: &str) -> Harness {
+    let config = DockerConfig {
+        endpoint: api.endpoint().parse().unwrap(),
+        instance: Some(instance.to_string()),
+        filters: Vec::new(),
+        tail: None,
+    };
+    start_config(api, config).await
+}
+
+fn tls_fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/docker-tls")
+}
+
+async fn start(api: FakeDockerApi) -> Harness {
+    start_with(api, &[]).await
+}
+
+async fn recv(rx: &mut mpsc::Receiver<LogEntry>) -> LogEntry {
+    tokio::time::timeout(Duration::from_secs(10), rx.recv())
+        .await
+        .expect("entry should arrive")
+        .expect("channel open")
+}
+
+async fn recv_n(rx: &mut mpsc::Receiver<LogEntry>, n: usize) -> Vec<LogEntry> {
+    let mut out = Vec::new();
+    for _ in 0..n {
+        out.push(recv(rx).await);
+    }
+    out
+}
+
+/// Wait for a status update on `producer` matching `pred`.
+async fn wait_status(
+    rx: &mut mpsc::Receiver<ProducerUpdate>,
+    producer: &str,
+    pred: impl Fn(&ProducerStatus) -> bool,
+) -> ProducerStatus {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let update = rx.recv().await.expect("updates open");
+            if update.producer == producer && pred(&update.status) {
+                return update.status;
+            }
+        }
+    })
+    .await
+    .expect("status update should arrive")
+}
+
+/// Wait for metadata on `producer` whose state matches `pred`.
+async fn wait_state(
+    rx: &mut mpsc::Receiver<ProducerMeta>,
+    producer: &str,
+    pred: impl Fn(&ProducerState) -> bool,
+) -> ProducerMeta {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let meta = rx.recv().await.expect("metadata open");
+            if meta.producer == producer && pred(&meta.state) {
+                return meta;
+            }
+        }
+    })
+    .await
+    .expect("metadata should arrive")
+}
+
+/// Wait until the feed has a follow stream open on the container.
+async fn wait_following(api: &FakeDockerApi, id: &str) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while api.follower_count(id).await == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("feed should open a log stream");
+}
+
+/// Nothing else arrives within a short grace period.
+async fn assert_quiet(rx: &mut mpsc::Receiver<LogEntry>) {
+    if let Ok(Some(extra)) = tokio::time::timeout(Duration::from_millis(200), rx.recv()).await {
+        panic!("unexpected entry: {} {:?}", extra.producer, extra.message);
+    }
+}
 
 // ---------------------------------------------------------------------------
 // Multiplexed frame decoding
@@ -34,32 +183,72 @@ use common::*;
 
 /// Raw Docker multiplexed frames must be decoded into clean log lines.
 /// The 8-byte header must be stripped before the line is stored.
-#[test]
-#[ignore = "not yet implemented"]
-fn multiplexed_frames_are_decoded() {
-    todo!("construct raw Docker frame bytes; feed through ingestor; assert header stripped")
+#[tokio::test]
+async fn multiplexed_frames_are_decoded() {
+    let api = FakeDockerApi::start().await.unwrap();
+    api.add_container("abc123", "api").await;
+    api.stream_log("abc123", r#"{"level":"INFO","msg":"hello"}"#)
+        .await;
+    let mut h = start(api).await;
+
+    let entry = recv(&mut h.entries).await;
+    assert_source!(entry, FeedKind::Docker);
+    assert_eq!(entry.raw, r#"{"level":"INFO","msg":"hello"}"#);
+    assert_has_field!(entry, "container", "api");
+    assert_has_field!(entry, "container_id", "abc123");
+    assert_has_field!(entry, "image", "fake/image:latest");
 }
 
 /// A frame with stream_type=1 (stdout) must tag the entry with `stream=stdout`.
-#[test]
-#[ignore = "not yet implemented"]
-fn stdout_frame_tagged_as_stdout() {
-    todo!("send frame with type=1; assert entry.fields[stream] == stdout")
+#[tokio::test]
+async fn stdout_frame_tagged_as_stdout() {
+    let api = FakeDockerApi::start().await.unwrap();
+    api.add_container("abc123", "api").await;
+    api.stream_log("abc123", "ready").await;
+    let mut h = start(api).await;
+
+    let entry = recv(&mut h.entries).await;
+    assert_has_field!(entry, "stream", "stdout");
 }
 
 /// A frame with stream_type=2 (stderr) must tag the entry with `stream=stderr`.
-#[test]
-#[ignore = "not yet implemented"]
-fn stderr_frame_tagged_as_stderr() {
-    todo!("send frame with type=2; assert entry.fields[stream] == stderr")
+#[tokio::test]
+async fn stderr_frame_tagged_as_stderr() {
+    let api = FakeDockerApi::start().await.unwrap();
+    api.add_container("abc123", "api").await;
+    api.stream_stderr("abc123", "panic: oh no").await;
+    let mut h = start(api).await;
+
+    let entry = recv(&mut h.entries).await;
+    assert_eq!(entry.raw, "panic: oh no");
+    assert_has_field!(entry, "stream", "stderr");
 }
 
 /// Interleaved stdout and stderr frames must be decoded correctly without
 /// cross-contamination between streams.
-#[test]
-#[ignore = "not yet implemented"]
-fn interleaved_stdout_stderr_decoded_correctly() {
-    todo!("send alternating stdout/stderr frames; assert stream field correct on each")
+#[tokio::test]
+async fn interleaved_stdout_stderr_decoded_correctly() {
+    let api = FakeDockerApi::start().await.unwrap();
+    api.add_container("abc123", "api").await;
+    let mut h = start(api).await;
+    wait_following(&h.api, "abc123").await;
+    for i in 0..6 {
+        if i % 2 == 0 {
+            h.api.stream_log("abc123", &format!("out {i}")).await;
+        } else {
+            h.api.stream_stderr("abc123", &format!("err {i}")).await;
+        }
+    }
+
+    for (i, entry) in recv_n(&mut h.entries, 6).await.iter().enumerate() {
+        let (prefix, stream) = if i % 2 == 0 {
+            ("out", "stdout")
+        } else {
+            ("err", "stderr")
+        };
+        assert_eq!(entry.raw, format!("{prefix} {i}"));
+        assert_has_field!(entry, "stream", stream);
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -69,44 +258,232 @@ fn interleaved_stdout_stderr_decoded_correctly() {
 /// A container with `com.docker.compose.project=myapp` and
 /// `com.docker.compose.service=api` must appear under `myapp/api` in the
 /// producer tree.
-#[test]
-#[ignore = "not yet implemented"]
-fn compose_container_producer_includes_project_and_service() {
-    todo!("add compose-labelled container; assert producer == project/service")
+#[tokio::test]
+async fn compose_container_producer_includes_project_and_service() {
+    let api = FakeDockerApi::start().await.unwrap();
+    api.add_compose_container("c1", "myapp", "api", 1).await;
+    api.stream_log("c1", "listening").await;
+    let mut h = start(api).await;
+
+    let entry = recv(&mut h.entries).await;
+    assert_producer!(entry, "myapp/api");
+    assert_has_field!(entry, "container", "myapp-api-1");
 }
 
 /// A container without compose labels must appear at the top level with just
 /// its container name.
-#[test]
-#[ignore = "not yet implemented"]
-fn unlabelled_container_producer_is_container_name() {
-    todo!("add unlabelled container; assert producer == container name")
+#[tokio::test]
+async fn unlabelled_container_producer_is_container_name() {
+    let api = FakeDockerApi::start().await.unwrap();
+    api.add_container("c1", "postgres").await;
+    api.stream_log("c1", "ready to accept connections").await;
+    let mut h = start(api).await;
+
+    let entry = recv(&mut h.entries).await;
+    assert_producer!(entry, "postgres");
 }
 
 /// Multiple services within the same compose project are grouped correctly.
-#[test]
-#[ignore = "not yet implemented"]
-fn multiple_compose_services_grouped_under_project() {
-    todo!("add api, worker, db containers under same project; verify grouping")
+#[tokio::test]
+async fn multiple_compose_services_grouped_under_project() {
+    let api = FakeDockerApi::start().await.unwrap();
+    api.add_compose_container("c1", "myapp", "api", 1).await;
+    api.add_compose_container("c2", "myapp", "api", 2).await;
+    api.add_compose_container("c3", "myapp", "worker", 1).await;
+    api.add_compose_container("c4", "myapp", "db", 1).await;
+    for id in ["c1", "c2", "c3", "c4"] {
+        api.stream_log(id, "up").await;
+    }
+    let mut h = start(api).await;
+
+    let mut tree = ProducerTreeState::default();
+    for entry in recv_n(&mut h.entries, 4).await {
+        tree.insert_producer(entry.source, &entry.producer);
+    }
+    let project = &tree.nodes[0].children[0];
+    assert_eq!(project.id, "docker/myapp");
+    let mut services: Vec<_> = project.children.iter().map(|n| n.label.as_str()).collect();
+    services.sort();
+    assert_eq!(services, ["api", "api-2", "db", "worker"]);
 }
 
 // ---------------------------------------------------------------------------
 // Container lifecycle
 // ---------------------------------------------------------------------------
 
-/// When a container exits, the log stream closes without error.
-#[test]
-#[ignore = "not yet implemented"]
-fn container_exit_closes_stream_cleanly() {
-    todo!("close FakeDockerApi stream; assert ingestor shuts down without panic")
+/// When a container exits, the log stream closes without error and the exit
+/// code is reported.
+#[tokio::test]
+async fn container_exit_closes_stream_cleanly() {
+    let api = FakeDockerApi::start().await.unwrap();
+    api.add_container("c1", "migrate").await;
+    api.add_container("c2", "web").await;
+    let mut h = start(api).await;
+    wait_following(&h.api, "c1").await;
+    wait_following(&h.api, "c2").await;
+
+    h.api.stream_log("c1", "applying 0042").await;
+    recv(&mut h.entries).await;
+    h.api.stop_container("c1", 3).await;
+    let status = wait_status(&mut h.updates, "migrate", |s| {
+        matches!(s, ProducerStatus::Ended { .. })
+    })
+    .await;
+    assert_eq!(status, ProducerStatus::Ended { code: Some(3) });
+
+    // The feed keeps following the other container.
+    h.api.stream_log("c2", "still here").await;
+    let entry = recv(&mut h.entries).await;
+    assert_producer!(entry, "web");
 }
 
 /// When a container is removed while being tailed, the entry is removed from
 /// the producer tree and no further lines arrive for that container.
-#[test]
-#[ignore = "not yet implemented"]
-fn removed_container_disappears_from_producer_tree() {
-    todo!("remove container from FakeDockerApi; assert no further entries arrive")
+#[tokio::test]
+async fn removed_container_disappears_from_producer_tree() {
+    let api = FakeDockerApi::start().await.unwrap();
+    api.add_container("c1", "api").await;
+    api.add_container("c2", "db").await;
+    api.stream_log("c1", "hello").await;
+    let mut h = start(api).await;
+
+    let mut tree = ProducerTreeState::default();
+    let entry = recv(&mut h.entries).await;
+    tree.insert_producer(entry.source, &entry.producer);
+    tree.insert_producer(FeedKind::Docker, "db");
+
+    h.api.remove_container("c1").await;
+    let status = wait_status(&mut h.updates, "api", |s| *s == ProducerStatus::Removed).await;
+    tree.set_status(FeedKind::Docker, "api", status);
+
+    let ids: Vec<_> = tree.nodes[0]
+        .children
+        .iter()
+        .map(|n| n.id.as_str())
+        .collect();
+    assert_eq!(ids, ["docker/db"]);
+    assert_quiet(&mut h.entries).await;
+}
+
+/// A container started after the feed is attached from its `start` event,
+/// and re-attached after a restart without replaying earlier lines.
+#[tokio::test]
+async fn started_container_is_attached_from_events() {
+    let api = FakeDockerApi::start().await.unwrap();
+    let mut h = start(api).await;
+
+    h.api.run_container("c1", "worker", &[]).await;
+    wait_following(&h.api, "c1").await;
+    h.api.stream_log("c1", "first run").await;
+    assert_eq!(recv(&mut h.entries).await.raw, "first run");
+
+    h.api.stop_container("c1", 0).await;
+    wait_status(&mut h.updates, "worker", |s| {
+        matches!(s, ProducerStatus::Ended { .. })
+    })
+    .await;
+    h.api.start_container("c1").await;
+    wait_following(&h.api, "c1").await;
+    h.api.stream_log("c1", "second run").await;
+
+    let entry = recv(&mut h.entries).await;
+    assert_eq!(entry.raw, "second run");
+}
+
+// ---------------------------------------------------------------------------
+// Filters and reconnects
+// ---------------------------------------------------------------------------
+
+/// Only containers matching a filter are followed, whether they were running
+/// at startup or started later.
+#[tokio::test]
+async fn filters_narrow_followed_containers() {
+    let api = FakeDockerApi::start().await.unwrap();
+    api.add_container_with("c1", "api", &[("tier", "web")])
+        .await;
+    api.add_container_with("c2", "db", &[("tier", "data")])
+        .await;
+    api.stream_log("c1", "api line").await;
+    api.stream_log("c2", "db line").await;
+    let mut h = start_with(api, &["label=tier=web", "name=cron"]).await;
+
+    assert_producer!(recv(&mut h.entries).await, "api");
+    h.api.run_container("c3", "nightly-cron", &[]).await;
+    h.api.run_container("c4", "cache", &[]).await;
+    h.api.stream_log("c3", "cron line").await;
+    h.api.stream_log("c4", "cache line").await;
+
+    assert_producer!(recv(&mut h.entries).await, "nightly-cron");
+    assert_quiet(&mut h.entries).await;
+}
+
+/// A log stream dropped while the container runs is re-opened from the last
+/// line seen: nothing is lost and nothing is repeated.
+#[tokio::test]
+async fn dropped_log_stream_resumes_without_duplicates() {
+    let api = FakeDockerApi::start().await.unwrap();
+    api.add_container("c1", "api").await;
+    let mut h = start(api).await;
+    wait_following(&h.api, "c1").await;
+    for i in 0..3 {
+        h.api.stream_log("c1", &format!("line {i}")).await;
+    }
+    recv_n(&mut h.entries, 3).await;
+
+    h.api.drop_log_streams("c1").await;
+    h.api.stream_log("c1", "while disconnected").await;
+    wait_following(&h.api, "c1").await;
+    h.api.stream_log("c1", "after reconnect").await;
+
+    let raws: Vec<_> = recv_n(&mut h.entries, 2)
+        .await
+        .into_iter()
+        .map(|e| e.raw)
+        .collect();
+    assert_eq!(raws, ["while disconnected", "after reconnect"]);
+    assert_quiet(&mut h.entries).await;
+}
+
+/// Stopping a container in the UI closes its log stream; starting it opens a
+/// new one from that moment, leaving out what was logged while stopped.
+#[tokio::test]
+async fn stop_closes_the_log_stream_until_started() {
+    let api = FakeDockerApi::start().await.unwrap();
+    api.add_container("c1", "api").await;
+    let (tx, mut entries) = mpsc::channel(256);
+    let (utx, _updates) = mpsc::channel(256);
+    let (mtx, _metadata) = mpsc::channel(256);
+    let (control, senders, relay) = FeedControl::attach(tx, utx, mtx);
+    tokio::spawn(relay.run());
+    let config = DockerConfig {
+        endpoint: api.endpoint().parse().unwrap(),
+        instance: None,
+        filters: Vec::new(),
+        tail: None,
+    };
+    let feed = DockerFeed::new(&config)
+        .unwrap()
+        .with_signals(control.signals());
+    tokio::spawn(feed.run(senders.entries, senders.updates));
+    wait_following(&api, "c1").await;
+    api.stream_log("c1", "before").await;
+    assert_eq!(recv(&mut entries).await.raw, "before");
+
+    control.stop("docker/api");
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while api.follower_count("c1").await > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the log stream should close");
+    api.stream_log("c1", "while stopped").await;
+
+    control.start("docker/api");
+    wait_following(&api, "c1").await;
+    api.stream_log("c1", "after").await;
+    assert_eq!(recv(&mut entries).await.raw, "after");
+    assert_quiet(&mut entries).await;
 }
 
 // ---------------------------------------------------------------------------
@@ -114,8 +491,200 @@ fn removed_container_disappears_from_producer_tree() {
 // ---------------------------------------------------------------------------
 
 /// /containers/json response is correctly mapped to the available producer list.
-#[test]
-#[ignore = "not yet implemented"]
-fn container_list_populates_producer_tree() {
-    todo!("start FakeDockerApi with 3 containers; verify all 3 appear as selectable producers")
+#[tokio::test]
+async fn container_list_populates_producer_tree() {
+    let api = FakeDockerApi::start().await.unwrap();
+    for (id, name) in [("c1", "api"), ("c2", "db"), ("c3", "cache")] {
+        api.add_container(id, name).await;
+    }
+    let mut h = start(api).await;
+
+    let mut tree = ProducerTreeState::default();
+    let mut streaming = 0;
+    while streaming < 3 {
+        let update = tokio::time::timeout(Duration::from_secs(10), h.updates.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        if update.status == ProducerStatus::Connecting {
+            continue;
+        }
+        assert_eq!(update.status, ProducerStatus::Streaming);
+        tree.set_status(update.source, &update.producer, update.status);
+        streaming += 1;
+    }
+    let mut names: Vec<_> = tree.nodes[0]
+        .children
+        .iter()
+        .map(|n| n.label.as_str())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["api", "cache", "db"]);
+}
+
+// ---------------------------------------------------------------------------
+// Container state
+// ---------------------------------------------------------------------------
+
+/// Health check transitions and the exit code are reported as metadata.
+#[tokio::test]
+async fn health_and_exit_are_reported_as_metadata() {
+    let api = FakeDockerApi::start().await.unwrap();
+    api.add_container("c1", "api").await;
+    let mut h = start(api).await;
+    let attached = wait_state(&mut h.metadata, "api", |_| true).await;
+    assert_eq!(attached.state, ProducerState::Running);
+
+    h.api.set_health("c1", "unhealthy").await;
+    wait_state(&mut h.metadata, "api", |s| *s == ProducerState::Unhealthy).await;
+    h.api.set_health("c1", "healthy").await;
+    wait_state(&mut h.metadata, "api", |s| *s == ProducerState::Healthy).await;
+
+    h.api.stop_container("c1", 3).await;
+    let exited = wait_state(&mut h.metadata, "api", |s| {
+        matches!(s, ProducerState::Terminated { .. })
+    })
+    .await;
+    assert_eq!(exited.state.to_string(), "exited(3)");
+    assert_eq!(exited.fields["exit_code"], "3");
+}
+
+/// An `oom` event before `die` turns the exit into `OOMKilled`.
+#[tokio::test]
+async fn oom_kill_is_reported_as_oomkilled() {
+    let api = FakeDockerApi::start().await.unwrap();
+    api.add_container("c1", "worker").await;
+    let mut h = start(api).await;
+    wait_state(&mut h.metadata, "worker", |_| true).await;
+
+    h.api.oom_kill("c1").await;
+    let killed = wait_state(&mut h.metadata, "worker", |s| {
+        matches!(s, ProducerState::Terminated { .. })
+    })
+    .await;
+    assert_eq!(killed.state.to_string(), "OOMKilled");
+    assert_eq!(killed.fields["exit_code"], "137");
+}
+
+// ---------------------------------------------------------------------------
+// Engines
+// ---------------------------------------------------------------------------
+
+/// Each engine is its own root in the producer tree, so same-named
+/// containers on two hosts stay apart.
+#[tokio::test]
+async fn engines_get_separate_tree_roots() {
+    let mut tree = ProducerTreeState::default();
+    let mut harnesses = Vec::new();
+    for instance in ["local", "remote"] {
+        let api = FakeDockerApi::start().await.unwrap();
+        api.add_container("c1", "api").await;
+        let mut h = start_instance(api, instance).await;
+        wait_following(&h.api, "c1").await;
+        h.api
+            .stream_log("c1", &format!("hello from {instance}"))
+            .await;
+        let entry = recv(&mut h.entries).await;
+        assert_eq!(entry.instance.as_deref(), Some(instance));
+        assert_eq!(entry.feed_root(), format!("docker@{instance}"));
+
+        let update = h.updates.recv().await.unwrap();
+        assert_eq!(update.instance.as_deref(), Some(instance));
+        tree.set_status(update.feed_root(), &update.producer, update.status);
+        harnesses.push(h);
+    }
+
+    let roots: Vec<_> = tree.nodes.iter().map(|n| n.label.as_str()).collect();
+    assert_eq!(roots, ["docker@local", "docker@remote"]);
+    for root in &tree.nodes {
+        assert_eq!(root.children.len(), 1);
+        assert_eq!(root.children[0].label, "api");
+    }
+}
+
+/// `tcp://` with client certificates: the feed verifies the server against
+/// `ca.pem` and presents `cert.pem` / `key.pem`.
+#[tokio::test]
+async fn tls_endpoint_uses_client_certificates() {
+    let api = FakeDockerApi::start_tls(&tls_fixtures()).await.unwrap();
+    api.add_container("c1", "secure").await;
+    let mut endpoint: Endpoint = api.endpoint().parse().unwrap();
+    if let Endpoint::Tcp { tls, .. } = &mut endpoint {
+        *tls = Some(Tls::from_dir(&tls_fixtures(), true));
+    }
+    let config = DockerConfig {
+        endpoint,
+        instance: None,
+        filters: Vec::new(),
+        tail: None,
+    };
+    let mut h = start_config(api, config).await;
+    wait_following(&h.api, "c1").await;
+
+    h.api.stream_log("c1", "over tls").await;
+    let entry = recv(&mut h.entries).await;
+    assert_producer!(entry, "secure");
+    assert_eq!(entry.message.as_deref(), Some("over tls"));
+}
+
+/// Without a client certificate the server refuses the handshake and the
+/// feed reports an error instead of streaming.
+#[tokio::test]
+async fn tls_endpoint_without_client_certificate_fails() {
+    let api = FakeDockerApi::start_tls(&tls_fixtures()).await.unwrap();
+    let mut endpoint: Endpoint = api.endpoint().parse().unwrap();
+    if let Endpoint::Tcp { tls, .. } = &mut endpoint {
+        *tls = Some(Tls {
+            ca: Some(tls_fixtures().join("ca.pem")),
+            client: None,
+            verify: true,
+        });
+    }
+    let config = DockerConfig {
+        endpoint,
+        instance: None,
+        filters: Vec::new(),
+        tail: None,
+    };
+    let (tx, _entries) = mpsc::channel(16);
+    let (utx, _updates) = mpsc::channel(16);
+    let feed = DockerFeed::new(&config).unwrap();
+    let task = tokio::spawn(feed.run(tx, utx));
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(api.event_subscribers(), 0);
+    task.abort();
+}
+
+/// With no `DOCKER_HOST`, context or rootful socket, a rootless Podman
+/// socket under `$XDG_RUNTIME_DIR` is found and followed.
+#[tokio::test]
+async fn rootless_podman_socket_is_detected() {
+    let runtime = tempfile::tempdir().unwrap();
+    let config_dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(runtime.path().join("podman")).unwrap();
+    let socket = runtime.path().join("podman/podman.sock");
+    let api = FakeDockerApi::start_unix(&socket).await.unwrap();
+    api.add_container("c1", "pod-app").await;
+
+    let env = Environment {
+        config_dir: config_dir.path().to_path_buf(),
+        runtime_dir: Some(runtime.path().to_path_buf()),
+        system_sockets: vec![("docker".into(), runtime.path().join("missing.sock"))],
+        ..Environment::default()
+    };
+    let engine = engine::detect(&env).unwrap();
+    assert_eq!(engine.name, "podman");
+    assert_eq!(engine.endpoint, Endpoint::Unix(socket.clone()));
+
+    let config = DockerConfig {
+        endpoint: engine.endpoint,
+        instance: None,
+        filters: Vec::new(),
+        tail: None,
+    };
+    let mut h = start_config(api, config).await;
+    wait_following(&h.api, "c1").await;
+    h.api.stream_log("c1", "from podman").await;
+    let entry = recv(&mut h.entries).await;
+    assert_producer!(entry, "pod-app");
 }
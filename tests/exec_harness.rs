@@ -0,0 +1,239 @@
+#![allow(unused)]
+//! Exec ingestor integration harness.
+//!
+//! # What this covers
+//!
+//! - **stdout / stderr producers**: each command contributes `<name>/stdout`
+//!   and `<name>/stderr`, and every entry carries `stream=stdout|stderr`.
+//! - **Exit status**: when the process exits, an `Ended` update with its exit
+//!   code is reported for the `<name>` node.
+//! - **Restart with backoff**: with `restart` set, the command is re-spawned
+//!   after each exit and `Backoff` updates count the attempts.
+//! - **Multiple commands**: several commands running in one session stay
+//!   separate producers.
+//! - **Stop and reconnect**: stopping a command kills it until started, and a
+//!   reconnect respawns it.
+//!
+//! # What this does NOT cover
+//!
+//! - Long-running remote commands (`ssh`, `stern`); `sh -c` stands in for them
+//!
+//! # Running
+//!
+//! ```sh
+//! cargo test --test exec_harness
+//! ```
+
+mod common;
+use common::fake_process::fake_process;
+use common::*;
+use fml_core::{FeedKind, LogEntry, ProducerStatus, ProducerUpdate};
+use fml_feeds::exec::{self, ExecConfig};
+use fml_feeds::{FeedControl, FeedHandle};
+use futures::StreamExt;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+fn sh(name: &str, script: &str) -> ExecConfig {
+    ExecConfig::named(name, "sh", vec!["-c".to_string(), script.to_string()])
+}
+
+fn drain<T>(rx: &mut mpsc::Receiver<T>) -> Vec<T> {
+    let mut out = Vec::new();
+    while let Ok(item) = rx.try_recv() {
+        out.push(item);
+    }
+    out
+}
+
+// ---------------------------------------------------------------------------
+// stdout / stderr producers
+// ---------------------------------------------------------------------------
+
+/// stdout and stderr lines land on separate producers with a `stream` tag.
+#[tokio::test]
+async fn stdout_and_stderr_are_separate_producers() {
+    let (out_w, out) = fake_process();
+    let (err_w, err) = fake_process();
+    out_w.send_line("compiling fml");
+    err_w.send_line("warning: unused variable");
+    out_w.close();
+    err_w.close();
+
+    let (tx, mut rx) = mpsc::channel(16);
+    exec::ingest("cargo", out.map(Ok), err.map(Ok), tx)
+        .await
+        .unwrap();
+    let mut entries = drain(&mut rx);
+    entries.sort_by(|a, b| a.producer.cmp(&b.producer));
+
+    assert_eq!(entries.len(), 2);
+    assert_producer!(entries[0], "cargo/stderr");
+    assert_has_field!(entries[0], "stream", "stderr");
+    assert_producer!(entries[1], "cargo/stdout");
+    assert_has_field!(entries[1], "stream", "stdout");
+    assert_source!(entries[1], FeedKind::Exec);
+    assert_eq!(entries[1].message.as_deref(), Some("compiling fml"));
+}
+
+// ---------------------------------------------------------------------------
+// Exit status
+// ---------------------------------------------------------------------------
+
+/// The exit code is surfaced as an `Ended` update on the command node.
+#[tokio::test]
+async fn exit_status_is_reported() {
+    let (tx, mut rx) = mpsc::channel(16);
+    let (utx, mut urx) = mpsc::channel(16);
+    exec::run(&sh("job", "echo done; echo oops >&2; exit 3"), tx, utx)
+        .await
+        .unwrap();
+
+    let entries = drain(&mut rx);
+    assert_eq!(entries.len(), 2);
+    assert_eq!(
+        drain(&mut urx),
+        [
+            ProducerUpdate {
+                source: FeedKind::Exec,
+                instance: None,
+                producer: "job".into(),
+                status: ProducerStatus::Streaming
+            },
+            ProducerUpdate {
+                source: FeedKind::Exec,
+                instance: None,
+                producer: "job".into(),
+                status: ProducerStatus::Ended { code: Some(3) }
+            },
+        ]
+    );
+}
+
+/// A command that cannot be spawned is an error when restart is off.
+#[tokio::test]
+async fn missing_program_is_an_error_without_restart() {
+    let (tx, _rx) = mpsc::channel(1);
+    let (utx, _urx) = mpsc::channel(1);
+    let cfg = ExecConfig::named("nope", "/definitely/not/a/program", vec![]);
+    assert!(matches!(
+        exec::run(&cfg, tx, utx).await,
+        Err(fml_feeds::FeedError::Spawn { .. })
+    ));
+}
+
+// ---------------------------------------------------------------------------
+// Restart with backoff
+// ---------------------------------------------------------------------------
+
+/// With restart enabled the command is re-run and backoff attempts increase.
+#[tokio::test]
+async fn restart_reruns_command_with_backoff() {
+    let mut cfg = sh("flaky", "echo tick; exit 1");
+    cfg.restart = true;
+    cfg.initial_backoff = Duration::from_millis(5);
+    cfg.max_backoff = Duration::from_millis(20);
+
+    let (tx, mut rx) = mpsc::channel(16);
+    let (utx, mut urx) = mpsc::channel(64);
+    let handle = tokio::spawn(async move { exec::run(&cfg, tx, utx).await });
+
+    for _ in 0..3 {
+        let entry = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("command should be restarted")
+            .unwrap();
+        assert_eq!(entry.message.as_deref(), Some("tick"));
+    }
+    drop(rx);
+    let result = tokio::time::timeout(Duration::from_secs(5), handle)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(result, Err(fml_feeds::FeedError::ChannelClosed)));
+
+    let attempts: Vec<u32> = drain(&mut urx)
+        .into_iter()
+        .filter_map(|u| match u.status {
+            ProducerStatus::Backoff { attempt } => Some(attempt),
+            _ => None,
+        })
+        .collect();
+    assert!(attempts.starts_with(&[1, 2]), "attempts: {attempts:?}");
+}
+
+// ---------------------------------------------------------------------------
+// Multiple commands
+// ---------------------------------------------------------------------------
+
+/// Two commands in one session feed the same channel as distinct producers.
+#[tokio::test]
+async fn multiple_commands_are_separate_producers() {
+    let (tx, mut rx) = mpsc::channel(16);
+    let (utx, _urx) = mpsc::channel(16);
+    let (api, worker) = (sh("api", "echo a"), sh("worker", "echo b"));
+    let a = exec::run(&api, tx.clone(), utx.clone());
+    let b = exec::run(&worker, tx, utx);
+    let (ra, rb) = tokio::join!(a, b);
+    ra.unwrap();
+    rb.unwrap();
+
+    let mut producers: Vec<String> = drain(&mut rx).into_iter().map(|e| e.producer).collect();
+    producers.sort();
+    assert_eq!(producers, ["api/stdout", "worker/stdout"]);
+}
+
+// ---------------------------------------------------------------------------
+// Stop and reconnect
+// ---------------------------------------------------------------------------
+
+/// Wait until process `pid` has exited.
+async fn wait_exited(pid: &str) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            match std::fs::read_to_string(format!("/proc/{pid}/stat")) {
+                Ok(stat) if !stat.contains(") Z ") => {}
+                _ => return,
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the process should be killed");
+}
+
+/// The pid the next run of `echo $$` printed.
+async fn pid(rx: &mut mpsc::Receiver<LogEntry>) -> String {
+    let entry = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("the command should be running")
+        .unwrap();
+    entry.message.unwrap()
+}
+
+/// Stopping a command kills it and starting it spawns it again; a reconnect
+/// kills and respawns it at once.
+#[tokio::test]
+async fn stop_kills_and_start_respawns() {
+    let (tx, mut rx) = mpsc::channel(16);
+    let (utx, _urx) = mpsc::channel(64);
+    let (mtx, _mrx) = mpsc::channel(16);
+    let (control, senders, relay) = FeedControl::attach(tx, utx, mtx);
+    tokio::spawn(relay.run());
+    let cfg = sh("job", "echo $$; exec sleep 30");
+    let signals = control.signals();
+    tokio::spawn(async move {
+        exec::run_with_signals(&cfg, signals, senders.entries, senders.updates).await
+    });
+    let first = pid(&mut rx).await;
+    control.reconnect("exec/job");
+    let second = pid(&mut rx).await;
+    assert_ne!(first, second);
+    wait_exited(&first).await;
+
+    control.stop("exec/job");
+    wait_exited(&second).await;
+    control.start("exec/job");
+    let third = pid(&mut rx).await;
+    assert_ne!(second, third);
+}
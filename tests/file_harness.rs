@@ -9,7 +9,17 @@
 //! - **Truncation**: when a file is truncated (e.g. `> file.log`), the ingestor
 //!   must seek to offset 0 and re-read from the beginning.
 //! - **Glob expansion**: `paths` config with glob patterns like `~/logs/**/*.log`
-//!   must discover matching files and tail all of them simultaneously.
+//!   must discover matching files and tail all of them simultaneously, pick up
+//!   files created later (in new directories too), and skip excluded ones.
+//! - **Archives**: rotated siblings (`.1`, `.gz`, `.zst`, `.bz2`, dated) are
+//!   decompressed and backfilled oldest first, within the horizon, and not
+//!   followed again as files of their own.
+//! - **Checkpoints**: a restarted feed resumes from the saved offsets, drains
+//!   a file rotated while it was stopped, and re-reads one rewritten meanwhile.
+//! - **Open-file limit**: with more matches than `max_open`, the most recently
+//!   modified files are followed; a parked file resumes without duplicates.
+//! - **Stop and reconnect**: a stopped file is closed and reopened at its end
+//!   once started; a reconnect reopens it where it was read to.
 //! - **Property: all written lines received**: for any sequence of writes,
 //!   all lines written to a file before it is closed must appear in the store.
 //!   Verified with proptest over random line content and write batch sizes.
@@ -27,31 +37,175 @@
 
 mod common;
 use common::*;
+use fml_core::{FeedKind, LogEntry, ProducerMeta, ProducerState};
+use fml_feeds::file::{FileConfig, FileFeed};
+use fml_feeds::{FeedControl, FeedHandle};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tempfile::TempDir;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// A running feed over files in a temporary directory.
+struct Harness {
+    dir: TempDir,
+    entries: mpsc::Receiver<LogEntry>,
+    metadata: mpsc::Receiver<ProducerMeta>,
+    feed: JoinHandle<Result<(), fml_feeds::FeedError>>,
+}
+
+impl Harness {
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    /// Producer name of `name`: the file under the temp directory's name.
+    fn producer(&self, name: &str) -> String {
+        let label = self.dir.path().file_name().unwrap().to_string_lossy();
+        format!("{label}/{name}")
+    }
+
+    async fn recv(&mut self) -> LogEntry {
+        tokio::time::timeout(Duration::from_secs(10), self.entries.recv())
+            .await
+            .expect("entry should arrive")
+            .expect("channel open")
+    }
+
+    async fn recv_messages(&mut self, n: usize) -> Vec<String> {
+        let mut out = Vec::new();
+        for _ in 0..n {
+            out.push(self.recv().await.message.unwrap());
+        }
+        out
+    }
+
+    /// Assert nothing else arrives for a moment.
+    async fn assert_quiet(&mut self) {
+        let extra = tokio::time::timeout(Duration::from_millis(1500), self.entries.recv()).await;
+        if let Ok(Some(entry)) = extra {
+            panic!("unexpected entry: {entry:?}");
+        }
+    }
+
+    async fn wait_state(&mut self, producer: &str, state: ProducerState) -> ProducerMeta {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let meta = self.metadata.recv().await.expect("metadata channel open");
+                if meta.producer == producer && meta.state == state {
+                    return meta;
+                }
+            }
+        })
+        .await
+        .expect("state should be reported")
+    }
+
+    /// Stop the feed, run `between` while it is down, and start a new one over
+    /// the same directory.
+    async fn restart(
+        self,
+        patterns: &[&str],
+        configure: impl FnOnce(&mut FileConfig),
+        between: impl FnOnce(&Path),
+    ) -> Harness {
+        drop(self.entries);
+        tokio::time::timeout(Duration::from_secs(10), self.feed)
+            .await
+            .expect("feed should stop once its receiver is gone")
+            .unwrap()
+            .unwrap();
+        between(self.dir.path());
+        spawn(self.dir, patterns, configure)
+    }
+}
+
+/// Follow `patterns` (relative to a fresh temp directory, after `setup` has
+/// populated it).
+async fn start(patterns: &[&str], setup: impl FnOnce(&Path)) -> Harness {
+    start_with(patterns, |_| {}, setup).await
+}
+
+async fn start_with(
+    patterns: &[&str],
+    configure: impl FnOnce(&mut FileConfig),
+    setup: impl FnOnce(&Path),
+) -> Harness {
+    let dir = tempfile::tempdir().unwrap();
+    setup(dir.path());
+    spawn(dir, patterns, configure)
+}
+
+fn spawn(dir: TempDir, patterns: &[&str], configure: impl FnOnce(&mut FileConfig)) -> Harness {
+    let mut config = FileConfig {
+        paths: patterns
+            .iter()
+            .map(|p| dir.path().join(p).to_string_lossy().into_owned())
+            .collect(),
+        ..FileConfig::default()
+    };
+    configure(&mut config);
+    let (tx, entries) = mpsc::channel(1024);
+    let (utx, _updates) = mpsc::channel(1024);
+    let (mtx, metadata) = mpsc::channel(1024);
+    let feed = FileFeed::new(&config).unwrap().with_metadata(mtx);
+    let feed = tokio::spawn(async move {
+        let _keep_updates_open = _updates;
+        feed.run(tx, utx).await
+    });
+    Harness {
+        dir,
+        entries,
+        metadata,
+        feed,
+    }
+}
+
+fn append(path: &Path, text: &str) {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .unwrap();
+    file.write_all(text.as_bytes()).unwrap();
+}
 
 // ---------------------------------------------------------------------------
 // Basic tailing
 // ---------------------------------------------------------------------------
 
 /// Lines written to a file after the ingestor starts must appear in the store.
-#[test]
-#[ignore = "not yet implemented"]
-fn appended_lines_are_ingested() {
-    todo!("write lines to tempfile; assert all arrive in store in order")
+#[tokio::test]
+async fn appended_lines_are_ingested() {
+    let mut h = start(&["app.log"], |dir| append(&dir.join("app.log"), "ready\n")).await;
+    assert_eq!(h.recv_messages(1).await, ["ready"]);
+
+    append(&h.path("app.log"), "one\ntwo\n");
+    append(&h.path("app.log"), "three\n");
+    assert_eq!(h.recv_messages(3).await, ["one", "two", "three"]);
 }
 
 /// Lines already in the file when the ingestor starts (backfill) must be
 /// ingested before new lines.
-#[test]
-#[ignore = "not yet implemented"]
-fn existing_lines_are_backfilled() {
-    todo!("pre-populate tempfile; start ingestor; assert existing lines arrive first")
+#[tokio::test]
+async fn existing_lines_are_backfilled() {
+    let mut h = start(&["app.log"], |dir| {
+        append(&dir.join("app.log"), "1\n2\n3\n")
+    })
+    .await;
+    append(&h.path("app.log"), "4\n");
+    assert_eq!(h.recv_messages(4).await, ["1", "2", "3", "4"]);
 }
 
 /// Producer name for file ingestor is the file path (or basename, per config).
-#[test]
-#[ignore = "not yet implemented"]
-fn producer_is_file_path() {
-    todo!("ingest a file; assert entry.producer matches the file path")
+#[tokio::test]
+async fn producer_is_file_path() {
+    let mut h = start(&["app.log"], |dir| append(&dir.join("app.log"), "hello\n")).await;
+    let entry = h.recv().await;
+    assert_source!(entry, FeedKind::File);
+    assert_producer!(entry, &h.producer("app.log"));
+    assert_has_field!(entry, "path", h.path("app.log").to_string_lossy());
 }
 
 // ---------------------------------------------------------------------------
@@ -60,25 +214,77 @@ fn producer_is_file_path() {
 
 /// When the file at the watched path is renamed and a new file is created,
 /// the ingestor must switch to the new file and continue tailing.
-#[test]
-#[ignore = "not yet implemented"]
-fn file_rotation_is_detected() {
-    todo!("rename tempfile; create new file at same path; assert ingestor follows new inode")
+#[tokio::test]
+async fn file_rotation_is_detected() {
+    let mut h = start(&["app.log"], |dir| append(&dir.join("app.log"), "before\n")).await;
+    assert_eq!(h.recv_messages(1).await, ["before"]);
+
+    std::fs::rename(h.path("app.log"), h.path("app.log.1")).unwrap();
+    append(&h.path("app.log"), "after\n");
+    assert_eq!(h.recv_messages(1).await, ["after"]);
+
+    let producer = h.producer("app.log");
+    let meta = h.wait_state(&producer, ProducerState::Rotated).await;
+    assert_eq!(meta.fields["rotations"], "1");
+
+    append(&h.path("app.log"), "still following\n");
+    assert_eq!(h.recv_messages(1).await, ["still following"]);
 }
 
 /// Lines written to the old file after rotation but before the ingestor
 /// detects the rotation must still arrive (drain the old file before switching).
-#[test]
-#[ignore = "not yet implemented"]
-fn old_file_is_drained_before_rotation() {
-    todo!("write to old file post-rotation; assert those lines arrive before new-file lines")
+#[tokio::test]
+async fn old_file_is_drained_before_rotation() {
+    let mut h = start(&["app.log"], |dir| append(&dir.join("app.log"), "ready\n")).await;
+    assert_eq!(h.recv_messages(1).await, ["ready"]);
+
+    // The writer keeps its descriptor across the rename, as a logger would.
+    let mut old = std::fs::OpenOptions::new()
+        .append(true)
+        .open(h.path("app.log"))
+        .unwrap();
+    std::fs::rename(h.path("app.log"), h.path("app.log.1")).unwrap();
+    old.write_all(b"late 1\nlate 2\n").unwrap();
+    append(&h.path("app.log"), "new\n");
+
+    assert_eq!(h.recv_messages(3).await, ["late 1", "late 2", "new"]);
 }
 
 /// No lines are duplicated across a file rotation.
-#[test]
-#[ignore = "not yet implemented"]
-fn no_duplicates_across_rotation() {
-    todo!("count total lines before and after rotation; assert no duplicates in store")
+#[tokio::test]
+async fn no_duplicates_across_rotation() {
+    // `app.log*` also matches the rotated `app.log.1`, which must not be
+    // read a second time.
+    let mut h = start(&["app.log*"], |dir| {
+        let lines: String = (0..50).map(|i| format!("old {i}\n")).collect();
+        append(&dir.join("app.log"), &lines);
+    })
+    .await;
+    assert_eq!(h.recv_messages(50).await.len(), 50);
+
+    std::fs::rename(h.path("app.log"), h.path("app.log.1")).unwrap();
+    let lines: String = (0..50).map(|i| format!("new {i}\n")).collect();
+    append(&h.path("app.log"), &lines);
+
+    let expected: Vec<String> = (0..50).map(|i| format!("new {i}")).collect();
+    assert_eq!(h.recv_messages(50).await, expected);
+    h.assert_quiet().await;
+}
+
+/// A deleted file is reported as such, and followed again from its start
+/// when it is re-created.
+#[tokio::test]
+async fn deleted_file_is_reported_and_recreated() {
+    let mut h = start(&["app.log"], |dir| append(&dir.join("app.log"), "ready\n")).await;
+    assert_eq!(h.recv_messages(1).await, ["ready"]);
+
+    std::fs::remove_file(h.path("app.log")).unwrap();
+    let producer = h.producer("app.log");
+    h.wait_state(&producer, ProducerState::Deleted).await;
+
+    append(&h.path("app.log"), "back\n");
+    assert_eq!(h.recv_messages(1).await, ["back"]);
+    h.wait_state(&producer, ProducerState::Running).await;
 }
 
 // ---------------------------------------------------------------------------
@@ -87,10 +293,16 @@ fn no_duplicates_across_rotation() {
 
 /// When a file is truncated (size drops below current read offset), the
 /// ingestor must seek to 0 and re-read.
-#[test]
-#[ignore = "not yet implemented"]
-fn truncated_file_is_re_read_from_start() {
-    todo!("truncate tempfile; write new content; assert new content arrives from offset 0")
+#[tokio::test]
+async fn truncated_file_is_re_read_from_start() {
+    let mut h = start(&["app.log"], |dir| {
+        append(&dir.join("app.log"), "a long first line\nanother one\n")
+    })
+    .await;
+    assert_eq!(h.recv_messages(2).await.len(), 2);
+
+    std::fs::write(h.path("app.log"), "fresh\n").unwrap();
+    assert_eq!(h.recv_messages(1).await, ["fresh"]);
 }
 
 // ---------------------------------------------------------------------------
@@ -98,28 +310,397 @@ fn truncated_file_is_re_read_from_start() {
 // ---------------------------------------------------------------------------
 
 /// A glob pattern that matches multiple files tails all of them simultaneously.
-#[test]
-#[ignore = "not yet implemented"]
-fn glob_matches_multiple_files() {
-    todo!("create 3 tempfiles matching glob; assert all 3 produce entries")
+#[tokio::test]
+async fn glob_matches_multiple_files() {
+    let mut h = start(&["*.log"], |dir| {
+        for name in ["a", "b", "c"] {
+            append(&dir.join(format!("{name}.log")), &format!("from {name}\n"));
+        }
+        append(&dir.join("notes.txt"), "not a log\n");
+    })
+    .await;
+
+    let mut producers: Vec<String> = Vec::new();
+    for _ in 0..3 {
+        producers.push(h.recv().await.producer);
+    }
+    producers.sort();
+    let expected: Vec<String> = ["a.log", "b.log", "c.log"]
+        .iter()
+        .map(|name| h.producer(name))
+        .collect();
+    assert_eq!(producers, expected);
+    h.assert_quiet().await;
 }
 
 /// A file created after the ingestor starts that matches the glob pattern is
 /// automatically picked up.
-#[test]
-#[ignore = "not yet implemented"]
-fn glob_discovers_new_files() {
-    todo!("create a matching file after ingestor starts; assert it is discovered")
+#[tokio::test]
+async fn glob_discovers_new_files() {
+    let mut h = start(&["**/*.log"], |dir| {
+        append(&dir.join("ready.log"), "ready\n")
+    })
+    .await;
+    assert_eq!(h.recv_messages(1).await, ["ready"]);
+
+    append(&h.path("later.log"), "top level\n");
+    let entry = h.recv().await;
+    assert_producer!(entry, &h.producer("later.log"));
+
+    // A new directory tree: the file may be written before the directory's
+    // own watch is in place.
+    std::fs::create_dir_all(h.path("api/v2")).unwrap();
+    append(&h.path("api/v2/access.log"), "nested\n");
+    let entry = h.recv().await;
+    assert_producer!(entry, &h.producer("api/v2/access.log"));
+    assert_eq!(entry.message.as_deref(), Some("nested"));
+}
+
+/// Files matching an exclude pattern are never read.
+#[tokio::test]
+async fn excluded_files_are_skipped() {
+    let mut h = start_with(
+        &[""],
+        |config| config.exclude = vec!["*.gz".into()],
+        |dir| {
+            append(&dir.join("app.log"), "plain\n");
+            append(&dir.join("app.log.1.gz"), "compressed\n");
+        },
+    )
+    .await;
+    assert_eq!(h.recv_messages(1).await, ["plain"]);
+
+    append(&h.path("app.log.2.gz"), "compressed\n");
+    h.assert_quiet().await;
+}
+
+/// Over the open-file limit, the most recently modified files are followed;
+/// a quiet file is parked and later resumes where it stopped.
+#[tokio::test]
+async fn max_open_files_favours_recently_modified() {
+    let mut h = start_with(
+        &["*.log"],
+        |config| config.max_open = 1,
+        |dir| {
+            append(&dir.join("quiet.log"), "quiet 1\n");
+            append(&dir.join("busy.log"), "busy 1\n");
+            let hour_ago = SystemTime::now() - Duration::from_secs(3600);
+            std::fs::File::options()
+                .append(true)
+                .open(dir.join("quiet.log"))
+                .unwrap()
+                .set_modified(hour_ago)
+                .unwrap();
+        },
+    )
+    .await;
+    assert_eq!(h.recv_messages(1).await, ["busy 1"]);
+    h.assert_quiet().await;
+
+    // Writing to the quiet file makes it the most recent: it takes the slot.
+    append(&h.path("quiet.log"), "quiet 2\n");
+    assert_eq!(h.recv_messages(2).await, ["quiet 1", "quiet 2"]);
+
+    // And the busy file resumes after `busy 1`.
+    append(&h.path("busy.log"), "busy 2\n");
+    assert_eq!(h.recv_messages(1).await, ["busy 2"]);
+}
+
+// ---------------------------------------------------------------------------
+// Archives
+// ---------------------------------------------------------------------------
+
+/// Compress `text` into `path` with an external tool (`zstd`, `bzip2`).
+fn compress_with(program: &str, path: &Path, text: &str) {
+    let mut child = std::process::Command::new(program)
+        .arg("-c")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::fs::File::create(path).unwrap())
+        .spawn()
+        .unwrap_or_else(|e| panic!("`{program}` should be installed: {e}"));
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(text.as_bytes())
+        .unwrap();
+    assert!(child.wait().unwrap().success());
+}
+
+fn gzip(path: &Path, text: &str) {
+    let file = std::fs::File::create(path).unwrap();
+    let mut encoder = flate2::write::GzEncoder::new(file, flate2::Compression::fast());
+    encoder.write_all(text.as_bytes()).unwrap();
+    encoder.finish().unwrap();
+}
+
+/// Set `path`'s modification time to `secs_ago` seconds in the past.
+fn age(path: &Path, secs_ago: u64) {
+    std::fs::File::options()
+        .append(true)
+        .open(path)
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(secs_ago))
+        .unwrap();
+}
+
+/// A rotated set: three days of archives in four formats, then the live file.
+fn rotated_set(dir: &Path) {
+    compress_with("bzip2", &dir.join("app.log.4.bz2"), "day -3\n");
+    age(&dir.join("app.log.4.bz2"), 3 * 86_400);
+    compress_with("zstd", &dir.join("app.log-20240102.zst"), "day -2\n");
+    age(&dir.join("app.log-20240102.zst"), 2 * 86_400);
+    gzip(&dir.join("app.log.2.gz"), "day -1 a\nday -1 b\n");
+    age(&dir.join("app.log.2.gz"), 86_400);
+    append(&dir.join("app.log.1"), "hour -1\n");
+    age(&dir.join("app.log.1"), 3600);
+    append(&dir.join("app.log"), "live\n");
+}
+
+/// Archives are decompressed and read oldest first, before the live file.
+#[tokio::test]
+async fn archives_are_backfilled_oldest_first() {
+    let mut h = start_with(&["app.log"], |config| config.archives = true, rotated_set).await;
+    let expected = [
+        "day -3", "day -2", "day -1 a", "day -1 b", "hour -1", "live",
+    ];
+    assert_eq!(h.recv_messages(6).await, expected);
+
+    append(&h.path("app.log"), "tailing\n");
+    let entry = h.recv().await;
+    assert_eq!(entry.message.as_deref(), Some("tailing"));
+    assert_producer!(entry, &h.producer("app.log"));
+}
+
+/// Archives beyond the horizon are left alone.
+#[tokio::test]
+async fn archive_horizon_limits_backfill() {
+    let mut h = start_with(
+        &["app.log"],
+        |config| {
+            config.archives = true;
+            config.archive_horizon = Some("36h".parse().unwrap());
+        },
+        rotated_set,
+    )
+    .await;
+    let entry = h.recv().await;
+    assert_eq!(entry.message.as_deref(), Some("day -1 a"));
+    assert_has_field!(entry, "path", h.path("app.log.2.gz").to_string_lossy());
+    assert_eq!(h.recv_messages(3).await, ["day -1 b", "hour -1", "live"]);
+}
+
+/// An archive matched by the pattern itself is read once, as backfill.
+#[tokio::test]
+async fn archives_matching_the_pattern_are_read_once() {
+    let mut h = start_with(
+        &["app.log*"],
+        |config| {
+            config.archives = true;
+            config.exclude = vec!["*.bz2".into(), "*.zst".into(), "*.gz".into()];
+        },
+        rotated_set,
+    )
+    .await;
+    assert_eq!(h.recv_messages(6).await.len(), 6);
+    h.assert_quiet().await;
+}
+
+// ---------------------------------------------------------------------------
+// Checkpoints
+// ---------------------------------------------------------------------------
+
+/// Save offsets to a file in `state`.
+fn checkpoint_to(state: &TempDir) -> impl Fn(&mut FileConfig) + '_ {
+    |config| config.checkpoint = Some(state.path().join("file-offsets.json"))
+}
+
+/// A restarted feed carries on after the last line sent, not from the start.
+#[tokio::test]
+async fn restart_resumes_from_checkpoint() {
+    let state = tempfile::tempdir().unwrap();
+    let mut h = start_with(&["app.log"], checkpoint_to(&state), |dir| {
+        append(&dir.join("app.log"), "one\ntwo\n");
+    })
+    .await;
+    assert_eq!(h.recv_messages(2).await, ["one", "two"]);
+    append(&h.path("app.log"), "three\n");
+    assert_eq!(h.recv_messages(1).await, ["three"]);
+
+    let mut h = h
+        .restart(&["app.log"], checkpoint_to(&state), |dir| {
+            append(&dir.join("app.log"), "four\n");
+        })
+        .await;
+    assert_eq!(h.recv_messages(1).await, ["four"]);
+    h.assert_quiet().await;
+}
+
+/// A file rotated while the feed was down is drained from its saved offset
+/// under the rotated name, then the new file is read whole.
+#[tokio::test]
+async fn restart_drains_file_rotated_while_stopped() {
+    let state = tempfile::tempdir().unwrap();
+    let mut h = start_with(&["app.log"], checkpoint_to(&state), |dir| {
+        append(&dir.join("app.log"), "one\ntwo\n");
+    })
+    .await;
+    assert_eq!(h.recv_messages(2).await, ["one", "two"]);
+
+    let mut h = h
+        .restart(&["app.log"], checkpoint_to(&state), |dir| {
+            append(&dir.join("app.log"), "three\n");
+            std::fs::rename(dir.join("app.log"), dir.join("app.log.1")).unwrap();
+            append(&dir.join("app.log"), "four\n");
+        })
+        .await;
+    let entry = h.recv().await;
+    assert_eq!(entry.message.as_deref(), Some("three"));
+    assert_has_field!(entry, "path", h.path("app.log.1").to_string_lossy());
+    assert_eq!(h.recv_messages(1).await, ["four"]);
+    h.assert_quiet().await;
+}
+
+/// A file rewritten while the feed was down no longer matches its
+/// fingerprint and is read from the start.
+#[tokio::test]
+async fn restart_rereads_file_rewritten_while_stopped() {
+    let state = tempfile::tempdir().unwrap();
+    let mut h = start_with(&["app.log"], checkpoint_to(&state), |dir| {
+        append(&dir.join("app.log"), "old one\nold two\n");
+    })
+    .await;
+    assert_eq!(h.recv_messages(2).await, ["old one", "old two"]);
+
+    let mut h = h
+        .restart(&["app.log"], checkpoint_to(&state), |dir| {
+            std::fs::write(dir.join("app.log"), "new one\nnew two\nnew three\n").unwrap();
+        })
+        .await;
+    assert_eq!(
+        h.recv_messages(3).await,
+        ["new one", "new two", "new three"]
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Stop and reconnect
+// ---------------------------------------------------------------------------
+
+/// Like [`start`], with the feed behind a [`FeedControl`] listening on its
+/// signals.
+async fn start_controlled(patterns: &[&str], setup: impl FnOnce(&Path)) -> (Harness, FeedControl) {
+    let dir = tempfile::tempdir().unwrap();
+    setup(dir.path());
+    let config = FileConfig {
+        paths: patterns
+            .iter()
+            .map(|p| dir.path().join(p).to_string_lossy().into_owned())
+            .collect(),
+        ..FileConfig::default()
+    };
+    let (tx, entries) = mpsc::channel(1024);
+    let (utx, updates) = mpsc::channel(1024);
+    let (mtx, metadata) = mpsc::channel(1024);
+    let (control, senders, relay) = FeedControl::attach(tx, utx, mtx);
+    tokio::spawn(relay.run());
+    let feed = FileFeed::new(&config)
+        .unwrap()
+        .with_metadata(senders.metadata)
+        .with_signals(control.signals());
+    let feed = tokio::spawn(async move {
+        let _keep_updates_open = updates;
+        feed.run(senders.entries, senders.updates).await
+    });
+    let harness = Harness {
+        dir,
+        entries,
+        metadata,
+        feed,
+    };
+    (harness, control)
+}
+
+/// Wait until this process holds `open` descriptors on `path`.
+async fn wait_open(path: &Path, open: usize) {
+    let path = path.canonicalize().unwrap();
+    let count = || {
+        std::fs::read_dir("/proc/self/fd")
+            .unwrap()
+            .filter_map(|fd| std::fs::read_link(fd.ok()?.path()).ok())
+            .filter(|target| *target == path)
+            .count()
+    };
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while count() != open {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("expected {open} open descriptors, found {}", count()));
+}
+
+/// Stopping a file closes it; starting it reopens it at its end, leaving out
+/// what was written in between.
+#[tokio::test]
+async fn stop_closes_the_file_until_started() {
+    let (mut h, control) =
+        start_controlled(&["app.log"], |dir| append(&dir.join("app.log"), "before\n")).await;
+    assert_eq!(h.recv_messages(1).await, ["before"]);
+    let path = h.path("app.log");
+    let id = format!("file/{}", h.producer("app.log"));
+
+    control.stop(&id);
+    wait_open(&path, 0).await;
+    append(&path, "while stopped\n");
+    control.start(&id);
+    wait_open(&path, 1).await;
+    append(&path, "after\n");
+    assert_eq!(h.recv_messages(1).await, ["after"]);
+    h.assert_quiet().await;
+}
+
+/// A reconnect reopens the file where it was read to, so nothing is lost or
+/// repeated.
+#[tokio::test]
+async fn reconnect_reopens_where_it_was_read_to() {
+    let (mut h, control) =
+        start_controlled(&["app.log"], |dir| append(&dir.join("app.log"), "one\n")).await;
+    assert_eq!(h.recv_messages(1).await, ["one"]);
+    control.reconnect(&format!("file/{}", h.producer("app.log")));
+    append(&h.path("app.log"), "two\n");
+    assert_eq!(h.recv_messages(1).await, ["two"]);
+    h.assert_quiet().await;
 }
 
 // ---------------------------------------------------------------------------
 // Property tests
 // ---------------------------------------------------------------------------
 
-/// Property: for any sequence of line writes, all lines written to a file
-/// before it is closed appear in the store exactly once.
-#[test]
-#[ignore = "not yet implemented"]
-fn prop_all_written_lines_received() {
-    todo!("proptest: random lines and write batches; assert store contains all lines exactly once")
+proptest::proptest! {
+    #![proptest_config(proptest::prelude::ProptestConfig::with_cases(8))]
+
+    /// Property: for any sequence of line writes, all lines written to a file
+    /// before it is closed appear in the store exactly once.
+    #[test]
+    fn prop_all_written_lines_received(
+        batches in proptest::collection::vec(
+            proptest::collection::vec("[a-zA-Z0-9 =:]{0,40}", 1..20),
+            1..6,
+        )
+    ) {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let mut h = start(&["app.log"], |dir| append(&dir.join("app.log"), "ready\n")).await;
+            assert_eq!(h.recv_messages(1).await, ["ready"]);
+
+            let mut written = Vec::new();
+            for batch in &batches {
+                let text: String = batch.iter().map(|line| format!("{line}\n")).collect();
+                append(&h.path("app.log"), &text);
+                written.extend(batch.iter().cloned());
+            }
+            assert_eq!(h.recv_messages(written.len()).await, written);
+        });
+    }
 }
@@ -0,0 +1,256 @@
+#![allow(unused)]
+//! Fluent forward receiver integration harness.
+//!
+//! # What this covers
+//!
+//! - **Event modes**: Message, Forward, PackedForward and gzip
+//!   CompressedPackedForward messages sent over TCP, as Fluent Bit and
+//!   Fluentd `forward` outputs do.
+//! - **Acks**: a message carrying a `chunk` option is answered with
+//!   `{"ack": chunk}` once its events are queued.
+//! - **Framing**: messages split across writes and several messages in one
+//!   write.
+//! - **Mapping**: dotted tags as producer paths, record keys as fields,
+//!   `EventTime` timestamps.
+//! - **Isolation**: a connection sending garbage, or a compressed payload
+//!   that inflates past the message limit, is dropped while others keep
+//!   working.
+//!
+//! # What this does NOT cover
+//!
+//! - Shared-key handshake (`HELO` / `PING` / `PONG`) and TLS
+//! - UDP heartbeats
+//!
+//! # Running
+//!
+//! ```sh
+//! cargo test --test forward_harness
+//! ```
+
+mod common;
+use common::*;
+use fml_core::{FeedKind, LogEntry, LogLevel};
+use fml_feeds::forward::{ForwardConfig, ForwardReceiver};
+use rmpv::Value as Msg;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+
+async fn start() -> (SocketAddr, mpsc::Receiver<LogEntry>) {
+    let receiver = ForwardReceiver::bind(&ForwardConfig {
+        addr: "127.0.0.1:0".parse().unwrap(),
+    })
+    .await
+    .unwrap();
+    let addr = receiver.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel(64);
+    tokio::spawn(receiver.run(tx));
+    (addr, rx)
+}
+
+async fn recv(rx: &mut mpsc::Receiver<LogEntry>) -> LogEntry {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("message should arrive")
+        .expect("channel open")
+}
+
+fn encode(value: &Msg) -> Vec<u8> {
+    let mut out = Vec::new();
+    rmpv::encode::write_value(&mut out, value).unwrap();
+    out
+}
+
+fn record(pairs: &[(&str, &str)]) -> Msg {
+    Msg::Map(
+        pairs
+            .iter()
+            .map(|(k, v)| (Msg::from(*k), Msg::from(*v)))
+            .collect(),
+    )
+}
+
+/// `EventTime`: ext type 0 with big-endian seconds and nanoseconds.
+fn event_time(secs: u32, nanos: u32) -> Msg {
+    let mut data = secs.to_be_bytes().to_vec();
+    data.extend(nanos.to_be_bytes());
+    Msg::Ext(0, data)
+}
+
+fn entry(log: &str) -> Msg {
+    Msg::Array(vec![
+        Msg::from(1_705_312_800),
+        record(&[("log", log), ("stream", "stdout")]),
+    ])
+}
+
+// ---------------------------------------------------------------------------
+// Event modes
+// ---------------------------------------------------------------------------
+
+/// A Message-mode event becomes one entry under the tag's producer path.
+#[tokio::test]
+async fn message_mode_is_ingested() {
+    let (addr, mut rx) = start().await;
+    let message = Msg::Array(vec![
+        Msg::from("kube.shop.api"),
+        event_time(1_705_312_800, 250_000_000),
+        record(&[
+            ("log", "payment failed"),
+            ("level", "error"),
+            ("pod", "api-1"),
+        ]),
+    ]);
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    conn.write_all(&encode(&message)).await.unwrap();
+
+    let e = recv(&mut rx).await;
+    assert_source!(e, FeedKind::Forward);
+    assert_producer!(e, "kube/shop/api");
+    assert_level!(e, LogLevel::Error);
+    assert_eq!(e.message.as_deref(), Some("payment failed"));
+    assert_eq!(e.ts.timestamp_millis(), 1_705_312_800_250);
+    assert_has_field!(e, "pod", "api-1");
+    assert_has_field!(e, "tag", "kube.shop.api");
+}
+
+/// Forward and PackedForward (plain and gzip) messages can share one
+/// connection; several messages may arrive in one write.
+#[tokio::test]
+async fn forward_and_packed_modes_share_a_connection() {
+    let (addr, mut rx) = start().await;
+
+    let forward = Msg::Array(vec![
+        Msg::from("app"),
+        Msg::Array(vec![entry("one"), entry("two")]),
+    ]);
+    let packed: Vec<u8> = [entry("three"), entry("four")]
+        .iter()
+        .flat_map(encode)
+        .collect();
+    let packed_forward = Msg::Array(vec![Msg::from("app"), Msg::Binary(packed.clone())]);
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    gz.write_all(&packed).unwrap();
+    let compressed = Msg::Array(vec![
+        Msg::from("app"),
+        Msg::Binary(gz.finish().unwrap()),
+        Msg::Map(vec![(Msg::from("compressed"), Msg::from("gzip"))]),
+    ]);
+
+    let mut bytes = encode(&forward);
+    bytes.extend(encode(&packed_forward));
+    bytes.extend(encode(&compressed));
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    conn.write_all(&bytes).await.unwrap();
+
+    let mut logs = Vec::new();
+    for _ in 0..6 {
+        let e = recv(&mut rx).await;
+        assert_producer!(e, "app");
+        assert_has_field!(e, "stream", "stdout");
+        logs.push(e.message.unwrap());
+    }
+    assert_eq!(logs, ["one", "two", "three", "four", "three", "four"]);
+}
+
+// ---------------------------------------------------------------------------
+// Acks and framing
+// ---------------------------------------------------------------------------
+
+/// A message with a chunk id is acked once queued, even when it arrives a few
+/// bytes at a time.
+#[tokio::test]
+async fn chunks_are_acked_after_split_writes() {
+    let (addr, mut rx) = start().await;
+    let message = Msg::Array(vec![
+        Msg::from("app"),
+        Msg::Array(vec![entry("one")]),
+        Msg::Map(vec![
+            (Msg::from("chunk"), Msg::from("p8n9gmxTQVC8/nh2wlKKeQ==")),
+            (Msg::from("size"), Msg::from(1)),
+        ]),
+    ]);
+    let bytes = encode(&message);
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    for piece in bytes.chunks(7) {
+        conn.write_all(piece).await.unwrap();
+        conn.flush().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    assert_eq!(recv(&mut rx).await.message.as_deref(), Some("one"));
+    let mut response = vec![0u8; 64];
+    let n = tokio::time::timeout(Duration::from_secs(5), conn.read(&mut response))
+        .await
+        .expect("ack should arrive")
+        .unwrap();
+    let ack = rmpv::decode::read_value(&mut &response[..n]).unwrap();
+    assert_eq!(
+        ack,
+        Msg::Map(vec![(
+            Msg::from("ack"),
+            Msg::from("p8n9gmxTQVC8/nh2wlKKeQ==")
+        )])
+    );
+}
+
+/// A sender that speaks something else is disconnected; other senders are
+/// unaffected.
+#[tokio::test]
+async fn garbage_drops_only_its_connection() {
+    let (addr, mut rx) = start().await;
+    let mut bad = TcpStream::connect(addr).await.unwrap();
+    bad.write_all(&encode(&Msg::from("not a message")))
+        .await
+        .unwrap();
+    let mut buf = [0u8; 8];
+    let n = tokio::time::timeout(Duration::from_secs(5), bad.read(&mut buf))
+        .await
+        .expect("connection should close")
+        .unwrap_or(0);
+    assert_eq!(n, 0);
+
+    let message = Msg::Array(vec![
+        Msg::from("app"),
+        Msg::from(1),
+        record(&[("log", "ok")]),
+    ]);
+    let mut good = TcpStream::connect(addr).await.unwrap();
+    good.write_all(&encode(&message)).await.unwrap();
+    assert_eq!(recv(&mut rx).await.message.as_deref(), Some("ok"));
+}
+
+/// A compressed payload that inflates past the message limit drops the
+/// connection unacked instead of being inflated whole.
+#[tokio::test]
+async fn gzip_bomb_drops_its_connection() {
+    let (addr, mut rx) = start().await;
+    // Concatenated gzip members of valid events, inflating to over 65 MiB.
+    let event = encode(&entry("x"));
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+    gz.write_all(&event.repeat(1024 * 1024 / event.len() + 1))
+        .unwrap();
+    let member = gz.finish().unwrap();
+    let bomb = member.repeat(65);
+    let message = Msg::Array(vec![
+        Msg::from("app"),
+        Msg::Binary(bomb),
+        Msg::Map(vec![
+            (Msg::from("compressed"), Msg::from("gzip")),
+            (Msg::from("chunk"), Msg::from("bomb")),
+        ]),
+    ]);
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    conn.write_all(&encode(&message)).await.unwrap();
+
+    let mut buf = [0u8; 64];
+    let n = tokio::time::timeout(Duration::from_secs(30), conn.read(&mut buf))
+        .await
+        .expect("connection should close")
+        .unwrap_or(0);
+    assert_eq!(n, 0, "no ack for an oversized payload");
+    assert!(rx.try_recv().is_err());
+}
@@ -0,0 +1,270 @@
+#![allow(unused)]
+//! HTTP push ingestion integration harness.
+//!
+//! # What this covers
+//!
+//! - **`POST /ingest`**: newline-delimited JSON, attributed to the `producer`
+//!   query parameter, with message / level / timestamp keys mapped.
+//! - **`POST /loki/api/v1/push`**: Loki JSON push payloads, producer built
+//!   from the stream labels, labels kept as fields.
+//! - **Acknowledgement**: `204` only after the whole batch is stored, handed
+//!   over in chunks when it is larger than the queue; `429` with nothing
+//!   ingested when the queue takes nothing in time, and `429` when the
+//!   overflow policy drops part of the batch.
+//! - **Rejection**: malformed bodies get `400`, protobuf pushes get `415`.
+//!
+//! # What this does NOT cover
+//!
+//! - Snappy-compressed protobuf Loki pushes
+//! - TLS (put a reverse proxy in front)
+//!
+//! # Running
+//!
+//! ```sh
+//! cargo test --test http_harness
+//! ```
+
+mod common;
+use common::*;
+use fml_core::{FeedKind, LogEntry, LogLevel, Overflow};
+use fml_feeds::http::{HttpConfig, HttpIngest};
+use fml_feeds::{FeedControl, FeedHandle};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+async fn start(capacity: usize) -> (SocketAddr, mpsc::Receiver<LogEntry>) {
+    let ingest = HttpIngest::bind(&HttpConfig {
+        addr: "127.0.0.1:0".parse().unwrap(),
+        ..HttpConfig::default()
+    })
+    .await
+    .unwrap();
+    let addr = ingest.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel(capacity);
+    tokio::spawn(ingest.run(tx));
+    (addr, rx)
+}
+
+async fn post(addr: SocketAddr, path: &str, content_type: &str, body: &str) -> u16 {
+    http_post(
+        addr,
+        path,
+        &[("Content-Type", content_type)],
+        body.as_bytes(),
+    )
+    .await
+    .status
+}
+
+/// Like [`start`], with the ingest queue behind a relay: requests are only
+/// answered once the test reports their lines stored through the control.
+async fn start_relayed(
+    capacity: usize,
+    overflow: Overflow,
+) -> (SocketAddr, FeedControl, mpsc::Receiver<LogEntry>) {
+    let (tx, rx) = mpsc::channel(capacity);
+    let (utx, _updates) = mpsc::channel(256);
+    let (mtx, _metadata) = mpsc::channel(256);
+    let (control, senders, relay) = FeedControl::attach(tx, utx, mtx);
+    tokio::spawn(relay.with_overflow(overflow, capacity).run());
+    let ingest = HttpIngest::bind(&HttpConfig {
+        addr: "127.0.0.1:0".parse().unwrap(),
+        ..HttpConfig::default()
+    })
+    .await
+    .unwrap()
+    .with_deliveries(control.deliveries());
+    let addr = ingest.local_addr().unwrap();
+    tokio::spawn(ingest.run(senders.entries));
+    (addr, control, rx)
+}
+
+async fn recv(rx: &mut mpsc::Receiver<LogEntry>) -> LogEntry {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("timed out waiting for an entry")
+        .unwrap()
+}
+
+fn drain(rx: &mut mpsc::Receiver<LogEntry>) -> Vec<LogEntry> {
+    let mut out = Vec::new();
+    while let Ok(entry) = rx.try_recv() {
+        out.push(entry);
+    }
+    out
+}
+
+// ---------------------------------------------------------------------------
+// /ingest
+// ---------------------------------------------------------------------------
+
+/// Every NDJSON line is queued before the request is acknowledged.
+#[tokio::test]
+async fn ndjson_batch_is_queued_before_ack() {
+    let (addr, mut rx) = start(16).await;
+    let body = "{\"msg\":\"started\",\"level\":\"info\"}\n\
+                {\"msg\":\"db timeout\",\"level\":\"error\",\"timestamp\":\"2024-01-15T10:00:00Z\"}\n";
+    let status = post(
+        addr,
+        "/ingest?producer=vector/api",
+        "application/x-ndjson",
+        body,
+    )
+    .await;
+    assert_eq!(status, 204);
+
+    let entries = drain(&mut rx);
+    assert_eq!(entries.len(), 2, "both lines queued by the time of the ack");
+    assert_source!(entries[1], FeedKind::Http);
+    assert_producer!(entries[1], "vector/api");
+    assert_level!(entries[1], LogLevel::Error);
+    assert_eq!(entries[1].message.as_deref(), Some("db timeout"));
+    assert_eq!(entries[1].ts.to_rfc3339(), "2024-01-15T10:00:00+00:00");
+}
+
+/// Without a `producer` parameter, entries are attributed to `http`.
+#[tokio::test]
+async fn producer_defaults_to_http() {
+    let (addr, mut rx) = start(4).await;
+    assert_eq!(
+        post(addr, "/ingest", "application/json", "{\"msg\":\"x\"}").await,
+        204
+    );
+    assert_producer!(drain(&mut rx)[0], "http");
+}
+
+/// A body with any malformed line is rejected whole.
+#[tokio::test]
+async fn malformed_body_is_rejected() {
+    let (addr, mut rx) = start(4).await;
+    let status = post(
+        addr,
+        "/ingest",
+        "application/json",
+        "{\"msg\":\"ok\"}\nnope\n",
+    )
+    .await;
+    assert_eq!(status, 400);
+    assert!(drain(&mut rx).is_empty());
+}
+
+// ---------------------------------------------------------------------------
+// Loki push
+// ---------------------------------------------------------------------------
+
+/// Stream labels become the producer path and entry fields.
+#[tokio::test]
+async fn loki_push_maps_labels() {
+    let (addr, mut rx) = start(8).await;
+    let body = r#"{"streams":[{"stream":{"namespace":"shop","pod":"checkout-7d9f","container":"app"},
+        "values":[["1705312800000000000","GET /cart 200"],["1705312801000000000","GET /pay 500"]]}]}"#;
+    assert_eq!(
+        post(addr, "/loki/api/v1/push", "application/json", body).await,
+        204
+    );
+
+    let entries = drain(&mut rx);
+    assert_eq!(entries.len(), 2);
+    assert_producer!(entries[0], "shop/checkout-7d9f/app");
+    assert_has_field!(entries[0], "namespace", "shop");
+    assert_eq!(entries[1].raw, "GET /pay 500");
+    assert_eq!(entries[1].ts.timestamp(), 1_705_312_801);
+}
+
+/// Protobuf pushes are refused so the shipper can be reconfigured for JSON.
+#[tokio::test]
+async fn loki_protobuf_is_unsupported() {
+    let (addr, _rx) = start(4).await;
+    let status = post(addr, "/loki/api/v1/push", "application/x-protobuf", "\0").await;
+    assert_eq!(status, 415);
+}
+
+// ---------------------------------------------------------------------------
+// Backpressure
+// ---------------------------------------------------------------------------
+
+/// A batch larger than the whole queue is handed over in chunks as the queue
+/// drains, rather than being refused forever.
+#[tokio::test]
+async fn batch_larger_than_queue_is_chunked() {
+    let (addr, mut rx) = start(2).await;
+    let body: String = (1..=5).map(|n| format!("{{\"msg\":\"{n}\"}}\n")).collect();
+    let push = tokio::spawn(async move { post(addr, "/ingest", "application/json", &body).await });
+
+    let mut messages = Vec::new();
+    while messages.len() < 5 {
+        let entry = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("the batch should keep flowing")
+            .unwrap();
+        messages.push(entry.message.unwrap());
+    }
+    assert_eq!(messages, ["1", "2", "3", "4", "5"]);
+    assert_eq!(push.await.unwrap(), 204);
+}
+
+/// When the queue stays full, the request gets 429 and nothing from it is
+/// ingested, so the shipper's retry cannot duplicate lines.
+#[tokio::test]
+async fn full_queue_rejects_whole_batch() {
+    let (addr, mut rx) = start(2).await;
+    let body = "{\"msg\":\"1\"}\n{\"msg\":\"2\"}\n";
+    assert_eq!(post(addr, "/ingest", "application/json", body).await, 204);
+
+    let body = "{\"msg\":\"3\"}\n";
+    assert_eq!(post(addr, "/ingest", "application/json", body).await, 429);
+    let messages: Vec<_> = drain(&mut rx)
+        .into_iter()
+        .map(|e| e.message.unwrap())
+        .collect();
+    assert_eq!(messages, ["1", "2"]);
+
+    assert_eq!(post(addr, "/ingest", "application/json", body).await, 204);
+    assert_eq!(drain(&mut rx).len(), 1);
+}
+
+/// A request is answered only once the UI reports its lines stored, not
+/// when they enter the queue.
+#[tokio::test]
+async fn ack_waits_until_batch_is_stored() {
+    let (addr, control, mut rx) = start_relayed(16, Overflow::Block).await;
+    let body = "{\"msg\":\"1\"}\n{\"msg\":\"2\"}\n";
+    let push = tokio::spawn(async move { post(addr, "/ingest", "application/json", body).await });
+
+    assert_eq!(recv(&mut rx).await.message.as_deref(), Some("1"));
+    assert_eq!(recv(&mut rx).await.message.as_deref(), Some("2"));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!push.is_finished(), "queued is not stored");
+
+    control.stored("http", 2);
+    assert_eq!(push.await.unwrap(), 204);
+}
+
+/// Lines the overflow policy drops turn the answer into 429, so the shipper
+/// knows fml did not keep them.
+#[tokio::test]
+async fn dropped_lines_are_not_acknowledged() {
+    let (addr, control, mut rx) = start_relayed(1, Overflow::DropOldest).await;
+    let body = "{\"msg\":\"1\"}\n{\"msg\":\"2\"}\n{\"msg\":\"3\"}\n";
+    let push = tokio::spawn(async move { post(addr, "/ingest", "application/json", body).await });
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let mut messages = Vec::new();
+    while let Ok(Some(entry)) = tokio::time::timeout(Duration::from_millis(200), rx.recv()).await {
+        messages.push(entry.message.unwrap());
+    }
+    assert!(messages.len() < 3, "something was dropped: {messages:?}");
+    control.stored("http", messages.len() as u64);
+    assert_eq!(push.await.unwrap(), 429);
+}
+
+/// A batch whose later chunks the queue never takes is turned away rather
+/// than left hanging.
+#[tokio::test]
+async fn stalled_chunk_is_rejected() {
+    let (addr, mut rx) = start(2).await;
+    let body: String = (1..=3).map(|n| format!("{{\"msg\":\"{n}\"}}\n")).collect();
+    assert_eq!(post(addr, "/ingest", "application/json", &body).await, 429);
+    assert_eq!(drain(&mut rx).len(), 2);
+}
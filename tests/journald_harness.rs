@@ -0,0 +1,126 @@
+#![allow(unused)]
+//! Journald ingestor integration harness.
+//!
+//! # What this covers
+//!
+//! - **Record mapping**: `journalctl -o json` records fed through a fake
+//!   process must arrive with `PRIORITY` → level, `__REALTIME_TIMESTAMP` → ts
+//!   and `_PID` / `_HOSTNAME` / `_COMM` as fields.
+//! - **Producer grouping**: producers are `host/unit`, so records from several
+//!   hosts and units stay distinguishable in a single stream.
+//! - **Robustness**: non-JSON lines (journalctl banners) are skipped, and the
+//!   ingestor returns cleanly on EOF.
+//!
+//! # What this does NOT cover
+//!
+//! - A real `journalctl` binary or systemd journal
+//!
+//! # Running
+//!
+//! ```sh
+//! cargo test --test journald_harness
+//! ```
+
+mod common;
+use common::fake_process::fake_process;
+use common::*;
+use fml_core::{FeedKind, LogEntry, LogLevel};
+use futures::StreamExt;
+use tokio::sync::mpsc;
+
+fn record(host: &str, unit: &str, priority: u8, message: &str) -> String {
+    serde_json::json!({
+        "__REALTIME_TIMESTAMP": "1705312800000000",
+        "PRIORITY": priority.to_string(),
+        "_HOSTNAME": host,
+        "_SYSTEMD_UNIT": unit,
+        "_PID": "4242",
+        "_COMM": unit.trim_end_matches(".service"),
+        "MESSAGE": message,
+    })
+    .to_string()
+}
+
+async fn collect(lines: &[String]) -> Vec<LogEntry> {
+    let (writer, stream) = fake_process();
+    for line in lines {
+        writer.send_line(line.clone());
+    }
+    writer.close();
+
+    let (tx, mut rx) = mpsc::channel(64);
+    fml_feeds::journald::ingest(stream.map(Ok), tx)
+        .await
+        .expect("ingest should finish cleanly on EOF");
+
+    let mut out = Vec::new();
+    while let Some(entry) = rx.recv().await {
+        out.push(entry);
+    }
+    out
+}
+
+// ---------------------------------------------------------------------------
+// Record mapping
+// ---------------------------------------------------------------------------
+
+/// Journal fields are mapped onto the entry and tagged with the journald source.
+#[tokio::test]
+async fn journal_record_is_mapped_to_entry() {
+    let entries = collect(&[record("bastion-1", "sshd.service", 4, "Invalid user")]).await;
+    assert_eq!(entries.len(), 1);
+    let entry = &entries[0];
+    assert_source!(entry, FeedKind::Journald);
+    assert_level!(entry, LogLevel::Warn);
+    assert_eq!(entry.message.as_deref(), Some("Invalid user"));
+    assert_eq!(entry.ts.timestamp(), 1_705_312_800);
+    assert_has_field!(entry, "pid", "4242");
+    assert_has_field!(entry, "hostname", "bastion-1");
+    assert_has_field!(entry, "comm", "sshd");
+}
+
+// ---------------------------------------------------------------------------
+// Producer grouping
+// ---------------------------------------------------------------------------
+
+/// Records from multiple hosts and units carry `host/unit` producers.
+#[tokio::test]
+async fn producers_are_grouped_by_host_then_unit() {
+    let entries = collect(&[
+        record("bastion-1", "sshd.service", 6, "a"),
+        record("bastion-1", "nginx.service", 6, "b"),
+        record("bastion-2", "sshd.service", 6, "c"),
+    ])
+    .await;
+    let producers: Vec<_> = entries.iter().map(|e| e.producer.as_str()).collect();
+    assert_eq!(
+        producers,
+        [
+            "bastion-1/sshd.service",
+            "bastion-1/nginx.service",
+            "bastion-2/sshd.service"
+        ]
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Robustness
+// ---------------------------------------------------------------------------
+
+/// Non-JSON lines are skipped without ending the feed.
+#[tokio::test]
+async fn non_record_lines_are_skipped() {
+    let entries = collect(&[
+        "-- Journal begins at Mon 2024-01-15 --".to_string(),
+        record("h", "cron.service", 6, "ran job"),
+    ])
+    .await;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].producer, "h/cron.service");
+}
+
+/// Immediate EOF ends the ingestor with no entries.
+#[tokio::test]
+async fn empty_stream_produces_no_entries() {
+    assert!(collect(&[]).await.is_empty());
+}
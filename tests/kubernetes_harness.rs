@@ -4,22 +4,35 @@
 //! # What this covers
 //!
 //! - **Producer tagging**: every log line from a pod must carry that pod's name
-//!   in `LogEntry::producer`, regardless of how many pods are multiplexed.
+//!   in `LogEntry::producer` (as `namespace/pod`), regardless of how many pods
+//!   are multiplexed.
 //! - **Namespace selection**: selecting a namespace implicitly tails all pods in
 //!   that namespace; pods in other namespaces must not appear.
-//! - **Reconnect-on-exit**: when `kubectl logs -f` exits (pod restart, preemption),
-//!   the ingestor must re-connect and resume tailing without duplicating lines.
-//! - **Container sub-selection**: multi-container pods must honour the container
-//!   filter; logs from unselected containers must not appear.
+//! - **Reconnect-on-close**: when the API server closes a log stream while the
+//!   container keeps running, the ingestor must re-connect and resume tailing
+//!   without duplicating lines.
+//! - **Container sub-selection**: multi-container pods get one producer per
+//!   container.
+//! - **Pod watch**: pods created after start are attached; deleted pods and
+//!   stopped containers are reported as ended.
+//! - **Restarts**: a restart fetches the previous instance's log once, tagged
+//!   `container_instance=previous`, and reports `Restarted` for the tree.
+//! - **Container state**: `Running`, `CrashLoopBackOff` with the previous
+//!   instance's `OOMKilled` reason and the restart count, on the metadata
+//!   channel.
+//! - **Events**: `namespace/events` producer, level from `type`, involved pod
+//!   as correlatable fields, recurrences as new entries.
+//! - **Workloads**: replicas nest under their owning workload; selecting a
+//!   workload or label selector follows replacement pods across rollouts.
+//! - **kubeconfig auth**: static bearer tokens and exec credential plugins,
+//!   read from a kubeconfig pointing at [`FakeKubeApi`].
 //! - **Property: no duplicate lines on retry**: after a reconnect, lines seen
-//!   before the disconnect must not be re-emitted. Verified with proptest over
-//!   random burst sizes and reconnect timings.
+//!   before the disconnect must not be re-emitted.
 //!
 //! # What this does NOT cover
 //!
-//! - kubeconfig auth (faked via FakeProcessSpawner, not a real cluster)
+//! - Client certificate auth and TLS validation (the fake serves plain HTTP)
 //! - RBAC / OIDC edge cases
-//! - Network-level TLS validation
 //!
 //! # Running
 //!
@@ -29,34 +42,210 @@
 //! ```
 
 mod common;
+use common::fake_kube_api::{exec_user, token_user, FakeKubeApi};
 use common::*;
+use fml_core::{
+    FeedKind, LogEntry, LogLevel, ProducerMeta, ProducerState, ProducerStatus, ProducerUpdate,
+};
+use fml_feeds::kubernetes::{KubernetesConfig, KubernetesFeed};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// A running feed against a fake API server.
+struct Harness {
+    api: FakeKubeApi,
+    entries: mpsc::Receiver<LogEntry>,
+    updates: mpsc::Receiver<ProducerUpdate>,
+    metadata: mpsc::Receiver<ProducerMeta>,
+    _dir: tempfile::TempDir,
+}
+
+async fn start_config(api: FakeKubeApi, user: &str, config: KubernetesConfig) -> Harness {
+    let dir = tempfile::tempdir().unwrap();
+    let kubeconfig = api.write_kubeconfig(dir.path(), user);
+    let feed = KubernetesFeed::connect(&KubernetesConfig {
+        kubeconfig: Some(kubeconfig),
+        ..config
+    })
+    .await
+    .expect("kubeconfig should load");
+    let (tx, entries) = mpsc::channel(256);
+    let (utx, updates) = mpsc::channel(256);
+    let (mtx, metadata) = mpsc::channel(256);
+    tokio::spawn(feed.with_metadata(mtx).run(tx, utx));
+    Harness {
+        api,
+        entries,
+        updates,
+        metadata,
+        _dir: dir,
+    }
+}
+
+fn in_namespaces(namespaces: &[&str]) -> KubernetesConfig {
+    KubernetesConfig {
+        namespaces: namespaces.iter().map(|s| s.to_string()).collect(),
+        ..KubernetesConfig::default()
+    }
+}
+
+async fn start_with(api: FakeKubeApi, user: &str, namespaces: &[&str]) -> Harness {
+    start_config(api, user, in_namespaces(namespaces)).await
+}
+
+async fn start(api: FakeKubeApi, namespaces: &[&str]) -> Harness {
+    start_with(api, &token_user("test-token"), namespaces).await
+}
+
+async fn recv(rx: &mut mpsc::Receiver<LogEntry>) -> LogEntry {
+    tokio::time::timeout(Duration::from_secs(10), rx.recv())
+        .await
+        .expect("entry should arrive")
+        .expect("channel open")
+}
+
+async fn recv_n(rx: &mut mpsc::Receiver<LogEntry>, n: usize) -> Vec<LogEntry> {
+    let mut out = Vec::new();
+    for _ in 0..n {
+        out.push(recv(rx).await);
+    }
+    out
+}
+
+/// Wait for a status update on `producer` matching `pred`.
+async fn wait_status(
+    rx: &mut mpsc::Receiver<ProducerUpdate>,
+    producer: &str,
+    pred: impl Fn(&ProducerStatus) -> bool,
+) -> ProducerStatus {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let update = rx.recv().await.expect("updates open");
+            if update.producer == producer && pred(&update.status) {
+                return update.status;
+            }
+        }
+    })
+    .await
+    .expect("status update should arrive")
+}
+
+/// Wait for metadata on `producer` whose state matches `pred`.
+async fn wait_state(
+    rx: &mut mpsc::Receiver<ProducerMeta>,
+    producer: &str,
+    pred: impl Fn(&ProducerState) -> bool,
+) -> ProducerMeta {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let meta = rx.recv().await.expect("metadata open");
+            if meta.producer == producer && pred(&meta.state) {
+                return meta;
+            }
+        }
+    })
+    .await
+    .expect("metadata should arrive")
+}
+
+/// Wait until the feed has a follow stream open on the container.
+async fn wait_following(api: &FakeKubeApi, ns: &str, pod: &str, container: &str) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while api.follower_count(ns, pod, container).await == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("feed should open a log stream");
+}
+
+/// Nothing else arrives within a short grace period.
+async fn assert_quiet(rx: &mut mpsc::Receiver<LogEntry>) {
+    if let Ok(Some(extra)) = tokio::time::timeout(Duration::from_millis(200), rx.recv()).await {
+        panic!("unexpected entry: {} {:?}", extra.producer, extra.message);
+    }
+}
 
 // ---------------------------------------------------------------------------
 // Producer tagging
 // ---------------------------------------------------------------------------
 
-/// A log line emitted by pod A must have `producer == "pod-a"`, never "pod-b",
-/// even when both pods are being tailed simultaneously.
-#[test]
-#[ignore = "not yet implemented"]
-fn producer_tag_is_pod_name() {
-    todo!("ingest two fake pod streams; assert each entry's producer matches its pod name")
+/// A log line emitted by pod A must have `producer == "default/pod-a"`, never
+/// pod B's, even when both pods are being tailed simultaneously.
+#[tokio::test]
+async fn producer_tag_is_pod_name() {
+    let api = FakeKubeApi::start().await.unwrap();
+    api.add_pod("default", "pod-a", &["app"]).await;
+    api.add_pod("default", "pod-b", &["app"]).await;
+    api.log("default", "pod-a", "app", "from a").await;
+    let mut h = start(api, &["default"]).await;
+
+    let entry = recv(&mut h.entries).await;
+    assert_source!(entry, FeedKind::Kubernetes);
+    assert_producer!(entry, "default/pod-a");
+    assert_eq!(entry.message.as_deref(), Some("from a"));
+    assert_has_field!(entry, "pod", "pod-a");
+    assert_has_field!(entry, "namespace", "default");
+
+    wait_following(&h.api, "default", "pod-b", "app").await;
+    h.api.log("default", "pod-b", "app", "from b").await;
+    assert_producer!(recv(&mut h.entries).await, "default/pod-b");
 }
 
 /// Log lines from different pods in the same namespace are merged into a single
 /// stream; the producer field distinguishes them.
-#[test]
-#[ignore = "not yet implemented"]
-fn multi_pod_streams_are_multiplexed() {
-    todo!("start 3 fake pod streams; assert all lines arrive with correct producer tags")
+#[tokio::test]
+async fn multi_pod_streams_are_multiplexed() {
+    let api = FakeKubeApi::start().await.unwrap();
+    for pod in ["web-1", "web-2", "web-3"] {
+        api.add_pod("default", pod, &["app"]).await;
+        api.log("default", pod, "app", &format!("hello from {pod}"))
+            .await;
+    }
+    let mut h = start(api, &["default"]).await;
+
+    let mut entries = recv_n(&mut h.entries, 3).await;
+    entries.sort_by(|a, b| a.producer.cmp(&b.producer));
+    for (entry, pod) in entries.iter().zip(["web-1", "web-2", "web-3"]) {
+        assert_producer!(entry, &format!("default/{pod}"));
+        assert_eq!(
+            entry.message.as_deref(),
+            Some(&*format!("hello from {pod}"))
+        );
+    }
 }
 
 /// Producer name includes the container name for multi-container pods, in the
-/// format `<pod>/<container>`.
-#[test]
-#[ignore = "not yet implemented"]
-fn multi_container_pod_producer_name_includes_container() {
-    todo!("fake a multi-container pod; check producer format is pod/container")
+/// format `<namespace>/<pod>/<container>`.
+#[tokio::test]
+async fn multi_container_pod_producer_name_includes_container() {
+    let api = FakeKubeApi::start().await.unwrap();
+    api.add_pod("default", "api-1", &["app", "proxy"]).await;
+    api.log("default", "api-1", "proxy", "upstream connect")
+        .await;
+    let mut h = start(api, &["default"]).await;
+
+    let entry = recv(&mut h.entries).await;
+    assert_producer!(entry, "default/api-1/proxy");
+    assert_has_field!(entry, "container", "proxy");
+}
+
+/// Timestamps come from the kubelet's `timestamps=true` prefix, which is
+/// stripped from the message.
+#[tokio::test]
+async fn kubelet_timestamp_becomes_entry_ts() {
+    let api = FakeKubeApi::start().await.unwrap();
+    api.add_pod("default", "pod-a", &["app"]).await;
+    api.log("default", "pod-a", "app", "ready").await;
+    let before = chrono::Utc::now();
+    let mut h = start(api, &["default"]).await;
+
+    let entry = recv(&mut h.entries).await;
+    assert_eq!(entry.raw, "ready");
+    assert!(
+        entry.ts <= before,
+        "ts is the log time, not the ingest time"
+    );
 }
 
 // ---------------------------------------------------------------------------
@@ -65,10 +254,18 @@ fn multi_container_pod_producer_name_includes_container() {
 
 /// Selecting the `default` namespace must tail all pods in `default` only.
 /// Pods in `kube-system` must produce zero lines in the stream.
-#[test]
-#[ignore = "not yet implemented"]
-fn namespace_selection_excludes_other_namespaces() {
-    todo!("configure ingestor for namespace=default; assert kube-system pods produce no entries")
+#[tokio::test]
+async fn namespace_selection_excludes_other_namespaces() {
+    let api = FakeKubeApi::start().await.unwrap();
+    api.add_pod("default", "app-1", &["app"]).await;
+    api.add_pod("kube-system", "coredns-1", &["coredns"]).await;
+    api.log("kube-system", "coredns-1", "coredns", "noise")
+        .await;
+    api.log("default", "app-1", "app", "signal").await;
+    let mut h = start(api, &["default"]).await;
+
+    assert_producer!(recv(&mut h.entries).await, "default/app-1");
+    assert_quiet(&mut h.entries).await;
 }
 
 /// Selecting a parent context node implicitly selects all namespaces and pods
@@ -79,17 +276,387 @@ fn context_selection_expands_to_all_namespaces() {
     todo!("select a context node; assert pods across all namespaces are tailed")
 }
 
+/// Several namespaces can be followed at once.
+#[tokio::test]
+async fn several_namespaces_are_followed() {
+    let api = FakeKubeApi::start().await.unwrap();
+    api.add_pod("shop", "cart-1", &["app"]).await;
+    api.add_pod("payments", "ledger-1", &["app"]).await;
+    api.log("shop", "cart-1", "app", "a").await;
+    api.log("payments", "ledger-1", "app", "b").await;
+    let mut h = start(api, &["shop", "payments"]).await;
+
+    let mut producers: Vec<_> = recv_n(&mut h.entries, 2)
+        .await
+        .into_iter()
+        .map(|e| e.producer)
+        .collect();
+    producers.sort();
+    assert_eq!(producers, ["payments/ledger-1", "shop/cart-1"]);
+}
+
+// ---------------------------------------------------------------------------
+// Pod watch
+// ---------------------------------------------------------------------------
+
+/// Pods created after the feed starts are attached automatically.
+#[tokio::test]
+async fn new_pods_are_attached() {
+    let api = FakeKubeApi::start().await.unwrap();
+    let mut h = start(api, &["default"]).await;
+
+    h.api.add_pod("default", "late-1", &["app"]).await;
+    wait_following(&h.api, "default", "late-1", "app").await;
+    h.api.log("default", "late-1", "app", "booted").await;
+    assert_producer!(recv(&mut h.entries).await, "default/late-1");
+    wait_status(&mut h.updates, "default/late-1", |s| {
+        *s == ProducerStatus::Streaming
+    })
+    .await;
+}
+
+/// A deleted pod is reported as ended.
+#[tokio::test]
+async fn deleted_pod_is_marked_ended() {
+    let api = FakeKubeApi::start().await.unwrap();
+    api.add_pod("default", "doomed-1", &["app"]).await;
+    let mut h = start(api, &["default"]).await;
+    wait_following(&h.api, "default", "doomed-1", "app").await;
+
+    h.api.delete_pod("default", "doomed-1").await;
+    let status = wait_status(&mut h.updates, "default/doomed-1", |s| {
+        matches!(s, ProducerStatus::Ended { .. })
+    })
+    .await;
+    assert_eq!(status, ProducerStatus::Ended { code: None });
+}
+
+/// A container that terminates reports its exit code.
+#[tokio::test]
+async fn stopped_container_reports_exit_code() {
+    let api = FakeKubeApi::start().await.unwrap();
+    api.add_pod("default", "job-1", &["task"]).await;
+    let mut h = start(api, &["default"]).await;
+    wait_following(&h.api, "default", "job-1", "task").await;
+
+    h.api.stop_container("default", "job-1", "task", 3).await;
+    let status = wait_status(&mut h.updates, "default/job-1", |s| {
+        matches!(s, ProducerStatus::Ended { .. })
+    })
+    .await;
+    assert_eq!(status, ProducerStatus::Ended { code: Some(3) });
+}
+
+// ---------------------------------------------------------------------------
+// Workloads
+// ---------------------------------------------------------------------------
+
+/// Add a Deployment replica: a pod owned by `<deployment>-<hash>`.
+async fn add_replica(api: &FakeKubeApi, deployment: &str, hash: &str, pod: &str) {
+    let replica_set = format!("{deployment}-{hash}");
+    api.add_pod_with(
+        "default",
+        pod,
+        &["app"],
+        &[("app", deployment), ("pod-template-hash", hash)],
+        Some(("ReplicaSet", &replica_set)),
+    )
+    .await;
+}
+
+/// Pods owned by a workload nest under the workload's name and carry it as a
+/// field.
+#[tokio::test]
+async fn replicas_nest_under_their_workload() {
+    let api = FakeKubeApi::start().await.unwrap();
+    add_replica(&api, "api", "7d9f8b6c5d", "api-7d9f8b6c5d-x2x4q").await;
+    api.add_pod_with("default", "db-0", &["pg"], &[], Some(("StatefulSet", "db")))
+        .await;
+    api.log("default", "api-7d9f8b6c5d-x2x4q", "app", "a").await;
+    api.log("default", "db-0", "pg", "b").await;
+    let mut h = start(api, &["default"]).await;
+
+    let mut entries = recv_n(&mut h.entries, 2).await;
+    entries.sort_by(|a, b| a.producer.cmp(&b.producer));
+    assert_producer!(entries[0], "default/api/api-7d9f8b6c5d-x2x4q");
+    assert_has_field!(entries[0], "workload", "deployment/api");
+    assert_producer!(entries[1], "default/db/db-0");
+    assert_has_field!(entries[1], "workload", "statefulset/db");
+}
+
+/// Selecting a Deployment follows it across a rollout: the replacement pod
+/// is attached, the old one ends, and other workloads stay quiet.
+#[tokio::test]
+async fn selected_workload_is_followed_across_rollouts() {
+    let api = FakeKubeApi::start().await.unwrap();
+    add_replica(&api, "api", "1111", "api-1111-aaaaa").await;
+    add_replica(&api, "worker", "3333", "worker-3333-ccccc").await;
+    api.add_pod("default", "debug-shell", &["app"]).await;
+    let mut h = start_config(
+        api,
+        &token_user("t"),
+        KubernetesConfig {
+            workloads: vec!["deployment/api".parse().unwrap()],
+            ..in_namespaces(&["default"])
+        },
+    )
+    .await;
+    wait_following(&h.api, "default", "api-1111-aaaaa", "app").await;
+    h.api
+        .log("default", "worker-3333-ccccc", "app", "noise")
+        .await;
+    h.api.log("default", "debug-shell", "app", "noise").await;
+    h.api.log("default", "api-1111-aaaaa", "app", "old").await;
+    assert_producer!(recv(&mut h.entries).await, "default/api/api-1111-aaaaa");
+
+    add_replica(&h.api, "api", "2222", "api-2222-bbbbb").await;
+    h.api.delete_pod("default", "api-1111-aaaaa").await;
+    wait_status(&mut h.updates, "default/api/api-1111-aaaaa", |s| {
+        matches!(s, ProducerStatus::Ended { .. })
+    })
+    .await;
+    wait_following(&h.api, "default", "api-2222-bbbbb", "app").await;
+    h.api.log("default", "api-2222-bbbbb", "app", "new").await;
+    let entry = recv(&mut h.entries).await;
+    assert_producer!(entry, "default/api/api-2222-bbbbb");
+    assert_eq!(entry.message.as_deref(), Some("new"));
+    assert_quiet(&mut h.entries).await;
+}
+
+/// A label selector is passed to the API server and narrows the pods
+/// followed, including pods created later.
+#[tokio::test]
+async fn label_selector_narrows_pods() {
+    let api = FakeKubeApi::start().await.unwrap();
+    api.add_pod_with("default", "web-1", &["app"], &[("tier", "web")], None)
+        .await;
+    api.add_pod_with("default", "cache-1", &["app"], &[("tier", "cache")], None)
+        .await;
+    api.log("default", "cache-1", "app", "noise").await;
+    let mut h = start_config(
+        api,
+        &token_user("t"),
+        KubernetesConfig {
+            label_selector: Some("tier=web".to_string()),
+            ..in_namespaces(&["default"])
+        },
+    )
+    .await;
+
+    h.api
+        .add_pod_with("default", "web-2", &["app"], &[("tier", "web")], None)
+        .await;
+    wait_following(&h.api, "default", "web-2", "app").await;
+    h.api.log("default", "web-2", "app", "hello").await;
+    assert_producer!(recv(&mut h.entries).await, "default/web-2");
+    assert_quiet(&mut h.entries).await;
+}
+
+// ---------------------------------------------------------------------------
+// Restarts
+// ---------------------------------------------------------------------------
+
+/// When a container restarts, the restart is reported for the tree's marker
+/// and the live stream carries on with the new instance. The dead instance's
+/// log was already streamed live, so fetching it does not ingest it again:
+/// every line arrives exactly once.
+#[tokio::test]
+async fn restart_captures_previous_container_log() {
+    let api = FakeKubeApi::start().await.unwrap();
+    api.add_pod("default", "api-1", &["app"]).await;
+    let mut h = start(api, &["default"]).await;
+    wait_following(&h.api, "default", "api-1", "app").await;
+    h.api.log("default", "api-1", "app", "booting").await;
+    h.api.log("default", "api-1", "app", "panic: nil map").await;
+    let mut entries = recv_n(&mut h.entries, 2).await;
+
+    h.api.restart_container("default", "api-1", "app", 2).await;
+    let status = wait_status(&mut h.updates, "default/api-1", |s| {
+        matches!(s, ProducerStatus::Restarted { .. })
+    })
+    .await;
+    assert_eq!(status, ProducerStatus::Restarted { count: 1 });
+
+    wait_following(&h.api, "default", "api-1", "app").await;
+    h.api.log("default", "api-1", "app", "booting again").await;
+    entries.push(recv(&mut h.entries).await);
+    assert_quiet(&mut h.entries).await;
+
+    let messages: Vec<_> = entries.iter().map(|e| e.message.as_deref()).collect();
+    assert_eq!(
+        messages,
+        [
+            Some("booting"),
+            Some("panic: nil map"),
+            Some("booting again")
+        ]
+    );
+    for entry in &entries {
+        assert!(!entry.fields.contains_key("container_instance"));
+    }
+}
+
+/// A container already crash-looping when the feed attaches gets its
+/// previous instance's log too.
+#[tokio::test]
+async fn crash_looping_container_gets_previous_log_on_attach() {
+    let api = FakeKubeApi::start().await.unwrap();
+    api.add_pod("default", "api-1", &["app"]).await;
+    api.log("default", "api-1", "app", "OOM: heap exhausted")
+        .await;
+    api.restart_container("default", "api-1", "app", 137).await;
+    let mut h = start(api, &["default"]).await;
+
+    let entry = recv(&mut h.entries).await;
+    assert_eq!(entry.message.as_deref(), Some("OOM: heap exhausted"));
+    assert_has_field!(entry, "container_instance", "previous");
+    assert_quiet(&mut h.entries).await;
+}
+
+// ---------------------------------------------------------------------------
+// Container state
+// ---------------------------------------------------------------------------
+
+/// A container going into `CrashLoopBackOff` after an OOM kill is reported
+/// with the kubelet's reason, the previous instance's reason and the restart
+/// count; it reads `Running` again once it is back.
+#[tokio::test]
+async fn crash_loop_state_is_reported_as_metadata() {
+    let api = FakeKubeApi::start().await.unwrap();
+    api.add_pod("default", "api-1", &["app"]).await;
+    let mut h = start(api, &["default"]).await;
+    let running = wait_state(&mut h.metadata, "default/api-1", |_| true).await;
+    assert_eq!(running.state, ProducerState::Running);
+    assert_eq!(running.fields["restarts"], "0");
+
+    h.api
+        .crash_loop("default", "api-1", "app", "OOMKilled", 137)
+        .await;
+    let crashing = wait_state(&mut h.metadata, "default/api-1", |s| {
+        *s != ProducerState::Running
+    })
+    .await;
+    assert_eq!(crashing.state.to_string(), "CrashLoopBackOff");
+    assert_eq!(crashing.fields["restarts"], "1");
+    assert_eq!(crashing.fields["last_reason"], "OOMKilled");
+    assert_eq!(crashing.fields["last_exit_code"], "137");
+
+    h.api
+        .restart_container("default", "api-1", "app", 137)
+        .await;
+    let back = wait_state(&mut h.metadata, "default/api-1", |s| {
+        *s == ProducerState::Running
+    })
+    .await;
+    assert_eq!(back.fields["restarts"], "2");
+}
+
+// ---------------------------------------------------------------------------
+// Events
+// ---------------------------------------------------------------------------
+
+fn backoff_event(count: i32) -> serde_json::Value {
+    serde_json::json!({
+        "metadata": {"name": "api-1.backoff"},
+        "type": "Warning",
+        "reason": "BackOff",
+        "message": "Back-off restarting failed container app in pod api-1",
+        "involvedObject": {"kind": "Pod", "name": "api-1", "namespace": "default",
+                           "fieldPath": "spec.containers{app}"},
+        "source": {"component": "kubelet", "host": "node-1"},
+        "count": count,
+        "lastTimestamp": chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+/// Events arrive on a per-namespace producer, with their level from `type`
+/// and the involved pod in the same fields its log lines carry.
+#[tokio::test]
+async fn events_correlate_with_pod_lines() {
+    let api = FakeKubeApi::start().await.unwrap();
+    api.add_pod("default", "api-1", &["app"]).await;
+    api.log("default", "api-1", "app", "starting").await;
+    api.record_event("default", backoff_event(1)).await;
+    let mut h = start_config(
+        api,
+        &token_user("t"),
+        KubernetesConfig {
+            events: true,
+            ..in_namespaces(&["default"])
+        },
+    )
+    .await;
+
+    let entries = recv_n(&mut h.entries, 2).await;
+    let line = entries
+        .iter()
+        .find(|e| e.producer == "default/api-1")
+        .unwrap();
+    let event = entries
+        .iter()
+        .find(|e| e.producer == "default/events")
+        .unwrap();
+    assert_eq!(event.level, Some(LogLevel::Warn));
+    assert_has_field!(event, "reason", "BackOff");
+    for key in ["pod", "container", "namespace"] {
+        assert_eq!(event.fields[key], line.fields[key], "{key}");
+    }
+}
+
+/// A recurring event (count bumped) is a new entry; an unchanged one is not
+/// repeated.
+#[tokio::test]
+async fn recurring_event_is_a_new_entry() {
+    let api = FakeKubeApi::start().await.unwrap();
+    api.record_event("default", backoff_event(1)).await;
+    let mut h = start_config(
+        api,
+        &token_user("t"),
+        KubernetesConfig {
+            events: true,
+            ..in_namespaces(&["default"])
+        },
+    )
+    .await;
+    assert_has_field!(recv(&mut h.entries).await, "count", 1);
+
+    h.api.record_event("default", backoff_event(2)).await;
+    assert_has_field!(recv(&mut h.entries).await, "count", 2);
+    assert_quiet(&mut h.entries).await;
+}
+
 // ---------------------------------------------------------------------------
 // Reconnect / retry
 // ---------------------------------------------------------------------------
 
-/// When `kubectl logs -f` exits unexpectedly, the ingestor must reconnect and
-/// resume tailing. No lines that arrived before the disconnect should be
-/// re-emitted.
-#[test]
-#[ignore = "not yet implemented"]
-fn reconnects_after_process_exit() {
-    todo!("close the fake process stream; verify reconnect happens; check no duplicate lines")
+/// When the API server closes a log stream while the container is still
+/// running, the ingestor must reconnect and resume tailing. No lines that
+/// arrived before the disconnect should be re-emitted.
+#[tokio::test]
+async fn reconnects_after_process_exit() {
+    let api = FakeKubeApi::start().await.unwrap();
+    api.add_pod("default", "api-1", &["app"]).await;
+    let mut h = start(api, &["default"]).await;
+    wait_following(&h.api, "default", "api-1", "app").await;
+
+    for i in 0..3 {
+        h.api
+            .log("default", "api-1", "app", &format!("before {i}"))
+            .await;
+    }
+    recv_n(&mut h.entries, 3).await;
+
+    h.api.drop_log_streams("default", "api-1", "app").await;
+    wait_status(&mut h.updates, "default/api-1", |s| {
+        matches!(s, ProducerStatus::Backoff { .. })
+    })
+    .await;
+    wait_following(&h.api, "default", "api-1", "app").await;
+
+    h.api.log("default", "api-1", "app", "after").await;
+    let entry = recv(&mut h.entries).await;
+    assert_eq!(entry.message.as_deref(), Some("after"), "no replayed lines");
+    assert_quiet(&mut h.entries).await;
 }
 
 /// Reconnect delay must use exponential backoff, capped at 30 s. This test
@@ -100,16 +667,85 @@ fn reconnect_uses_exponential_backoff() {
     todo!("verify reconnect intervals double up to 30s using tokio::time::pause()")
 }
 
+// ---------------------------------------------------------------------------
+// kubeconfig auth
+// ---------------------------------------------------------------------------
+
+/// A static bearer token from kubeconfig is sent on every request.
+#[tokio::test]
+async fn kubeconfig_token_is_sent() {
+    let api = FakeKubeApi::start().await.unwrap();
+    api.add_pod("default", "pod-a", &["app"]).await;
+    api.log("default", "pod-a", "app", "x").await;
+    let mut h = start_with(api, &token_user("s3cret"), &["default"]).await;
+
+    recv(&mut h.entries).await;
+    let headers = h.api.authorization_headers().await;
+    assert!(!headers.is_empty());
+    assert!(headers.iter().all(|h| h == "Bearer s3cret"), "{headers:?}");
+}
+
+/// An exec credential plugin is run and its token used.
+#[tokio::test]
+async fn exec_credential_plugin_is_used() {
+    let api = FakeKubeApi::start().await.unwrap();
+    api.add_pod("default", "pod-a", &["app"]).await;
+    api.log("default", "pod-a", "app", "x").await;
+    let mut h = start_with(api, &exec_user("from-plugin"), &["default"]).await;
+
+    recv(&mut h.entries).await;
+    let headers = h.api.authorization_headers().await;
+    assert!(
+        headers.contains(&"Bearer from-plugin".to_string()),
+        "{headers:?}"
+    );
+}
+
+/// With no namespaces given, the kubeconfig context's namespace is used.
+#[tokio::test]
+async fn context_namespace_is_the_default() {
+    let api = FakeKubeApi::start().await.unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let kubeconfig = api.write_kubeconfig(dir.path(), &token_user("t"));
+    let feed = KubernetesFeed::connect(&KubernetesConfig {
+        kubeconfig: Some(kubeconfig),
+        ..KubernetesConfig::default()
+    })
+    .await
+    .unwrap();
+    assert_eq!(feed.namespaces(), ["default"]);
+}
+
 // ---------------------------------------------------------------------------
 // Property tests
 // ---------------------------------------------------------------------------
 
 /// Property: for any burst of N lines followed by a reconnect, the total lines
 /// received == N (no duplicates, no drops).
-#[test]
-#[ignore = "not yet implemented"]
-fn prop_no_duplicate_lines_on_retry() {
-    todo!("proptest: random N and reconnect timing; assert total received == N")
+#[tokio::test]
+async fn prop_no_duplicate_lines_on_retry() {
+    let api = FakeKubeApi::start().await.unwrap();
+    api.add_pod("default", "api-1", &["app"]).await;
+    let mut h = start(api, &["default"]).await;
+
+    let mut sent = 0;
+    for burst in [1usize, 7, 3] {
+        wait_following(&h.api, "default", "api-1", "app").await;
+        for _ in 0..burst {
+            h.api
+                .log("default", "api-1", "app", &format!("line {sent}"))
+                .await;
+            sent += 1;
+        }
+        h.api.drop_log_streams("default", "api-1", "app").await;
+    }
+    wait_following(&h.api, "default", "api-1", "app").await;
+
+    let received = recv_n(&mut h.entries, sent).await;
+    let messages: Vec<_> = received.iter().filter_map(|e| e.message.clone()).collect();
+    let expected: Vec<_> = (0..sent).map(|i| format!("line {i}")).collect();
+    assert_eq!(messages, expected);
+    assert_quiet(&mut h.entries).await;
 }
 
 /// Property: all produced entries have non-empty producer field.
@@ -0,0 +1,245 @@
+#![allow(unused)]
+//! OTLP/HTTP logs receiver integration harness.
+//!
+//! # What this covers
+//!
+//! - **Encodings**: canned `ExportLogsServiceRequest` payloads POSTed to
+//!   `/v1/logs` as protobuf and as OTLP JSON, optionally gzip-compressed.
+//! - **Mapping**: `service.name` / `service.instance.id` producers, severity,
+//!   timestamps, trace / span ids and attributes as fields.
+//! - **Responses**: an empty `ExportLogsServiceResponse` in the request's
+//!   encoding on success, with exports larger than the queue handed over in
+//!   chunks; `400`, `413` (gzip bombs), `415` and `429` on rejection.
+//!
+//! # What this does NOT cover
+//!
+//! - OTLP/gRPC (port 4317)
+//! - Traces and metrics signals
+//!
+//! # Running
+//!
+//! ```sh
+//! cargo test --test otlp_harness
+//! ```
+
+mod common;
+use common::*;
+use fml_core::{FeedKind, LogEntry, LogLevel};
+use fml_feeds::otlp::proto::{self, string_attr};
+use fml_feeds::otlp::{OtlpConfig, OtlpReceiver};
+use prost::Message;
+use std::io::Write;
+use std::net::SocketAddr;
+use tokio::sync::mpsc;
+
+async fn start(capacity: usize) -> (SocketAddr, mpsc::Receiver<LogEntry>) {
+    let receiver = OtlpReceiver::bind(&OtlpConfig {
+        addr: "127.0.0.1:0".parse().unwrap(),
+    })
+    .await
+    .unwrap();
+    let addr = receiver.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel(capacity);
+    tokio::spawn(receiver.run(tx));
+    (addr, rx)
+}
+
+fn drain(rx: &mut mpsc::Receiver<LogEntry>) -> Vec<LogEntry> {
+    let mut out = Vec::new();
+    while let Ok(entry) = rx.try_recv() {
+        out.push(entry);
+    }
+    out
+}
+
+fn record(body: &str, severity: i32) -> proto::LogRecord {
+    proto::LogRecord {
+        time_unix_nano: 1_705_312_800_000_000_000,
+        severity_number: severity,
+        body: Some(proto::AnyValue {
+            value: Some(proto::any_value::Value::StringValue(body.to_string())),
+        }),
+        trace_id: vec![0xab; 16],
+        span_id: vec![0xcd; 8],
+        ..Default::default()
+    }
+}
+
+/// Two services, the first with two instances' worth of records.
+fn canned_export() -> proto::ExportLogsServiceRequest {
+    let resource = |service: &str, instance: &str, records| proto::ResourceLogs {
+        resource: Some(proto::Resource {
+            attributes: vec![
+                string_attr("service.name", service),
+                string_attr("service.instance.id", instance),
+            ],
+        }),
+        scope_logs: vec![proto::ScopeLogs {
+            scope: None,
+            log_records: records,
+        }],
+    };
+    proto::ExportLogsServiceRequest {
+        resource_logs: vec![
+            resource(
+                "checkout",
+                "pod-a",
+                vec![record("order placed", 9), record("payment declined", 17)],
+            ),
+            resource("checkout", "pod-b", vec![record("order placed", 9)]),
+            resource("inventory", "pod-c", vec![record("stock low", 13)]),
+        ],
+    }
+}
+
+const PROTOBUF: (&str, &str) = ("Content-Type", "application/x-protobuf");
+const JSON: (&str, &str) = ("Content-Type", "application/json");
+
+// ---------------------------------------------------------------------------
+// Protobuf
+// ---------------------------------------------------------------------------
+
+/// Every record becomes an entry under `service/instance`.
+#[tokio::test]
+async fn protobuf_export_is_ingested() {
+    let (addr, mut rx) = start(16).await;
+    let response = http_post(
+        addr,
+        "/v1/logs",
+        &[PROTOBUF],
+        &canned_export().encode_to_vec(),
+    )
+    .await;
+    assert_eq!(response.status, 200);
+    assert!(response.body.is_empty(), "empty ExportLogsServiceResponse");
+
+    let entries = drain(&mut rx);
+    assert_eq!(entries.len(), 4);
+    assert_source!(entries[0], FeedKind::Otlp);
+    assert_producer!(entries[0], "checkout/pod-a");
+    assert_producer!(entries[2], "checkout/pod-b");
+    assert_producer!(entries[3], "inventory/pod-c");
+    assert_level!(entries[1], LogLevel::Error);
+    assert_level!(entries[3], LogLevel::Warn);
+    assert_has_field!(entries[1], "trace_id", "abababababababababababababababab");
+    assert_has_field!(entries[1], "span_id", "cdcdcdcdcdcdcdcd");
+    assert_eq!(entries[1].ts.timestamp(), 1_705_312_800);
+}
+
+/// gzip-compressed bodies are inflated before decoding.
+#[tokio::test]
+async fn gzip_body_is_accepted() {
+    let (addr, mut rx) = start(16).await;
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gz.write_all(&canned_export().encode_to_vec()).unwrap();
+    let body = gz.finish().unwrap();
+
+    let response = http_post(
+        addr,
+        "/v1/logs",
+        &[PROTOBUF, ("Content-Encoding", "gzip")],
+        &body,
+    )
+    .await;
+    assert_eq!(response.status, 200);
+    assert_eq!(drain(&mut rx).len(), 4);
+}
+
+/// A body that inflates past the limit is refused before it is decoded.
+#[tokio::test]
+async fn gzip_bomb_is_too_large() {
+    let (addr, mut rx) = start(16).await;
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    let zeros = vec![0u8; 1024 * 1024];
+    for _ in 0..=fml_feeds::otlp::MAX_INFLATED / zeros.len() as u64 {
+        gz.write_all(&zeros).unwrap();
+    }
+    let body = gz.finish().unwrap();
+
+    let response = http_post(
+        addr,
+        "/v1/logs",
+        &[PROTOBUF, ("Content-Encoding", "gzip")],
+        &body,
+    )
+    .await;
+    assert_eq!(response.status, 413);
+    assert!(drain(&mut rx).is_empty());
+}
+
+// ---------------------------------------------------------------------------
+// JSON
+// ---------------------------------------------------------------------------
+
+/// OTLP JSON with hex ids and string-encoded nanoseconds.
+#[tokio::test]
+async fn json_export_is_ingested() {
+    let (addr, mut rx) = start(16).await;
+    let body = r#"{"resourceLogs":[{
+        "resource":{"attributes":[{"key":"service.name","value":{"stringValue":"gateway"}},
+                                  {"key":"host.name","value":{"stringValue":"edge-1"}}]},
+        "scopeLogs":[{"logRecords":[{
+            "timeUnixNano":"1705312800000000000","severityNumber":13,
+            "body":{"stringValue":"upstream slow"},
+            "attributes":[{"key":"http.route","value":{"stringValue":"/cart"}}],
+            "traceId":"5b8efff798038103d269b633813fc60c","spanId":"eee19b7ec3c1b174"}]}]}]}"#;
+    let response = http_post(addr, "/v1/logs", &[JSON], body.as_bytes()).await;
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, b"{}");
+
+    let entries = drain(&mut rx);
+    assert_eq!(entries.len(), 1);
+    assert_producer!(entries[0], "gateway/edge-1");
+    assert_level!(entries[0], LogLevel::Warn);
+    assert_has_field!(entries[0], "http.route", "/cart");
+    assert_has_field!(entries[0], "trace_id", "5b8efff798038103d269b633813fc60c");
+}
+
+// ---------------------------------------------------------------------------
+// Rejection
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn unknown_content_type_is_unsupported() {
+    let (addr, _rx) = start(4).await;
+    let response = http_post(addr, "/v1/logs", &[("Content-Type", "text/plain")], b"hi").await;
+    assert_eq!(response.status, 415);
+}
+
+#[tokio::test]
+async fn malformed_protobuf_is_rejected() {
+    let (addr, mut rx) = start(4).await;
+    let response = http_post(addr, "/v1/logs", &[PROTOBUF], b"\xff\xff\xff").await;
+    assert_eq!(response.status, 400);
+    assert!(drain(&mut rx).is_empty());
+}
+
+/// An export with more records than the queue holds is handed over in
+/// chunks as the queue drains.
+#[tokio::test]
+async fn export_larger_than_queue_is_chunked() {
+    let (addr, mut rx) = start(2).await;
+    let export = canned_export().encode_to_vec();
+    let push = tokio::spawn(async move { http_post(addr, "/v1/logs", &[PROTOBUF], &export).await });
+
+    for _ in 0..4 {
+        tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+            .await
+            .expect("the export should keep flowing")
+            .unwrap();
+    }
+    assert_eq!(push.await.unwrap().status, 200);
+}
+
+/// An export the full queue takes nothing from is refused whole, so the
+/// exporter's retry cannot duplicate records.
+#[tokio::test]
+async fn full_queue_returns_429() {
+    let (addr, mut rx) = start(4).await;
+    let export = canned_export().encode_to_vec();
+    let response = http_post(addr, "/v1/logs", &[PROTOBUF], &export).await;
+    assert_eq!(response.status, 200);
+    let response = http_post(addr, "/v1/logs", &[PROTOBUF], &export).await;
+    assert_eq!(response.status, 429);
+    assert_eq!(drain(&mut rx).len(), 4);
+}
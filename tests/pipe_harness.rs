@@ -0,0 +1,295 @@
+#![allow(unused)]
+//! Pipe feed integration harness.
+//!
+//! # What this covers
+//!
+//! - **Named pipes**: created when missing, lines from several writers in
+//!   turn, no EOF between them.
+//! - **Stream sockets**: each connection is its own producer, named after the
+//!   connecting process; a reconnect carries on as the same producer.
+//! - **Datagram sockets**: several lines per datagram, bound senders as their
+//!   own producer.
+//! - **Cleanup**: paths the feed created are removed when it stops; stale
+//!   socket files are replaced.
+//! - **Stop**: a stopped client is disconnected and refused until started.
+//!
+//! # What this does NOT cover
+//!
+//! - Abstract-namespace sockets
+//!
+//! # Running
+//!
+//! ```sh
+//! cargo test --test pipe_harness
+//! ```
+
+mod common;
+use common::*;
+use fml_core::{FeedKind, LogEntry, ProducerStatus, ProducerUpdate};
+use fml_feeds::pipe::{PipeConfig, PipeListener};
+use fml_feeds::{FeedControl, FeedHandle};
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+async fn recv<T>(rx: &mut mpsc::Receiver<T>) -> T {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("message should arrive")
+        .expect("channel open")
+}
+
+struct Feed {
+    entries: mpsc::Receiver<LogEntry>,
+    updates: mpsc::Receiver<ProducerUpdate>,
+    task: JoinHandle<()>,
+}
+
+async fn start(config: PipeConfig) -> Feed {
+    let listener = PipeListener::bind(&config).await.unwrap();
+    let (tx, entries) = mpsc::channel(64);
+    let (utx, updates) = mpsc::channel(64);
+    let task = tokio::spawn(async move {
+        let _ = listener.run(tx, utx).await;
+    });
+    Feed {
+        entries,
+        updates,
+        task,
+    }
+}
+
+/// This process's name, as the feed names its connections.
+fn comm() -> String {
+    std::fs::read_to_string("/proc/self/comm")
+        .unwrap()
+        .trim()
+        .to_string()
+}
+
+// ---------------------------------------------------------------------------
+// Named pipes
+// ---------------------------------------------------------------------------
+
+/// A missing pipe is created; writers come and go without ending the feed.
+#[tokio::test]
+async fn fifo_reads_lines_from_successive_writers() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.fifo");
+    let mut feed = start(PipeConfig {
+        fifos: vec![path.clone()],
+        ..Default::default()
+    })
+    .await;
+
+    for line in ["first writer\n", "second writer\npartial"] {
+        let path = path.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut fifo = std::fs::OpenOptions::new().write(true).open(path).unwrap();
+            fifo.write_all(line.as_bytes()).unwrap();
+        })
+        .await
+        .unwrap();
+    }
+    tokio::task::spawn_blocking({
+        let path = path.clone();
+        move || {
+            let mut fifo = std::fs::OpenOptions::new().write(true).open(path).unwrap();
+            fifo.write_all(b" line\n").unwrap();
+        }
+    })
+    .await
+    .unwrap();
+
+    for expected in ["first writer", "second writer", "partial line"] {
+        let entry = recv(&mut feed.entries).await;
+        assert_source!(entry, FeedKind::Pipe);
+        assert_producer!(entry, "app.fifo");
+        assert_eq!(entry.message.as_deref(), Some(expected));
+    }
+    let update = recv(&mut feed.updates).await;
+    assert_eq!(update.status, ProducerStatus::Streaming);
+    assert!(
+        feed.updates.try_recv().is_err(),
+        "writers closing is not an end"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Stream sockets
+// ---------------------------------------------------------------------------
+
+/// Concurrent connections are separate producers; a client that reconnects
+/// gets its name back.
+#[tokio::test]
+async fn stream_connections_are_producers() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tools.sock");
+    let mut feed = start(PipeConfig {
+        streams: vec![path.clone()],
+        ..Default::default()
+    })
+    .await;
+    let first = format!("tools.sock/{}", comm());
+    let second = format!("{first}-2");
+
+    let mut a = UnixStream::connect(&path).await.unwrap();
+    a.write_all(b"from a\n").await.unwrap();
+    let entry = recv(&mut feed.entries).await;
+    assert_producer!(entry, &first);
+
+    let mut b = UnixStream::connect(&path).await.unwrap();
+    b.write_all(b"from b\n").await.unwrap();
+    let entry = recv(&mut feed.entries).await;
+    assert_producer!(entry, &second);
+    assert_eq!(entry.message.as_deref(), Some("from b"));
+
+    drop(a);
+    let mut statuses = Vec::new();
+    while statuses.len() < 3 {
+        let update = recv(&mut feed.updates).await;
+        statuses.push((update.producer, update.status));
+    }
+    assert_eq!(
+        statuses,
+        [
+            (first.clone(), ProducerStatus::Streaming),
+            (second.clone(), ProducerStatus::Streaming),
+            (first.clone(), ProducerStatus::Ended { code: None }),
+        ]
+    );
+
+    let mut again = UnixStream::connect(&path).await.unwrap();
+    again.write_all(b"back again\n").await.unwrap();
+    let entry = recv(&mut feed.entries).await;
+    assert_producer!(entry, &first);
+    assert_eq!(entry.message.as_deref(), Some("back again"));
+    let update = recv(&mut feed.updates).await;
+    assert_eq!(
+        (update.producer, update.status),
+        (first, ProducerStatus::Streaming)
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Datagram sockets
+// ---------------------------------------------------------------------------
+
+/// A datagram may carry several lines; bound senders are their own producer.
+#[tokio::test]
+async fn datagrams_split_into_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.dgram");
+    let mut feed = start(PipeConfig {
+        datagrams: vec![path.clone()],
+        ..Default::default()
+    })
+    .await;
+
+    let anonymous = tokio::net::UnixDatagram::unbound().unwrap();
+    anonymous.send_to(b"one\ntwo\n", &path).await.unwrap();
+    let named = tokio::net::UnixDatagram::bind(dir.path().join("backup.sock")).unwrap();
+    named.send_to(b"three", &path).await.unwrap();
+
+    let entries = [
+        recv(&mut feed.entries).await,
+        recv(&mut feed.entries).await,
+        recv(&mut feed.entries).await,
+    ];
+    let got: Vec<_> = entries
+        .iter()
+        .map(|e| (e.producer.as_str(), e.message.as_deref().unwrap()))
+        .collect();
+    assert_eq!(
+        got,
+        [
+            ("app.dgram", "one"),
+            ("app.dgram", "two"),
+            ("app.dgram/backup.sock", "three"),
+        ]
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Stop
+// ---------------------------------------------------------------------------
+
+/// Wait for the feed to close `client`'s connection.
+async fn assert_closed(client: &mut UnixStream) {
+    let mut buf = [0u8; 16];
+    let read = tokio::time::timeout(Duration::from_secs(5), client.read(&mut buf))
+        .await
+        .expect("the connection should be closed");
+    assert_eq!(read.unwrap(), 0);
+}
+
+/// Stopping a client closes its connection and refuses it under that name
+/// until it is started.
+#[tokio::test]
+async fn stopped_client_is_disconnected_until_started() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tools.sock");
+    let listener = PipeListener::bind(&PipeConfig {
+        streams: vec![path.clone()],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let (tx, mut entries) = mpsc::channel(64);
+    let (utx, _updates) = mpsc::channel(64);
+    let (mtx, _metadata) = mpsc::channel(64);
+    let (control, senders, relay) = FeedControl::attach(tx, utx, mtx);
+    tokio::spawn(relay.run());
+    let listener = listener.with_signals(control.signals());
+    tokio::spawn(listener.run(senders.entries, senders.updates));
+
+    let mut client = UnixStream::connect(&path).await.unwrap();
+    client.write_all(b"hello\n").await.unwrap();
+    let id = format!("pipe/{}", recv(&mut entries).await.producer);
+
+    control.stop(&id);
+    assert_closed(&mut client).await;
+    let mut again = UnixStream::connect(&path).await.unwrap();
+    assert_closed(&mut again).await;
+
+    control.start(&id);
+    let mut back = UnixStream::connect(&path).await.unwrap();
+    back.write_all(b"back\n").await.unwrap();
+    assert_eq!(recv(&mut entries).await.message.as_deref(), Some("back"));
+}
+
+// ---------------------------------------------------------------------------
+// Cleanup
+// ---------------------------------------------------------------------------
+
+/// Created paths go away with the feed; a stale socket is replaced, but a
+/// pipe that was already there is left alone.
+#[tokio::test]
+async fn created_paths_are_removed() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("stale.sock");
+    drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+    assert!(socket.exists(), "a dropped listener leaves its socket file");
+    let fifo = dir.path().join("kept.fifo");
+    std::process::Command::new("mkfifo")
+        .arg(&fifo)
+        .status()
+        .unwrap();
+
+    let feed = start(PipeConfig {
+        fifos: vec![fifo.clone()],
+        streams: vec![socket.clone()],
+        ..Default::default()
+    })
+    .await;
+    UnixStream::connect(&socket).await.unwrap();
+
+    drop(feed.entries);
+    feed.task.await.unwrap();
+    assert!(!socket.exists());
+    assert!(fifo.exists());
+}
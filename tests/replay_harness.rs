@@ -0,0 +1,326 @@
+#![allow(unused)]
+//! Replay feed integration harness.
+//!
+//! # What this covers
+//!
+//! - **Exports**: an fml `jsonl` export replays with its source, instance,
+//!   producers, levels and fields restored.
+//! - **Plain logs**: timestamped lines (RFC 3339, `date time,ms`) from several
+//!   files, compressed or not, merge in timestamp order; continuation lines
+//!   keep the timestamp before them; rotated files share a producer.
+//! - **Pace**: entries arrive after their original gaps divided by the speed.
+//! - **Control**: pause holds entries back until resume, jump delivers
+//!   everything up to the target at once, speed changes take effect mid-way.
+//! - **Lifecycle**: producers stream with their first line and end after the
+//!   last.
+//!
+//! # What this does NOT cover
+//!
+//! - The `:replay` command bar wiring (unit-tested in `fml-tui`)
+//!
+//! # Running
+//!
+//! ```sh
+//! cargo test --test replay_harness
+//! ```
+
+mod common;
+use common::*;
+use fml_core::export::JsonlRecord;
+use fml_core::{
+    FeedKind, LogEntry, LogLevel, ProducerStatus, ProducerUpdate, ReplayCommand, ReplayTarget,
+    Speed,
+};
+use fml_feeds::replay::{ReplayConfig, ReplayFeed};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+fn ts(s: &str) -> chrono::DateTime<chrono::Utc> {
+    s.parse().unwrap()
+}
+
+fn write(dir: &tempfile::TempDir, name: &str, lines: &[&str]) -> PathBuf {
+    let path = dir.path().join(name);
+    std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+    path
+}
+
+/// A running replay: entries with the (paused-clock) time they arrived.
+struct Replay {
+    entries: mpsc::Receiver<LogEntry>,
+    updates: mpsc::Receiver<ProducerUpdate>,
+    control: mpsc::Sender<ReplayCommand>,
+    started: Instant,
+    feed: JoinHandle<()>,
+}
+
+impl Replay {
+    fn start(paths: Vec<PathBuf>, speed: &str) -> Self {
+        let config = ReplayConfig {
+            paths,
+            speed: speed.parse().unwrap(),
+        };
+        let (control, rx) = mpsc::channel(8);
+        let feed = ReplayFeed::load(&config).unwrap().with_control(rx);
+        let (tx, entries) = mpsc::channel(1024);
+        let (utx, updates) = mpsc::channel(1024);
+        let started = Instant::now();
+        let feed = tokio::spawn(async move { feed.run(tx, utx).await.unwrap() });
+        Self {
+            entries,
+            updates,
+            control,
+            started,
+            feed,
+        }
+    }
+
+    /// The next entry and how long after the start it arrived.
+    async fn next(&mut self) -> (Duration, LogEntry) {
+        let entry = self.entries.recv().await.expect("replay ended early");
+        (self.started.elapsed(), entry)
+    }
+
+    async fn all(mut self) -> (Vec<LogEntry>, Vec<ProducerUpdate>) {
+        let mut entries = Vec::new();
+        while let Some(entry) = self.entries.recv().await {
+            entries.push(entry);
+        }
+        self.feed.await.unwrap();
+        let mut updates = Vec::new();
+        while let Ok(update) = self.updates.try_recv() {
+            updates.push(update);
+        }
+        (entries, updates)
+    }
+}
+
+fn messages(entries: &[LogEntry]) -> Vec<&str> {
+    entries
+        .iter()
+        .map(|e| e.message.as_deref().unwrap_or(""))
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Inputs
+// ---------------------------------------------------------------------------
+
+/// Replaying an export restores every entry as it was exported.
+#[tokio::test(start_paused = true)]
+async fn exports_restore_producers_and_fields() {
+    let dir = tempfile::tempdir().unwrap();
+    let original = LogEntry {
+        seq: 7,
+        raw: r#"{"msg":"payment failed","order":42}"#.into(),
+        ts: ts("2024-01-15T10:32:05.250Z"),
+        level: Some(LogLevel::Error),
+        source: FeedKind::Kubernetes,
+        instance: Some("prod".into()),
+        producer: "shop/api-7f9/api".into(),
+        fields: [("order".to_string(), 42.into())].into(),
+        message: Some("payment failed".into()),
+    };
+    let export = dir.path().join("incident.jsonl");
+    let mut file = std::fs::File::create(&export).unwrap();
+    for offset in 0..3 {
+        let entry = LogEntry {
+            ts: original.ts + chrono::TimeDelta::seconds(offset),
+            ..original.clone()
+        };
+        let line = serde_json::to_string(&JsonlRecord::from(&entry)).unwrap();
+        writeln!(file, "{line}").unwrap();
+    }
+
+    let (entries, updates) = Replay::start(vec![export], "1x").all().await;
+    assert_eq!(entries.len(), 3);
+    assert_eq!(
+        entries[0],
+        LogEntry {
+            seq: 0,
+            ..original.clone()
+        }
+    );
+    assert_eq!(
+        updates
+            .iter()
+            .map(|u| (u.feed_root(), u.producer.as_str(), &u.status))
+            .collect::<Vec<_>>(),
+        [
+            (
+                "kubernetes@prod".to_string(),
+                "shop/api-7f9/api",
+                &ProducerStatus::Streaming
+            ),
+            (
+                "kubernetes@prod".to_string(),
+                "shop/api-7f9/api",
+                &ProducerStatus::Ended { code: None }
+            ),
+        ]
+    );
+}
+
+/// Plain logs from several files, one of them a gzipped rotation, merge in
+/// timestamp order; continuation lines stay with the line before them.
+#[tokio::test(start_paused = true)]
+async fn plain_logs_merge_by_timestamp() {
+    let dir = tempfile::tempdir().unwrap();
+    let api = write(
+        &dir,
+        "api.log",
+        &[
+            "2024-01-15T10:00:02Z GET /orders 500",
+            "java.lang.IllegalStateException: pool exhausted",
+            "    at Pool.get(Pool.java:42)",
+            "2024-01-15T10:00:04Z GET /orders 200",
+        ],
+    );
+    let db = write(
+        &dir,
+        "db.log",
+        &[
+            "2024-01-15 10:00:01,500 WARN slow query",
+            "2024-01-15 10:00:03,000 ERROR too many connections",
+        ],
+    );
+    let rotated = dir.path().join("api.log.1.gz");
+    let mut gz = flate2::write::GzEncoder::new(
+        std::fs::File::create(&rotated).unwrap(),
+        flate2::Compression::default(),
+    );
+    writeln!(gz, "2024-01-15T10:00:00Z starting").unwrap();
+    gz.finish().unwrap();
+
+    let (entries, _) = Replay::start(vec![api, db, rotated], "1000x").all().await;
+    assert_eq!(
+        messages(&entries),
+        [
+            "starting",
+            "WARN slow query",
+            "GET /orders 500",
+            "java.lang.IllegalStateException: pool exhausted",
+            "    at Pool.get(Pool.java:42)",
+            "ERROR too many connections",
+            "GET /orders 200",
+        ]
+    );
+    let producers: Vec<_> = entries.iter().map(|e| e.producer.as_str()).collect();
+    assert_eq!(
+        producers,
+        ["api.log", "db.log", "api.log", "api.log", "api.log", "db.log", "api.log"]
+    );
+    assert!(entries.iter().all(|e| e.source == FeedKind::Replay));
+    assert_eq!(entries[4].ts, ts("2024-01-15T10:00:02Z"));
+    assert_eq!(entries[1].ts, ts("2024-01-15T10:00:01.500Z"));
+}
+
+/// A file nothing can be paced by is refused up front.
+#[test]
+fn untimestamped_files_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write(&dir, "notes.txt", &["no", "timestamps", "here"]);
+    let config = ReplayConfig {
+        paths: vec![path],
+        speed: Speed::default(),
+    };
+    let err = ReplayFeed::load(&config).err().unwrap();
+    assert!(err.to_string().contains("notes.txt"), "{err}");
+}
+
+// ---------------------------------------------------------------------------
+// Pace and control
+// ---------------------------------------------------------------------------
+
+fn ticks(dir: &tempfile::TempDir, count: usize, gap_secs: i64) -> PathBuf {
+    let start = ts("2024-01-15T10:00:00Z");
+    let lines: Vec<String> = (0..count)
+        .map(|i| {
+            let at = start + chrono::TimeDelta::seconds(gap_secs * i as i64);
+            format!("{} tick {i}", at.to_rfc3339())
+        })
+        .collect();
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+    write(dir, "ticks.log", &lines)
+}
+
+/// Lines ten seconds apart arrive a second apart at 10x.
+#[tokio::test(start_paused = true)]
+async fn original_gaps_are_divided_by_speed() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut replay = Replay::start(vec![ticks(&dir, 3, 10)], "10x");
+    let arrivals = [
+        replay.next().await.0,
+        replay.next().await.0,
+        replay.next().await.0,
+    ];
+    assert_eq!(
+        arrivals.map(|d| d.as_millis()),
+        [0, 1000, 2000],
+        "arrivals: {arrivals:?}"
+    );
+}
+
+/// Nothing arrives while paused; resuming continues where it stopped.
+#[tokio::test(start_paused = true)]
+async fn pause_holds_entries_until_resume() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut replay = Replay::start(vec![ticks(&dir, 2, 10)], "1x");
+    replay.next().await;
+    tokio::time::sleep(Duration::from_secs(4)).await;
+    replay.control.send(ReplayCommand::Pause).await.unwrap();
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    assert!(replay.entries.try_recv().is_err());
+
+    replay.control.send(ReplayCommand::Resume).await.unwrap();
+    let (at, entry) = replay.next().await;
+    assert_eq!(entry.message.as_deref(), Some("tick 1"));
+    // 4s played before the pause, 60s paused, 6s left after it.
+    assert_eq!(at.as_secs(), 70);
+}
+
+/// A jump delivers every line up to the target at once, then keeps pace.
+#[tokio::test(start_paused = true)]
+async fn jump_skips_ahead() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut replay = Replay::start(vec![ticks(&dir, 5, 60)], "1x");
+    replay.next().await;
+    let target = ReplayTarget::TimeOfDay(chrono::NaiveTime::from_hms_opt(10, 3, 0).unwrap());
+    replay
+        .control
+        .send(ReplayCommand::Jump(target))
+        .await
+        .unwrap();
+    let jumped = [
+        replay.next().await,
+        replay.next().await,
+        replay.next().await,
+    ];
+    assert!(jumped.iter().all(|(at, _)| at.is_zero()));
+    assert_eq!(jumped[2].1.message.as_deref(), Some("tick 3"));
+
+    let (at, last) = replay.next().await;
+    assert_eq!(last.message.as_deref(), Some("tick 4"));
+    assert_eq!(at.as_secs(), 60);
+}
+
+/// A speed change applies from the moment it is made.
+#[tokio::test(start_paused = true)]
+async fn speed_changes_mid_replay() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut replay = Replay::start(vec![ticks(&dir, 2, 100)], "1x");
+    replay.next().await;
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    let speed = "10x".parse().unwrap();
+    replay
+        .control
+        .send(ReplayCommand::Speed(speed))
+        .await
+        .unwrap();
+    // 90s of log time left at 10x.
+    assert_eq!(replay.next().await.0.as_secs(), 19);
+}
@@ -12,6 +12,9 @@
 //!   has fewer).
 //! - **Empty input**: an immediate EOF must result in an empty store and a
 //!   clean exit.
+//! - **Prefixed streams**: `docker compose logs`, `kubectl logs --prefix` and
+//!   custom-regex prefixes are split into producers and payloads, detected
+//!   automatically when not given, and coloured prefixes still match.
 //!
 //! # What this does NOT cover
 //!
@@ -25,7 +28,47 @@
 //! ```
 
 mod common;
+use common::fake_process::fake_process;
 use common::*;
+use fml_core::{FeedKind, LogEntry, ProducerStatus, ProducerUpdate};
+use fml_feeds::stdin::{self, Prefix, StdinConfig};
+use futures::StreamExt;
+use tokio::sync::mpsc;
+
+/// Feed `lines` through the stdin ingestor and collect what it sends.
+async fn ingest(prefix: Prefix, lines: &[&str]) -> (Vec<LogEntry>, Vec<ProducerUpdate>) {
+    let (writer, stream) = fake_process();
+    writer.send_burst(lines);
+    writer.close();
+    let config = StdinConfig {
+        prefix,
+        ..StdinConfig::default()
+    };
+    let (tx, mut rx) = mpsc::channel(lines.len().max(1));
+    let (utx, mut updates) = mpsc::channel(64);
+    let collect = tokio::spawn(async move {
+        let mut entries = Vec::new();
+        while let Some(entry) = rx.recv().await {
+            entries.push(entry);
+        }
+        entries
+    });
+    stdin::ingest(&config, stream.map(Ok), tx, utx)
+        .await
+        .unwrap();
+    let mut seen = Vec::new();
+    while let Ok(update) = updates.try_recv() {
+        seen.push(update);
+    }
+    (collect.await.unwrap(), seen)
+}
+
+fn messages(entries: &[LogEntry]) -> Vec<&str> {
+    entries
+        .iter()
+        .map(|e| e.message.as_deref().unwrap())
+        .collect()
+}
 
 // ---------------------------------------------------------------------------
 // Finite input
@@ -33,24 +76,26 @@ use common::*;
 
 /// When stdin reaches EOF, the ingestor terminates and the store contains
 /// exactly the lines that were provided.
-#[test]
-#[ignore = "not yet implemented"]
-fn eof_terminates_ingestor() {
-    todo!("pipe finite bytes through fake stdin; assert store has exact line count on EOF")
+#[tokio::test]
+async fn eof_terminates_ingestor() {
+    let (entries, _) = ingest(Prefix::None, &["one", "two", "three"]).await;
+    assert_eq!(entries.len(), 3);
 }
 
 /// Empty stdin (immediate EOF) produces an empty store and a clean exit.
-#[test]
-#[ignore = "not yet implemented"]
-fn empty_stdin_produces_empty_store() {
-    todo!("send EOF immediately; assert store.len() == 0")
+#[tokio::test]
+async fn empty_stdin_produces_empty_store() {
+    let (entries, updates) = ingest(Prefix::Detect, &[]).await;
+    assert!(entries.is_empty());
+    assert!(updates.is_empty());
 }
 
 /// A single line on stdin is ingested correctly.
-#[test]
-#[ignore = "not yet implemented"]
-fn single_line_is_ingested() {
-    todo!("pipe one line; assert store.len() == 1 and content matches")
+#[tokio::test]
+async fn single_line_is_ingested() {
+    let (entries, _) = ingest(Prefix::Detect, &["GET /health 200"]).await;
+    assert_eq!(messages(&entries), ["GET /health 200"]);
+    assert_eq!(entries[0].raw, "GET /health 200");
 }
 
 // ---------------------------------------------------------------------------
@@ -58,17 +103,21 @@ fn single_line_is_ingested() {
 // ---------------------------------------------------------------------------
 
 /// 10 000 lines sent in a single burst are all received in the store.
-#[test]
-#[ignore = "not yet implemented"]
-fn burst_of_10k_lines_all_received() {
-    todo!("pipe 10k lines rapidly; assert store.len() == 10_000")
+#[tokio::test]
+async fn burst_of_10k_lines_all_received() {
+    let lines: Vec<String> = (0..10_000).map(|i| format!("line {i}")).collect();
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+    let (entries, _) = ingest(Prefix::Detect, &lines).await;
+    assert_eq!(entries.len(), 10_000);
 }
 
 /// Lines are received in the order they were sent (no reordering).
-#[test]
-#[ignore = "not yet implemented"]
-fn lines_are_ordered() {
-    todo!("pipe numbered lines; assert entries are in sequence order")
+#[tokio::test]
+async fn lines_are_ordered() {
+    let lines: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+    let (entries, _) = ingest(Prefix::Detect, &lines).await;
+    assert_eq!(messages(&entries), lines);
 }
 
 // ---------------------------------------------------------------------------
@@ -103,8 +152,96 @@ fn headless_tail_larger_than_store_prints_all() {
 
 /// The stdin ingestor sets producer to an empty string or a configurable name,
 /// never to a pod/container name.
-#[test]
-#[ignore = "not yet implemented"]
-fn stdin_producer_field_is_set() {
-    todo!("ingest via stdin; assert entry.source == FeedKind::Stdin")
+#[tokio::test]
+async fn stdin_producer_field_is_set() {
+    let (entries, _) = ingest(Prefix::Detect, &["plain line"]).await;
+    assert_source!(entries[0], FeedKind::Stdin);
+    assert_producer!(entries[0], "");
+}
+
+// ---------------------------------------------------------------------------
+// Prefixed streams
+// ---------------------------------------------------------------------------
+
+/// `docker compose logs` output becomes one producer per service, with the
+/// padding and `|` dropped from the message.
+#[tokio::test]
+async fn compose_prefixes_become_producers() {
+    let (entries, updates) = ingest(
+        Prefix::Compose,
+        &[
+            "api-1  | listening on :8080",
+            "db-1   | ready to accept connections",
+            "api-1  | GET /health 200",
+        ],
+    )
+    .await;
+    assert_producer!(entries[0], "api-1");
+    assert_producer!(entries[1], "db-1");
+    assert_eq!(
+        messages(&entries),
+        [
+            "listening on :8080",
+            "ready to accept connections",
+            "GET /health 200"
+        ]
+    );
+    let streaming: Vec<&str> = updates
+        .iter()
+        .filter(|u| u.status == ProducerStatus::Streaming)
+        .map(|u| u.producer.as_str())
+        .collect();
+    assert_eq!(streaming, ["api-1", "db-1"]);
+    assert!(updates
+        .iter()
+        .any(|u| u.producer == "db-1" && u.status == ProducerStatus::Ended { code: None }));
+}
+
+/// `kubectl logs --prefix` names pod and container, which nest in the tree.
+#[tokio::test]
+async fn kubectl_prefixes_nest_pod_and_container() {
+    let (entries, _) = ingest(
+        Prefix::Kubectl,
+        &[
+            "[pod/api-7f9c/api] started",
+            "[pod/api-7f9c/envoy] upstream ok",
+        ],
+    )
+    .await;
+    assert_producer!(entries[0], "api-7f9c/api");
+    assert_producer!(entries[1], "api-7f9c/envoy");
+    assert_eq!(messages(&entries), ["started", "upstream ok"]);
+}
+
+/// A custom regex names the producer; unmatched lines stay at the root.
+#[tokio::test]
+async fn custom_pattern_splits_lines() {
+    let prefix: Prefix = r"^(?P<producer>[a-z]+)\[\d+\]: ".parse().unwrap();
+    let (entries, _) = ingest(prefix, &["worker[42]: job done", "no prefix"]).await;
+    assert_producer!(entries[0], "worker");
+    assert_eq!(entries[0].message.as_deref(), Some("job done"));
+    assert_producer!(entries[1], "");
+    assert_eq!(entries[1].message.as_deref(), Some("no prefix"));
+}
+
+/// Without a format, compose output is recognised from its first lines, and
+/// coloured prefixes match once the colour codes are stripped.
+#[tokio::test]
+async fn compose_prefix_is_detected() {
+    let mut lines = vec!["Attaching to api-1, db-1"];
+    lines.extend(["\x1b[36mapi-1  |\x1b[0m request served"; 10]);
+    let (entries, _) = ingest(Prefix::Detect, &lines).await;
+    assert_producer!(entries[0], "");
+    assert!(entries[1..].iter().all(|e| e.producer == "api-1"));
+    assert_eq!(entries[10].message.as_deref(), Some("request served"));
+}
+
+/// Ordinary logs are left alone by detection, even with a stray `|`.
+#[tokio::test]
+async fn plain_lines_are_not_split_by_detection() {
+    let mut lines = vec!["INFO boot"; 10];
+    lines.push("cache  | miss");
+    let (entries, _) = ingest(Prefix::Detect, &lines).await;
+    assert!(entries.iter().all(|e| e.producer.is_empty()));
+    assert_eq!(entries[10].message.as_deref(), Some("cache  | miss"));
 }
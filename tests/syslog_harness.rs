@@ -0,0 +1,133 @@
+#![allow(unused)]
+//! Syslog listener integration harness.
+//!
+//! # What this covers
+//!
+//! - **UDP**: one datagram is one message, parsed as RFC 5424 or RFC 3164.
+//! - **TCP framing**: octet-counted and newline-delimited frames on the same
+//!   listener, including several messages per connection.
+//! - **Producers**: `hostname/app-name`, so each sending device/daemon pair is
+//!   its own producer.
+//!
+//! # What this does NOT cover
+//!
+//! - TLS syslog (RFC 5425)
+//! - Privileged port 514 (tests bind an ephemeral port)
+//!
+//! # Running
+//!
+//! ```sh
+//! cargo test --test syslog_harness
+//! ```
+
+mod common;
+use common::*;
+use fml_core::{FeedKind, LogEntry, LogLevel};
+use fml_feeds::syslog::{SyslogConfig, SyslogListener};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+async fn recv(rx: &mut mpsc::Receiver<LogEntry>) -> LogEntry {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("message should arrive")
+        .expect("channel open")
+}
+
+// ---------------------------------------------------------------------------
+// UDP
+// ---------------------------------------------------------------------------
+
+/// A UDP datagram is parsed into an entry tagged with the syslog source.
+#[tokio::test]
+async fn udp_datagram_is_ingested() {
+    let listener = SyslogListener::bind(&SyslogConfig {
+        udp: Some("127.0.0.1:0".parse().unwrap()),
+        tcp: None,
+    })
+    .await
+    .unwrap();
+    let addr = listener.udp_addr().unwrap();
+    let (tx, mut rx) = mpsc::channel(8);
+    tokio::spawn(listener.run(tx));
+
+    let sender = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    sender
+        .send_to(
+            b"<131>1 2024-01-15T10:00:00Z core-sw1 lldpd - - - neighbor lost\n",
+            addr,
+        )
+        .await
+        .unwrap();
+
+    let entry = recv(&mut rx).await;
+    assert_source!(entry, FeedKind::Syslog);
+    assert_producer!(entry, "core-sw1/lldpd");
+    assert_level!(entry, LogLevel::Error);
+    assert_eq!(entry.message.as_deref(), Some("neighbor lost"));
+    assert_has_field!(entry, "peer", "127.0.0.1");
+}
+
+// ---------------------------------------------------------------------------
+// TCP framing
+// ---------------------------------------------------------------------------
+
+/// Octet-counted and newline-delimited frames can share one connection.
+#[tokio::test]
+async fn tcp_accepts_both_framings() {
+    let listener = SyslogListener::bind(&SyslogConfig {
+        udp: None,
+        tcp: Some("127.0.0.1:0".parse().unwrap()),
+    })
+    .await
+    .unwrap();
+    let addr = listener.tcp_addr().unwrap();
+    let (tx, mut rx) = mpsc::channel(8);
+    tokio::spawn(listener.run(tx));
+
+    let first = "<14>Jan 15 10:00:00 web1 nginx: GET / 200";
+    let mut conn = tokio::net::TcpStream::connect(addr).await.unwrap();
+    conn.write_all(format!("{} {first}", first.len()).as_bytes())
+        .await
+        .unwrap();
+    conn.write_all(b"<11>Jan 15 10:00:01 web1 php-fpm[88]: segfault\n")
+        .await
+        .unwrap();
+    conn.shutdown().await.unwrap();
+
+    let a = recv(&mut rx).await;
+    assert_producer!(a, "web1/nginx");
+    assert_eq!(a.message.as_deref(), Some("GET / 200"));
+
+    let b = recv(&mut rx).await;
+    assert_producer!(b, "web1/php-fpm");
+    assert_level!(b, LogLevel::Error);
+    assert_has_field!(b, "procid", "88");
+}
+
+/// Separate senders on the same listener become separate producers.
+#[tokio::test]
+async fn tcp_connections_are_independent_producers() {
+    let listener = SyslogListener::bind(&SyslogConfig {
+        udp: None,
+        tcp: Some("127.0.0.1:0".parse().unwrap()),
+    })
+    .await
+    .unwrap();
+    let addr = listener.tcp_addr().unwrap();
+    let (tx, mut rx) = mpsc::channel(8);
+    tokio::spawn(listener.run(tx));
+
+    for host in ["db1", "db2"] {
+        let mut conn = tokio::net::TcpStream::connect(addr).await.unwrap();
+        conn.write_all(format!("<13>1 - {host} postgres - - - ready\n").as_bytes())
+            .await
+            .unwrap();
+        conn.shutdown().await.unwrap();
+    }
+
+    let mut producers = vec![recv(&mut rx).await.producer, recv(&mut rx).await.producer];
+    producers.sort();
+    assert_eq!(producers, ["db1/postgres", "db2/postgres"]);
+}